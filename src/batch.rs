@@ -1,4 +1,5 @@
 use crate::runtime::executor::ContractExecutor;
+use crate::simulator::SnapshotLoader;
 use crate::DebuggerError;
 use crate::Result;
 use rayon::prelude::*;
@@ -48,6 +49,8 @@ pub struct BatchSummary {
 pub struct BatchExecutor {
     wasm_bytes: Vec<u8>,
     function: String,
+    /// Network snapshot applied before each call
+    snapshot: Option<SnapshotLoader>,
 }
 
 impl BatchExecutor {
@@ -56,9 +59,16 @@ impl BatchExecutor {
         Self {
             wasm_bytes,
             function,
+            snapshot: None,
         }
     }
 
+    /// Run every call against the ledger state of `snapshot`
+    pub fn with_snapshot(mut self, snapshot: SnapshotLoader) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
     /// Load batch items from a JSON file
     pub fn load_batch_file<P: AsRef<Path>>(path: P) -> Result<Vec<BatchItem>> {
         let content = fs::read_to_string(path.as_ref()).map_err(|e| {
@@ -95,7 +105,13 @@ impl BatchExecutor {
     fn execute_single(&self, index: usize, item: &BatchItem) -> BatchResult {
         let start = Instant::now();
 
-        let executor_result = ContractExecutor::new(self.wasm_bytes.clone());
+        let executor_result =
+            ContractExecutor::new(self.wasm_bytes.clone()).and_then(|mut executor| {
+                if let Some(snapshot) = &self.snapshot {
+                    snapshot.apply_to_environment(&mut executor)?;
+                }
+                Ok(executor)
+            });
 
         let (result_str, success, error) = match executor_result {
             Ok(executor) => match executor.execute(&self.function, Some(&item.args)) {
@@ -111,6 +111,9 @@ pub enum Commands {
     /// Connect to remote debug server
     Remote(RemoteArgs),
 
+    /// Start a Debug Adapter Protocol server for editors
+    Dap(DapArgs),
+
     /// Analyze contract for security vulnerabilities
     Analyze(AnalyzeArgs),
 }
@@ -181,6 +184,10 @@ pub struct RunArgs {
     #[arg(long, value_name = "CONTRACT_ID.function=return_value")]
     pub mock: Vec<String>,
 
+    /// Deploy another contract (e.g. a callee) before running; its address is printed (repeatable)
+    #[arg(long, value_name = "WASM")]
+    pub deploy: Vec<PathBuf>,
+
     /// Filter storage output by key pattern (repeatable). Supports:
     ///   prefix*       — match keys starting with prefix
     ///   re:<regex>    — match keys by regex
@@ -199,6 +206,13 @@ pub struct RunArgs {
     /// Step mode for instruction debugging (into, over, out, block)
     #[arg(long, default_value = "into")]
     pub step_mode: String,
+
+    /// Estimate the CPU and memory of nested calls by replaying each one
+    /// on its own (slower; figures are marked "est.", and are "n/a" without
+    /// this flag)
+    #[arg(long)]
+    pub estimate_frame_budgets: bool,
+
     /// Execute contract in dry-run mode: simulate execution without persisting storage changes
     #[arg(long)]
     pub dry_run: bool,
@@ -211,6 +225,15 @@ pub struct RunArgs {
     #[arg(long)]
     pub import_storage: Option<PathBuf>,
 
+    /// Write the complete ledger state after execution as a network snapshot,
+    /// to pass to a later run's --network-snapshot
+    #[arg(long, value_name = "FILE")]
+    pub export_network_snapshot: Option<PathBuf>,
+
+    /// Format of --export-network-snapshot (network, ledger)
+    #[arg(long, default_value = "network", requires = "export_network_snapshot")]
+    pub export_snapshot_format: String,
+
     /// Path to JSON file containing array of argument sets for batch execution
     #[arg(long)]
     pub batch_args: Option<PathBuf>,
@@ -234,6 +257,14 @@ pub struct RunArgs {
     /// TTL warning threshold in ledger sequence numbers (default: 1000)
     #[arg(long, default_value = "1000")]
     pub ttl_warning_threshold: u32,
+
+    /// Watch the WASM file for changes and re-run, showing what changed since the previous run
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Extra file to watch in --watch mode (repeatable). Storage and snapshot files are watched automatically
+    #[arg(long, value_name = "PATH", requires = "watch")]
+    pub watch_path: Vec<PathBuf>,
 }
 
 impl RunArgs {
@@ -269,8 +300,16 @@ impl RunArgs {
 #[derive(Parser)]
 pub struct InteractiveArgs {
     /// Path to the contract WASM file
+    #[arg(short, long, required_unless_present = "session")]
+    pub contract: Option<PathBuf>,
+
+    /// Reopen a session saved with `session save`
+    #[arg(long, conflicts_with_all = ["contract", "network_snapshot"])]
+    pub session: Option<PathBuf>,
+
+    /// Set breakpoint at function name
     #[arg(short, long)]
-    pub contract: PathBuf,
+    pub breakpoint: Vec<String>,
 
     /// Deprecated: use --contract instead
     #[arg(long, hide = true, alias = "wasm", alias = "contract-path")]
@@ -287,11 +326,27 @@ pub struct InteractiveArgs {
     /// Expected SHA-256 hash of the WASM file. If provided, loading will fail if the computed hash does not match.
     #[arg(long)]
     pub expected_hash: Option<String>,
+
+    /// Enable instruction-level debugging (locals, value stack, stepping)
+    #[arg(long)]
+    pub instruction_debug: bool,
+
+    /// Estimate the CPU and memory of nested calls by replaying each one
+    /// on its own (slower; figures are marked "est.", and are "n/a" without
+    /// this flag)
+    #[arg(long)]
+    pub estimate_frame_budgets: bool,
 }
 
 impl InteractiveArgs {
-    pub fn merge_config(&mut self, _config: &Config) {
-        // Future interactive-specific config could go here
+    pub fn merge_config(&mut self, config: &Config) {
+        // Saved sessions carry their own breakpoints
+        if self.session.is_none()
+            && self.breakpoint.is_empty()
+            && !config.debug.breakpoints.is_empty()
+        {
+            self.breakpoint = config.debug.breakpoints.clone();
+        }
     }
 }
 
@@ -431,6 +486,16 @@ pub struct TuiArgs {
     /// Network snapshot file to load before execution
     #[arg(long)]
     pub network_snapshot: Option<PathBuf>,
+
+    /// Enable instruction-level debugging (locals, value stack, stepping)
+    #[arg(long)]
+    pub instruction_debug: bool,
+
+    /// Estimate the CPU and memory of nested calls by replaying each one
+    /// on its own (slower; figures are marked "est.", and are "n/a" without
+    /// this flag)
+    #[arg(long)]
+    pub estimate_frame_budgets: bool,
 }
 
 #[derive(Parser)]
@@ -511,13 +576,38 @@ pub struct ServerArgs {
     #[arg(short, long)]
     pub token: Option<String>,
 
+    /// Token that lets clients observe sessions without changing them (repeatable)
+    #[arg(long, value_name = "TOKEN")]
+    pub observer_token: Vec<String>,
+
     /// TLS certificate file path (optional)
-    #[arg(long)]
+    #[arg(long, requires = "tls_key")]
     pub tls_cert: Option<PathBuf>,
 
     /// TLS private key file path (optional)
-    #[arg(long)]
+    #[arg(long, requires = "tls_cert")]
     pub tls_key: Option<PathBuf>,
+
+    /// Require clients to present a certificate signed by this CA (PEM)
+    #[arg(long, requires = "tls_cert")]
+    pub tls_client_ca: Option<PathBuf>,
+
+    /// Destroy detached sessions unused for this many seconds (0 keeps them forever)
+    #[arg(long, default_value = "1800", value_name = "SECS")]
+    pub idle_timeout: u64,
+
+    /// Also accept JSON-RPC 2.0 over WebSocket on this port, e.g. for web UIs
+    #[arg(long, value_name = "PORT")]
+    pub websocket_port: Option<u16>,
+
+    /// Reject messages larger than this many bytes (64 MiB by default)
+    #[arg(long, default_value = "67108864", value_name = "BYTES")]
+    pub max_message_size: usize,
+
+    /// Append every request and response to this JSON Lines file, for bug
+    /// reports and `remote --replay`
+    #[arg(long, value_name = "PATH")]
+    pub transcript: Option<PathBuf>,
 }
 
 #[derive(Parser)]
@@ -530,10 +620,46 @@ pub struct RemoteArgs {
     #[arg(short, long)]
     pub token: Option<String>,
 
+    /// Connect over TLS, verifying the server against the system roots
+    #[arg(long)]
+    pub tls: bool,
+
+    /// Verify the server against this CA (PEM) instead of the system roots;
+    /// implies --tls
+    #[arg(long)]
+    pub tls_ca: Option<PathBuf>,
+
+    /// Accept only a server certificate with this SHA-256 fingerprint (hex);
+    /// implies --tls
+    #[arg(long, value_name = "SHA256")]
+    pub tls_pin: Option<String>,
+
+    /// Client certificate (PEM) for servers that require one; implies --tls
+    #[arg(long, requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// Private key (PEM) for --tls-cert
+    #[arg(long, requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Attach to this named session, creating it if needed; it survives
+    /// disconnects and can be reattached later
+    #[arg(long)]
+    pub session: Option<String>,
+
     /// Path to the contract WASM file
     #[arg(short, long)]
     pub contract: Option<PathBuf>,
 
+    /// Send the contract from this machine instead of reading it from the
+    /// server's filesystem
+    #[arg(long)]
+    pub upload: bool,
+
+    /// Expected SHA-256 hash of the WASM file. If provided, loading will fail if the computed hash does not match.
+    #[arg(long)]
+    pub expected_hash: Option<String>,
+
     /// Function name to execute
     #[arg(short, long)]
     pub function: Option<String>,
@@ -541,6 +667,18 @@ pub struct RemoteArgs {
     /// Function arguments as JSON array
     #[arg(short, long)]
     pub args: Option<String>,
+
+    /// Send the requests of a server transcript (see `server --transcript`)
+    /// and report responses that differ
+    #[arg(long, value_name = "TRANSCRIPT", conflicts_with_all = ["session", "contract", "function"])]
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct DapArgs {
+    /// Listen on this TCP port instead of using stdin/stdout
+    #[arg(short, long)]
+    pub port: Option<u16>,
 }
 
 #[derive(Parser)]
@@ -1,15 +1,17 @@
 use crate::cli::args::{
-    AnalyzeArgs, CompareArgs, InspectArgs, InteractiveArgs, OptimizeArgs, ProfileArgs, RemoteArgs,
-    ReplayArgs, RunArgs, ServerArgs, SymbolicArgs, TuiArgs, UpgradeCheckArgs, Verbosity,
+    AnalyzeArgs, CompareArgs, DapArgs, InspectArgs, InteractiveArgs, OptimizeArgs, ProfileArgs,
+    RemoteArgs, ReplayArgs, RunArgs, ServerArgs, SymbolicArgs, TuiArgs, UpgradeCheckArgs,
+    Verbosity,
 };
 use crate::debugger::engine::DebuggerEngine;
 use crate::debugger::instruction_pointer::StepMode;
+use crate::debugger::session::Session;
 use crate::history::{check_regression, HistoryManager, RunHistory};
 use crate::logging;
 use crate::output::OutputConfig;
 use crate::repeat::RepeatRunner;
 use crate::runtime::executor::ContractExecutor;
-use crate::simulator::SnapshotLoader;
+use crate::simulator::{NetworkSnapshot, SnapshotFormat, SnapshotLoader, SnapshotManager};
 use crate::ui::formatter::Formatter;
 use crate::ui::tui::DebuggerUI;
 use crate::{DebuggerError, Result};
@@ -29,6 +31,91 @@ fn print_warning(message: impl AsRef<str>) {
     println!("{}", Formatter::warning(message));
 }
 
+/// Read and validate the `--network-snapshot` file, if one was given
+pub(crate) fn load_network_snapshot(
+    path: Option<&std::path::Path>,
+) -> Result<Option<SnapshotLoader>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    print_info(format!("\nLoading network snapshot: {:?}", path));
+    logging::log_loading_snapshot(&path.to_string_lossy());
+    SnapshotLoader::from_file(path).map(Some)
+}
+
+/// Install a network snapshot's ledger state in `executor` and log its summary
+fn apply_network_snapshot(
+    snapshot: Option<&SnapshotLoader>,
+    executor: &mut ContractExecutor,
+) -> Result<()> {
+    if let Some(loader) = snapshot {
+        let loaded_snapshot = loader.apply_to_environment(executor)?;
+        logging::log_display(loaded_snapshot.format_summary(), logging::LogLevel::Info);
+    }
+    Ok(())
+}
+
+/// The storage a run starts with: `--import-storage`, or else `--storage`
+pub(crate) fn initial_storage(args: &RunArgs) -> Result<Option<String>> {
+    if let Some(import_path) = &args.import_storage {
+        print_info(format!("Importing storage from: {:?}", import_path));
+        let imported = crate::inspector::storage::StorageState::import_from_file(import_path)?;
+        print_success(format!("Imported {} storage entries", imported.len()));
+        let json = serde_json::to_string(&imported).map_err(|e| {
+            DebuggerError::StorageError(format!("Failed to serialize imported storage: {}", e))
+        })?;
+        return Ok(Some(json));
+    }
+    args.storage.as_deref().map(parse_storage).transpose()
+}
+
+/// A fresh executor for a run: the network snapshot, initial storage and
+/// mocks installed, and the `--deploy` contracts deployed
+pub(crate) fn run_executor(
+    args: &RunArgs,
+    wasm_bytes: Vec<u8>,
+    snapshot: Option<&SnapshotLoader>,
+    initial_storage: Option<String>,
+) -> Result<ContractExecutor> {
+    let mut executor = ContractExecutor::new(wasm_bytes)?;
+    executor.set_timeout(args.timeout);
+    apply_network_snapshot(snapshot, &mut executor)?;
+
+    if let Some(storage) = initial_storage {
+        executor.set_initial_storage(storage)?;
+    }
+    if !args.mock.is_empty() {
+        executor.set_mock_specs(&args.mock)?;
+    }
+    for path in &args.deploy {
+        let address = executor.deploy(&crate::utils::wasm::load_wasm(path)?.bytes)?;
+        print_info(format!("Deployed {:?} at {}", path, address));
+    }
+    Ok(executor)
+}
+
+/// Save the ledger state of `executor` as a network snapshot and show how
+/// it differs from `before`
+fn export_network_snapshot(
+    executor: &ContractExecutor,
+    before: &NetworkSnapshot,
+    path: &std::path::Path,
+    format: SnapshotFormat,
+) -> Result<()> {
+    print_info(format!("\nExporting network snapshot to: {:?}", path));
+    let after = SnapshotManager::capture(executor)?;
+    SnapshotManager::save_as(&after, path, format)?;
+    let diff = SnapshotManager::diff_snapshots(before, &after);
+    logging::log_display(diff.format_summary(), logging::LogLevel::Info);
+    print_success(format!(
+        "Exported {} accounts, {} contracts and {} other ledger entries",
+        after.accounts.len(),
+        after.contracts.len(),
+        after.ledger_entries.len()
+    ));
+    Ok(())
+}
+
 /// Execute batch mode with parallel execution
 fn run_batch(args: &RunArgs, batch_file: &std::path::Path) -> Result<()> {
     print_info(format!("Loading contract: {:?}", args.contract));
@@ -51,13 +138,7 @@ fn run_batch(args: &RunArgs, batch_file: &std::path::Path) -> Result<()> {
     let batch_items = crate::batch::BatchExecutor::load_batch_file(batch_file)?;
     print_success(format!("Loaded {} test cases", batch_items.len()));
 
-    if let Some(snapshot_path) = &args.network_snapshot {
-        print_info(format!("\nLoading network snapshot: {:?}", snapshot_path));
-        logging::log_loading_snapshot(&snapshot_path.to_string_lossy());
-        let loader = SnapshotLoader::from_file(snapshot_path)?;
-        let loaded_snapshot = loader.apply_to_environment()?;
-        logging::log_display(loaded_snapshot.format_summary(), logging::LogLevel::Info);
-    }
+    let snapshot = load_network_snapshot(args.network_snapshot.as_deref())?;
 
     print_info(format!(
         "\nExecuting {} test cases in parallel for function: {}",
@@ -66,7 +147,12 @@ fn run_batch(args: &RunArgs, batch_file: &std::path::Path) -> Result<()> {
     ));
     logging::log_execution_start(&args.function, None);
 
-    let executor = crate::batch::BatchExecutor::new(wasm_bytes, args.function.clone());
+    let mut executor = crate::batch::BatchExecutor::new(wasm_bytes.clone(), args.function.clone());
+    if let Some(snapshot) = snapshot {
+        // Surface snapshot errors once rather than for every test case
+        apply_network_snapshot(Some(&snapshot), &mut ContractExecutor::new(wasm_bytes)?)?;
+        executor = executor.with_snapshot(snapshot);
+    }
     let results = executor.execute_batch(batch_items)?;
     let summary = crate::batch::BatchExecutor::summarize(&results);
 
@@ -108,9 +194,22 @@ fn run_batch(args: &RunArgs, batch_file: &std::path::Path) -> Result<()> {
 pub fn run(args: RunArgs, verbosity: Verbosity) -> Result<()> {
     // Handle batch execution mode
     if let Some(batch_file) = &args.batch_args {
+        if args.watch {
+            print_warning("--watch is not supported with --batch-args; running the batch once");
+        }
         return run_batch(&args, batch_file);
     }
 
+    if args.watch {
+        if args.step_instructions {
+            print_warning("--step-instructions is not supported in watch mode; ignoring it");
+        }
+        if args.export_network_snapshot.is_some() {
+            print_warning("--export-network-snapshot is not supported in watch mode; ignoring it");
+        }
+        return crate::cli::watch::run_watch(&args);
+    }
+
     if args.dry_run {
         return run_dry_run(&args);
     }
@@ -147,13 +246,8 @@ pub fn run(args: RunArgs, verbosity: Verbosity) -> Result<()> {
 
     logging::log_contract_loaded(wasm_bytes.len());
 
-    if let Some(snapshot_path) = &args.network_snapshot {
-        print_info(format!("\nLoading network snapshot: {:?}", snapshot_path));
-        logging::log_loading_snapshot(&snapshot_path.to_string_lossy());
-        let loader = SnapshotLoader::from_file(snapshot_path)?;
-        let loaded_snapshot = loader.apply_to_environment()?;
-        logging::log_display(loaded_snapshot.format_summary(), logging::LogLevel::Info);
-    }
+    let snapshot = load_network_snapshot(args.network_snapshot.as_deref())?;
+    let export_format: SnapshotFormat = args.export_snapshot_format.parse()?;
 
     let parsed_args = if let Some(args_json) = &args.args {
         Some(parse_args(args_json)?)
@@ -161,25 +255,17 @@ pub fn run(args: RunArgs, verbosity: Verbosity) -> Result<()> {
         None
     };
 
-    let mut initial_storage = if let Some(storage_json) = &args.storage {
-        Some(parse_storage(storage_json)?)
-    } else {
-        None
-    };
-
-    // Import storage if specified
-    if let Some(import_path) = &args.import_storage {
-        print_info(format!("Importing storage from: {:?}", import_path));
-        let imported = crate::inspector::storage::StorageState::import_from_file(import_path)?;
-        print_success(format!("Imported {} storage entries", imported.len()));
-        initial_storage = Some(serde_json::to_string(&imported).map_err(|e| {
-            DebuggerError::StorageError(format!("Failed to serialize imported storage: {}", e))
-        })?);
-    }
+    let initial_storage = initial_storage(&args)?;
 
     if let Some(n) = args.repeat {
+        if args.export_network_snapshot.is_some() {
+            print_warning("--export-network-snapshot is not supported with --repeat; ignoring it");
+        }
         logging::log_repeat_execution(&args.function, n as usize);
-        let runner = RepeatRunner::new(wasm_bytes, args.breakpoint, initial_storage);
+        let mut runner = RepeatRunner::new(wasm_bytes, args.breakpoint, initial_storage);
+        if let Some(snapshot) = snapshot {
+            runner = runner.with_snapshot(snapshot);
+        }
         let stats = runner.run(&args.function, parsed_args.as_deref(), n)?;
         stats.display();
         return Ok(());
@@ -192,17 +278,14 @@ pub fn run(args: RunArgs, verbosity: Verbosity) -> Result<()> {
     }
     logging::log_execution_start(&args.function, parsed_args.as_deref());
 
-    let mut executor = ContractExecutor::new(wasm_bytes.clone())?;
-    executor.set_timeout(args.timeout);
-
-    if let Some(storage) = initial_storage {
-        executor.set_initial_storage(storage)?;
-    }
-    if !args.mock.is_empty() {
-        executor.set_mock_specs(&args.mock)?;
-    }
-
+    let executor = run_executor(
+        &args,
+        wasm_bytes.clone(),
+        snapshot.as_ref(),
+        initial_storage,
+    )?;
     let mut engine = DebuggerEngine::new(executor, args.breakpoint);
+    engine.set_frame_budget_estimates(args.estimate_frame_budgets);
 
     if args.instruction_debug {
         print_info("Enabling instruction-level debugging...");
@@ -215,6 +298,7 @@ pub fn run(args: RunArgs, verbosity: Verbosity) -> Result<()> {
                 args.step_mode
             ));
             engine.start_instruction_stepping(step_mode)?;
+            engine.prepare_invocation(&args.function, parsed_args.as_deref())?;
             run_instruction_stepping(&mut engine, &args.function, parsed_args.as_deref())?;
             return Ok(());
         }
@@ -222,6 +306,10 @@ pub fn run(args: RunArgs, verbosity: Verbosity) -> Result<()> {
 
     print_info("\n--- Execution Start ---\n");
     let storage_before = engine.executor().get_storage_snapshot()?;
+    let ledger_before = match &args.export_network_snapshot {
+        Some(_) => Some(SnapshotManager::capture(engine.executor())?),
+        None => None,
+    };
     let result = engine.execute(&args.function, parsed_args.as_deref())?;
     let storage_after = engine.executor().get_storage_snapshot()?;
     print_success("\n--- Execution Complete ---\n");
@@ -242,6 +330,9 @@ pub fn run(args: RunArgs, verbosity: Verbosity) -> Result<()> {
         print_info(format!("\nExporting storage to: {:?}", export_path));
         crate::inspector::storage::StorageState::export_to_file(&storage_after, export_path)?;
     }
+    if let (Some(export_path), Some(before)) = (&args.export_network_snapshot, &ledger_before) {
+        export_network_snapshot(engine.executor(), before, export_path, export_format)?;
+    }
     let mock_calls = engine.executor().get_mock_call_log();
     if !args.mock.is_empty() {
         display_mock_call_log(&mock_calls);
@@ -464,10 +555,12 @@ fn run_dry_run(args: &RunArgs) -> Result<()> {
             "\n[DRY RUN] Loading network snapshot: {:?}",
             snapshot_path
         ));
-        let loader = SnapshotLoader::from_file(snapshot_path)?;
-        let loaded_snapshot = loader.apply_to_environment()?;
-        print_info(format!("[DRY RUN] {}", loaded_snapshot.format_summary()));
     }
+    let snapshot = args
+        .network_snapshot
+        .as_deref()
+        .map(SnapshotLoader::from_file)
+        .transpose()?;
 
     let parsed_args = if let Some(args_json) = &args.args {
         Some(parse_args(args_json)?)
@@ -482,6 +575,10 @@ fn run_dry_run(args: &RunArgs) -> Result<()> {
     };
 
     let mut executor = ContractExecutor::new(wasm_bytes)?;
+    if let Some(loader) = &snapshot {
+        let loaded_snapshot = loader.apply_to_environment(&mut executor)?;
+        print_info(format!("[DRY RUN] {}", loaded_snapshot.format_summary()));
+    }
     if let Some(storage) = initial_storage {
         executor.set_initial_storage(storage)?;
     }
@@ -533,14 +630,36 @@ fn run_dry_run(args: &RunArgs) -> Result<()> {
 
 /// Execute the interactive command.
 pub fn interactive(args: InteractiveArgs, _verbosity: Verbosity) -> Result<()> {
-    print_info(format!(
-        "Starting interactive debugger for: {:?}",
-        args.contract
-    ));
-    logging::log_loading_contract(&args.contract.to_string_lossy());
+    if let Some(session_path) = &args.session {
+        print_info(format!("Restoring session: {:?}", session_path));
+        let session = Session::load(session_path)?;
+        let mut engine = session.restore()?;
+        engine.set_frame_budget_estimates(args.estimate_frame_budgets);
+        print_success(format!(
+            "Session restored ({} breakpoints, {} watches, {} mocks)",
+            session.config.debug.breakpoints.len(),
+            session.watches.len(),
+            session.mocks.len()
+        ));
 
-    let wasm_file = crate::utils::wasm::load_wasm(&args.contract)
-        .with_context(|| format!("Failed to read WASM file: {:?}", args.contract))?;
+        print_info("\nStarting interactive mode...");
+        print_info("Type 'help' for available commands\n");
+        logging::log_interactive_mode_start();
+
+        let mut ui = DebuggerUI::new(engine)?;
+        ui.apply_session(&session);
+        return ui.run();
+    }
+
+    let contract = args
+        .contract
+        .clone()
+        .ok_or_else(|| DebuggerError::InvalidArguments("--contract is required".to_string()))?;
+    print_info(format!("Starting interactive debugger for: {:?}", contract));
+    logging::log_loading_contract(&contract.to_string_lossy());
+
+    let wasm_file = crate::utils::wasm::load_wasm(&contract)
+        .with_context(|| format!("Failed to read WASM file: {:?}", contract))?;
     let wasm_bytes = wasm_file.bytes;
     let wasm_hash = wasm_file.sha256_hash;
 
@@ -568,22 +687,24 @@ pub fn interactive(args: InteractiveArgs, _verbosity: Verbosity) -> Result<()> {
 
     logging::log_contract_loaded(wasm_bytes.len());
 
-    if let Some(snapshot_path) = &args.network_snapshot {
-        print_info(format!("\nLoading network snapshot: {:?}", snapshot_path));
-        logging::log_loading_snapshot(&snapshot_path.to_string_lossy());
-        let loader = SnapshotLoader::from_file(snapshot_path)?;
-        let loaded_snapshot = loader.apply_to_environment()?;
-        logging::log_display(loaded_snapshot.format_summary(), logging::LogLevel::Info);
-    }
+    let snapshot = load_network_snapshot(args.network_snapshot.as_deref())?;
+    let mut executor = ContractExecutor::new(wasm_bytes.clone())?;
+    apply_network_snapshot(snapshot.as_ref(), &mut executor)?;
+    let mut engine = DebuggerEngine::new(executor, args.breakpoint.clone());
+    engine.set_frame_budget_estimates(args.estimate_frame_budgets);
 
-    let executor = ContractExecutor::new(wasm_bytes)?;
-    let engine = DebuggerEngine::new(executor, vec![]);
+    if args.instruction_debug {
+        engine.enable_instruction_debug(&wasm_bytes)?;
+        engine.start_instruction_stepping(StepMode::StepInto)?;
+        print_info("Instruction-level debugging enabled");
+    }
 
     print_info("\nStarting interactive mode...");
     print_info("Type 'help' for available commands\n");
     logging::log_interactive_mode_start();
 
     let mut ui = DebuggerUI::new(engine)?;
+    ui.set_contract_path(contract);
     ui.run()?;
 
     Ok(())
@@ -598,10 +719,11 @@ pub fn tui(args: TuiArgs, _verbosity: Verbosity) -> Result<()> {
         ))
     })?;
 
-    if let Some(snapshot_path) = &args.network_snapshot {
-        let loader = SnapshotLoader::from_file(snapshot_path)?;
-        loader.apply_to_environment()?;
-    }
+    let snapshot = args
+        .network_snapshot
+        .as_deref()
+        .map(SnapshotLoader::from_file)
+        .transpose()?;
 
     let parsed_args = if let Some(ref a) = args.args {
         Some(parse_args(a)?)
@@ -615,12 +737,20 @@ pub fn tui(args: TuiArgs, _verbosity: Verbosity) -> Result<()> {
         None
     };
 
-    let mut executor = ContractExecutor::new(wasm_bytes)?;
+    let mut executor = ContractExecutor::new(wasm_bytes.clone())?;
+    if let Some(loader) = &snapshot {
+        loader.apply_to_environment(&mut executor)?;
+    }
     if let Some(storage) = initial_storage {
         executor.set_initial_storage(storage)?;
     }
 
     let mut engine = DebuggerEngine::new(executor, args.breakpoint);
+    engine.set_frame_budget_estimates(args.estimate_frame_budgets);
+    if args.instruction_debug {
+        engine.enable_instruction_debug(&wasm_bytes)?;
+        engine.start_instruction_stepping(StepMode::StepInto)?;
+    }
 
     // Pre-execute so live data is available immediately in the dashboard
     let _ = engine.execute(&args.function, parsed_args.as_deref());
@@ -900,13 +1030,7 @@ pub fn optimize(args: OptimizeArgs, _verbosity: Verbosity) -> Result<()> {
 
     logging::log_contract_loaded(wasm_bytes.len());
 
-    if let Some(snapshot_path) = &args.network_snapshot {
-        print_info(format!("\nLoading network snapshot: {:?}", snapshot_path));
-        logging::log_loading_snapshot(&snapshot_path.to_string_lossy());
-        let loader = SnapshotLoader::from_file(snapshot_path)?;
-        let loaded_snapshot = loader.apply_to_environment()?;
-        logging::log_display(loaded_snapshot.format_summary(), logging::LogLevel::Info);
-    }
+    let snapshot = load_network_snapshot(args.network_snapshot.as_deref())?;
 
     let functions_to_analyze = if args.function.is_empty() {
         print_warning("No functions specified, analyzing all exported functions...");
@@ -916,6 +1040,7 @@ pub fn optimize(args: OptimizeArgs, _verbosity: Verbosity) -> Result<()> {
     };
 
     let mut executor = ContractExecutor::new(wasm_bytes)?;
+    apply_network_snapshot(snapshot.as_ref(), &mut executor)?;
     if let Some(storage_json) = &args.storage {
         let storage = parse_storage(storage_json)?;
         executor.set_initial_storage(storage)?;
@@ -1411,7 +1536,8 @@ fn run_instruction_stepping(
         std::io::stdin()
             .read_line(&mut input)
             .map_err(|e| DebuggerError::FileError(format!("Failed to read line: {}", e)))?;
-        let input = input.trim().to_lowercase();
+        let raw_input = input.trim().to_string();
+        let input = raw_input.to_lowercase();
 
         match input.as_str() {
             "n" | "next" | "s" | "step" | "into" | "" => match engine.step_into() {
@@ -1476,6 +1602,10 @@ fn run_instruction_stepping(
                 let size = size_input.trim().parse().unwrap_or(5);
                 display_instruction_context(engine, size);
             }
+            "l" | "locals" => display_locals(engine),
+            cmd if matches!(cmd.split_whitespace().next(), Some("m" | "mem" | "memory")) => {
+                display_memory(engine, &raw_input)
+            }
             "h" | "help" => println!("{}", Formatter::format_stepping_help()),
             "q" | "quit" | "exit" => {
                 println!("Exiting instruction stepping mode...");
@@ -1493,12 +1623,54 @@ fn run_instruction_stepping(
     Ok(())
 }
 
+fn display_locals(engine: &DebuggerEngine) {
+    match engine.inspect_locals() {
+        Ok(snapshot) => println!("{}", Formatter::format_locals(&snapshot)),
+        Err(e) => println!("Cannot inspect locals: {}", e),
+    }
+}
+
+fn display_memory(engine: &mut DebuggerEngine, command: &str) {
+    let args: Vec<&str> = command.split_whitespace().skip(1).collect();
+    match engine
+        .memory_inspector()
+        .and_then(|inspector| inspector.run_command(&args))
+    {
+        Ok(output) => println!("{}", output),
+        Err(e) => println!("Cannot inspect memory: {}", e),
+    }
+}
+
 fn display_instruction_context(engine: &DebuggerEngine, context_size: usize) {
+    display_contract_stack(engine);
     let context = engine.get_instruction_context(context_size);
     let formatted = Formatter::format_instruction_context(&context, context_size);
     println!("{}", formatted);
 }
 
+/// Contracts being stepped through, when stepping has entered a callee.
+fn display_contract_stack(engine: &DebuggerEngine) {
+    let state = engine.state();
+    let Ok(state) = state.lock() else {
+        return;
+    };
+    if state.module_depth() == 0 {
+        return;
+    }
+    println!("Contract call stack:");
+    for (depth, (module, _)) in state.modules().into_iter().enumerate() {
+        println!(
+            "  #{} {} in {}",
+            depth,
+            module.function.as_deref().unwrap_or("<unknown>"),
+            module
+                .contract_id
+                .as_deref()
+                .unwrap_or("<unknown contract>")
+        );
+    }
+}
+
 fn display_instruction_info(engine: &DebuggerEngine) {
     if let Ok(state) = engine.state().lock() {
         let ip = state.instruction_pointer();
@@ -1631,14 +1803,36 @@ pub fn show_budget_trend(contract: Option<&str>, function: Option<&str>) -> crat
 /// Start the debug server
 pub fn server(args: ServerArgs) -> Result<()> {
     use crate::server::DebugServer;
+    use std::time::Duration;
 
     print_info(format!("Starting debug server on port {}", args.port));
 
-    let mut server = DebugServer::new(args.port, args.token);
+    let idle_timeout = (args.idle_timeout > 0).then(|| Duration::from_secs(args.idle_timeout));
+    let mut server = DebugServer::new(args.port, args.token)
+        .with_idle_timeout(idle_timeout)
+        .with_max_message_size(args.max_message_size);
+
+    if let Some(path) = args.transcript {
+        print_info(format!("Recording a transcript to {:?}", path));
+        server = server.with_transcript(path);
+    }
+
+    for token in args.observer_token {
+        server = server.with_observer_token(token);
+    }
+
+    if let Some(port) = args.websocket_port {
+        server = server.with_websocket(port);
+        print_info(format!("WebSocket (JSON-RPC) on port {}", port));
+    }
 
     if let (Some(cert), Some(key)) = (args.tls_cert, args.tls_key) {
         server = server.with_tls(cert, key);
         print_info("TLS enabled");
+        if let Some(ca) = args.tls_client_ca {
+            server = server.with_client_ca(ca);
+            print_info("Client certificates required");
+        }
     }
 
     print_success("Debug server started. Waiting for connections...");
@@ -1649,22 +1843,117 @@ pub fn server(args: ServerArgs) -> Result<()> {
     Ok(())
 }
 
+/// Serve the Debug Adapter Protocol over stdio, or TCP with `--port`
+pub fn dap(args: DapArgs) -> Result<()> {
+    match args.port {
+        Some(port) => {
+            print_info(format!("DAP server listening on 127.0.0.1:{}", port));
+            print_info("Press Ctrl+C to stop the server");
+            crate::dap::serve_tcp(port)
+        }
+        // stdout carries the protocol, so print nothing else
+        None => crate::dap::serve_stdio(),
+    }
+}
+
 /// Connect to remote debug server and run interactive session
 pub fn remote(args: RemoteArgs, _verbosity: Verbosity) -> Result<()> {
     use crate::client::RemoteClient;
+    use crate::server::tls::TlsOptions;
+    use crate::server::{DebugEvent, DebugResponse, InstructionPosition, Scope, StepKind};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
 
     print_info(format!(
         "Connecting to remote debug server at {}",
         args.remote
     ));
 
-    let mut client = RemoteClient::connect(&args.remote, args.token.clone())?;
-    print_success("Connected to debug server");
+    let tls =
+        args.tls || args.tls_ca.is_some() || args.tls_pin.is_some() || args.tls_cert.is_some();
+    let connect = || {
+        if tls {
+            let options = TlsOptions {
+                ca: args.tls_ca.clone(),
+                pin: args.tls_pin.clone(),
+                cert: args.tls_cert.clone(),
+                key: args.tls_key.clone(),
+            };
+            RemoteClient::connect_tls(&args.remote, args.token.clone(), &options)
+        } else {
+            RemoteClient::connect(&args.remote, args.token.clone())
+        }
+    };
+
+    if let Some(path) = &args.replay {
+        let entries = crate::server::transcript::read_transcript(path)?;
+        print_info(format!(
+            "Replaying {} requests from {:?}",
+            entries.len(),
+            path
+        ));
+        let report = crate::client::replay::replay(&entries, connect)?;
+        for difference in &report.differences {
+            print_warning(difference.to_string());
+        }
+        if report.differences.is_empty() {
+            print_success(format!(
+                "Replayed {} requests; all responses match",
+                report.replayed
+            ));
+            return Ok(());
+        }
+        return Err(miette::miette!(
+            "Found {} differences in {} replayed requests",
+            report.differences.len(),
+            report.replayed
+        ));
+    }
+
+    let mut client = connect()?;
+    print_success(format!(
+        "Connected to debug server (protocol version {})",
+        client.protocol_version()
+    ));
+    let print_position = |position: Option<&InstructionPosition>| match position {
+        Some(position) => println!(
+            "{}{} in {}",
+            "  ".repeat(position.depth),
+            position.instruction,
+            position.function.as_deref().unwrap_or("<unknown>")
+        ),
+        None => println!("No instruction to stop at"),
+    };
+    if client.scope() == Scope::Observer {
+        print_info("Connected as an observer: sessions can be inspected but not changed");
+    }
+
+    if let Some(name) = &args.session {
+        let exists = client.list_sessions()?.iter().any(|s| &s.id == name);
+        if exists {
+            let (paused, function) = client.attach_session(name)?;
+            print_success(format!("Attached to session {}", name));
+            if paused {
+                print_info(format!(
+                    "Session is paused in {}",
+                    function.as_deref().unwrap_or("<unknown>")
+                ));
+            }
+        } else {
+            client.create_session(Some(name))?;
+            print_success(format!("Created session {}", name));
+        }
+    }
 
     // If contract and function are provided, execute directly
     if let (Some(contract), Some(function)) = (&args.contract, &args.function) {
-        print_info(format!("Loading contract: {:?}", contract));
-        let _size = client.load_contract(&contract.to_string_lossy())?;
+        if args.upload {
+            print_info(format!("Uploading contract: {:?}", contract));
+            client.upload_contract(contract, args.expected_hash.as_deref())?;
+        } else {
+            print_info(format!("Loading contract: {:?}", contract));
+            let _size = client.load_contract(&contract.to_string_lossy())?;
+        }
 
         print_info(format!("Executing function: {}", function));
         match client.execute(function, args.args.as_deref()) {
@@ -1681,6 +1970,11 @@ pub fn remote(args: RemoteArgs, _verbosity: Verbosity) -> Result<()> {
         // Interactive mode
         print_info("Starting interactive remote debugging session");
         print_info("Type 'help' for available commands");
+        client.on_event(|event| match event {
+            // Reported by the command that triggered them
+            DebugEvent::ExecutionComplete { .. } | DebugEvent::BreakpointChanged { .. } => {}
+            event => print_info(event.to_string()),
+        });
 
         loop {
             print!("\n(remote-debug) ");
@@ -1709,6 +2003,26 @@ pub fn remote(args: RemoteArgs, _verbosity: Verbosity) -> Result<()> {
                         }
                     }
                 }
+                "upload" | "u" => {
+                    if parts.len() < 2 {
+                        print_warning("Usage: upload <contract_path> [sha256]");
+                    } else {
+                        match client.upload_contract(Path::new(parts[1]), parts.get(2).copied()) {
+                            Ok(size) => print_success(format!("Contract uploaded: {} bytes", size)),
+                            Err(e) => print_warning(format!("Failed to upload contract: {}", e)),
+                        }
+                    }
+                }
+                "upload-snapshot" => {
+                    if parts.len() < 2 {
+                        print_warning("Usage: upload-snapshot <snapshot_path> [sha256]");
+                    } else {
+                        match client.upload_snapshot(Path::new(parts[1]), parts.get(2).copied()) {
+                            Ok(summary) => print_success(format!("Snapshot loaded: {}", summary)),
+                            Err(e) => print_warning(format!("Failed to upload snapshot: {}", e)),
+                        }
+                    }
+                }
                 "exec" | "e" => {
                     if parts.len() < 2 {
                         print_warning("Usage: exec <function> [args_json]");
@@ -1743,10 +2057,119 @@ pub fn remote(args: RemoteArgs, _verbosity: Verbosity) -> Result<()> {
                     }
                     Err(e) => print_warning(format!("Inspect failed: {}", e)),
                 },
-                "storage" => match client.get_storage() {
+                "storage" if parts.len() == 1 => match client.get_storage() {
                     Ok(storage) => println!("Storage: {}", storage),
                     Err(e) => print_warning(format!("Get storage failed: {}", e)),
                 },
+                "storage" => {
+                    let key =
+                        crate::ui::repl::split_args(&parts.get(3..).unwrap_or_default().join(" "));
+                    match (parts[1], parts.get(2), key.as_deref()) {
+                        ("get", Some(storage), Ok([key])) => {
+                            match client.get_storage_entry(storage, key.clone()) {
+                                Ok(Some(value)) => println!("{}", value),
+                                Ok(None) => println!("(not set)"),
+                                Err(e) => print_warning(format!("Get storage failed: {}", e)),
+                            }
+                        }
+                        _ => print_warning(
+                            "Usage: storage get <instance|persistent|temporary> <key>",
+                        ),
+                    }
+                }
+                "events" => match client.get_contract_events() {
+                    Ok(events) if events.is_empty() => println!("No events"),
+                    Ok(events) => {
+                        for event in events {
+                            println!(
+                                "- {} [{}] {}",
+                                event.contract_id.as_deref().unwrap_or("<unknown>"),
+                                event.topics.join(", "),
+                                event.data
+                            );
+                        }
+                    }
+                    Err(e) => print_warning(format!("Get events failed: {}", e)),
+                },
+                "istep" => {
+                    if parts.len() < 2 {
+                        print_warning("Usage: istep <function> [args]");
+                    } else {
+                        let args = parts.get(2..).map(|rest| rest.join(" "));
+                        let args = args.as_deref().filter(|rest| !rest.is_empty());
+                        match client.start_instruction_stepping(parts[1], args) {
+                            Ok(position) => print_position(position.as_ref()),
+                            Err(e) => print_warning(format!("Instruction stepping failed: {}", e)),
+                        }
+                    }
+                }
+                "into" | "over" | "out" | "block" | "back" => {
+                    let kind = match parts[0] {
+                        "into" => StepKind::Into,
+                        "over" => StepKind::Over,
+                        "out" => StepKind::Out,
+                        "block" => StepKind::Block,
+                        _ => StepKind::Back,
+                    };
+                    match client.step_instruction(kind) {
+                        Ok((true, position)) => print_position(position.as_ref()),
+                        Ok((false, _)) => print_warning("Cannot step: nowhere to go"),
+                        Err(e) => print_warning(format!("Step failed: {}", e)),
+                    }
+                }
+                "context" => {
+                    let size = parts.get(1).and_then(|n| n.parse().ok()).unwrap_or(5);
+                    match client.instruction_context(size) {
+                        Ok(lines) => {
+                            for line in lines {
+                                let marker = if line.current { "=>" } else { "  " };
+                                println!("{} {:>5}  {}", marker, line.index, line.instruction);
+                            }
+                        }
+                        Err(e) => print_warning(format!("Get context failed: {}", e)),
+                    }
+                }
+                "locals" => match client.get_locals() {
+                    Ok(DebugResponse::Locals {
+                        function,
+                        locals,
+                        value_stack,
+                        stack_exact,
+                        variables,
+                    }) => {
+                        println!("Locals in {}:", function.as_deref().unwrap_or("<unknown>"));
+                        let show = |value: &crate::server::ValueInfo| {
+                            format!(
+                                "{}{} = {}",
+                                value.name.as_deref().unwrap_or("_"),
+                                value
+                                    .value_type
+                                    .as_deref()
+                                    .map(|t| format!(": {}", t))
+                                    .unwrap_or_default(),
+                                value.value.as_deref().unwrap_or("?")
+                            )
+                        };
+                        for local in &locals {
+                            println!("  {}", show(local));
+                        }
+                        if !variables.is_empty() {
+                            println!("Source variables:");
+                            for variable in &variables {
+                                println!("  {}", show(variable));
+                            }
+                        }
+                        println!(
+                            "Value stack{}:",
+                            if stack_exact { "" } else { " (approximate)" }
+                        );
+                        for slot in &value_stack {
+                            println!("  {}", show(slot));
+                        }
+                    }
+                    Ok(_) => print_warning("Unexpected response to GetLocals"),
+                    Err(e) => print_warning(format!("Get locals failed: {}", e)),
+                },
                 "stack" => match client.get_stack() {
                     Ok(stack) => println!("Call stack: {:?}", stack),
                     Err(e) => print_warning(format!("Get stack failed: {}", e)),
@@ -1790,6 +2213,78 @@ pub fn remote(args: RemoteArgs, _verbosity: Verbosity) -> Result<()> {
                     }
                     Err(e) => print_warning(format!("List breakpoints failed: {}", e)),
                 },
+                "sessions" => match client.list_sessions() {
+                    Ok(sessions) => {
+                        if sessions.is_empty() {
+                            println!("No sessions");
+                        }
+                        for session in sessions {
+                            println!(
+                                "- {}{}{} (idle {}s){}{}",
+                                session.id,
+                                session
+                                    .contract
+                                    .map(|c| format!(" {}", c))
+                                    .unwrap_or_default(),
+                                if session.paused { " [paused]" } else { "" },
+                                session.idle_secs,
+                                if session.attached { " attached" } else { "" },
+                                match session.observers {
+                                    0 => String::new(),
+                                    n => format!(", {} observing", n),
+                                },
+                            );
+                        }
+                    }
+                    Err(e) => print_warning(format!("List sessions failed: {}", e)),
+                },
+                "attach" => {
+                    if parts.len() < 2 {
+                        print_warning("Usage: attach <session>");
+                    } else {
+                        match client.attach_session(parts[1]) {
+                            Ok((paused, function)) => {
+                                print_success(format!("Attached to session {}", parts[1]));
+                                if paused {
+                                    print_info(format!(
+                                        "Session is paused in {}",
+                                        function.as_deref().unwrap_or("<unknown>")
+                                    ));
+                                }
+                            }
+                            Err(e) => print_warning(format!("Attach failed: {}", e)),
+                        }
+                    }
+                }
+                "destroy" => {
+                    if parts.len() < 2 {
+                        print_warning("Usage: destroy <session>");
+                    } else {
+                        match client.destroy_session(parts[1]) {
+                            Ok(_) => print_success(format!("Session {} destroyed", parts[1])),
+                            Err(e) => print_warning(format!("Destroy failed: {}", e)),
+                        }
+                    }
+                }
+                "watch" => {
+                    let secs = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(30);
+                    let deadline = Instant::now() + Duration::from_secs(secs);
+                    print_info(format!("Watching for events for {}s", secs));
+                    loop {
+                        let remaining = deadline.saturating_duration_since(Instant::now());
+                        if remaining.is_zero() {
+                            break;
+                        }
+                        match client.wait_for_event(remaining) {
+                            Ok(Some(event)) => print_info(event.to_string()),
+                            Ok(None) => break,
+                            Err(e) => {
+                                print_warning(format!("Watch failed: {}", e));
+                                break;
+                            }
+                        }
+                    }
+                }
                 "ping" => match client.ping() {
                     Ok(_) => print_success("Server is responsive"),
                     Err(e) => print_warning(format!("Ping failed: {}", e)),
@@ -1797,16 +2292,31 @@ pub fn remote(args: RemoteArgs, _verbosity: Verbosity) -> Result<()> {
                 "help" | "h" => {
                     println!("Remote debugger commands:");
                     println!("  load <path>          Load a contract");
+                    println!("  upload <path> [hash]  Send a local contract to the server");
+                    println!("  upload-snapshot <path> [hash]");
+                    println!("                        Send a local network snapshot");
                     println!("  exec <func> [args]    Execute a function");
                     println!("  step | s             Step execution");
+                    println!("  istep <func> [args]   Start instruction stepping at a function");
+                    println!("  into | over | out | block | back");
+                    println!("                        Step by instruction");
+                    println!("  context [n]           Show instructions around the current one");
+                    println!("  locals                Show locals and the value stack");
                     println!("  continue | c          Continue execution");
                     println!("  inspect | i           Inspect current state");
                     println!("  storage               Show storage state");
+                    println!("  storage get <kind> <key>");
+                    println!("                        Read one storage entry");
+                    println!("  events                Show contract events of the last call");
                     println!("  stack                 Show call stack");
                     println!("  budget | b            Show budget usage");
                     println!("  break <func>          Set breakpoint");
                     println!("  clear <func>          Clear breakpoint");
                     println!("  list-breaks           List breakpoints");
+                    println!("  sessions              List sessions on the server");
+                    println!("  attach <session>      Attach to a session");
+                    println!("  destroy <session>     Destroy a session");
+                    println!("  watch [secs]          Print events as they arrive (default 30s)");
                     println!("  ping                  Ping server");
                     println!("  help | h              Show this help");
                     println!("  quit | q              Exit");
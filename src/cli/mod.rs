@@ -1,5 +1,6 @@
 pub mod args;
 pub mod commands;
+pub mod watch;
 
 pub use args::{
     AnalyzeArgs, Cli, Commands, CompareArgs, CompletionsArgs, InspectArgs, InteractiveArgs,
@@ -0,0 +1,376 @@
+//! `run --watch`: re-run an invocation whenever its inputs change and show
+//! how the result, events and budget moved since the previous run.
+
+use crate::cli::args::RunArgs;
+use crate::cli::commands::{initial_storage, load_network_snapshot, parse_args, run_executor};
+use crate::debugger::engine::DebuggerEngine;
+use crate::inspector::events::{ContractEvent, EventInspector};
+use crate::ui::formatter::Formatter;
+use crate::{DebuggerError, Result};
+use notify::{RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+/// Quiet period after the last file event before re-running.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How often the loop checks for Ctrl+C while idle.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What one run of the invocation produced.
+#[derive(Debug, Clone)]
+pub struct RunOutcome {
+    pub result: std::result::Result<String, String>,
+    pub events: Vec<ContractEvent>,
+    pub cpu_instructions: u64,
+    pub memory_bytes: u64,
+}
+
+/// Differences between two runs.
+#[derive(Debug, Default)]
+pub struct RunDiff {
+    pub result: Option<(String, String)>,
+    pub added_events: Vec<String>,
+    pub removed_events: Vec<String>,
+    pub cpu: (u64, u64),
+    pub memory: (u64, u64),
+}
+
+impl RunOutcome {
+    fn result_text(&self) -> String {
+        match &self.result {
+            Ok(value) => format!("Ok({})", value),
+            Err(e) => format!("Err({})", e.lines().next().unwrap_or_default()),
+        }
+    }
+
+    /// Compare against the previous run.
+    pub fn diff(&self, previous: &RunOutcome) -> RunDiff {
+        let (before, after) = (previous.result_text(), self.result_text());
+        let describe = |e: &ContractEvent| format!("{} {}", e.topics.join(", "), e.data);
+        let old: Vec<String> = previous.events.iter().map(describe).collect();
+        let new: Vec<String> = self.events.iter().map(describe).collect();
+
+        RunDiff {
+            result: (before != after).then_some((before, after)),
+            added_events: multiset_difference(&new, &old),
+            removed_events: multiset_difference(&old, &new),
+            cpu: (previous.cpu_instructions, self.cpu_instructions),
+            memory: (previous.memory_bytes, self.memory_bytes),
+        }
+    }
+}
+
+/// Items of `a` not matched one-for-one by items of `b`, in order.
+fn multiset_difference(a: &[String], b: &[String]) -> Vec<String> {
+    let mut unmatched: Vec<&String> = b.iter().collect();
+    a.iter()
+        .filter(
+            |item| match unmatched.iter().position(|other| other == item) {
+                Some(i) => {
+                    unmatched.remove(i);
+                    false
+                }
+                None => true,
+            },
+        )
+        .cloned()
+        .collect()
+}
+
+impl RunDiff {
+    pub fn is_empty(&self) -> bool {
+        self.result.is_none()
+            && self.added_events.is_empty()
+            && self.removed_events.is_empty()
+            && self.cpu.0 == self.cpu.1
+            && self.memory.0 == self.memory.1
+    }
+
+    /// Human readable summary, one change per line.
+    pub fn format(&self) -> String {
+        if self.is_empty() {
+            return "No changes in result, events or budget".to_string();
+        }
+        let mut lines = Vec::new();
+        if let Some((before, after)) = &self.result {
+            lines.push(format!("Result: {} -> {}", before, after));
+        }
+        for event in &self.added_events {
+            lines.push(format!("Event + {}", event));
+        }
+        for event in &self.removed_events {
+            lines.push(format!("Event - {}", event));
+        }
+        for (label, (before, after)) in [("CPU", self.cpu), ("Memory", self.memory)] {
+            if before != after {
+                lines.push(format!(
+                    "{}: {} -> {} ({})",
+                    label,
+                    before,
+                    after,
+                    signed_change(before, after)
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+fn signed_change(before: u64, after: u64) -> String {
+    let delta = after as i128 - before as i128;
+    if before == 0 {
+        return format!("{:+}", delta);
+    }
+    format!("{:+}, {:+.1}%", delta, delta as f64 / before as f64 * 100.0)
+}
+
+/// Files whose changes trigger a re-run.
+fn watched_files(args: &RunArgs) -> Vec<PathBuf> {
+    let mut files = vec![args.contract.clone()];
+    files.extend(args.import_storage.clone());
+    files.extend(args.network_snapshot.clone());
+    files.extend(args.deploy.iter().cloned());
+    files.extend(args.watch_path.iter().cloned());
+    files
+}
+
+/// Load the contract and run the invocation once with a fresh environment.
+pub fn execute_once(args: &RunArgs) -> Result<RunOutcome> {
+    let wasm = crate::utils::wasm::load_wasm(&args.contract)?;
+    if let Some(expected) = &args.expected_hash {
+        if expected.to_lowercase() != wasm.sha256_hash {
+            return Err(DebuggerError::ChecksumMismatch {
+                expected: expected.clone(),
+                actual: wasm.sha256_hash,
+            }
+            .into());
+        }
+    }
+    let snapshot = load_network_snapshot(args.network_snapshot.as_deref())?;
+    let parsed_args = args.args.as_deref().map(parse_args).transpose()?;
+    let initial_storage = initial_storage(args)?;
+
+    let executor = run_executor(args, wasm.bytes, snapshot.as_ref(), initial_storage)?;
+    let mut engine = DebuggerEngine::new(executor, args.breakpoint.clone());
+    engine.set_frame_budget_estimates(args.estimate_frame_budgets);
+
+    println!("{}", Formatter::info("\n--- Execution Start ---\n"));
+    let result = engine
+        .execute(&args.function, parsed_args.as_deref())
+        .map_err(|e| e.to_string());
+    match &result {
+        Ok(value) => {
+            println!("{}", Formatter::success("\n--- Execution Complete ---\n"));
+            println!("{}", Formatter::success(format!("Result: {:?}", value)));
+        }
+        Err(e) => println!("{}", Formatter::error(format!("Error: {}", e))),
+    }
+
+    let executor = engine.executor();
+    let events = executor.get_events()?;
+    let events = match &args.filter_topic {
+        Some(topic) => EventInspector::filter_events(&events, topic),
+        None => events,
+    };
+    let (cpu_instructions, memory_bytes) = executor.budget_consumed();
+    Ok(RunOutcome {
+        result,
+        events,
+        cpu_instructions,
+        memory_bytes,
+    })
+}
+
+/// Run the invocation, then again after every change to its inputs, until
+/// Ctrl+C. Errors are reported and watching continues.
+pub fn run_watch(args: &RunArgs) -> Result<()> {
+    let files = watched_files(args);
+    let watch_err = |e: notify::Error| DebuggerError::FileError(format!("Watch failed: {}", e));
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = stop.clone();
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::SeqCst)).map_err(|e| {
+        DebuggerError::FileError(format!("Failed to install Ctrl+C handler: {}", e))
+    })?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(watch_err)?;
+    // Editors and compilers often replace files, so watch their directories
+    let mut dirs = HashSet::new();
+    for file in &files {
+        let dir = match file.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        if dirs.insert(dir.clone()) {
+            watcher
+                .watch(&dir, RecursiveMode::NonRecursive)
+                .map_err(watch_err)?;
+        }
+    }
+    let names: HashSet<_> = files.iter().filter_map(|f| f.file_name()).collect();
+
+    println!(
+        "{}",
+        Formatter::info(format!("Starting watch mode for: {:?}", args.contract))
+    );
+    println!("{}", Formatter::info("Press Ctrl+C to exit\n"));
+    println!("{}", Formatter::info("--- Initial Run ---"));
+    let mut previous = report(execute_once(args), None);
+
+    loop {
+        println!("{}", Formatter::info("\nWaiting for changes..."));
+        let Some(changed) = wait_for_change(&rx, &names, &stop) else {
+            break;
+        };
+        // Clear the screen and move the cursor home
+        print!("\x1b[2J\x1b[H");
+        println!(
+            "{}",
+            Formatter::info(format!("File changed: {:?}", changed))
+        );
+        println!("{}", Formatter::info("Re-running..."));
+        previous = report(execute_once(args), previous);
+    }
+
+    println!("{}", Formatter::info("\nWatch mode stopped"));
+    Ok(())
+}
+
+/// Print the outcome of a run and its diff against the previous one.
+fn report(outcome: Result<RunOutcome>, previous: Option<RunOutcome>) -> Option<RunOutcome> {
+    match outcome {
+        Ok(outcome) => {
+            if let Some(previous) = &previous {
+                println!("{}", Formatter::info("\n--- Changes Since Last Run ---"));
+                println!("{}", outcome.diff(previous).format());
+            }
+            Some(outcome)
+        }
+        Err(e) => {
+            println!("{}", Formatter::error(format!("Error: {}", e)));
+            previous
+        }
+    }
+}
+
+/// Block until one of the watched files changes and no further events
+/// arrive for [`DEBOUNCE`]. Returns `None` once Ctrl+C is pressed.
+fn wait_for_change(
+    rx: &mpsc::Receiver<notify::Result<notify::Event>>,
+    names: &HashSet<&std::ffi::OsStr>,
+    stop: &AtomicBool,
+) -> Option<PathBuf> {
+    let is_watched = |path: &Path| path.file_name().is_some_and(|n| names.contains(n));
+    let mut changed: Option<PathBuf> = None;
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return None;
+        }
+        let timeout = if changed.is_some() {
+            DEBOUNCE
+        } else {
+            POLL_INTERVAL
+        };
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(event)) if !event.kind.is_access() => {
+                if let Some(path) = event.paths.into_iter().find(|p| is_watched(p)) {
+                    changed = Some(path);
+                }
+            }
+            Ok(_) => {}
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if changed.is_some() {
+                    return changed;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(result: &str, events: &[&str], cpu: u64) -> RunOutcome {
+        RunOutcome {
+            result: Ok(result.to_string()),
+            events: events
+                .iter()
+                .map(|topic| ContractEvent {
+                    contract_id: None,
+                    topics: vec![topic.to_string()],
+                    data: "Void".to_string(),
+                })
+                .collect(),
+            cpu_instructions: cpu,
+            memory_bytes: 100,
+        }
+    }
+
+    #[test]
+    fn diff_reports_result_events_and_budget() {
+        let before = outcome("U32(1)", &["mint", "transfer"], 1000);
+        let after = outcome("U32(2)", &["transfer", "burn"], 1100);
+
+        let diff = after.diff(&before);
+        assert_eq!(
+            diff.result,
+            Some(("Ok(U32(1))".to_string(), "Ok(U32(2))".to_string()))
+        );
+        assert_eq!(diff.added_events, vec!["burn Void"]);
+        assert_eq!(diff.removed_events, vec!["mint Void"]);
+
+        let text = diff.format();
+        assert!(text.contains("CPU: 1000 -> 1100 (+100, +10.0%)"));
+        assert!(!text.contains("Memory"));
+    }
+
+    #[test]
+    fn identical_runs_have_no_diff() {
+        let run = outcome("U32(1)", &["mint", "mint"], 1000);
+        let diff = run.diff(&outcome("U32(1)", &["mint", "mint"], 1000));
+        assert!(diff.is_empty());
+        assert_eq!(diff.format(), "No changes in result, events or budget");
+    }
+
+    #[test]
+    fn deployed_contracts_are_deployed_and_watched() {
+        use crate::runtime::executor::ContractExecutor;
+        use crate::runtime::test_wasm;
+        use clap::Parser;
+
+        let dir = tempfile::tempdir().unwrap();
+        let relay = dir.path().join("relay.wasm");
+        let ping = dir.path().join("ping.wasm");
+        std::fs::write(&relay, test_wasm::relay()).unwrap();
+        std::fs::write(&ping, test_wasm::ping()).unwrap();
+        let callee = ContractExecutor::new(test_wasm::relay())
+            .unwrap()
+            .deploy(&test_wasm::ping())
+            .unwrap();
+
+        let args = RunArgs::try_parse_from([
+            "run".as_ref(),
+            "--contract".as_ref(),
+            relay.as_os_str(),
+            "--function".as_ref(),
+            "relay".as_ref(),
+            "--args".as_ref(),
+            format!(r#"["{}"]"#, callee).as_ref(),
+            "--deploy".as_ref(),
+            ping.as_os_str(),
+            "--watch".as_ref(),
+        ])
+        .unwrap();
+        assert!(watched_files(&args).contains(&ping));
+        let outcome = execute_once(&args).unwrap();
+        assert!(outcome.result.is_ok(), "{:?}", outcome.result);
+    }
+}
@@ -0,0 +1,1021 @@
+//! Async client for the debug server, on tokio.
+//!
+//! [`AsyncRemoteClient`] keeps a connection open from a background task.
+//! Requests can be sent concurrently and are matched to their responses by
+//! ID; events go to every [`events`](AsyncRemoteClient::events) receiver.
+//! When the connection drops, or a heartbeat `Ping` goes unanswered, the
+//! task reconnects, authenticates again and reattaches the session. The
+//! server's private session for a client without one dies with the
+//! connection, so requests then fail until the contract is loaded again.
+
+use crate::client::remote_client::decode_frame;
+use crate::server::framing::{self, FrameReader, Framing, DEFAULT_MAX_MESSAGE_SIZE};
+use crate::server::protocol::{
+    Capabilities, DebugEvent, DebugMessage, DebugRequest, DebugResponse, Scope, SessionInfo,
+    MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+use crate::server::tls::{self, TlsOptions};
+use crate::utils::wasm::load_wasm;
+use crate::{DebuggerError, Result};
+use base64::Engine as _;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval};
+use tracing::{info, warn};
+
+/// Events kept for receivers that fall behind
+const EVENT_BUFFER: usize = 1024;
+
+/// Longest wait for a connection to be set up, handshake included
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How to reconnect after the connection drops
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    /// Attempts before giving up; 0 disables reconnecting
+    pub max_attempts: u32,
+    /// Wait before the first attempt, doubled after each failed one
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Never reconnect
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Wait before the 1-based `attempt`
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Settings for [`AsyncRemoteClient::connect`]
+#[derive(Debug, Clone)]
+pub struct AsyncClientOptions {
+    /// Token sent with `Authenticate` on every connection
+    pub token: Option<String>,
+    /// Connect over TLS
+    pub tls: Option<TlsOptions>,
+    /// Ping the server this often. A ping still unanswered at the next one
+    /// counts as a lost connection, so keep it longer than the slowest
+    /// request the server runs.
+    pub heartbeat: Option<Duration>,
+    pub reconnect: ReconnectPolicy,
+    /// Ask the server to cancel requests that take longer than this
+    pub request_timeout: Option<Duration>,
+}
+
+impl Default for AsyncClientOptions {
+    fn default() -> Self {
+        Self {
+            token: None,
+            tls: None,
+            heartbeat: Some(Duration::from_secs(30)),
+            reconnect: ReconnectPolicy::default(),
+            request_timeout: None,
+        }
+    }
+}
+
+/// What was agreed with the server on the current connection
+#[derive(Debug)]
+struct Status {
+    connected: bool,
+    protocol_version: u32,
+    capabilities: Capabilities,
+    scope: Scope,
+    /// Session to reattach after reconnecting
+    session: Option<String>,
+    /// Requests ran in the private session the server makes for a client
+    /// that has none
+    private_session: bool,
+    /// The private session was lost with a dropped connection
+    session_lost: bool,
+}
+
+enum Command {
+    Request {
+        id: u64,
+        request: DebugRequest,
+        reply: oneshot::Sender<Result<DebugResponse>>,
+    },
+    /// Ask the server to drop a request that timed out
+    Cancel { request_id: u64 },
+}
+
+/// Async client for a debug server. Methods take `&self`, so one client
+/// can be shared between tasks.
+pub struct AsyncRemoteClient {
+    commands: mpsc::UnboundedSender<Command>,
+    events: broadcast::Sender<DebugEvent>,
+    status: Arc<Mutex<Status>>,
+    next_id: Arc<AtomicU64>,
+    request_timeout: Option<Duration>,
+    driver: JoinHandle<()>,
+}
+
+impl AsyncRemoteClient {
+    /// Connect to a debug server, agree on a protocol version and
+    /// authenticate. Fails if this first connection can't be set up; later
+    /// drops are handled by reconnecting.
+    pub async fn connect(addr: &str, options: AsyncClientOptions) -> Result<Self> {
+        info!("Connecting to debug server at {}", addr);
+        let status = Arc::new(Mutex::new(Status {
+            connected: false,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Capabilities::default(),
+            scope: Scope::Controller,
+            session: None,
+            private_session: false,
+            session_lost: false,
+        }));
+        let next_id = Arc::new(AtomicU64::new(0));
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+
+        let driver = Driver {
+            addr: addr.to_string(),
+            options: options.clone(),
+            status: status.clone(),
+            next_id: next_id.clone(),
+            events: events.clone(),
+            pending: HashMap::new(),
+            heartbeat: None,
+            closing: false,
+        };
+        let connection = driver.open().await?;
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let driver = tokio::spawn(driver.run(connection, receiver));
+
+        Ok(Self {
+            commands,
+            events,
+            status,
+            next_id,
+            request_timeout: options.request_timeout,
+            driver,
+        })
+    }
+
+    /// Protocol version agreed with the server
+    pub fn protocol_version(&self) -> u32 {
+        lock(&self.status).protocol_version
+    }
+
+    /// Capabilities both this client and the server support
+    pub fn capabilities(&self) -> Capabilities {
+        lock(&self.status).capabilities
+    }
+
+    /// Whether this client controls its session or only observes it
+    pub fn scope(&self) -> Scope {
+        lock(&self.status).scope
+    }
+
+    /// Whether the connection is up, as opposed to being re-established
+    pub fn is_connected(&self) -> bool {
+        lock(&self.status).connected
+    }
+
+    /// The session this client created or attached to, which is reattached
+    /// after reconnecting
+    pub fn session(&self) -> Option<String> {
+        lock(&self.status).session.clone()
+    }
+
+    /// Receive the events the server pushes from now on. A receiver that
+    /// falls more than 1024 events behind misses the oldest ones.
+    pub fn events(&self) -> broadcast::Receiver<DebugEvent> {
+        self.events.subscribe()
+    }
+
+    /// Send any request and return the server's response as it is.
+    /// Requests sent while reconnecting wait for the new connection; those
+    /// in flight when the connection drops fail, since the server may or
+    /// may not have run them.
+    pub async fn request(&self, request: DebugRequest) -> Result<DebugResponse> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (reply, mut response) = oneshot::channel();
+        self.commands
+            .send(Command::Request { id, request, reply })
+            .map_err(|_| closed())?;
+
+        if let Some(timeout) = self.request_timeout {
+            match tokio::time::timeout(timeout, &mut response).await {
+                Ok(result) => return result.map_err(|_| closed())?,
+                // A request the server already started is still answered
+                Err(_) => {
+                    let _ = self.commands.send(Command::Cancel { request_id: id });
+                }
+            }
+        }
+        response.await.map_err(|_| closed())?
+    }
+
+    /// Send a request, turning error answers into errors
+    async fn send_request(&self, request: DebugRequest) -> Result<DebugResponse> {
+        match self.request(request).await? {
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            DebugResponse::Unsupported { request } => Err(DebuggerError::ExecutionError(format!(
+                "The server does not support {} requests",
+                request
+            ))
+            .into()),
+            DebugResponse::Cancelled => Err(DebuggerError::ExecutionError(format!(
+                "Request timed out after {:?} and was cancelled",
+                self.request_timeout.unwrap_or_default()
+            ))
+            .into()),
+            response => Ok(response),
+        }
+    }
+
+    /// Ping the server
+    pub async fn ping(&self) -> Result<()> {
+        match self.send_request(DebugRequest::Ping).await? {
+            DebugResponse::Pong => Ok(()),
+            _ => Err(unexpected("Ping")),
+        }
+    }
+
+    /// Load a contract from the server's file system
+    pub async fn load_contract(&self, contract_path: &str) -> Result<usize> {
+        let request = DebugRequest::LoadContract {
+            contract_path: contract_path.to_string(),
+        };
+        match self.send_request(request).await? {
+            DebugResponse::ContractLoaded { size } => Ok(size),
+            _ => Err(unexpected("LoadContract")),
+        }
+    }
+
+    /// Send a local contract to the server and load it
+    pub async fn upload_contract(&self, contract_path: &Path) -> Result<usize> {
+        let wasm = load_wasm(contract_path)?;
+        let request = DebugRequest::UploadContract {
+            wasm_base64: base64::engine::general_purpose::STANDARD.encode(&wasm.bytes),
+            sha256: Some(wasm.sha256_hash),
+        };
+        match self.send_request(request).await? {
+            DebugResponse::ContractLoaded { size } => Ok(size),
+            _ => Err(unexpected("UploadContract")),
+        }
+    }
+
+    /// Execute a function, returning its output
+    pub async fn execute(&self, function: &str, args: Option<&str>) -> Result<String> {
+        let request = DebugRequest::Execute {
+            function: function.to_string(),
+            args: args.map(str::to_string),
+        };
+        match self.send_request(request).await? {
+            DebugResponse::ExecutionResult {
+                success: true,
+                output,
+                ..
+            } => Ok(output),
+            DebugResponse::ExecutionResult { error, .. } => Err(DebuggerError::ExecutionError(
+                error.unwrap_or_else(|| "Unknown error".to_string()),
+            )
+            .into()),
+            _ => Err(unexpected("Execute")),
+        }
+    }
+
+    /// Step execution. Returns whether it is still paused, where, and the
+    /// step count.
+    pub async fn step(&self) -> Result<(bool, Option<String>, u64)> {
+        match self.send_request(DebugRequest::Step).await? {
+            DebugResponse::StepResult {
+                paused,
+                current_function,
+                step_count,
+            } => Ok((paused, current_function, step_count)),
+            _ => Err(unexpected("Step")),
+        }
+    }
+
+    /// Continue execution. Returns whether it completed.
+    pub async fn continue_execution(&self) -> Result<bool> {
+        match self.send_request(DebugRequest::Continue).await? {
+            DebugResponse::ContinueResult { completed, .. } => Ok(completed),
+            _ => Err(unexpected("Continue")),
+        }
+    }
+
+    /// Set a breakpoint
+    pub async fn set_breakpoint(&self, function: &str) -> Result<()> {
+        let request = DebugRequest::SetBreakpoint {
+            function: function.to_string(),
+        };
+        match self.send_request(request).await? {
+            DebugResponse::BreakpointSet { .. } => Ok(()),
+            _ => Err(unexpected("SetBreakpoint")),
+        }
+    }
+
+    /// Clear a breakpoint
+    pub async fn clear_breakpoint(&self, function: &str) -> Result<()> {
+        let request = DebugRequest::ClearBreakpoint {
+            function: function.to_string(),
+        };
+        match self.send_request(request).await? {
+            DebugResponse::BreakpointCleared { .. } => Ok(()),
+            _ => Err(unexpected("ClearBreakpoint")),
+        }
+    }
+
+    /// List all breakpoints
+    pub async fn list_breakpoints(&self) -> Result<Vec<String>> {
+        match self.send_request(DebugRequest::ListBreakpoints).await? {
+            DebugResponse::BreakpointsList { breakpoints } => Ok(breakpoints),
+            _ => Err(unexpected("ListBreakpoints")),
+        }
+    }
+
+    /// Inspect the current state: function, step count, whether it is
+    /// paused, and the call stack
+    pub async fn inspect(&self) -> Result<(Option<String>, u64, bool, Vec<String>)> {
+        match self.send_request(DebugRequest::Inspect).await? {
+            DebugResponse::InspectionResult {
+                function,
+                step_count,
+                paused,
+                call_stack,
+            } => Ok((function, step_count, paused, call_stack)),
+            _ => Err(unexpected("Inspect")),
+        }
+    }
+
+    /// Get storage state as JSON
+    pub async fn get_storage(&self) -> Result<String> {
+        match self.send_request(DebugRequest::GetStorage).await? {
+            DebugResponse::StorageState { storage_json } => Ok(storage_json),
+            _ => Err(unexpected("GetStorage")),
+        }
+    }
+
+    /// Get the CPU instructions and memory bytes used
+    pub async fn get_budget(&self) -> Result<(u64, u64)> {
+        match self.send_request(DebugRequest::GetBudget).await? {
+            DebugResponse::BudgetInfo {
+                cpu_instructions,
+                memory_bytes,
+            } => Ok((cpu_instructions, memory_bytes)),
+            _ => Err(unexpected("GetBudget")),
+        }
+    }
+
+    /// Create a session that survives disconnects and attach to it.
+    /// Returns the session ID.
+    pub async fn create_session(&self, name: Option<&str>) -> Result<String> {
+        let request = DebugRequest::CreateSession {
+            name: name.map(str::to_string),
+        };
+        match self.send_request(request).await? {
+            DebugResponse::SessionCreated { session_id } => Ok(session_id),
+            _ => Err(unexpected("CreateSession")),
+        }
+    }
+
+    /// Attach to an existing session. Returns whether it is paused, and in
+    /// which function.
+    pub async fn attach_session(&self, session_id: &str) -> Result<(bool, Option<String>)> {
+        let request = DebugRequest::AttachSession {
+            session_id: session_id.to_string(),
+        };
+        match self.send_request(request).await? {
+            DebugResponse::SessionAttached {
+                paused, function, ..
+            } => Ok((paused, function)),
+            _ => Err(unexpected("AttachSession")),
+        }
+    }
+
+    /// List sessions on the server
+    pub async fn list_sessions(&self) -> Result<Vec<SessionInfo>> {
+        match self.send_request(DebugRequest::ListSessions).await? {
+            DebugResponse::SessionList { sessions } => Ok(sessions),
+            _ => Err(unexpected("ListSessions")),
+        }
+    }
+
+    /// Destroy a session
+    pub async fn destroy_session(&self, session_id: &str) -> Result<()> {
+        let request = DebugRequest::DestroySession {
+            session_id: session_id.to_string(),
+        };
+        match self.send_request(request).await? {
+            DebugResponse::SessionDestroyed { .. } => Ok(()),
+            _ => Err(unexpected("DestroySession")),
+        }
+    }
+
+    /// Disconnect from the server and stop reconnecting
+    pub async fn disconnect(self) -> Result<()> {
+        let _ = self.request(DebugRequest::Disconnect).await;
+        let Self {
+            commands, driver, ..
+        } = self;
+        drop(commands);
+        let _ = driver.await;
+        info!("Disconnected from server");
+        Ok(())
+    }
+}
+
+/// Background task that owns the connection
+struct Driver {
+    addr: String,
+    options: AsyncClientOptions,
+    status: Arc<Mutex<Status>>,
+    next_id: Arc<AtomicU64>,
+    events: broadcast::Sender<DebugEvent>,
+    /// Requests sent on the current connection, awaiting their responses
+    pending: HashMap<u64, oneshot::Sender<Result<DebugResponse>>>,
+    /// ID of the heartbeat ping awaiting its answer
+    heartbeat: Option<u64>,
+    /// Set once a `Disconnect` was sent, so the server closing the
+    /// connection isn't taken for a drop
+    closing: bool,
+}
+
+impl Driver {
+    async fn run(
+        mut self,
+        mut connection: Connection,
+        mut commands: mpsc::UnboundedReceiver<Command>,
+    ) {
+        let mut heartbeat = self
+            .options
+            .heartbeat
+            .map(|period| tokio::time::interval_at(Instant::now() + period, period));
+
+        loop {
+            let outcome = tokio::select! {
+                command = commands.recv() => match command {
+                    Some(command) => self.send(&mut connection, command).await,
+                    None => {
+                        // The client was dropped without disconnecting
+                        if !self.closing {
+                            let id = self.next_id();
+                            let _ = connection.write(id, DebugRequest::Disconnect).await;
+                        }
+                        return;
+                    }
+                },
+                message = connection.next_message() => message.map(|message| self.receive(message)),
+                _ = tick(&mut heartbeat) => self.beat(&mut connection).await,
+            };
+
+            if let Err(e) = outcome {
+                for (_, reply) in self.pending.drain() {
+                    let _ = reply.send(Err(DebuggerError::FileError(format!(
+                        "Connection lost before the server answered: {}",
+                        e
+                    ))
+                    .into()));
+                }
+                self.heartbeat = None;
+                lock(&self.status).connected = false;
+                if self.closing {
+                    return;
+                }
+                warn!("Lost connection to {}: {}", self.addr, e);
+                match self.reconnect().await {
+                    Some(reconnected) => {
+                        let mut status = lock(&self.status);
+                        if status.private_session && status.session.is_none() {
+                            warn!("The server discarded this client's unnamed session");
+                            status.private_session = false;
+                            status.session_lost = true;
+                        }
+                        drop(status);
+                        connection = reconnected;
+                        if let Some(heartbeat) = &mut heartbeat {
+                            heartbeat.reset();
+                        }
+                    }
+                    None => return,
+                }
+            }
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    async fn send(&mut self, connection: &mut Connection, command: Command) -> Result<()> {
+        match command {
+            Command::Request { id, request, reply } => {
+                if matches!(request, DebugRequest::Disconnect) {
+                    self.closing = true;
+                }
+                if request.uses_session() {
+                    let mut status = lock(&self.status);
+                    // Loading a contract starts the session over
+                    let starts_over = matches!(
+                        request,
+                        DebugRequest::LoadContract { .. } | DebugRequest::UploadContract { .. }
+                    );
+                    if status.session_lost && !starts_over {
+                        let _ = reply.send(Err(session_lost()));
+                        return Ok(());
+                    }
+                    status.session_lost = false;
+                    status.private_session |= status.session.is_none();
+                }
+                self.pending.insert(id, reply);
+                connection.write(id, request).await
+            }
+            Command::Cancel { request_id } if self.pending.contains_key(&request_id) => {
+                let id = self.next_id();
+                connection
+                    .write(id, DebugRequest::Cancel { request_id })
+                    .await
+            }
+            Command::Cancel { .. } => Ok(()),
+        }
+    }
+
+    fn receive(&mut self, message: DebugMessage) {
+        let (id, response) = match message {
+            DebugMessage {
+                event: Some(event), ..
+            } => {
+                let _ = self.events.send(event);
+                return;
+            }
+            DebugMessage {
+                id,
+                response: Some(response),
+                ..
+            } => (id, response),
+            _ => {
+                warn!("Ignoring a message without a response or event");
+                return;
+            }
+        };
+        if self.heartbeat == Some(id) {
+            self.heartbeat = None;
+            return;
+        }
+
+        match &response {
+            DebugResponse::SessionCreated { session_id }
+            | DebugResponse::SessionAttached { session_id, .. } => {
+                let mut status = lock(&self.status);
+                status.session = Some(session_id.clone());
+                status.private_session = false;
+                status.session_lost = false;
+            }
+            DebugResponse::SessionDestroyed { session_id } => {
+                let mut status = lock(&self.status);
+                if status.session.as_ref() == Some(session_id) {
+                    status.session = None;
+                }
+            }
+            _ => {}
+        }
+
+        match self.pending.remove(&id) {
+            Some(reply) => {
+                let _ = reply.send(Ok(response));
+            }
+            // The server couldn't read something we sent
+            None if id == 0 => warn!("Server rejected a message: {:?}", response),
+            // Answers to a Cancel
+            None => {}
+        }
+    }
+
+    /// Send a heartbeat ping, or give up on the connection if the last one
+    /// is still unanswered
+    async fn beat(&mut self, connection: &mut Connection) -> Result<()> {
+        if self.heartbeat.is_some() {
+            return Err(DebuggerError::FileError(
+                "The server did not answer the heartbeat".to_string(),
+            )
+            .into());
+        }
+        let id = self.next_id();
+        self.heartbeat = Some(id);
+        connection.write(id, DebugRequest::Ping).await
+    }
+
+    async fn reconnect(&self) -> Option<Connection> {
+        let policy = self.options.reconnect;
+        for attempt in 1..=policy.max_attempts {
+            tokio::time::sleep(policy.delay(attempt)).await;
+            match self.open().await {
+                Ok(connection) => {
+                    info!("Reconnected to {}", self.addr);
+                    return Some(connection);
+                }
+                Err(e) => warn!(
+                    "Reconnect attempt {} of {} failed: {}",
+                    attempt, policy.max_attempts, e
+                ),
+            }
+        }
+        warn!("Giving up on {}", self.addr);
+        None
+    }
+
+    /// Connect, agree on a protocol, authenticate and reattach the session
+    async fn open(&self) -> Result<Connection> {
+        tokio::time::timeout(CONNECT_TIMEOUT, self.set_up())
+            .await
+            .map_err(|_| {
+                DebuggerError::FileError(format!(
+                    "Timed out connecting to {} after {:?}",
+                    self.addr, CONNECT_TIMEOUT
+                ))
+            })?
+    }
+
+    async fn set_up(&self) -> Result<Connection> {
+        let mut connection = Connection::connect(&self.addr, self.options.tls.as_ref()).await?;
+
+        let response = self
+            .call(
+                &mut connection,
+                DebugRequest::Handshake {
+                    protocol_version: PROTOCOL_VERSION,
+                    capabilities: Capabilities::current(),
+                },
+            )
+            .await?;
+        let (protocol_version, capabilities) = match response {
+            DebugResponse::HandshakeResult {
+                protocol_version, ..
+            } if protocol_version < MIN_PROTOCOL_VERSION => {
+                return Err(DebuggerError::ExecutionError(format!(
+                    "Server speaks protocol version {}, but this client needs at least {}",
+                    protocol_version, MIN_PROTOCOL_VERSION
+                ))
+                .into())
+            }
+            DebugResponse::HandshakeResult {
+                protocol_version,
+                capabilities,
+                ..
+            } => (
+                protocol_version,
+                capabilities.intersect(Capabilities::current()),
+            ),
+            DebugResponse::Error { message } => {
+                return Err(DebuggerError::ExecutionError(message).into())
+            }
+            _ => return Err(unexpected("Handshake")),
+        };
+        if capabilities.content_length {
+            connection.framing = Framing::ContentLength;
+        }
+
+        let mut scope = Scope::Controller;
+        if let Some(token) = &self.options.token {
+            let request = DebugRequest::Authenticate {
+                token: token.clone(),
+            };
+            match self.call(&mut connection, request).await? {
+                DebugResponse::Authenticated {
+                    success: true,
+                    scope: granted,
+                    ..
+                } => scope = granted.unwrap_or(Scope::Controller),
+                DebugResponse::Authenticated { message, .. } => {
+                    return Err(DebuggerError::ExecutionError(format!(
+                        "Authentication failed: {}",
+                        message
+                    ))
+                    .into())
+                }
+                _ => return Err(unexpected("Authenticate")),
+            }
+        }
+
+        let session = lock(&self.status).session.clone();
+        if let Some(session_id) = session {
+            let request = DebugRequest::AttachSession {
+                session_id: session_id.clone(),
+            };
+            match self.call(&mut connection, request).await? {
+                DebugResponse::SessionAttached { .. } => {
+                    info!("Reattached to session {}", session_id)
+                }
+                DebugResponse::Error { message } => {
+                    return Err(DebuggerError::ExecutionError(format!(
+                        "Failed to reattach to session {}: {}",
+                        session_id, message
+                    ))
+                    .into())
+                }
+                _ => return Err(unexpected("AttachSession")),
+            }
+        }
+
+        let mut status = lock(&self.status);
+        status.connected = true;
+        status.protocol_version = protocol_version;
+        status.capabilities = capabilities;
+        status.scope = scope;
+        Ok(connection)
+    }
+
+    /// Send a request and wait for its response, while setting up a
+    /// connection
+    async fn call(
+        &self,
+        connection: &mut Connection,
+        request: DebugRequest,
+    ) -> Result<DebugResponse> {
+        let id = self.next_id();
+        connection.write(id, request).await?;
+        loop {
+            match connection.next_message().await? {
+                DebugMessage {
+                    event: Some(event), ..
+                } => {
+                    let _ = self.events.send(event);
+                }
+                DebugMessage {
+                    id: reply,
+                    response: Some(response),
+                    ..
+                } if reply == id => return Ok(response),
+                _ => {}
+            }
+        }
+    }
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// One connection to the server, plain or TLS
+struct Connection {
+    stream: Box<dyn Stream>,
+    frames: FrameReader,
+    /// Framing for requests; `Content-Length` once the server agrees to it
+    framing: Framing,
+}
+
+impl Connection {
+    async fn connect(addr: &str, tls: Option<&TlsOptions>) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await.map_err(|e| {
+            DebuggerError::FileError(format!("Failed to connect to {}: {}", addr, e))
+        })?;
+        let stream: Box<dyn Stream> = match tls {
+            Some(options) => {
+                let connector = tokio_rustls::TlsConnector::from(tls::client_config(options)?);
+                let stream = connector
+                    .connect(tls::server_name(addr)?, stream)
+                    .await
+                    .map_err(|e| {
+                        DebuggerError::FileError(format!(
+                            "TLS handshake with {} failed: {}",
+                            addr, e
+                        ))
+                    })?;
+                Box::new(stream)
+            }
+            None => Box::new(stream),
+        };
+        Ok(Self {
+            stream,
+            frames: FrameReader::new(DEFAULT_MAX_MESSAGE_SIZE),
+            framing: Framing::Line,
+        })
+    }
+
+    async fn write(&mut self, id: u64, request: DebugRequest) -> Result<()> {
+        let json = serde_json::to_string(&DebugMessage::request(id, request))
+            .map_err(|e| DebuggerError::FileError(format!("Failed to serialize request: {}", e)))?;
+        let mut frame = Vec::new();
+        framing::write_frame(&mut frame, self.framing, &json)
+            .map_err(|e| DebuggerError::FileError(format!("Failed to frame request: {}", e)))?;
+        let written = match self.stream.write_all(&frame).await {
+            Ok(()) => self.stream.flush().await,
+            Err(e) => Err(e),
+        };
+        written.map_err(|e| {
+            DebuggerError::FileError(format!("Failed to write to stream: {}", e)).into()
+        })
+    }
+
+    /// Read the next message. Safe to cancel: bytes read so far stay
+    /// buffered for the next call.
+    async fn next_message(&mut self) -> Result<DebugMessage> {
+        let mut chunk = [0; 8192];
+        loop {
+            while let Some((_, frame)) = self.frames.buffered_frame() {
+                if let Some(message) = decode_frame(frame)? {
+                    return Ok(message);
+                }
+            }
+            match self.stream.read(&mut chunk).await {
+                Ok(0) => {
+                    return Err(
+                        DebuggerError::FileError("Connection closed by server".to_string()).into(),
+                    )
+                }
+                Ok(n) => self.frames.extend(&chunk[..n]),
+                Err(e) => {
+                    return Err(
+                        DebuggerError::FileError(format!("Failed to read response: {}", e)).into(),
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Wait for the next heartbeat, forever without one
+async fn tick(heartbeat: &mut Option<Interval>) {
+    match heartbeat {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+fn lock(status: &Mutex<Status>) -> MutexGuard<'_, Status> {
+    status.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn session_lost() -> miette::Report {
+    DebuggerError::ExecutionError(
+        "The connection dropped and the server discarded this client's unnamed session, \
+         with its contract and state. Load the contract again, or use create_session for \
+         a session that survives reconnecting"
+            .to_string(),
+    )
+    .into()
+}
+
+fn closed() -> miette::Report {
+    DebuggerError::FileError("The connection to the debug server is closed".to_string()).into()
+}
+
+fn unexpected(request: &str) -> miette::Report {
+    DebuggerError::ExecutionError(format!("Unexpected response to {}", request)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use crate::server::DebugServer;
+    use tokio::net::TcpListener;
+
+    /// Forward connections on a local port to `target`. Sending on the
+    /// returned channel cuts the current connections.
+    async fn proxy(target: String) -> (String, broadcast::Sender<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (cut, _) = broadcast::channel(1);
+        let cuts = cut.clone();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                let mut server = TcpStream::connect(&target).await.unwrap();
+                let mut cut = cuts.subscribe();
+                tokio::spawn(async move {
+                    tokio::select! {
+                        _ = tokio::io::copy_bidirectional(&mut client, &mut server) => {}
+                        _ = cut.recv() => {}
+                    }
+                });
+            }
+        });
+        (addr, cut)
+    }
+
+    #[test]
+    fn reconnect_delays_back_off_up_to_the_limit() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(800));
+        assert_eq!(policy.delay(30), policy.max_delay);
+    }
+
+    #[tokio::test]
+    async fn requests_events_and_reconnecting_to_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let contract = dir.path().join("ping.wasm");
+        std::fs::write(&contract, test_wasm::ping()).unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_addr = listener.local_addr().unwrap().to_string();
+        let server = DebugServer::new(0, Some("secret".to_string()));
+        std::thread::spawn(move || server.serve(listener));
+        let (addr, cut) = proxy(server_addr).await;
+
+        let options = AsyncClientOptions {
+            token: Some("secret".to_string()),
+            reconnect: ReconnectPolicy {
+                initial_delay: Duration::from_millis(10),
+                ..ReconnectPolicy::default()
+            },
+            ..AsyncClientOptions::default()
+        };
+        let client = AsyncRemoteClient::connect(&addr, options).await.unwrap();
+        assert!(client.capabilities().events);
+        let mut events = client.events();
+
+        let session = client.create_session(Some("harness")).await.unwrap();
+        let path = contract.to_string_lossy().into_owned();
+        let (size, ()) = tokio::try_join!(client.load_contract(&path), client.ping()).unwrap();
+        assert_eq!(size, test_wasm::ping().len());
+        client.set_breakpoint("ping").await.unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            DebugEvent::BreakpointChanged { .. }
+        ));
+
+        cut.send(()).unwrap();
+        // Requests fail while the drop goes unnoticed, then wait for the
+        // new connection
+        let mut breakpoints = client.list_breakpoints().await;
+        if breakpoints.is_err() {
+            breakpoints = client.list_breakpoints().await;
+        }
+        assert_eq!(breakpoints.unwrap(), vec!["ping".to_string()]);
+        assert!(client.is_connected());
+        assert_eq!(client.session(), Some(session));
+
+        client.disconnect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn losing_the_unnamed_session_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let contract = dir.path().join("ping.wasm");
+        std::fs::write(&contract, test_wasm::ping()).unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_addr = listener.local_addr().unwrap().to_string();
+        let server = DebugServer::new(0, None);
+        std::thread::spawn(move || server.serve(listener));
+        let (addr, cut) = proxy(server_addr).await;
+
+        let options = AsyncClientOptions {
+            reconnect: ReconnectPolicy {
+                initial_delay: Duration::from_millis(10),
+                ..ReconnectPolicy::default()
+            },
+            ..AsyncClientOptions::default()
+        };
+        let client = AsyncRemoteClient::connect(&addr, options).await.unwrap();
+        let path = contract.to_string_lossy().into_owned();
+        client.load_contract(&path).await.unwrap();
+        client.set_breakpoint("ping").await.unwrap();
+
+        cut.send(()).unwrap();
+        let lost = |result: &Result<Vec<String>>| {
+            result
+                .as_ref()
+                .is_err_and(|e| e.to_string().contains("unnamed session"))
+        };
+        let mut breakpoints = client.list_breakpoints().await;
+        if !lost(&breakpoints) {
+            // The drop went unnoticed until this request
+            assert!(breakpoints.is_err());
+            breakpoints = client.list_breakpoints().await;
+        }
+        assert!(lost(&breakpoints), "{:?}", breakpoints);
+        assert!(client.ping().await.is_ok());
+
+        client.load_contract(&path).await.unwrap();
+        assert!(client.list_breakpoints().await.unwrap().is_empty());
+
+        client.disconnect().await.unwrap();
+    }
+}
@@ -1,3 +1,6 @@
+pub mod async_client;
 pub mod remote_client;
+pub mod replay;
 
+pub use async_client::{AsyncClientOptions, AsyncRemoteClient, ReconnectPolicy};
 pub use remote_client::RemoteClient;
@@ -1,30 +1,85 @@
-use crate::server::protocol::{DebugMessage, DebugRequest, DebugResponse};
+use crate::server::framing::{self, Frame, FrameReader, Framing, DEFAULT_MAX_MESSAGE_SIZE};
+use crate::server::protocol::{
+    Capabilities, ContractEventInfo, DebugEvent, DebugMessage, DebugRequest, DebugResponse,
+    InstructionLine, InstructionPosition, Scope, SessionInfo, StepKind, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
+};
+use crate::server::tls::{self, TlsOptions};
+use crate::utils::wasm::{compute_wasm_sha256, load_wasm};
 use crate::{DebuggerError, Result};
-use std::io::{BufRead, BufReader, Write};
+use base64::Engine as _;
+use rustls::{ClientConnection, StreamOwned};
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
 use std::net::TcpStream;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use tracing::info;
 
+/// Callback for events pushed by the server
+pub type EventHandler = Box<dyn FnMut(&DebugEvent) + Send>;
+
 /// Remote client for connecting to a debug server
 pub struct RemoteClient {
-    stream: TcpStream,
+    stream: Transport,
+    reader: FrameReader,
+    /// Framing for requests; `Content-Length` once the server agrees to it
+    framing: Framing,
     message_id: u64,
     authenticated: bool,
+    scope: Scope,
+    protocol_version: u32,
+    capabilities: Capabilities,
+    /// Events received while waiting for responses, when no handler is set
+    events: VecDeque<DebugEvent>,
+    event_handler: Option<EventHandler>,
+    request_timeout: Option<Duration>,
 }
 
 impl RemoteClient {
     /// Connect to a remote debug server
     pub fn connect(addr: &str, token: Option<String>) -> Result<Self> {
+        Self::connect_with(addr, token, None)
+    }
+
+    /// Connect to a remote debug server over TLS
+    pub fn connect_tls(addr: &str, token: Option<String>, tls: &TlsOptions) -> Result<Self> {
+        Self::connect_with(addr, token, Some(tls))
+    }
+
+    fn connect_with(addr: &str, token: Option<String>, tls: Option<&TlsOptions>) -> Result<Self> {
         info!("Connecting to debug server at {}", addr);
         let stream = TcpStream::connect(addr).map_err(|e| {
             DebuggerError::FileError(format!("Failed to connect to {}: {}", addr, e))
         })?;
 
+        let transport = match tls {
+            Some(options) => {
+                let connection =
+                    ClientConnection::new(tls::client_config(options)?, tls::server_name(addr)?)
+                        .map_err(|e| {
+                            DebuggerError::FileError(format!("TLS setup failed: {}", e))
+                        })?;
+                Transport::Tls(Box::new(StreamOwned::new(connection, stream)))
+            }
+            None => Transport::Plain(stream),
+        };
         let mut client = Self {
-            stream,
+            stream: transport,
+            reader: FrameReader::new(DEFAULT_MAX_MESSAGE_SIZE),
+            framing: Framing::Line,
             message_id: 0,
             authenticated: token.is_none(),
+            scope: Scope::Controller,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Capabilities::default(),
+            events: VecDeque::new(),
+            event_handler: None,
+            request_timeout: None,
         };
 
+        client.handshake()?;
+
         // Authenticate if token is provided
         if let Some(token) = token {
             client.authenticate(&token)?;
@@ -33,6 +88,60 @@ impl RemoteClient {
         Ok(client)
     }
 
+    /// Agree on a protocol version and capabilities with the server
+    fn handshake(&mut self) -> Result<()> {
+        let response = self.send_request(DebugRequest::Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Capabilities::current(),
+        })?;
+
+        match response {
+            DebugResponse::HandshakeResult {
+                protocol_version,
+                server_version,
+                capabilities,
+            } => {
+                if protocol_version < MIN_PROTOCOL_VERSION {
+                    return Err(DebuggerError::ExecutionError(format!(
+                        "Server speaks protocol version {}, but this client needs at least {}",
+                        protocol_version, MIN_PROTOCOL_VERSION
+                    ))
+                    .into());
+                }
+                info!(
+                    "Server {} speaks protocol version {}",
+                    server_version, protocol_version
+                );
+                self.protocol_version = protocol_version;
+                self.capabilities = capabilities.intersect(Capabilities::current());
+                if self.capabilities.content_length {
+                    self.framing = Framing::ContentLength;
+                }
+                Ok(())
+            }
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(
+                DebuggerError::ExecutionError("Unexpected response to Handshake".to_string())
+                    .into(),
+            ),
+        }
+    }
+
+    /// Protocol version agreed with the server
+    pub fn protocol_version(&self) -> u32 {
+        self.protocol_version
+    }
+
+    /// Capabilities both this client and the server support
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Whether this client controls its session or only observes it
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+
     /// Authenticate with the server
     pub fn authenticate(&mut self, token: &str) -> Result<()> {
         let response = self.send_request(DebugRequest::Authenticate {
@@ -40,9 +149,14 @@ impl RemoteClient {
         })?;
 
         match response {
-            DebugResponse::Authenticated { success, message } => {
+            DebugResponse::Authenticated {
+                success,
+                message,
+                scope,
+            } => {
                 if success {
                     self.authenticated = true;
+                    self.scope = scope.unwrap_or(Scope::Controller);
                     info!("Authentication successful");
                     Ok(())
                 } else {
@@ -79,6 +193,32 @@ impl RemoteClient {
         }
     }
 
+    /// Send a local contract to the server and load it. The server checks
+    /// it against `expected_hash`, or against the local file's hash.
+    pub fn upload_contract(
+        &mut self,
+        contract_path: &Path,
+        expected_hash: Option<&str>,
+    ) -> Result<usize> {
+        let wasm = load_wasm(contract_path)?;
+        let response = self.send_request(DebugRequest::UploadContract {
+            wasm_base64: base64::engine::general_purpose::STANDARD.encode(&wasm.bytes),
+            sha256: Some(expected_hash.map_or(wasm.sha256_hash, str::to_string)),
+        })?;
+
+        match response {
+            DebugResponse::ContractLoaded { size } => {
+                info!("Contract uploaded: {} bytes", size);
+                Ok(size)
+            }
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to UploadContract".to_string(),
+            )
+            .into()),
+        }
+    }
+
     /// Execute a function on the remote server
     pub fn execute(&mut self, function: &str, args: Option<&str>) -> Result<String> {
         let response = self.send_request(DebugRequest::Execute {
@@ -138,6 +278,109 @@ impl RemoteClient {
         }
     }
 
+    /// Enable instruction stepping and stop at the first instruction of
+    /// `function`
+    pub fn start_instruction_stepping(
+        &mut self,
+        function: &str,
+        args: Option<&str>,
+    ) -> Result<Option<InstructionPosition>> {
+        let response = self.send_request(DebugRequest::StartInstructionStepping {
+            function: function.to_string(),
+            args: args.map(str::to_string),
+        })?;
+
+        match response {
+            DebugResponse::InstructionStepped { position, .. } => Ok(position),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to StartInstructionStepping".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Step by instruction. Returns whether it stepped, and where it is
+    /// paused now.
+    pub fn step_instruction(
+        &mut self,
+        kind: StepKind,
+    ) -> Result<(bool, Option<InstructionPosition>)> {
+        let response = self.send_request(DebugRequest::StepInstruction { kind })?;
+
+        match response {
+            DebugResponse::InstructionStepped { stepped, position } => Ok((stepped, position)),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to StepInstruction".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Up to `context_size` instructions either side of the current one
+    pub fn instruction_context(&mut self, context_size: usize) -> Result<Vec<InstructionLine>> {
+        let response = self.send_request(DebugRequest::GetInstructionContext { context_size })?;
+
+        match response {
+            DebugResponse::InstructionContext { instructions } => Ok(instructions),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to GetInstructionContext".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Locals, operand stack and source variables at the current instruction
+    pub fn get_locals(&mut self) -> Result<DebugResponse> {
+        let response = self.send_request(DebugRequest::GetLocals)?;
+
+        match response {
+            DebugResponse::Locals { .. } => Ok(response),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(
+                DebuggerError::ExecutionError("Unexpected response to GetLocals".to_string())
+                    .into(),
+            ),
+        }
+    }
+
+    /// Read one storage entry (`instance`, `persistent` or `temporary`)
+    pub fn get_storage_entry(
+        &mut self,
+        storage: &str,
+        key: serde_json::Value,
+    ) -> Result<Option<String>> {
+        let response = self.send_request(DebugRequest::GetStorageEntry {
+            storage: storage.to_string(),
+            key,
+        })?;
+
+        match response {
+            DebugResponse::StorageValue { value, .. } => Ok(value),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to GetStorageEntry".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Contract events emitted by the last invocation
+    pub fn get_contract_events(&mut self) -> Result<Vec<ContractEventInfo>> {
+        let response = self.send_request(DebugRequest::GetContractEvents)?;
+
+        match response {
+            DebugResponse::ContractEvents { events } => Ok(events),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to GetContractEvents".to_string(),
+            )
+            .into()),
+        }
+    }
+
     /// Inspect current state
     pub fn inspect(&mut self) -> Result<(Option<String>, u64, bool, Vec<String>)> {
         let response = self.send_request(DebugRequest::Inspect)?;
@@ -290,6 +533,182 @@ impl RemoteClient {
         }
     }
 
+    /// Send a local network snapshot to the server and load it, checked
+    /// against `expected_hash` or the local file's hash
+    pub fn upload_snapshot(
+        &mut self,
+        snapshot_path: &Path,
+        expected_hash: Option<&str>,
+    ) -> Result<String> {
+        let snapshot_json = std::fs::read_to_string(snapshot_path).map_err(|e| {
+            DebuggerError::FileError(format!(
+                "Failed to read snapshot file {:?}: {}",
+                snapshot_path, e
+            ))
+        })?;
+        let sha256 = expected_hash.map_or_else(
+            || compute_wasm_sha256(snapshot_json.as_bytes()),
+            str::to_string,
+        );
+        let response = self.send_request(DebugRequest::UploadSnapshot {
+            snapshot_json,
+            sha256: Some(sha256),
+        })?;
+
+        match response {
+            DebugResponse::SnapshotLoaded { summary } => {
+                info!("Snapshot uploaded: {}", summary);
+                Ok(summary)
+            }
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to UploadSnapshot".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Read `length` bytes of linear memory starting at `address`
+    pub fn inspect_memory(&mut self, address: u32, length: usize) -> Result<Vec<u8>> {
+        let response = self.send_request(DebugRequest::InspectMemory { address, length })?;
+
+        match response {
+            DebugResponse::MemoryContents { hex, .. } => hex::decode(hex).map_err(|e| {
+                DebuggerError::ExecutionError(format!("Invalid memory contents: {}", e)).into()
+            }),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to InspectMemory".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Search linear memory for a hex byte or quoted string pattern
+    pub fn search_memory(&mut self, pattern: &str) -> Result<Vec<u32>> {
+        let response = self.send_request(DebugRequest::SearchMemory {
+            pattern: pattern.to_string(),
+        })?;
+
+        match response {
+            DebugResponse::MemorySearchResult { addresses } => Ok(addresses),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to SearchMemory".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Decode a string or integer from linear memory
+    pub fn decode_memory(
+        &mut self,
+        address: u32,
+        kind: &str,
+        max_len: Option<usize>,
+    ) -> Result<String> {
+        let response = self.send_request(DebugRequest::DecodeMemory {
+            address,
+            kind: kind.to_string(),
+            max_len,
+        })?;
+
+        match response {
+            DebugResponse::MemoryValue { value, .. } => Ok(value),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to DecodeMemory".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Get linear memory size and growth
+    pub fn get_memory_info(&mut self) -> Result<DebugResponse> {
+        let response = self.send_request(DebugRequest::GetMemoryInfo)?;
+
+        match response {
+            DebugResponse::MemoryInfo { .. } => Ok(response),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to GetMemoryInfo".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Create a session that survives disconnects and attach to it.
+    /// Returns the session ID.
+    pub fn create_session(&mut self, name: Option<&str>) -> Result<String> {
+        let response = self.send_request(DebugRequest::CreateSession {
+            name: name.map(|s| s.to_string()),
+        })?;
+
+        match response {
+            DebugResponse::SessionCreated { session_id } => {
+                info!("Session {} created", session_id);
+                Ok(session_id)
+            }
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to CreateSession".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Attach to an existing session. Returns whether it is paused, and in
+    /// which function.
+    pub fn attach_session(&mut self, session_id: &str) -> Result<(bool, Option<String>)> {
+        let response = self.send_request(DebugRequest::AttachSession {
+            session_id: session_id.to_string(),
+        })?;
+
+        match response {
+            DebugResponse::SessionAttached {
+                paused, function, ..
+            } => {
+                info!("Attached to session {}", session_id);
+                Ok((paused, function))
+            }
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to AttachSession".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// List sessions on the server
+    pub fn list_sessions(&mut self) -> Result<Vec<SessionInfo>> {
+        let response = self.send_request(DebugRequest::ListSessions)?;
+
+        match response {
+            DebugResponse::SessionList { sessions } => Ok(sessions),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to ListSessions".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    /// Destroy a session
+    pub fn destroy_session(&mut self, session_id: &str) -> Result<()> {
+        let response = self.send_request(DebugRequest::DestroySession {
+            session_id: session_id.to_string(),
+        })?;
+
+        match response {
+            DebugResponse::SessionDestroyed { .. } => Ok(()),
+            DebugResponse::Error { message } => Err(DebuggerError::ExecutionError(message).into()),
+            _ => Err(DebuggerError::ExecutionError(
+                "Unexpected response to DestroySession".to_string(),
+            )
+            .into()),
+        }
+    }
+
     /// Ping the server
     pub fn ping(&mut self) -> Result<()> {
         let response = self.send_request(DebugRequest::Ping)?;
@@ -305,6 +724,47 @@ impl RemoteClient {
         }
     }
 
+    /// Give up on requests that take longer than `timeout`. The server is
+    /// asked to cancel the request, which only works while it is still
+    /// waiting its turn; one already running is answered when it finishes.
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
+
+    /// Call `handler` for each event the server pushes, as it arrives,
+    /// instead of queueing it for [`take_events`](Self::take_events).
+    pub fn on_event(&mut self, handler: impl FnMut(&DebugEvent) + Send + 'static) {
+        self.event_handler = Some(Box::new(handler));
+    }
+
+    /// Events received so far that no handler has consumed.
+    pub fn take_events(&mut self) -> Vec<DebugEvent> {
+        self.events.drain(..).collect()
+    }
+
+    /// Wait up to `timeout` for the next event. Events already received are
+    /// returned first.
+    pub fn wait_for_event(&mut self, timeout: Duration) -> Result<Option<DebugEvent>> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+        self.set_read_timeout(Some(timeout))?;
+        let message = self.read_message();
+        self.set_read_timeout(None)?;
+
+        match message {
+            Ok(Some(DebugMessage {
+                event: Some(event), ..
+            })) => Ok(Some(event)),
+            Ok(Some(_)) => Err(DebuggerError::ExecutionError(
+                "Unexpected response without a request".to_string(),
+            )
+            .into()),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Disconnect from the server
     pub fn disconnect(&mut self) -> Result<()> {
         let _ = self.send_request(DebugRequest::Disconnect);
@@ -314,10 +774,30 @@ impl RemoteClient {
 
     /// Send a request and wait for response
     fn send_request(&mut self, request: DebugRequest) -> Result<DebugResponse> {
+        match self.request(request)? {
+            DebugResponse::Unsupported { request } => Err(DebuggerError::ExecutionError(format!(
+                "The server does not support {} requests",
+                request
+            ))
+            .into()),
+            DebugResponse::Cancelled => Err(DebuggerError::ExecutionError(format!(
+                "Request timed out after {:?} and was cancelled",
+                self.request_timeout.unwrap_or_default()
+            ))
+            .into()),
+            response => Ok(response),
+        }
+    }
+
+    /// Send any request and return the server's response as it is. Events
+    /// that arrive meanwhile go to the handler or the queue.
+    pub fn request(&mut self, request: DebugRequest) -> Result<DebugResponse> {
         if !self.authenticated
             && !matches!(
                 request,
-                DebugRequest::Authenticate { .. } | DebugRequest::Ping
+                DebugRequest::Handshake { .. }
+                    | DebugRequest::Authenticate { .. }
+                    | DebugRequest::Ping
             )
         {
             return Err(DebuggerError::ExecutionError(
@@ -327,37 +807,305 @@ impl RemoteClient {
         }
 
         self.message_id += 1;
-        let message = DebugMessage::request(self.message_id, request);
+        let id = self.message_id;
+        self.write_request(id, request)?;
+
+        // Read the response, dispatching any events that arrive first
+        let deadline = self.request_timeout.map(|timeout| Instant::now() + timeout);
+        let mut cancelled = false;
+        loop {
+            let timeout = deadline.filter(|_| !cancelled).map(|deadline| {
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .max(Duration::from_millis(1))
+            });
+            self.set_read_timeout(timeout)?;
+            let message = self.read_message();
+            self.set_read_timeout(None)?;
+
+            let Some(message) = message? else {
+                // Timed out: drop the request if the server hasn't started it
+                self.message_id += 1;
+                self.write_request(self.message_id, DebugRequest::Cancel { request_id: id })?;
+                cancelled = true;
+                continue;
+            };
+            match message {
+                DebugMessage {
+                    event: Some(event), ..
+                } => self.dispatch(event),
+                DebugMessage {
+                    id: reply_id,
+                    response: Some(response),
+                    ..
+                } if reply_id == id => return Ok(response),
+                // The server couldn't read what we sent
+                DebugMessage {
+                    id: 0,
+                    response: Some(DebugResponse::Error { message }),
+                    ..
+                } => return Err(DebuggerError::ExecutionError(message).into()),
+                // Answers to a Cancel
+                DebugMessage {
+                    response: Some(_), ..
+                } => {}
+                _ => {
+                    return Err(DebuggerError::FileError(
+                        "Response message has no response field".to_string(),
+                    )
+                    .into())
+                }
+            }
+        }
+    }
 
-        let request_json = serde_json::to_string(&message)
+    fn write_request(&mut self, id: u64, request: DebugRequest) -> Result<()> {
+        let json = serde_json::to_string(&DebugMessage::request(id, request))
             .map_err(|e| DebuggerError::FileError(format!("Failed to serialize request: {}", e)))?;
+        framing::write_frame(&mut self.stream, self.framing, &json).map_err(|e| {
+            DebuggerError::FileError(format!("Failed to write to stream: {}", e)).into()
+        })
+    }
 
-        // Send request
-        writeln!(self.stream, "{}", request_json)
-            .map_err(|e| DebuggerError::FileError(format!("Failed to write to stream: {}", e)))?;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
         self.stream
-            .flush()
-            .map_err(|e| DebuggerError::FileError(format!("Failed to flush stream: {}", e)))?;
-
-        // Read response
-        let reader = BufReader::new(&self.stream);
-        let mut lines = reader.lines();
-        let response_line = lines
-            .next()
-            .ok_or_else(|| DebuggerError::FileError("No response from server".to_string()))?
-            .map_err(|e| DebuggerError::FileError(format!("Failed to read response: {}", e)))?;
-
-        let response_message: DebugMessage = serde_json::from_str(&response_line)
-            .map_err(|e| DebuggerError::FileError(format!("Failed to parse response: {}", e)))?;
-
-        response_message.response.ok_or_else(|| {
-            DebuggerError::FileError("Response message has no response field".to_string()).into()
-        })
+            .socket()
+            .set_read_timeout(timeout)
+            .map_err(|e| DebuggerError::FileError(format!("Failed to set timeout: {}", e)).into())
+    }
+
+    /// Read one message, skipping events this client doesn't know. Returns
+    /// `None` on a read timeout; a closed connection is an error.
+    fn read_message(&mut self) -> Result<Option<DebugMessage>> {
+        loop {
+            let frame = match self.reader.next_frame(&mut self.stream) {
+                Ok(Some((_, frame))) => frame,
+                Ok(None) => {
+                    return Err(
+                        DebuggerError::FileError("Connection closed by server".to_string()).into(),
+                    )
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(None)
+                }
+                Err(e) => {
+                    return Err(
+                        DebuggerError::FileError(format!("Failed to read response: {}", e)).into(),
+                    )
+                }
+            };
+            if let Some(message) = decode_frame(frame)? {
+                return Ok(Some(message));
+            }
+        }
+    }
+
+    fn dispatch(&mut self, event: DebugEvent) {
+        match &mut self.event_handler {
+            Some(handler) => handler(&event),
+            None => self.events.push_back(event),
+        }
+    }
+}
+
+/// Connection to the server, plain or TLS
+enum Transport {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Transport {
+    fn socket(&self) -> &TcpStream {
+        match self {
+            Self::Plain(stream) => stream,
+            Self::Tls(stream) => &stream.sock,
+        }
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf),
+            Self::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buf),
+            Self::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            Self::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Parse a frame from the server. Events this client doesn't know are
+/// skipped and give `None`.
+pub(crate) fn decode_frame(frame: Frame) -> Result<Option<DebugMessage>> {
+    let line = match frame {
+        Frame::Message(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Frame::TooLarge(size) => {
+            return Err(DebuggerError::FileError(format!(
+                "Server sent a message of {} bytes, over the {} byte limit",
+                size, DEFAULT_MAX_MESSAGE_SIZE
+            ))
+            .into())
+        }
+        Frame::InvalidHeader(header) => {
+            return Err(
+                DebuggerError::FileError(format!("Invalid header from server: {}", header)).into(),
+            )
+        }
+    };
+    match serde_json::from_str(&line) {
+        Ok(message) => Ok(Some(message)),
+        Err(e) if is_event(&line) => {
+            info!("Ignoring unknown event from server: {}", e);
+            Ok(None)
+        }
+        Err(e) => Err(DebuggerError::FileError(format!("Failed to parse response: {}", e)).into()),
     }
 }
 
+/// Whether `line` is an event message, known to this client or not
+fn is_event(line: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(line)
+        .is_ok_and(|value| value.get("event").is_some_and(|e| !e.is_null()))
+}
+
 impl Drop for RemoteClient {
     fn drop(&mut self) {
         let _ = self.disconnect();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Serve one client: answer the handshake, then reply to each request
+    /// with the messages `respond` returns, in the request's framing.
+    fn fake_server(
+        respond: impl Fn(u64, DebugRequest) -> Vec<String> + Send + 'static,
+    ) -> (String, std::thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = FrameReader::new(DEFAULT_MAX_MESSAGE_SIZE);
+            // Serve until the client hangs up
+            while let Some((framing, frame)) = reader.next_frame(&mut stream).unwrap() {
+                let Frame::Message(bytes) = frame else {
+                    panic!("unexpected frame: {:?}", frame);
+                };
+                let message: DebugMessage = serde_json::from_slice(&bytes).unwrap();
+                let lines = match message.request.unwrap() {
+                    DebugRequest::Handshake { .. } => {
+                        let response = DebugResponse::HandshakeResult {
+                            protocol_version: PROTOCOL_VERSION,
+                            server_version: "test".to_string(),
+                            capabilities: Capabilities::current(),
+                        };
+                        let message = DebugMessage::response(message.id, response);
+                        vec![serde_json::to_string(&message).unwrap()]
+                    }
+                    request => respond(message.id, request),
+                };
+                for line in lines {
+                    framing::write_frame(&mut stream, framing, &line).unwrap();
+                }
+            }
+        });
+        (addr, server)
+    }
+
+    #[test]
+    fn events_before_a_response_are_queued_or_handled() {
+        let (addr, server) = fake_server(|id, _| {
+            let stopped = DebugMessage::event(DebugEvent::Stopped {
+                reason: "breakpoint".to_string(),
+                function: Some("transfer".to_string()),
+            });
+            let pong = DebugMessage::response(id, DebugResponse::Pong);
+            vec![
+                r#"{"id":0,"event":{"type":"FromTheFuture"}}"#.to_string(),
+                serde_json::to_string(&stopped).unwrap(),
+                serde_json::to_string(&pong).unwrap(),
+            ]
+        });
+
+        let mut client = RemoteClient::connect(&addr, None).unwrap();
+        client.ping().unwrap();
+        let events = client.take_events();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], DebugEvent::Stopped { reason, .. } if reason == "breakpoint"));
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        client.on_event(move |event| tx.send(event.clone()).unwrap());
+        client.ping().unwrap();
+        assert!(rx.try_recv().is_ok());
+        assert!(client.take_events().is_empty());
+        assert!(client
+            .wait_for_event(Duration::from_millis(50))
+            .unwrap()
+            .is_none());
+
+        drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn the_handshake_agrees_on_capabilities_and_unsupported_requests_fail() {
+        let (addr, server) = fake_server(|id, _| {
+            let response = DebugResponse::Unsupported {
+                request: "Ping".to_string(),
+            };
+            vec![serde_json::to_string(&DebugMessage::response(id, response)).unwrap()]
+        });
+
+        let mut client = RemoteClient::connect(&addr, None).unwrap();
+        assert_eq!(client.protocol_version(), PROTOCOL_VERSION);
+        assert!(client.capabilities().events);
+        let error = client.ping().unwrap_err().to_string();
+        assert!(error.contains("does not support Ping"), "{}", error);
+
+        drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn requests_that_time_out_are_cancelled() {
+        let (addr, server) = fake_server(|id, request| {
+            let reply = |id, response| serde_json::to_string(&DebugMessage::response(id, response));
+            match request {
+                // Never answered until cancelled
+                DebugRequest::Execute { .. } => vec![],
+                DebugRequest::Cancel { request_id } => vec![
+                    reply(id, DebugResponse::CancelAccepted { request_id }).unwrap(),
+                    reply(request_id, DebugResponse::Cancelled).unwrap(),
+                ],
+                _ => vec![reply(id, DebugResponse::Pong).unwrap()],
+            }
+        });
+
+        let mut client = RemoteClient::connect(&addr, None).unwrap();
+        assert_eq!(client.framing, Framing::ContentLength);
+        client.set_request_timeout(Some(Duration::from_millis(50)));
+        let error = client.execute("slow", None).unwrap_err().to_string();
+        assert!(error.contains("cancelled"), "{}", error);
+        client.ping().unwrap();
+
+        drop(client);
+        server.join().unwrap();
+    }
+}
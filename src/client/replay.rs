@@ -0,0 +1,220 @@
+//! Replaying a server transcript against another server.
+
+use crate::client::RemoteClient;
+use crate::server::protocol::{Capabilities, DebugRequest, PROTOCOL_VERSION};
+use crate::server::transcript::TranscriptEntry;
+use crate::utils::wasm::compute_wasm_sha256;
+use crate::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Fields that legitimately change from run to run
+const VOLATILE_FIELDS: &[&str] = &["server_version", "idle_secs"];
+
+/// A request whose answer differs from the transcript
+#[derive(Debug, Clone)]
+pub struct Difference {
+    /// 1-based position of the entry in the transcript
+    pub entry: usize,
+    pub connection: u64,
+    /// Request type, e.g. `Step`
+    pub request: String,
+    pub expected: Value,
+    pub actual: Value,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Entry {} ({} on connection {}):\n  expected: {}\n  actual:   {}",
+            self.entry, self.request, self.connection, self.expected, self.actual
+        )
+    }
+}
+
+/// Outcome of a replay
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    /// Requests sent to the server
+    pub replayed: usize,
+    pub differences: Vec<Difference>,
+}
+
+/// Send the requests in `entries` to a server, one client per recorded
+/// connection, and compare the responses and events with the recorded
+/// ones. `connect` opens and authenticates each client.
+///
+/// `Authenticate` requests are skipped since transcripts don't keep
+/// tokens. For `LoadContract`, the contract on disk is also checked
+/// against the recorded hash.
+pub fn replay(
+    entries: &[TranscriptEntry],
+    mut connect: impl FnMut() -> Result<RemoteClient>,
+) -> Result<ReplayReport> {
+    let mut clients: HashMap<u64, RemoteClient> = HashMap::new();
+    let mut report = ReplayReport::default();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let Some(request) = entry.request.request.clone() else {
+            continue;
+        };
+        if matches!(request, DebugRequest::Authenticate { .. }) {
+            continue;
+        }
+        let kind = serde_json::to_value(&request)
+            .ok()
+            .and_then(|value| Some(value.get("type")?.as_str()?.to_string()))
+            .unwrap_or_default();
+        let mut difference = |expected, actual| {
+            report.differences.push(Difference {
+                entry: index + 1,
+                connection: entry.connection,
+                request: kind.clone(),
+                expected,
+                actual,
+            })
+        };
+
+        if let (DebugRequest::LoadContract { contract_path }, Some(recorded)) =
+            (&request, &entry.wasm_sha256)
+        {
+            if let Ok(bytes) = std::fs::read(contract_path) {
+                let hash = compute_wasm_sha256(&bytes);
+                if &hash != recorded {
+                    difference(
+                        json!({ "wasm_sha256": recorded }),
+                        json!({ "wasm_sha256": hash }),
+                    );
+                }
+            }
+        }
+
+        let client = match clients.entry(entry.connection) {
+            std::collections::hash_map::Entry::Occupied(client) => client.into_mut(),
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                let mut client = connect()?;
+                // Start from the capabilities of a fresh connection, as the
+                // recorded client did
+                client.request(DebugRequest::Handshake {
+                    protocol_version: PROTOCOL_VERSION,
+                    capabilities: Capabilities::default(),
+                })?;
+                slot.insert(client)
+            }
+        };
+
+        let disconnect = matches!(request, DebugRequest::Disconnect);
+        let actual = match client.request(request) {
+            // Events follow the response; the answer to a ping comes after them
+            Ok(response) if disconnect => answer(json!(response), json!(client.take_events())),
+            Ok(response) => match client.ping() {
+                Ok(()) => answer(json!(response), json!(client.take_events())),
+                Err(e) => json!({ "error": e.to_string() }),
+            },
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+        report.replayed += 1;
+        if disconnect {
+            clients.remove(&entry.connection);
+        }
+
+        let recorded_response = entry.responses.iter().find_map(|m| m.response.as_ref());
+        let recorded_events: Vec<_> = entry
+            .responses
+            .iter()
+            .filter_map(|m| m.event.as_ref())
+            .collect();
+        let expected = answer(json!(recorded_response), json!(recorded_events));
+        if expected != actual {
+            difference(expected, actual);
+        }
+    }
+
+    for (_, mut client) in clients {
+        let _ = client.disconnect();
+    }
+    Ok(report)
+}
+
+/// A response and its events, without the fields that change between runs
+fn answer(response: Value, events: Value) -> Value {
+    let mut value = json!({ "response": response, "events": events });
+    strip_volatile(&mut value);
+    value
+}
+
+fn strip_volatile(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for field in VOLATILE_FIELDS {
+                map.remove(*field);
+            }
+            map.values_mut().for_each(strip_volatile);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_volatile),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use crate::server::transcript::read_transcript;
+    use crate::server::{DebugResponse, DebugServer};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+
+    fn serve(transcript: Option<PathBuf>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let mut server = DebugServer::new(0, None);
+        if let Some(path) = transcript {
+            server = server.with_transcript(path);
+        }
+        std::thread::spawn(move || server.serve(listener));
+        addr
+    }
+
+    #[test]
+    fn replaying_a_transcript_reports_changed_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let contract = dir.path().join("ping.wasm");
+        std::fs::write(&contract, test_wasm::ping()).unwrap();
+        let transcript = dir.path().join("transcript.jsonl");
+
+        let addr = serve(Some(transcript.clone()));
+        let mut client = RemoteClient::connect(&addr, None).unwrap();
+        client.create_session(Some("ci")).unwrap();
+        client.load_contract(&contract.to_string_lossy()).unwrap();
+        client.set_breakpoint("ping").unwrap();
+        client.execute("ping", None).unwrap();
+        client.get_budget().unwrap();
+        client.list_sessions().unwrap();
+        client.disconnect().unwrap();
+
+        let mut entries = read_transcript(&transcript).unwrap();
+        assert!(entries
+            .iter()
+            .any(|entry| entry.wasm_sha256 == Some(compute_wasm_sha256(&test_wasm::ping()))));
+
+        let fresh = serve(None);
+        let report = replay(&entries, || RemoteClient::connect(&fresh, None)).unwrap();
+        assert_eq!(report.replayed, entries.len());
+        assert!(report.differences.is_empty(), "{:?}", report.differences);
+
+        // A server that answers differently is caught
+        let ping = entries
+            .iter()
+            .position(|entry| matches!(entry.request.request, Some(DebugRequest::ListSessions)))
+            .unwrap();
+        entries[ping].responses[0].response = Some(DebugResponse::Pong);
+        let fresh = serve(None);
+        let report = replay(&entries, || RemoteClient::connect(&fresh, None)).unwrap();
+        assert_eq!(report.differences.len(), 1);
+        assert_eq!(report.differences[0].entry, ping + 1);
+        assert_eq!(report.differences[0].request, "ListSessions");
+    }
+}
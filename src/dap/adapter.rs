@@ -0,0 +1,1158 @@
+//! A DAP session that maps requests onto a [`DebuggerEngine`].
+//!
+//! One session debugs one invocation. A breakpoint on the invoked function
+//! pauses before it runs. Source-line breakpoints and stepping walk the
+//! function's instructions, like the REPL's instruction stepping, and step
+//! into contracts it calls. They need DWARF debug info to map instructions
+//! to lines. Resuming past the last breakpoint runs the invocation and ends
+//! the session.
+
+use crate::dap::protocol::{OutgoingMessage, Request};
+use crate::debugger::engine::DebuggerEngine;
+use crate::debugger::evaluator::Evaluator;
+use crate::debugger::instruction_pointer::StepMode;
+use crate::debugger::source_map::{SourceLocation, SourceMap};
+use crate::debugger::Session;
+use crate::runtime::executor::ContractExecutor;
+use crate::runtime::instruction::Instruction;
+use crate::ui::formatter::Formatter;
+use crate::utils::wasm::{load_wasm, parse_function_exports, parse_function_signatures};
+use crate::{DebuggerError, Result};
+use serde_json::{json, Value};
+use soroban_env_host::xdr::ScVal;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const THREAD_ID: i64 = 1;
+const ARGUMENTS_REF: i64 = 1;
+const LOCALS_REF: i64 = 2;
+const STORAGE_REF: i64 = 3;
+const BUDGET_REF: i64 = 4;
+const VALUE_STACK_REF: i64 = 5;
+
+/// A breakpoint on a source line.
+#[derive(Debug, Clone)]
+pub struct LineBreakpoint {
+    pub id: i64,
+    pub line: u32,
+    pub condition: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Into,
+    Over,
+    Out,
+    Back,
+}
+
+/// State of one debug adapter session.
+#[derive(Default)]
+pub struct DapAdapter {
+    engine: Option<DebuggerEngine>,
+    function: String,
+    args: Option<String>,
+    stop_on_entry: bool,
+    /// Exported function names by function index
+    exports: HashMap<u32, String>,
+    function_conditions: HashMap<String, String>,
+    line_breakpoints: HashMap<PathBuf, Vec<LineBreakpoint>>,
+    next_breakpoint_id: i64,
+    evaluator: Evaluator,
+    /// Events to send after the current response
+    events: Vec<OutgoingMessage>,
+    paused: bool,
+    terminated: bool,
+    finished: bool,
+}
+
+impl DapAdapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the client has disconnected.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Handle a request, returning its response followed by any events.
+    pub fn handle(&mut self, request: &Request) -> Vec<OutgoingMessage> {
+        let args = &request.arguments;
+        let result = match request.command.as_str() {
+            "initialize" => Ok(Some(capabilities())),
+            "launch" => self.launch(args).map(|_| None),
+            "attach" => self.attach(args).map(|_| None),
+            "setBreakpoints" => self.set_breakpoints(args).map(Some),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(args).map(Some),
+            "setExceptionBreakpoints" => Ok(Some(json!({ "breakpoints": [] }))),
+            "configurationDone" => self.start().map(|_| None),
+            "threads" => Ok(Some(
+                json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+            )),
+            "stackTrace" => self.stack_trace().map(Some),
+            "scopes" => self.scopes().map(Some),
+            "variables" => self.variables(args).map(Some),
+            "continue" => self
+                .require_paused()
+                .and_then(|_| self.resume(false))
+                .map(|_| Some(json!({ "allThreadsContinued": true }))),
+            "next" => self.step(Step::Over, args).map(|_| None),
+            "stepIn" => self.step(Step::Into, args).map(|_| None),
+            "stepOut" => self.step(Step::Out, args).map(|_| None),
+            "stepBack" => self.step(Step::Back, args).map(|_| None),
+            "evaluate" => self.evaluate(args).map(Some),
+            // Invocations run synchronously, so there is nothing to interrupt
+            "pause" => Ok(None),
+            "terminate" => {
+                self.terminate();
+                Ok(None)
+            }
+            "disconnect" => {
+                self.finished = true;
+                Ok(None)
+            }
+            other => Err(miette::miette!("Unsupported request: {}", other)),
+        };
+
+        let response = match result {
+            Ok(body) => OutgoingMessage::Response {
+                request_seq: request.seq,
+                success: true,
+                command: request.command.clone(),
+                message: None,
+                body,
+            },
+            Err(e) => OutgoingMessage::Response {
+                request_seq: request.seq,
+                success: false,
+                command: request.command.clone(),
+                message: Some(e.to_string()),
+                body: None,
+            },
+        };
+        let mut messages = vec![response];
+        messages.append(&mut self.events);
+        messages
+    }
+
+    fn launch(&mut self, args: &Value) -> Result<()> {
+        let path = str_arg(args, "contractPath")
+            .or_else(|| str_arg(args, "program"))
+            .ok_or_else(|| miette::miette!("launch requires `contractPath`"))?;
+        let wasm = load_wasm(path)?;
+        let mut executor = ContractExecutor::new(wasm.bytes.clone())?;
+        if let Some(snapshot) = str_arg(args, "snapshot") {
+            executor.load_snapshot(Path::new(snapshot))?;
+        }
+        let mocks: Vec<String> = args
+            .get("mocks")
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|m| m.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !mocks.is_empty() {
+            executor.set_mock_specs(&mocks)?;
+        }
+        for path in args
+            .get("contracts")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            let address = executor.deploy(&load_wasm(path)?.bytes)?;
+            self.output("console", format!("Deployed {} at {}\n", path, address));
+        }
+        self.begin(DebuggerEngine::new(executor, Vec::new()), &wasm.bytes, args)
+    }
+
+    /// Attach to a session saved with `session save`.
+    fn attach(&mut self, args: &Value) -> Result<()> {
+        let path = str_arg(args, "session")
+            .ok_or_else(|| miette::miette!("attach requires `session`, a saved session file"))?;
+        let session = Session::load(Path::new(path))?;
+        self.begin(session.restore()?, &session.wasm_bytes()?, args)
+    }
+
+    fn begin(&mut self, mut engine: DebuggerEngine, wasm: &[u8], args: &Value) -> Result<()> {
+        if self.engine.is_some() {
+            return Err(miette::miette!("A contract is already being debugged"));
+        }
+        let function =
+            str_arg(args, "function").ok_or_else(|| miette::miette!("`function` is required"))?;
+        self.exports = parse_function_exports(wasm)?
+            .into_iter()
+            .map(|(name, index)| (index, name))
+            .collect();
+        if !self.exports.values().any(|name| name == function) {
+            return Err(DebuggerError::InvalidFunction(function.to_string()).into());
+        }
+
+        let instruction_debug = args
+            .get("instructionDebug")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        if instruction_debug && !engine.is_instruction_debug_enabled() {
+            engine.enable_instruction_debug(wasm)?;
+        }
+        engine.set_console_output(false);
+        // A timeout would exit the adapter and end the debug session
+        engine.executor_mut().set_timeout(0);
+
+        self.function = function.to_string();
+        self.args = match args.get("args") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(value) => Some(value.to_string()),
+        };
+        self.stop_on_entry = args
+            .get("stopOnEntry")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        self.engine = Some(engine);
+        self.events
+            .push(OutgoingMessage::event("initialized", json!({})));
+        Ok(())
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Result<Value> {
+        let path = args
+            .pointer("/source/path")
+            .and_then(Value::as_str)
+            .ok_or_else(|| miette::miette!("setBreakpoints requires `source.path`"))?;
+        let source_map = self.engine.as_ref().and_then(|e| e.source_map());
+
+        let mut breakpoints = Vec::new();
+        let mut results = Vec::new();
+        for requested in args
+            .get("breakpoints")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let line = requested.get("line").and_then(Value::as_u64).unwrap_or(0) as u32;
+            self.next_breakpoint_id += 1;
+            let id = self.next_breakpoint_id;
+
+            let verified = source_map.is_some_and(|m| m.contains_line(Path::new(path), line));
+            let mut result = json!({ "id": id, "verified": verified, "line": line });
+            if !verified {
+                result["message"] = json!(match source_map {
+                    Some(_) => "No code at this line",
+                    None => "Line breakpoints need DWARF debug info and instruction debugging",
+                });
+            }
+            results.push(result);
+            breakpoints.push(LineBreakpoint {
+                id,
+                line,
+                condition: condition_arg(requested),
+            });
+        }
+
+        self.line_breakpoints
+            .insert(PathBuf::from(path), breakpoints);
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    fn set_function_breakpoints(&mut self, args: &Value) -> Result<Value> {
+        let engine = self.engine.as_mut().ok_or_else(no_program)?;
+        engine.breakpoints_mut().clear();
+        self.function_conditions.clear();
+
+        let mut results = Vec::new();
+        for requested in args
+            .get("breakpoints")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let name = requested.get("name").and_then(Value::as_str).unwrap_or("");
+            engine.breakpoints_mut().add(name);
+            if let Some(condition) = condition_arg(requested) {
+                self.function_conditions.insert(name.to_string(), condition);
+            }
+
+            self.next_breakpoint_id += 1;
+            let verified = self.exports.values().any(|export| export == name);
+            let mut result = json!({ "id": self.next_breakpoint_id, "verified": verified });
+            if !verified {
+                result["message"] = json!(format!("No exported function named `{}`", name));
+            }
+            results.push(result);
+        }
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    /// Handle `configurationDone`: position at the invoked function and run
+    /// to the first breakpoint.
+    fn start(&mut self) -> Result<()> {
+        let engine = self.engine.as_mut().ok_or_else(no_program)?;
+        if engine.is_instruction_debug_enabled() {
+            engine.start_instruction_stepping(StepMode::StepInto)?;
+            engine.prepare_invocation(&self.function, self.args.as_deref())?;
+        }
+
+        if self.stop_on_entry {
+            self.stop("entry", &[]);
+            return Ok(());
+        }
+        let breaks = engine.breakpoints_mut().should_break(&self.function);
+        let condition = self.function_conditions.get(&self.function).cloned();
+        if breaks && self.condition_holds(condition.as_deref()) {
+            self.stop("function breakpoint", &[]);
+            return Ok(());
+        }
+        self.resume(true)
+    }
+
+    /// Run to the next line breakpoint, returning from callees into their
+    /// callers, or to the end of the invocation.
+    fn resume(&mut self, mut include_current: bool) -> Result<()> {
+        loop {
+            for (index, id, condition) in self.line_breakpoint_hits(include_current) {
+                if self.condition_holds(condition.as_deref()) {
+                    if let Some(engine) = self.engine.as_mut() {
+                        engine.jump_to_instruction(index);
+                    }
+                    self.stop("breakpoint", &[id]);
+                    return Ok(());
+                }
+            }
+            let engine = self.engine.as_mut().ok_or_else(no_program)?;
+            if module_depth(engine) == 0 || !engine.step_out()? {
+                return self.run_to_completion();
+            }
+            include_current = true;
+        }
+    }
+
+    fn line_breakpoint_hits(&self, include_current: bool) -> Vec<(usize, i64, Option<String>)> {
+        let Some(engine) = &self.engine else {
+            return Vec::new();
+        };
+        let Some(source_map) = engine.source_map() else {
+            return Vec::new();
+        };
+        let state = engine.state();
+        let Ok(state) = state.lock() else {
+            return Vec::new();
+        };
+        find_line_breakpoints(
+            state.instructions(),
+            state.instruction_pointer().current_index(),
+            include_current,
+            source_map,
+            &self.line_breakpoints,
+        )
+    }
+
+    fn step(&mut self, kind: Step, args: &Value) -> Result<()> {
+        self.require_paused()?;
+        let engine = self.engine.as_mut().ok_or_else(no_program)?;
+        if !engine.is_instruction_debug_enabled() {
+            self.output(
+                "console",
+                "Stepping needs instruction debugging; running to completion\n".to_string(),
+            );
+            return self.run_to_completion();
+        }
+
+        let granularity = args.get("granularity").and_then(Value::as_str);
+        let Some((depth, function, start)) = position(engine) else {
+            return self.run_to_completion();
+        };
+        loop {
+            let stepped = match kind {
+                Step::Into => engine.step_into()?,
+                Step::Over => engine.step_over()?,
+                Step::Out => engine.step_out()?,
+                Step::Back => engine.step_back()?,
+            };
+            let current = position(engine);
+            if current.as_ref().is_some_and(|(d, _, _)| *d != depth) {
+                // Entered a callee or returned to the caller
+                break;
+            }
+            if !stepped || current.as_ref().map(|(_, f, _)| *f) != Some(function) {
+                // Calls within a contract are not entered, so leaving the
+                // invoked function means it is done
+                if kind != Step::Back {
+                    return self.run_to_completion();
+                }
+                if stepped {
+                    engine.step_into()?;
+                }
+                break;
+            }
+            let by_line = engine.source_map().is_some() && granularity != Some("instruction");
+            if !by_line || kind == Step::Out {
+                break;
+            }
+            let location = current.and_then(|(_, _, location)| location);
+            if location.is_some() && location != start {
+                break;
+            }
+        }
+        self.stop("step", &[]);
+        Ok(())
+    }
+
+    fn run_to_completion(&mut self) -> Result<()> {
+        let engine = self.engine.as_mut().ok_or_else(no_program)?;
+        let result = engine.execute(&self.function, self.args.as_deref());
+
+        let exit_code = match result {
+            Ok(value) => {
+                self.output("stdout", format!("Result: {}\n", value));
+                0
+            }
+            Err(e) => {
+                self.output("stderr", format!("Error: {}\n", e));
+                1
+            }
+        };
+        self.events.push(OutgoingMessage::event(
+            "exited",
+            json!({ "exitCode": exit_code }),
+        ));
+        self.paused = false;
+        self.terminate();
+        Ok(())
+    }
+
+    /// One frame per contract being stepped through, innermost first,
+    /// named `function [contract]`.
+    fn stack_trace(&self) -> Result<Value> {
+        self.require_paused()?;
+        let engine = self.engine.as_ref().ok_or_else(no_program)?;
+        let state = engine.state();
+        let state = state
+            .lock()
+            .map_err(|_| miette::miette!("Debug state is unavailable"))?;
+
+        let modules = state.modules();
+        let root = modules.len() - 1;
+        let mut frames = Vec::new();
+        for (depth, (module, inst)) in modules.into_iter().enumerate() {
+            let id = depth as i64 + 1;
+            let Some(inst) = inst else {
+                frames.push(json!({ "id": id, "name": self.function, "line": 0, "column": 0 }));
+                continue;
+            };
+            let function = match &module.function {
+                Some(name) if module.function_index == Some(inst.function_index) => name.clone(),
+                _ if depth == root => self.function_name(inst.function_index),
+                _ => format!("func[{}]", inst.function_index),
+            };
+            let name = match &module.contract_id {
+                Some(contract) => format!("{} [{}]", function, short_contract_id(contract)),
+                None => function,
+            };
+            let mut frame = json!({
+                "id": id,
+                "name": name,
+                "line": 0,
+                "column": 0,
+                "instructionPointerReference": format!("0x{:x}", inst.offset),
+            });
+            if let Some(location) = engine
+                .module_source_map(depth)
+                .and_then(|m| m.lookup(inst.offset))
+            {
+                frame["source"] = source(&location.file);
+                frame["line"] = json!(location.line);
+                frame["column"] = json!(location.column.unwrap_or(1));
+            }
+            frames.push(frame);
+        }
+        Ok(json!({ "totalFrames": frames.len(), "stackFrames": frames }))
+    }
+
+    fn scopes(&self) -> Result<Value> {
+        self.require_paused()?;
+        let engine = self.engine.as_ref().ok_or_else(no_program)?;
+        let mut scopes = vec![scope("Arguments", ARGUMENTS_REF, Some("arguments"))];
+        if engine.is_instruction_debug_enabled() {
+            scopes.push(scope("Locals", LOCALS_REF, Some("locals")));
+            scopes.push(scope("Value Stack", VALUE_STACK_REF, None));
+        }
+        scopes.push(scope("Storage", STORAGE_REF, None));
+        scopes.push(scope("Budget", BUDGET_REF, None));
+        Ok(json!({ "scopes": scopes }))
+    }
+
+    fn variables(&self, args: &Value) -> Result<Value> {
+        let engine = self.engine.as_ref().ok_or_else(no_program)?;
+        let variables = match args.get("variablesReference").and_then(Value::as_i64) {
+            Some(ARGUMENTS_REF) => self.argument_variables(engine),
+            Some(LOCALS_REF) => local_variables(engine),
+            Some(VALUE_STACK_REF) => value_stack_variables(engine),
+            Some(STORAGE_REF) => engine
+                .executor()
+                .storage_entries()
+                .iter()
+                .map(|(kind, key, value)| {
+                    variable(
+                        format!("{}: {}", kind.name(), Formatter::format_sc_val(key)),
+                        Formatter::format_sc_val(value),
+                        None,
+                    )
+                })
+                .collect(),
+            Some(BUDGET_REF) => {
+                let (cpu, memory) = engine.executor().budget_consumed();
+                vec![
+                    variable("cpu_instructions".into(), cpu.to_string(), Some("u64")),
+                    variable("memory_bytes".into(), memory.to_string(), Some("u64")),
+                ]
+            }
+            _ => Vec::new(),
+        };
+        Ok(json!({ "variables": variables }))
+    }
+
+    fn argument_variables(&self, engine: &DebuggerEngine) -> Vec<Value> {
+        let values = match self.args.as_deref().map(serde_json::from_str::<Value>) {
+            Some(Ok(Value::Array(items))) => items,
+            Some(Ok(value)) => vec![value],
+            _ => Vec::new(),
+        };
+        let params = parse_function_signatures(engine.executor().wasm_bytes())
+            .ok()
+            .and_then(|signatures| signatures.into_iter().find(|s| s.name == self.function))
+            .map(|signature| signature.params)
+            .unwrap_or_default();
+
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let param = params.get(i);
+                variable(
+                    param.map_or_else(|| format!("arg{}", i), |p| p.name.clone()),
+                    value.to_string(),
+                    param.map(|p| p.type_name.as_str()),
+                )
+            })
+            .collect()
+    }
+
+    fn evaluate(&mut self, args: &Value) -> Result<Value> {
+        let expression = str_arg(args, "expression")
+            .ok_or_else(|| miette::miette!("evaluate requires `expression`"))?;
+        let engine = self.engine.as_ref().ok_or_else(no_program)?;
+        let evaluation = self.evaluator.evaluate(engine.executor(), expression)?;
+        Ok(json!({
+            "result": Formatter::format_sc_val(&evaluation.value),
+            "variablesReference": 0,
+        }))
+    }
+
+    /// A breakpoint condition is met unless it evaluates to `false` or
+    /// `()`. Conditions that fail to evaluate stop so the error is seen.
+    fn condition_holds(&mut self, condition: Option<&str>) -> bool {
+        let (Some(condition), Some(engine)) = (condition, &self.engine) else {
+            return true;
+        };
+        match self.evaluator.evaluate(engine.executor(), condition) {
+            Ok(evaluation) => !matches!(evaluation.value, ScVal::Bool(false) | ScVal::Void),
+            Err(e) => {
+                self.output(
+                    "stderr",
+                    format!("Breakpoint condition `{}` failed: {}\n", condition, e),
+                );
+                true
+            }
+        }
+    }
+
+    fn require_paused(&self) -> Result<()> {
+        if self.engine.is_none() {
+            return Err(no_program());
+        }
+        if !self.paused {
+            return Err(miette::miette!("The contract is not paused"));
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self, reason: &str, hit_breakpoints: &[i64]) {
+        self.paused = true;
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+        if !hit_breakpoints.is_empty() {
+            body["hitBreakpointIds"] = json!(hit_breakpoints);
+        }
+        self.events.push(OutgoingMessage::event("stopped", body));
+    }
+
+    fn terminate(&mut self) {
+        if !self.terminated {
+            self.terminated = true;
+            self.events
+                .push(OutgoingMessage::event("terminated", json!({})));
+        }
+    }
+
+    fn output(&mut self, category: &str, output: String) {
+        self.events.push(OutgoingMessage::event(
+            "output",
+            json!({ "category": category, "output": output }),
+        ));
+    }
+
+    fn function_name(&self, index: u32) -> String {
+        self.exports
+            .get(&index)
+            .cloned()
+            .unwrap_or_else(|| format!("func[{}]", index))
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsFunctionBreakpoints": true,
+        "supportsConditionalBreakpoints": true,
+        "supportsEvaluateForHovers": true,
+        "supportsStepBack": true,
+        "supportsSteppingGranularity": true,
+        "supportsTerminateRequest": true,
+    })
+}
+
+/// Line breakpoints reached walking forward from `current` to the end of its
+/// function, as `(instruction index, breakpoint id, condition)`. Only the
+/// first instruction of each line counts, so resuming from a breakpoint does
+/// not hit it again.
+fn find_line_breakpoints(
+    instructions: &[Instruction],
+    current: usize,
+    include_current: bool,
+    source_map: &SourceMap,
+    breakpoints: &HashMap<PathBuf, Vec<LineBreakpoint>>,
+) -> Vec<(usize, i64, Option<String>)> {
+    let Some(function) = instructions.get(current).map(|inst| inst.function_index) else {
+        return Vec::new();
+    };
+    let (start, mut previous) = if include_current {
+        (current, None)
+    } else {
+        (current + 1, source_map.lookup(instructions[current].offset))
+    };
+
+    let mut hits = Vec::new();
+    for (index, inst) in instructions.iter().enumerate().skip(start) {
+        if inst.function_index != function {
+            break;
+        }
+        let location = source_map.lookup(inst.offset);
+        if location == previous {
+            continue;
+        }
+        previous = location.clone();
+        let Some(location) = location else {
+            continue;
+        };
+        for (file, lines) in breakpoints {
+            for bp in lines.iter().filter(|bp| location.matches(file, bp.line)) {
+                hits.push((index, bp.id, bp.condition.clone()));
+            }
+        }
+    }
+    hits
+}
+
+/// Module depth, function index and source location of the current
+/// instruction.
+fn position(engine: &DebuggerEngine) -> Option<(usize, u32, Option<SourceLocation>)> {
+    let inst = engine.current_instruction()?;
+    let location = engine.source_map().and_then(|m| m.lookup(inst.offset));
+    Some((module_depth(engine), inst.function_index, location))
+}
+
+fn module_depth(engine: &DebuggerEngine) -> usize {
+    engine
+        .state()
+        .lock()
+        .map(|state| state.module_depth())
+        .unwrap_or(0)
+}
+
+/// `CABC…WXYZ` for a contract address.
+fn short_contract_id(contract_id: &str) -> String {
+    if contract_id.len() > 12 {
+        format!(
+            "{}…{}",
+            &contract_id[..4],
+            &contract_id[contract_id.len() - 4..]
+        )
+    } else {
+        contract_id.to_string()
+    }
+}
+
+fn local_variables(engine: &DebuggerEngine) -> Vec<Value> {
+    let Ok(snapshot) = engine.inspect_locals() else {
+        return Vec::new();
+    };
+    let sources = snapshot.variables.iter().map(|v| {
+        variable(
+            v.variable.name.clone(),
+            v.value
+                .map_or_else(|| "<unavailable>".to_string(), |x| x.to_string()),
+            v.variable.type_name.as_deref(),
+        )
+    });
+    let locals = snapshot.locals.iter().map(|local| {
+        variable(
+            local
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("local{}", local.index)),
+            local
+                .value
+                .map_or_else(|| "<unknown>".to_string(), |x| x.to_string()),
+            Some(&local.value_type),
+        )
+    });
+    sources.chain(locals).collect()
+}
+
+/// The operand stack, top first, after a note when it is only approximate.
+fn value_stack_variables(engine: &DebuggerEngine) -> Vec<Value> {
+    let Ok(snapshot) = engine.inspect_locals() else {
+        return Vec::new();
+    };
+    let note = (!snapshot.stack_exact).then(|| {
+        variable(
+            "note".to_string(),
+            "approximate: passed an instruction whose stack effect is not modelled".to_string(),
+            None,
+        )
+    });
+    let slots = snapshot
+        .value_stack
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(depth, slot)| {
+            variable(
+                format!("[{}]", depth),
+                slot.value
+                    .map_or_else(|| "<unknown>".to_string(), |x| x.to_string()),
+                Some(&slot.value_type),
+            )
+        });
+    note.into_iter().chain(slots).collect()
+}
+
+fn variable(name: String, value: String, type_name: Option<&str>) -> Value {
+    let mut variable = json!({ "name": name, "value": value, "variablesReference": 0 });
+    if let Some(type_name) = type_name {
+        variable["type"] = json!(type_name);
+    }
+    variable
+}
+
+fn scope(name: &str, reference: i64, hint: Option<&str>) -> Value {
+    let mut scope = json!({ "name": name, "variablesReference": reference, "expensive": false });
+    if let Some(hint) = hint {
+        scope["presentationHint"] = json!(hint);
+    }
+    scope
+}
+
+fn source(path: &Path) -> Value {
+    json!({
+        "name": path.file_name().map(|n| n.to_string_lossy()),
+        "path": path,
+    })
+}
+
+fn str_arg<'a>(args: &'a Value, key: &str) -> Option<&'a str> {
+    args.get(key).and_then(Value::as_str)
+}
+
+fn condition_arg(breakpoint: &Value) -> Option<String> {
+    str_arg(breakpoint, "condition")
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+}
+
+fn no_program() -> miette::Report {
+    miette::miette!("No contract is being debugged; send launch or attach first")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use wasmparser::Operator;
+
+    /// A contract whose `hello()` returns `()` after a few instructions.
+    fn contract(dir: &Path) -> PathBuf {
+        let (mut module, _) = test_wasm::module();
+        let mut hello =
+            walrus::FunctionBuilder::new(&mut module.types, &[], &[walrus::ValType::I64]);
+        hello
+            .func_body()
+            .i64_const(1)
+            .drop()
+            .i64_const(test_wasm::VOID);
+        let hello = hello.finish(vec![], &mut module.funcs);
+        module.exports.add("hello", hello);
+
+        let path = dir.join("hello.wasm");
+        std::fs::write(&path, test_wasm::finish(module)).unwrap();
+        path
+    }
+
+    fn send(adapter: &mut DapAdapter, command: &str, arguments: Value) -> Vec<OutgoingMessage> {
+        adapter.handle(&Request {
+            seq: 1,
+            kind: "request".to_string(),
+            command: command.to_string(),
+            arguments,
+        })
+    }
+
+    fn body(messages: &[OutgoingMessage]) -> Value {
+        match &messages[0] {
+            OutgoingMessage::Response {
+                success: true,
+                body,
+                ..
+            } => body.clone().unwrap_or(Value::Null),
+            other => panic!("request failed: {:?}", other),
+        }
+    }
+
+    fn events(messages: &[OutgoingMessage], name: &str) -> Vec<Value> {
+        messages
+            .iter()
+            .filter_map(|m| match m {
+                OutgoingMessage::Event { event, body } if event == name => body.clone(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn launch(adapter: &mut DapAdapter, dir: &Path, stop_on_entry: bool) {
+        let path = contract(dir);
+        let messages = send(
+            adapter,
+            "launch",
+            json!({ "contractPath": path, "function": "hello", "stopOnEntry": stop_on_entry }),
+        );
+        body(&messages);
+        assert_eq!(events(&messages, "initialized").len(), 1);
+    }
+
+    #[test]
+    fn stops_on_entry_then_steps_and_runs_to_completion() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut adapter = DapAdapter::new();
+        let capabilities = body(&send(&mut adapter, "initialize", json!({})));
+        assert_eq!(capabilities["supportsConditionalBreakpoints"], true);
+
+        launch(&mut adapter, dir.path(), true);
+        let messages = send(&mut adapter, "configurationDone", json!({}));
+        assert_eq!(events(&messages, "stopped")[0]["reason"], "entry");
+
+        let trace = body(&send(&mut adapter, "stackTrace", json!({ "threadId": 1 })));
+        let frame = &trace["stackFrames"][0];
+        assert!(frame["name"].as_str().unwrap().starts_with("hello [C"));
+        let entry = frame["instructionPointerReference"].clone();
+        assert!(entry.is_string());
+
+        let scopes = body(&send(&mut adapter, "scopes", json!({ "frameId": 1 })));
+        let names: Vec<&str> = scopes["scopes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["Arguments", "Locals", "Value Stack", "Storage", "Budget"]
+        );
+        let budget = body(&send(
+            &mut adapter,
+            "variables",
+            json!({ "variablesReference": BUDGET_REF }),
+        ));
+        assert_eq!(budget["variables"][0]["name"], "cpu_instructions");
+
+        let evaluated = body(&send(
+            &mut adapter,
+            "evaluate",
+            json!({ "expression": "1 == 1" }),
+        ));
+        assert_eq!(evaluated["result"], "true");
+
+        let messages = send(&mut adapter, "next", json!({ "threadId": 1 }));
+        assert_eq!(events(&messages, "stopped")[0]["reason"], "step");
+        let trace = body(&send(&mut adapter, "stackTrace", json!({ "threadId": 1 })));
+        assert_ne!(
+            trace["stackFrames"][0]["instructionPointerReference"],
+            entry
+        );
+        let stack = body(&send(
+            &mut adapter,
+            "variables",
+            json!({ "variablesReference": VALUE_STACK_REF }),
+        ));
+        assert_eq!(stack["variables"][0]["name"], "[0]");
+        assert_eq!(stack["variables"][0]["type"], "i64");
+        assert!(stack["variables"][0]["value"]
+            .as_str()
+            .unwrap()
+            .starts_with("1"));
+
+        let messages = send(&mut adapter, "continue", json!({ "threadId": 1 }));
+        let output = events(&messages, "output");
+        assert!(output[0]["output"].as_str().unwrap().starts_with("Result:"));
+        assert_eq!(events(&messages, "exited")[0]["exitCode"], 0);
+        assert_eq!(events(&messages, "terminated").len(), 1);
+
+        send(&mut adapter, "disconnect", json!({}));
+        assert!(adapter.is_finished());
+    }
+
+    #[test]
+    fn steps_into_and_out_of_a_called_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let relay = dir.path().join("relay.wasm");
+        let ping = dir.path().join("ping.wasm");
+        std::fs::write(&relay, test_wasm::relay()).unwrap();
+        std::fs::write(&ping, test_wasm::ping()).unwrap();
+
+        let mut adapter = DapAdapter::new();
+        // Deployed contracts get predictable addresses, so deploy once to
+        // learn the callee's
+        let callee = {
+            let mut executor = ContractExecutor::new(test_wasm::relay()).unwrap();
+            executor.deploy(&test_wasm::ping()).unwrap()
+        };
+        let messages = send(
+            &mut adapter,
+            "launch",
+            json!({
+                "contractPath": relay,
+                "contracts": [ping],
+                "function": "relay",
+                "args": [callee],
+                "stopOnEntry": true,
+            }),
+        );
+        body(&messages);
+        assert!(events(&messages, "output")[0]["output"]
+            .as_str()
+            .unwrap()
+            .contains(&callee));
+        send(&mut adapter, "configurationDone", json!({}));
+
+        let step_in = json!({ "threadId": 1, "granularity": "instruction" });
+        for _ in 0..4 {
+            let messages = send(&mut adapter, "stepIn", step_in.clone());
+            assert_eq!(events(&messages, "stopped")[0]["reason"], "step");
+        }
+        let trace = body(&send(&mut adapter, "stackTrace", json!({ "threadId": 1 })));
+        assert_eq!(trace["totalFrames"], 2);
+        let short = short_contract_id(&callee);
+        assert_eq!(trace["stackFrames"][0]["name"], format!("ping [{}]", short));
+        assert!(trace["stackFrames"][1]["name"]
+            .as_str()
+            .unwrap()
+            .starts_with("relay [C"));
+
+        let messages = send(&mut adapter, "stepOut", json!({ "threadId": 1 }));
+        assert_eq!(events(&messages, "stopped")[0]["reason"], "step");
+        let trace = body(&send(&mut adapter, "stackTrace", json!({ "threadId": 1 })));
+        assert_eq!(trace["totalFrames"], 1);
+
+        let messages = send(&mut adapter, "continue", json!({ "threadId": 1 }));
+        let output = events(&messages, "output");
+        assert!(output[0]["output"].as_str().unwrap().starts_with("Result:"));
+    }
+
+    #[test]
+    fn stepping_in_after_a_line_breakpoint_enters_the_next_callee() {
+        let dir = tempfile::tempdir().unwrap();
+        let relay = dir.path().join("relay_both.wasm");
+        let ping = dir.path().join("ping.wasm");
+        std::fs::write(&relay, test_wasm::relay_both()).unwrap();
+        std::fs::write(&ping, test_wasm::ping()).unwrap();
+        let (first, second) = {
+            let mut executor = ContractExecutor::new(test_wasm::relay_both()).unwrap();
+            let first = executor.deploy(&test_wasm::ping()).unwrap();
+            (first, executor.deploy(&test_wasm::ping()).unwrap())
+        };
+
+        let mut adapter = DapAdapter::new();
+        body(&send(
+            &mut adapter,
+            "launch",
+            json!({
+                "contractPath": relay,
+                "contracts": [ping, ping],
+                "function": "relay_both",
+                "args": [first, second],
+                "stopOnEntry": true,
+            }),
+        ));
+        send(&mut adapter, "configurationDone", json!({}));
+
+        // relay_both: local.get, i64.const, call v._, call d._, drop, ...
+        // Line 6 starts at the drop, just past the first call.
+        let engine = adapter.engine.as_mut().unwrap();
+        let offsets: Vec<usize> = {
+            let state = engine.state();
+            let state = state.lock().unwrap();
+            let entry = state.instruction_pointer().current_index();
+            state.instructions()[entry..entry + 6]
+                .iter()
+                .map(|inst| inst.offset)
+                .collect()
+        };
+        let mut source_map = SourceMap::new();
+        for (offset, line) in [(offsets[0], 5), (offsets[4], 6), (offsets[5], 7)] {
+            source_map.add_mapping(
+                offset,
+                SourceLocation {
+                    file: PathBuf::from("/home/dev/relay/src/lib.rs"),
+                    line,
+                    column: None,
+                },
+            );
+        }
+        engine.set_source_map(source_map);
+        let set = body(&send(
+            &mut adapter,
+            "setBreakpoints",
+            json!({ "source": { "path": "src/lib.rs" }, "breakpoints": [{ "line": 6 }] }),
+        ));
+        assert_eq!(set["breakpoints"][0]["verified"], true);
+
+        let messages = send(&mut adapter, "continue", json!({ "threadId": 1 }));
+        assert_eq!(events(&messages, "stopped")[0]["reason"], "breakpoint");
+        let step_in = json!({ "threadId": 1, "granularity": "instruction" });
+        for _ in 0..5 {
+            send(&mut adapter, "stepIn", step_in.clone());
+        }
+        let trace = body(&send(&mut adapter, "stackTrace", json!({ "threadId": 1 })));
+        assert_eq!(trace["totalFrames"], 2);
+        assert_eq!(
+            trace["stackFrames"][0]["name"],
+            format!("ping [{}]", short_contract_id(&second))
+        );
+    }
+
+    #[test]
+    fn function_breakpoint_conditions_decide_whether_to_stop() {
+        let dir = tempfile::tempdir().unwrap();
+        for (condition, stops) in [("1 == 2", false), ("1 == 1", true)] {
+            let mut adapter = DapAdapter::new();
+            launch(&mut adapter, dir.path(), false);
+            let set = body(&send(
+                &mut adapter,
+                "setFunctionBreakpoints",
+                json!({ "breakpoints": [
+                    { "name": "hello", "condition": condition },
+                    { "name": "missing" },
+                ] }),
+            ));
+            assert_eq!(set["breakpoints"][0]["verified"], true);
+            assert_eq!(set["breakpoints"][1]["verified"], false);
+
+            let messages = send(&mut adapter, "configurationDone", json!({}));
+            let stopped = events(&messages, "stopped");
+            assert_eq!(!stopped.is_empty(), stops, "condition {}", condition);
+            if stops {
+                assert_eq!(stopped[0]["reason"], "function breakpoint");
+            } else {
+                assert_eq!(events(&messages, "terminated").len(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn launch_rejects_unknown_functions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = contract(dir.path());
+        let mut adapter = DapAdapter::new();
+        let messages = send(
+            &mut adapter,
+            "launch",
+            json!({ "contractPath": path, "function": "nope" }),
+        );
+        assert!(matches!(
+            &messages[0],
+            OutgoingMessage::Response { success: false, message: Some(m), .. } if m.contains("nope")
+        ));
+        assert!(events(&messages, "initialized").is_empty());
+    }
+
+    #[test]
+    fn line_breakpoints_are_found_at_the_first_instruction_of_a_line() {
+        let instructions: Vec<Instruction> = [(10, 1), (12, 1), (14, 1), (16, 1), (20, 2)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(offset, function))| {
+                Instruction::new(offset, Operator::Nop, function, i as u32)
+            })
+            .collect();
+        let mut source_map = SourceMap::new();
+        for (offset, line) in [(10, 5), (12, 5), (14, 6), (16, 7), (20, 8)] {
+            source_map.add_mapping(
+                offset,
+                SourceLocation {
+                    file: PathBuf::from("/home/dev/token/src/lib.rs"),
+                    line,
+                    column: None,
+                },
+            );
+        }
+        let breakpoints = HashMap::from([(
+            PathBuf::from("src/lib.rs"),
+            [5, 7, 8]
+                .iter()
+                .map(|&line| LineBreakpoint {
+                    id: line as i64,
+                    line,
+                    condition: None,
+                })
+                .collect(),
+        )]);
+
+        let hits = |current, include_current| -> Vec<(usize, i64)> {
+            find_line_breakpoints(
+                &instructions,
+                current,
+                include_current,
+                &source_map,
+                &breakpoints,
+            )
+            .into_iter()
+            .map(|(index, id, _)| (index, id))
+            .collect()
+        };
+        // Line 8 is in another function
+        assert_eq!(hits(0, true), vec![(0, 5), (3, 7)]);
+        // Resuming from line 5 skips the rest of that line
+        assert_eq!(hits(0, false), vec![(3, 7)]);
+        assert!(source_map.contains_line(Path::new("src/lib.rs"), 6));
+        assert!(!source_map.contains_line(Path::new("src/main.rs"), 6));
+    }
+}
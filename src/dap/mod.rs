@@ -0,0 +1,75 @@
+//! Debug Adapter Protocol server, so any DAP client (VS Code, Neovim, Helix,
+//! Zed, JetBrains) can debug contracts through `soroban-debug dap`.
+
+pub mod adapter;
+pub mod protocol;
+
+pub use adapter::DapAdapter;
+pub use protocol::{OutgoingMessage, Request};
+
+use crate::{DebuggerError, Result};
+use protocol::{read_message, write_message};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use tracing::{error, info, warn};
+
+/// Serve one DAP session until the client disconnects or the stream ends.
+pub fn serve(mut reader: impl BufRead, mut writer: impl Write) -> Result<()> {
+    let mut adapter = DapAdapter::new();
+    let mut seq = 0;
+    while let Some(message) = read_message(&mut reader)? {
+        let request: Request = match serde_json::from_value(message) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Ignoring malformed DAP message: {}", e);
+                continue;
+            }
+        };
+        // Responses to reverse requests need no handling
+        if request.kind != "request" {
+            continue;
+        }
+        for outgoing in adapter.handle(&request) {
+            seq += 1;
+            write_message(&mut writer, seq, &outgoing)?;
+        }
+        if adapter.is_finished() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Serve one session over stdin and stdout.
+pub fn serve_stdio() -> Result<()> {
+    serve(io::stdin().lock(), io::stdout().lock())
+}
+
+/// Accept clients on `127.0.0.1:port`, one session at a time.
+pub fn serve_tcp(port: u16) -> Result<()> {
+    let addr = format!("127.0.0.1:{}", port);
+    let listener = TcpListener::bind(&addr)
+        .map_err(|e| DebuggerError::FileError(format!("Failed to bind to {}: {}", addr, e)))?;
+    info!("DAP server listening on {}", addr);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Failed to accept DAP connection: {}", e);
+                continue;
+            }
+        };
+        let reader = match stream.try_clone() {
+            Ok(clone) => BufReader::new(clone),
+            Err(e) => {
+                error!("Failed to clone DAP connection: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = serve(reader, stream) {
+            error!("DAP session failed: {}", e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+//! Debug Adapter Protocol wire format: JSON messages framed by a
+//! `Content-Length` header, as used by every DAP client.
+
+use crate::{DebuggerError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, Write};
+
+/// A request from the client.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Request {
+    pub seq: i64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// A response or event sent to the client.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum OutgoingMessage {
+    Response {
+        request_seq: i64,
+        success: bool,
+        command: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        body: Option<Value>,
+    },
+    Event {
+        event: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        body: Option<Value>,
+    },
+}
+
+impl OutgoingMessage {
+    pub fn event(event: &str, body: Value) -> Self {
+        Self::Event {
+            event: event.to_string(),
+            body: Some(body),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    seq: i64,
+    #[serde(flatten)]
+    message: &'a OutgoingMessage,
+}
+
+fn io_error(e: std::io::Error) -> miette::Report {
+    DebuggerError::FileError(format!("DAP connection failed: {}", e)).into()
+}
+
+/// Read one framed message. Returns `None` when the stream ends cleanly.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(io_error)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>().map_err(|e| {
+                    DebuggerError::FileError(format!("Invalid Content-Length {:?}: {}", value, e))
+                })?);
+            }
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body).map_err(io_error)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| DebuggerError::FileError(format!("Invalid DAP message: {}", e)).into())
+}
+
+/// Write one framed message with sequence number `seq`.
+pub fn write_message(writer: &mut impl Write, seq: i64, message: &OutgoingMessage) -> Result<()> {
+    let json = serde_json::to_string(&Envelope { seq, message })
+        .map_err(|e| DebuggerError::FileError(format!("Failed to serialize DAP message: {}", e)))?;
+    write!(writer, "Content-Length: {}\r\n\r\n{}", json.len(), json).map_err(io_error)?;
+    writer.flush().map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
+
+    #[test]
+    fn framed_messages_round_trip() {
+        let mut buffer = Vec::new();
+        let event = OutgoingMessage::event("stopped", json!({"reason": "entry"}));
+        write_message(&mut buffer, 7, &event).unwrap();
+        write_message(&mut buffer, 8, &event).unwrap();
+
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.starts_with("Content-Length: "));
+
+        let mut reader = Cursor::new(buffer);
+        let first = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(first["seq"], 7);
+        assert_eq!(first["type"], "event");
+        assert_eq!(first["body"]["reason"], "entry");
+        assert_eq!(read_message(&mut reader).unwrap().unwrap()["seq"], 8);
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn requests_parse_without_arguments() {
+        let body = r#"{"seq":1,"type":"request","command":"threads"}"#;
+        let framed = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let value = read_message(&mut Cursor::new(framed)).unwrap().unwrap();
+        let request: Request = serde_json::from_value(value).unwrap();
+        assert_eq!(request.command, "threads");
+        assert!(request.arguments.is_null());
+    }
+}
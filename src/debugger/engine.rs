@@ -1,13 +1,50 @@
 use crate::debugger::breakpoint::BreakpointManager;
 use crate::debugger::instruction_pointer::StepMode;
-use crate::debugger::state::DebugState;
+use crate::debugger::source_map::SourceMap;
+use crate::debugger::state::{DebugState, ModuleFrame};
 use crate::debugger::stepper::Stepper;
+use crate::inspector::locals::{LocalsInspector, LocalsSnapshot};
+use crate::inspector::memory::MemoryInspector;
+use crate::inspector::stack::{CallFrame, CallStackInspector};
 use crate::runtime::executor::ContractExecutor;
-use crate::runtime::instruction::Instruction;
+use crate::runtime::instruction::{Instruction, InstructionParser};
 use crate::runtime::instrumentation::Instrumenter;
-use crate::Result;
+use crate::utils::wasm::{find_function_import, parse_function_exports};
+use crate::{DebuggerError, Result};
+use soroban_ledger_snapshot::LedgerSnapshot;
+use soroban_sdk::testutils::Snapshot;
 use std::sync::{Arc, Mutex};
-use tracing::info;
+use tracing::{info, warn};
+use wasmparser::Operator;
+
+/// Nested frames beyond this many are not replayed to measure their cost.
+const MAX_MEASURED_FRAMES: usize = 64;
+
+/// Analysis of one contract's code. The engine keeps one for each module on
+/// the stepping stack.
+#[derive(Default)]
+struct ModuleContext {
+    locals_inspector: Option<LocalsInspector>,
+    source_map: Option<SourceMap>,
+    /// Function index of the `d._` host import (`call`), if imported.
+    call_import: Option<u32>,
+}
+
+impl ModuleContext {
+    fn load(wasm_bytes: &[u8]) -> Result<Self> {
+        // DWARF info is optional; release builds usually strip it
+        let mut source_map = SourceMap::new();
+        let source_map = match source_map.load(wasm_bytes) {
+            Ok(()) if !source_map.is_empty() => Some(source_map),
+            _ => None,
+        };
+        Ok(Self {
+            locals_inspector: Some(LocalsInspector::new(wasm_bytes)?),
+            source_map,
+            call_import: find_function_import(wasm_bytes, "d", "_")?,
+        })
+    }
+}
 
 /// Core debugging engine that orchestrates execution and debugging.
 pub struct DebuggerEngine {
@@ -18,6 +55,18 @@ pub struct DebuggerEngine {
     instrumenter: Instrumenter,
     paused: bool,
     instruction_debug_enabled: bool,
+    /// The module being stepped through
+    module: ModuleContext,
+    /// Caller modules suspended while stepping through a callee
+    callers: Vec<ModuleContext>,
+    /// Calls made by the invocation being stepped, from a traced run
+    call_trace: Vec<CallFrame>,
+    memory_inspector: Option<MemoryInspector>,
+    /// Ledger state before the last `execute`, to capture memory against
+    invocation_ledger: Option<LedgerSnapshot>,
+    /// Whether nested frames are replayed to estimate their budget
+    estimate_frame_budgets: bool,
+    console_output: bool,
 }
 
 impl DebuggerEngine {
@@ -38,9 +87,31 @@ impl DebuggerEngine {
             instrumenter: Instrumenter::new(),
             paused: false,
             instruction_debug_enabled: false,
+            module: ModuleContext::default(),
+            callers: Vec::new(),
+            call_trace: Vec::new(),
+            memory_inspector: None,
+            invocation_ledger: None,
+            estimate_frame_budgets: false,
+            console_output: true,
         }
     }
 
+    /// Estimate the budget of nested call frames by replaying each one on
+    /// its own against the state before the invocation. That costs a call
+    /// per frame, and the figures are wrong for frames that read state an
+    /// earlier frame changed, so it is off by default.
+    pub fn set_frame_budget_estimates(&mut self, enabled: bool) {
+        self.estimate_frame_budgets = enabled;
+    }
+
+    /// Whether errors and the call stack are printed to stdout. Front ends
+    /// that own stdout (such as the DAP server) turn this off.
+    pub fn set_console_output(&mut self, enabled: bool) {
+        self.console_output = enabled;
+        self.executor.set_console_output(enabled);
+    }
+
     /// Enable instruction-level debugging.
     pub fn enable_instruction_debug(&mut self, wasm_bytes: &[u8]) -> Result<()> {
         let instructions = self
@@ -54,6 +125,10 @@ impl DebuggerEngine {
             state.enable_instruction_debug();
         }
 
+        self.module = ModuleContext::load(wasm_bytes)?;
+        self.callers.clear();
+        self.call_trace.clear();
+
         self.instrumenter.enable();
         self.instruction_debug_enabled = true;
         Ok(())
@@ -83,76 +158,154 @@ impl DebuggerEngine {
             state.call_stack_mut().clear();
             state.call_stack_mut().push(function.to_string(), None);
         }
+        self.memory_inspector = None;
 
         if self.breakpoints.should_break(function) {
             self.pause_at_function(function);
         }
 
+        let state_before = self.executor.state_snapshot();
+        self.invocation_ledger = Some(state_before.ledger.clone());
+
         let start_time = std::time::Instant::now();
         let result = self.executor.execute(function, args);
         let duration = start_time.elapsed();
 
-        self.update_call_stack(duration)?;
+        self.update_call_stack(function, &state_before, duration)?;
 
-        if let Err(ref e) = result {
-            println!("\n[ERROR] Execution failed: {}", e);
-            if let Ok(state) = self.state.lock() {
-                state.call_stack().display();
+        if self.console_output {
+            if let Err(ref e) = result {
+                println!("\n[ERROR] Execution failed: {}", e);
             }
-        } else if self.is_paused() {
-            if let Ok(state) = self.state.lock() {
-                state.call_stack().display();
+            if result.is_err() || self.is_paused() {
+                if let Ok(state) = self.state.lock() {
+                    state.call_stack().display();
+                }
             }
         }
 
         result
     }
 
-    fn update_call_stack(&mut self, total_duration: std::time::Duration) -> Result<()> {
+    /// Rebuild the call stack from the diagnostic events of the last
+    /// invocation (the host clears them when a new one starts). The
+    /// top-level frame gets the invocation's exact budget usage. With
+    /// [`set_frame_budget_estimates`](Self::set_frame_budget_estimates),
+    /// nested frames get estimates from replaying them against
+    /// `state_before`.
+    fn update_call_stack(
+        &mut self,
+        function: &str,
+        state_before: &Snapshot,
+        total_duration: std::time::Duration,
+    ) -> Result<()> {
         let events = self.executor.get_diagnostic_events()?;
+        let mut stack = CallStackInspector::from_events(&events);
 
-        let current_func = if let Ok(state) = self.state.lock() {
-            state.current_function().unwrap_or("entry").to_string()
-        } else {
-            "entry".to_string()
-        };
+        // Nothing was invoked (e.g. unknown function), so the events are stale
+        let invoked = stack.get_stack().first().map(|f| f.function.as_str()) == Some(function);
+        if !invoked {
+            stack = CallStackInspector::new();
+            stack.push(function.to_string(), None);
+        }
 
-        if let Ok(mut state) = self.state.lock() {
-            let stack = state.call_stack_mut();
-            stack.clear();
-            stack.push(current_func, None);
-
-            for event in events {
-                let event_str = format!("{:?}", event);
-                if event_str.contains("ContractCall")
-                    || (event_str.contains("call") && event.contract_id.is_some())
-                {
-                    let contract_id = event.contract_id.as_ref().map(|cid| format!("{:?}", cid));
-                    stack.push("nested_call".to_string(), contract_id);
-                } else if (event_str.contains("ContractReturn") || event_str.contains("return"))
-                    && stack.get_stack().len() > 1
-                {
-                    stack.pop();
+        let (cpu, mem) = self.executor.budget_consumed();
+        for (i, frame) in stack.frames_mut().iter_mut().enumerate() {
+            if i == 0 {
+                frame.duration = Some(total_duration);
+                if invoked {
+                    frame.cpu_instructions = Some(cpu);
+                    frame.memory_bytes = Some(mem);
+                }
+            } else if self.estimate_frame_budgets && i < MAX_MEASURED_FRAMES {
+                if let Some(contract_id) = &frame.contract_id {
+                    if let Some((cpu, mem)) = self.executor.measure_call(
+                        state_before,
+                        contract_id,
+                        &frame.function,
+                        &frame.args,
+                    ) {
+                        frame.cpu_instructions = Some(cpu);
+                        frame.memory_bytes = Some(mem);
+                        frame.budget_estimated = true;
+                    }
                 }
             }
+        }
 
-            if let Some(mut frame) = stack.pop() {
-                frame.duration = Some(total_duration);
-                stack.push_frame(frame);
-            }
+        if let Ok(mut state) = self.state.lock() {
+            *state.call_stack_mut() = stack;
         }
 
         Ok(())
     }
 
-    /// Step into next instruction.
+    /// Position instruction stepping at the entry of `function`, and trace
+    /// the cross-contract calls it makes so that stepping can follow them.
+    pub fn prepare_invocation(&mut self, function: &str, args: Option<&str>) -> Result<()> {
+        if !self.instruction_debug_enabled {
+            return Err(miette::miette!("Instruction debugging not enabled"));
+        }
+        let entry = parse_function_exports(self.executor.wasm_bytes())?
+            .into_iter()
+            .find(|(name, _)| name == function)
+            .map(|(_, index)| index)
+            .ok_or_else(|| DebuggerError::InvalidFunction(function.to_string()))?;
+
+        self.call_trace = self
+            .executor
+            .trace_calls(function, args)
+            .unwrap_or_else(|e| {
+                warn!("Could not trace cross-contract calls: {}", e);
+                Vec::new()
+            });
+        let traced = self.call_trace.first().map(|f| f.function.as_str()) == Some(function);
+        // Nothing has run yet; memory is captured from the current state
+        self.invocation_ledger = None;
+        self.memory_inspector = None;
+
+        let state = Arc::clone(&self.state);
+        let mut state = state
+            .lock()
+            .map_err(|_| miette::miette!("Debug state is unavailable"))?;
+        while state.module_depth() > 0 {
+            self.leave_module(&mut state);
+        }
+        state.set_current_function(function.to_string(), args.map(str::to_string));
+        if let Some(index) = state
+            .instructions()
+            .iter()
+            .position(|inst| inst.function_index == entry)
+        {
+            state.advance_to_instruction(index);
+        }
+        *state.module_mut() = ModuleFrame {
+            contract_id: Some(self.executor.contract_id()),
+            function: Some(function.to_string()),
+            function_index: Some(entry),
+            trace_index: traced.then_some(0),
+            calls_made: 0,
+        };
+        Ok(())
+    }
+
+    /// Step into next instruction. A cross-contract call is followed into
+    /// the callee when its code is deployed in the environment.
     pub fn step_into(&mut self) -> Result<bool> {
         if !self.instruction_debug_enabled {
             return Err(miette::miette!("Instruction debugging not enabled"));
         }
 
-        let stepped = if let Ok(mut state) = self.state.lock() {
-            self.stepper.step_into(&mut state)
+        let state = Arc::clone(&self.state);
+        let stepped = if let Ok(mut state) = state.lock() {
+            if self.stepper.is_active() && self.enter_callee(&mut state).is_some() {
+                true
+            } else {
+                let from = state.instruction_pointer().current_index();
+                let stepped = self.stepper.step_into(&mut state);
+                self.count_calls_passed(&mut state, from);
+                self.return_from_callee(&mut state, stepped)
+            }
         } else {
             false
         };
@@ -166,8 +319,12 @@ impl DebuggerEngine {
             return Err(miette::miette!("Instruction debugging not enabled"));
         }
 
-        let stepped = if let Ok(mut state) = self.state.lock() {
-            self.stepper.step_over(&mut state)
+        let state = Arc::clone(&self.state);
+        let stepped = if let Ok(mut state) = state.lock() {
+            let from = state.instruction_pointer().current_index();
+            let stepped = self.stepper.step_over(&mut state);
+            self.count_calls_passed(&mut state, from);
+            self.return_from_callee(&mut state, stepped)
         } else {
             false
         };
@@ -175,14 +332,23 @@ impl DebuggerEngine {
         Ok(stepped)
     }
 
-    /// Step out of current function.
+    /// Step out of current function. In a callee, this returns to the
+    /// calling contract just past its call.
     pub fn step_out(&mut self) -> Result<bool> {
         if !self.instruction_debug_enabled {
             return Err(miette::miette!("Instruction debugging not enabled"));
         }
 
-        let stepped = if let Ok(mut state) = self.state.lock() {
-            self.stepper.step_out(&mut state)
+        let state = Arc::clone(&self.state);
+        let stepped = if let Ok(mut state) = state.lock() {
+            if self.stepper.is_active() && state.module_depth() > 0 {
+                self.return_from_callee(&mut state, false)
+            } else {
+                let from = state.instruction_pointer().current_index();
+                let stepped = self.stepper.step_out(&mut state);
+                self.count_calls_passed(&mut state, from);
+                stepped
+            }
         } else {
             false
         };
@@ -196,8 +362,12 @@ impl DebuggerEngine {
             return Err(miette::miette!("Instruction debugging not enabled"));
         }
 
-        let stepped = if let Ok(mut state) = self.state.lock() {
-            self.stepper.step_block(&mut state)
+        let state = Arc::clone(&self.state);
+        let stepped = if let Ok(mut state) = state.lock() {
+            let from = state.instruction_pointer().current_index();
+            let stepped = self.stepper.step_block(&mut state);
+            self.count_calls_passed(&mut state, from);
+            self.return_from_callee(&mut state, stepped)
         } else {
             false
         };
@@ -205,14 +375,30 @@ impl DebuggerEngine {
         Ok(stepped)
     }
 
-    /// Step backwards to previous instruction.
+    /// Step backwards to previous instruction. Stepping back from a
+    /// callee's first instruction returns to the caller's call.
     pub fn step_back(&mut self) -> Result<bool> {
         if !self.instruction_debug_enabled {
             return Err(miette::miette!("Instruction debugging not enabled"));
         }
 
-        let stepped = if let Ok(mut state) = self.state.lock() {
-            self.stepper.step_back(&mut state)
+        let state = Arc::clone(&self.state);
+        let stepped = if let Ok(mut state) = state.lock() {
+            let from = state.instruction_pointer().current_index();
+            if self.stepper.step_back(&mut state) {
+                self.count_calls_passed(&mut state, from);
+                true
+            } else if self.stepper.is_active()
+                && state.module_depth() > 0
+                && self.leave_module(&mut state)
+            {
+                // Back at the caller's call, which is yet to be made
+                let caller = state.module_mut();
+                caller.calls_made = caller.calls_made.saturating_sub(1);
+                true
+            } else {
+                false
+            }
         } else {
             false
         };
@@ -220,6 +406,125 @@ impl DebuggerEngine {
         Ok(stepped)
     }
 
+    /// Move forward to instruction `index` of the module being stepped
+    /// through, as stopping at a line breakpoint does, counting the
+    /// contract calls passed on the way. Returns whether `index` exists.
+    pub fn jump_to_instruction(&mut self, index: usize) -> bool {
+        let state = Arc::clone(&self.state);
+        let Ok(mut state) = state.lock() else {
+            return false;
+        };
+        let from = state.instruction_pointer().current_index();
+        if state.advance_to_instruction(index).is_none() {
+            return false;
+        }
+        self.count_calls_passed(&mut state, from);
+        true
+    }
+
+    /// Test-only: use `source_map` for the module being stepped through.
+    #[cfg(test)]
+    pub(crate) fn set_source_map(&mut self, source_map: SourceMap) {
+        self.module.source_map = Some(source_map);
+    }
+
+    /// If the current instruction calls another contract and the traced run
+    /// shows which, suspend this module and stop at the callee's entry.
+    /// Calls are matched to the trace in the order they are made.
+    fn enter_callee(&mut self, state: &mut DebugState) -> Option<()> {
+        if !self.is_contract_call(state.current_instruction()?) {
+            return None;
+        }
+
+        let ordinal = state.module().calls_made;
+        let parent = state.module().trace_index?;
+        let depth = self.call_trace.get(parent)?.depth;
+        let trace_index = self.call_trace[parent + 1..]
+            .iter()
+            .enumerate()
+            .take_while(|(_, frame)| frame.depth > depth)
+            .filter(|(_, frame)| frame.depth == depth + 1)
+            .nth(ordinal)
+            .map(|(i, _)| parent + 1 + i)?;
+
+        let callee = &self.call_trace[trace_index];
+        let contract_id = callee.contract_id.clone()?;
+        let wasm = self.executor.contract_wasm(&contract_id)?;
+        let function_index = parse_function_exports(&wasm)
+            .ok()?
+            .into_iter()
+            .find(|(name, _)| *name == callee.function)
+            .map(|(_, index)| index)?;
+        let instructions = InstructionParser::new().parse(&wasm).ok()?.to_vec();
+        let entry = instructions
+            .iter()
+            .position(|inst| inst.function_index == function_index)?;
+        let context = ModuleContext::load(&wasm).ok()?;
+
+        let frame = ModuleFrame {
+            contract_id: Some(contract_id),
+            function: Some(callee.function.clone()),
+            function_index: Some(function_index),
+            trace_index: Some(trace_index),
+            calls_made: 0,
+        };
+        state.module_mut().calls_made += 1;
+        state.enter_module(frame, instructions, entry);
+        self.callers
+            .push(std::mem::replace(&mut self.module, context));
+        Some(())
+    }
+
+    /// Whether `inst` calls another contract.
+    fn is_contract_call(&self, inst: &Instruction) -> bool {
+        matches!(inst.operator, Operator::Call { function_index } if Some(function_index) == self.module.call_import)
+    }
+
+    /// Count the contract calls stepping passed on the way from instruction
+    /// `from` to the current one, or take them back when stepping back.
+    fn count_calls_passed(&self, state: &mut DebugState, from: usize) {
+        let to = state.instruction_pointer().current_index();
+        let calls_in = |start: usize, end: usize| {
+            state.instructions().get(start..end).map_or(0, |insts| {
+                insts.iter().filter(|i| self.is_contract_call(i)).count()
+            })
+        };
+        let frame_calls = state.module().calls_made;
+        let calls_made = if to >= from {
+            frame_calls + calls_in(from, to)
+        } else {
+            frame_calls.saturating_sub(calls_in(to, from))
+        };
+        state.module_mut().calls_made = calls_made;
+    }
+
+    /// Once stepping leaves a callee's function (or could not step at all),
+    /// return to the caller and step past its call. Returns whether there
+    /// is still an instruction to stop at.
+    fn return_from_callee(&mut self, state: &mut DebugState, mut stepped: bool) -> bool {
+        while state.module_depth() > 0 {
+            let inside = stepped
+                && state.current_instruction().map(|inst| inst.function_index)
+                    == state.module().function_index;
+            if inside {
+                break;
+            }
+            self.leave_module(state);
+            stepped = state.next_instruction().is_some();
+        }
+        stepped
+    }
+
+    fn leave_module(&mut self, state: &mut DebugState) -> bool {
+        if !state.leave_module() {
+            return false;
+        }
+        if let Some(caller) = self.callers.pop() {
+            self.module = caller;
+        }
+        true
+    }
+
     /// Start instruction stepping with given mode.
     pub fn start_instruction_stepping(&mut self, mode: StepMode) -> Result<()> {
         if !self.instruction_debug_enabled {
@@ -249,7 +554,9 @@ impl DebuggerEngine {
 
         if let Ok(mut state) = self.state.lock() {
             state.set_current_function(function.to_string(), None);
-            state.call_stack().display();
+            if self.console_output {
+                state.call_stack().display();
+            }
         }
     }
 
@@ -280,6 +587,74 @@ impl DebuggerEngine {
         }
     }
 
+    /// Inspect WASM locals, the value stack and source variables at the
+    /// current instruction.
+    pub fn inspect_locals(&self) -> Result<LocalsSnapshot> {
+        let inspector = self
+            .module
+            .locals_inspector
+            .as_ref()
+            .ok_or_else(|| miette::miette!("Instruction debugging not enabled"))?;
+        let state = self
+            .state
+            .lock()
+            .map_err(|_| miette::miette!("Debug state is unavailable"))?;
+        let current = state.instruction_pointer().current_index();
+
+        inspector
+            .inspect(
+                state.instructions(),
+                current,
+                self.module.source_map.as_ref(),
+            )
+            .ok_or_else(|| miette::miette!("No function context at the current instruction"))
+    }
+
+    /// DWARF source map of the module being stepped through, when the
+    /// contract was built with debug info.
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        self.module.source_map.as_ref()
+    }
+
+    /// Source map of the module `depth` levels below the current one on
+    /// the stepping stack, in the order of [`DebugState::modules`].
+    pub fn module_source_map(&self, depth: usize) -> Option<&SourceMap> {
+        match depth {
+            0 => self.source_map(),
+            _ => self
+                .callers
+                .len()
+                .checked_sub(depth)
+                .and_then(|i| self.callers[i].source_map.as_ref()),
+        }
+    }
+
+    /// Linear memory of the current invocation. Memory is captured lazily by
+    /// re-running the function against the state it started from, and falls
+    /// back to the module's initial image before anything has executed.
+    pub fn memory_inspector(&mut self) -> Result<&MemoryInspector> {
+        if self.memory_inspector.is_none() {
+            let mut inspector = MemoryInspector::new(self.executor.wasm_bytes())?;
+            let invocation = self.state.lock().ok().and_then(|state| {
+                state
+                    .current_function()
+                    .map(|f| (f.to_string(), state.current_args().map(str::to_string)))
+            });
+            if let Some((function, args)) = invocation {
+                inspector.capture(
+                    &self.executor,
+                    self.invocation_ledger.clone(),
+                    &function,
+                    args.as_deref(),
+                );
+            }
+            self.memory_inspector = Some(inspector);
+        }
+        self.memory_inspector
+            .as_ref()
+            .ok_or_else(|| miette::miette!("Memory inspector unavailable"))
+    }
+
     pub fn breakpoints_mut(&mut self) -> &mut BreakpointManager {
         &mut self.breakpoints
     }
@@ -303,3 +678,177 @@ impl DebuggerEngine {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use soroban_env_host::xdr::{Hash, ScAddress, ScVal};
+
+    fn callee() -> String {
+        ScAddress::Contract(Hash([7; 32])).to_string()
+    }
+
+    fn engine() -> DebuggerEngine {
+        let mut executor = ContractExecutor::new(test_wasm::relay()).unwrap();
+        executor
+            .set_mock_specs(&[format!("{}.ping=7", callee())])
+            .unwrap();
+        DebuggerEngine::new(executor, Vec::new())
+    }
+
+    #[test]
+    fn call_stack_records_nested_calls_with_arguments_and_results() {
+        let mut engine = engine();
+        let args = format!(r#"["{}"]"#, callee());
+        // Run twice so events from the first invocation must be ignored
+        engine.execute("relay", Some(&args)).unwrap();
+        engine.execute("relay", Some(&args)).unwrap();
+
+        let state = engine.state();
+        let state = state.lock().unwrap();
+        let frames = state.call_stack().get_stack();
+        assert_eq!(frames.len(), 2);
+
+        assert_eq!(frames[0].function, "relay");
+        assert_eq!(frames[0].depth, 0);
+        assert!(frames[0].contract_id.as_deref().unwrap().starts_with('C'));
+        assert_eq!(frames[0].args.len(), 1);
+        assert!(matches!(frames[0].args[0], ScVal::Address(_)));
+        assert!(frames[0].cpu_instructions.unwrap() > 0);
+        assert!(frames[0].duration.is_some());
+
+        assert_eq!(frames[1].function, "ping");
+        assert_eq!(frames[1].depth, 1);
+        assert_eq!(frames[1].contract_id, Some(callee()));
+        assert!(frames[1].args.is_empty());
+        assert_eq!(frames[1].return_value, frames[0].return_value);
+        assert!(frames[1].return_value.is_some());
+    }
+
+    #[test]
+    fn nested_frame_budgets_are_only_estimated_on_request() {
+        let mut executor = ContractExecutor::new(test_wasm::relay()).unwrap();
+        let callee = executor.deploy(&test_wasm::ping()).unwrap();
+        let mut engine = DebuggerEngine::new(executor, Vec::new());
+        let args = format!(r#"["{}"]"#, callee);
+        let nested_frame = |engine: &DebuggerEngine| {
+            let state = engine.state();
+            let state = state.lock().unwrap();
+            state.call_stack().get_stack()[1].clone()
+        };
+
+        engine.execute("relay", Some(&args)).unwrap();
+        let frame = nested_frame(&engine);
+        assert_eq!(frame.cpu_instructions, None);
+        assert_eq!(frame.cost(), " (cost n/a)");
+
+        engine.set_frame_budget_estimates(true);
+        engine.execute("relay", Some(&args)).unwrap();
+        let frame = nested_frame(&engine);
+        assert!(frame.budget_estimated);
+        assert!(frame.cost().starts_with(" (est. cpu "));
+    }
+
+    #[test]
+    fn stepping_follows_cross_contract_calls_into_the_callee() {
+        let wasm = test_wasm::relay();
+        let mut executor = ContractExecutor::new(wasm.clone()).unwrap();
+        let callee = executor.deploy(&test_wasm::ping()).unwrap();
+        let caller = executor.contract_id();
+        let mut engine = DebuggerEngine::new(executor, Vec::new());
+        engine.enable_instruction_debug(&wasm).unwrap();
+        engine
+            .start_instruction_stepping(StepMode::StepInto)
+            .unwrap();
+        let args = format!(r#"["{}"]"#, callee);
+        engine.prepare_invocation("relay", Some(&args)).unwrap();
+
+        let state = engine.state();
+        let module_of = |engine: &DebuggerEngine| {
+            let state = engine.state();
+            let state = state.lock().unwrap();
+            (state.module_depth(), state.module().clone())
+        };
+        // relay: local.get, i64.const, call v._, call d._
+        for _ in 0..3 {
+            assert!(engine.step_into().unwrap());
+        }
+        assert_eq!(module_of(&engine).0, 0);
+
+        assert!(engine.step_into().unwrap());
+        let (depth, frame) = module_of(&engine);
+        assert_eq!(depth, 1);
+        assert_eq!(frame.contract_id, Some(callee.clone()));
+        assert_eq!(frame.function.as_deref(), Some("ping"));
+        {
+            let state = state.lock().unwrap();
+            let modules = state.modules();
+            assert_eq!(modules.len(), 2);
+            assert_eq!(modules[1].0.contract_id, Some(caller));
+            assert!(matches!(
+                modules[0].1.unwrap().operator,
+                Operator::I64Const { .. }
+            ));
+        }
+
+        // Stepping back from the callee's entry returns to the call
+        assert!(engine.step_back().unwrap());
+        assert_eq!(module_of(&engine).0, 0);
+        assert!(engine.step_into().unwrap());
+        assert_eq!(module_of(&engine).0, 1);
+
+        assert!(engine.step_out().unwrap());
+        let (depth, frame) = module_of(&engine);
+        assert_eq!(depth, 0);
+        assert_eq!(frame.function.as_deref(), Some("relay"));
+        assert!(matches!(
+            engine.current_instruction().unwrap().operator,
+            Operator::End
+        ));
+    }
+
+    #[test]
+    fn stepping_into_calls_follows_the_order_they_are_made() {
+        let wasm = test_wasm::relay_both();
+        let mut executor = ContractExecutor::new(wasm.clone()).unwrap();
+        let first = executor.deploy(&test_wasm::ping()).unwrap();
+        let second = executor.deploy(&test_wasm::ping()).unwrap();
+        let mut engine = DebuggerEngine::new(executor, Vec::new());
+        engine.enable_instruction_debug(&wasm).unwrap();
+        engine
+            .start_instruction_stepping(StepMode::StepInto)
+            .unwrap();
+        let args = format!(r#"["{}", "{}"]"#, first, second);
+        engine
+            .prepare_invocation("relay_both", Some(&args))
+            .unwrap();
+
+        let callee_of = |engine: &DebuggerEngine| {
+            let state = engine.state();
+            let state = state.lock().unwrap();
+            (state.module_depth() > 0).then(|| state.module().contract_id.clone().unwrap())
+        };
+        // relay_both: local.get, i64.const, call v._, call d._, drop, ...
+        for _ in 0..3 {
+            assert!(engine.step_into().unwrap());
+        }
+        assert!(engine.step_into().unwrap());
+        assert_eq!(callee_of(&engine), Some(first.clone()));
+
+        // Stepping back to the call makes it the next one again
+        assert!(engine.step_back().unwrap());
+        assert_eq!(callee_of(&engine), None);
+        assert!(engine.step_into().unwrap());
+        assert_eq!(callee_of(&engine), Some(first));
+        assert!(engine.step_out().unwrap());
+
+        // drop, local.get, i64.const, call v._, then into the second call
+        for _ in 0..4 {
+            assert!(engine.step_into().unwrap());
+        }
+        assert_eq!(callee_of(&engine), None);
+        assert!(engine.step_into().unwrap());
+        assert_eq!(callee_of(&engine), Some(second));
+    }
+}
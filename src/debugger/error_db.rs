@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use soroban_env_host::xdr::{ScError, ScErrorCode, ScErrorType};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,7 +13,9 @@ pub struct ErrorExplanation {
 
 pub struct ErrorDatabase {
     standard_errors: HashMap<u32, ErrorExplanation>,
-    custom_errors: HashMap<u32, ErrorExplanation>,
+    /// Contract errors by code. Separate `#[contracterror]` enums may
+    /// declare the same code, so each code keeps every candidate.
+    custom_errors: HashMap<u32, Vec<ErrorExplanation>>,
 }
 
 impl ErrorDatabase {
@@ -25,6 +28,16 @@ impl ErrorDatabase {
         db
     }
 
+    /// A database with the contract's `#[contracterror]` enums loaded from
+    /// its spec. Contracts without a spec just get the standard errors.
+    pub fn from_wasm(wasm: &[u8]) -> Self {
+        let mut db = Self::new();
+        if let Err(e) = db.load_custom_errors_from_wasm(wasm) {
+            tracing::debug!(error = %e, "No contract error spec loaded");
+        }
+        db
+    }
+
     fn init_standard_errors(&mut self) {
         let errors = vec![
             ErrorExplanation {
@@ -104,14 +117,132 @@ impl ErrorDatabase {
         }
     }
 
-    pub fn lookup(&self, code: u32) -> Option<&ErrorExplanation> {
-        self.custom_errors
-            .get(&code)
-            .or_else(|| self.standard_errors.get(&code))
+    pub fn lookup(&self, code: u32) -> Option<ErrorExplanation> {
+        self.custom_error(code)
+            .or_else(|| self.standard_errors.get(&code).cloned())
     }
 
+    /// Add a contract error. An error with the same code and name replaces
+    /// the earlier one; other errors with the same code are kept alongside.
     pub fn add_custom_error(&mut self, error: ErrorExplanation) {
-        self.custom_errors.insert(error.code, error);
+        let candidates = self.custom_errors.entry(error.code).or_default();
+        match candidates.iter_mut().find(|c| c.name == error.name) {
+            Some(existing) => *existing = error,
+            None => candidates.push(error),
+        }
+    }
+
+    /// Every contract error declared with `code`.
+    pub fn custom_errors(&self, code: u32) -> &[ErrorExplanation] {
+        self.custom_errors.get(&code).map_or(&[], Vec::as_slice)
+    }
+
+    /// The contract error declared with `code`. If more than one enum
+    /// declares it, the explanation names every candidate.
+    fn custom_error(&self, code: u32) -> Option<ErrorExplanation> {
+        match self.custom_errors(code) {
+            [] => None,
+            [only] => Some(only.clone()),
+            candidates => Some(ErrorExplanation {
+                code,
+                name: candidates
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+                description: std::iter::once(format!(
+                    "Ambiguous: {} error enums declare code {}",
+                    candidates.len(),
+                    code
+                ))
+                .chain(
+                    candidates
+                        .iter()
+                        .map(|c| format!("  {}: {}", c.name, c.description)),
+                )
+                .collect::<Vec<_>>()
+                .join("\n"),
+                common_cause: String::new(),
+                suggested_fix: String::new(),
+            }),
+        }
+    }
+
+    /// Load the `#[contracterror]` variants from the WASM `contractspecv0`
+    /// section. Returns how many were loaded.
+    pub fn load_custom_errors_from_wasm(&mut self, wasm: &[u8]) -> crate::Result<usize> {
+        let errors = crate::utils::wasm::parse_contract_errors(wasm)?;
+        let count = errors.len();
+        for error in errors {
+            let description = if error.doc.is_empty() {
+                format!(
+                    "Contract error declared as {}::{}",
+                    error.enum_name, error.name
+                )
+            } else {
+                error.doc
+            };
+            self.add_custom_error(ErrorExplanation {
+                code: error.value,
+                name: format!("{}::{}", error.enum_name, error.name),
+                description,
+                common_cause: String::new(),
+                suggested_fix: String::new(),
+            });
+        }
+        Ok(count)
+    }
+
+    /// Explain an error raised by a contract invocation. Contract codes are
+    /// looked up in the errors loaded from the contract.
+    pub fn explain(&self, error: &ScError) -> Option<ErrorExplanation> {
+        match error {
+            ScError::Contract(code) => self.custom_error(*code),
+            ScError::WasmVm(code)
+            | ScError::Context(code)
+            | ScError::Storage(code)
+            | ScError::Object(code)
+            | ScError::Crypto(code)
+            | ScError::Events(code)
+            | ScError::Budget(code)
+            | ScError::Value(code)
+            | ScError::Auth(code) => Some(host_error_explanation(error.discriminant(), *code)),
+        }
+    }
+
+    /// Multi-line description of an error, e.g.
+    /// `Error(Contract, #7): TokenError::InsufficientBalance` followed by the
+    /// variant's doc comment.
+    pub fn describe(&self, error: &ScError) -> String {
+        let header = match error {
+            ScError::Contract(code) => format!("Error(Contract, #{})", code),
+            ScError::WasmVm(code)
+            | ScError::Context(code)
+            | ScError::Storage(code)
+            | ScError::Object(code)
+            | ScError::Crypto(code)
+            | ScError::Events(code)
+            | ScError::Budget(code)
+            | ScError::Value(code)
+            | ScError::Auth(code) => {
+                format!("Error({}, {})", error.discriminant().name(), code.name())
+            }
+        };
+
+        let Some(explanation) = self.explain(error) else {
+            return format!("{}: not declared in the contract's error spec", header);
+        };
+        let mut lines = vec![
+            format!("{}: {}", header, explanation.name),
+            format!("  {}", explanation.description),
+        ];
+        if !explanation.common_cause.is_empty() {
+            lines.push(format!("  Common cause: {}", explanation.common_cause));
+        }
+        if !explanation.suggested_fix.is_empty() {
+            lines.push(format!("  Suggested fix: {}", explanation.suggested_fix));
+        }
+        lines.join("\n")
     }
 
     pub fn load_custom_errors_from_spec(&mut self, spec_path: &str) -> Result<(), String> {
@@ -176,8 +307,253 @@ impl ErrorDatabase {
     }
 }
 
+/// Explanation for a host error, specific to the pair where the meaning is
+/// well known and composed from the type and code otherwise.
+fn host_error_explanation(error_type: ScErrorType, code: ScErrorCode) -> ErrorExplanation {
+    use ScErrorCode as C;
+    use ScErrorType as T;
+
+    let specific: Option<(&str, &str, &str, &str)> = match (error_type, code) {
+        (T::WasmVm, C::InvalidAction) => Some((
+            "WasmTrap",
+            "The contract's WebAssembly code trapped",
+            "A Rust panic: `panic!`, `unwrap()`/`expect()` on None or Err, an assertion, or an `unreachable` instruction",
+            "Check the panic message in the diagnostic events and the values the contract unwraps",
+        )),
+        (T::WasmVm, C::ArithDomain) => Some((
+            "WasmArithmeticTrap",
+            "A WebAssembly arithmetic instruction trapped",
+            "Integer division by zero or an overflowing signed division",
+            "Validate divisors and use checked arithmetic",
+        )),
+        (T::WasmVm, C::MissingValue) => Some((
+            "FunctionNotFound",
+            "The invoked function is not exported by the contract",
+            "A typo in the function name or calling a contract built from different code",
+            "List the exported functions with `inspect --functions` and check the contract ID",
+        )),
+        (T::WasmVm, C::UnexpectedSize) => Some((
+            "WrongArgumentCount",
+            "The function was called with the wrong number of arguments",
+            "The arguments do not match the function signature",
+            "Compare the arguments with the signature from `inspect --functions`",
+        )),
+        (T::Budget, C::ExceededLimit) => Some((
+            "BudgetExceeded",
+            "The CPU instruction or memory budget was exhausted",
+            "Unbounded loops, large collections, or expensive host calls",
+            "Reduce work per invocation and check `budget` / `profile` output for hotspots",
+        )),
+        (T::Storage, C::MissingValue) => Some((
+            "StorageEntryMissing",
+            "A storage entry that was accessed does not exist",
+            "Reading a key that was never written, or whose TTL expired and was archived",
+            "Check the key with `storage get` and initialize or extend the entry first",
+        )),
+        (T::Storage, C::ExceededLimit) => Some((
+            "StorageLimitExceeded",
+            "A storage access fell outside the footprint or an entry exceeded the size limit",
+            "Accessing keys not declared in the transaction footprint, or writing oversized values",
+            "Simulate the transaction to build the footprint and keep entries small",
+        )),
+        (T::Auth, C::InvalidAction) => Some((
+            "AuthorizationFailed",
+            "A `require_auth` check failed",
+            "The address did not authorize this invocation, or authorized different arguments",
+            "Provide the authorization (or mock it in tests) with exactly the invoked arguments",
+        )),
+        (T::Auth, C::ExistingValue) => Some((
+            "AuthorizationReplayed",
+            "An authorization nonce was already used",
+            "The same signed authorization entry was submitted twice",
+            "Sign a fresh authorization entry with a new nonce",
+        )),
+        (T::Context, C::InvalidAction) => Some((
+            "InvalidContext",
+            "The call is not allowed in the current context",
+            "Re-entering a contract that is already on the call stack",
+            "Restructure the calls so a contract is not re-entered",
+        )),
+        (T::Value, C::UnexpectedType) => Some((
+            "UnexpectedType",
+            "A value had a different type than expected",
+            "Arguments of the wrong type, or storage holding a value of another type",
+            "Check argument types against the function signature and the stored values",
+        )),
+        (T::Value, C::InvalidInput) => Some((
+            "InvalidValue",
+            "A value could not be converted",
+            "Out-of-range integers, invalid symbols, or malformed addresses",
+            "Validate inputs before converting them",
+        )),
+        (T::Object, C::IndexBounds) => Some((
+            "IndexOutOfBounds",
+            "An index was outside a Vec, Bytes or String",
+            "Accessing an element past the end of a collection",
+            "Check lengths before indexing",
+        )),
+        (T::Object, C::MissingValue) => Some((
+            "KeyNotFound",
+            "A key was not found in a Map",
+            "Reading a map entry that does not exist",
+            "Use `contains_key` or `try_get` before reading",
+        )),
+        (T::Object, C::ArithDomain) => Some((
+            "HostArithmeticOverflow",
+            "Host integer arithmetic overflowed or divided by zero",
+            "Overflow in 128/256-bit arithmetic performed by the host",
+            "Use checked arithmetic and validate operands",
+        )),
+        (T::Crypto, C::InvalidInput) => Some((
+            "CryptoInvalidInput",
+            "A cryptographic operation rejected its input",
+            "An invalid signature, public key, or hash length",
+            "Verify the signature, key and message being checked",
+        )),
+        _ => None,
+    };
+
+    match specific {
+        Some((name, description, common_cause, suggested_fix)) => ErrorExplanation {
+            code: code as u32,
+            name: name.to_string(),
+            description: description.to_string(),
+            common_cause: common_cause.to_string(),
+            suggested_fix: suggested_fix.to_string(),
+        },
+        None => ErrorExplanation {
+            code: code as u32,
+            name: format!("{}{}", error_type.name(), code.name()),
+            description: format!(
+                "{} ({})",
+                error_type_description(error_type),
+                error_code_description(code)
+            ),
+            common_cause: String::new(),
+            suggested_fix: String::new(),
+        },
+    }
+}
+
+fn error_type_description(error_type: ScErrorType) -> &'static str {
+    match error_type {
+        ScErrorType::Contract => "The contract returned an error",
+        ScErrorType::WasmVm => "The WebAssembly VM failed",
+        ScErrorType::Context => "The invocation context was invalid",
+        ScErrorType::Storage => "A ledger storage operation failed",
+        ScErrorType::Object => "An operation on a host object failed",
+        ScErrorType::Crypto => "A cryptographic operation failed",
+        ScErrorType::Events => "Publishing an event failed",
+        ScErrorType::Budget => "The resource budget was violated",
+        ScErrorType::Value => "A value was invalid",
+        ScErrorType::Auth => "An authorization check failed",
+    }
+}
+
+fn error_code_description(code: ScErrorCode) -> &'static str {
+    match code {
+        ScErrorCode::ArithDomain => "arithmetic overflow, underflow or division by zero",
+        ScErrorCode::IndexBounds => "index out of bounds",
+        ScErrorCode::InvalidInput => "invalid input",
+        ScErrorCode::MissingValue => "a required value is missing",
+        ScErrorCode::ExistingValue => "the value already exists",
+        ScErrorCode::ExceededLimit => "a limit was exceeded",
+        ScErrorCode::InvalidAction => "the operation is not allowed",
+        ScErrorCode::InternalError => "internal host error",
+        ScErrorCode::UnexpectedType => "a value had an unexpected type",
+        ScErrorCode::UnexpectedSize => "a value had an unexpected size",
+    }
+}
+
 impl Default for ErrorDatabase {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+
+    /// A contract whose spec declares `TokenError { InsufficientBalance = 7 }`.
+    fn wasm_with_error_spec() -> Vec<u8> {
+        let (mut module, _) = test_wasm::module();
+        test_wasm::add_error_spec(
+            &mut module,
+            "TokenError",
+            &[(
+                "InsufficientBalance",
+                7,
+                "The sender's balance is lower than the amount.",
+            )],
+        );
+        test_wasm::finish(module)
+    }
+
+    #[test]
+    fn codes_shared_by_several_enums_are_ambiguous() {
+        let (mut module, _) = test_wasm::module();
+        test_wasm::add_error_spec(&mut module, "TokenError", &[("Paused", 3, "Paused.")]);
+        test_wasm::add_error_spec(&mut module, "AdminError", &[("NotAdmin", 3, "")]);
+        let mut db = ErrorDatabase::from_wasm(&test_wasm::finish(module));
+        assert_eq!(db.custom_errors(3).len(), 2);
+
+        assert_eq!(
+            db.describe(&ScError::Contract(3)),
+            "Error(Contract, #3): TokenError::Paused or AdminError::NotAdmin\n  \
+             Ambiguous: 2 error enums declare code 3\n  \
+             TokenError::Paused: Paused.\n  \
+             AdminError::NotAdmin: Contract error declared as AdminError::NotAdmin"
+        );
+
+        // Adding a known error again replaces it
+        db.add_custom_error(ErrorExplanation {
+            code: 3,
+            name: "TokenError::Paused".to_string(),
+            description: "Transfers are paused.".to_string(),
+            common_cause: String::new(),
+            suggested_fix: String::new(),
+        });
+        assert_eq!(db.custom_errors(3).len(), 2);
+        assert_eq!(db.custom_errors(3)[0].description, "Transfers are paused.");
+    }
+
+    #[test]
+    fn explains_contract_errors_from_the_spec() {
+        let db = ErrorDatabase::from_wasm(&wasm_with_error_spec());
+
+        let described = db.describe(&ScError::Contract(7));
+        assert_eq!(
+            described,
+            "Error(Contract, #7): TokenError::InsufficientBalance\n  The sender's balance is lower than the amount."
+        );
+        assert_eq!(
+            db.describe(&ScError::Contract(8)),
+            "Error(Contract, #8): not declared in the contract's error spec"
+        );
+    }
+
+    #[test]
+    fn explains_host_errors() {
+        let db = ErrorDatabase::new();
+
+        let budget = db
+            .explain(&ScError::Budget(ScErrorCode::ExceededLimit))
+            .unwrap();
+        assert_eq!(budget.name, "BudgetExceeded");
+
+        let described = db.describe(&ScError::WasmVm(ScErrorCode::InvalidAction));
+        assert!(described.starts_with("Error(WasmVm, InvalidAction): WasmTrap"));
+        assert!(described.contains("Suggested fix:"));
+
+        let generic = db
+            .explain(&ScError::Events(ScErrorCode::InternalError))
+            .unwrap();
+        assert_eq!(generic.name, "EventsInternalError");
+        assert_eq!(
+            generic.description,
+            "Publishing an event failed (internal host error)"
+        );
+    }
+}
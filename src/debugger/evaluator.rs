@@ -0,0 +1,620 @@
+//! Expression evaluation for the interactive REPL.
+//!
+//! Expressions run against a fork of the session's ledger, so contract calls
+//! and storage reads never change the state being debugged.
+//!
+//! ```text
+//! expr    := or
+//! or      := and ("||" and)*
+//! and     := cmp ("&&" cmp)*
+//! cmp     := primary (("==" | "!=" | "<" | "<=" | ">" | ">=") primary)?
+//! primary := call | "[" list "]" | "(" expr ")" | identifier | json
+//! call    := identifier "(" list ")"
+//! ```
+//!
+//! Calls invoke contract functions, except for the built-ins `storage(key)`,
+//! `instance(key)`, `persistent(key)` and `temporary(key)`, which read the
+//! contract's storage. JSON literals use the same conventions as `--args`,
+//! including `{"type": "u32", "value": 1}` annotations. `let name = expr`
+//! binds a value for later expressions.
+
+use crate::runtime::executor::ContractExecutor;
+use crate::utils::ArgumentParser;
+use crate::{DebuggerError, Result};
+use serde_json::Value;
+use soroban_env_host::xdr::ScVal;
+use soroban_sdk::{Address, Env, InvokeError, Symbol, TryFromVal, Val, Vec as SorobanVec};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Built-in functions that read contract storage.
+const STORAGE_BUILTINS: &[&str] = &["storage", "instance", "persistent", "temporary"];
+
+/// A parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Variable(String),
+    List(Vec<Expr>),
+    Call { function: String, args: Vec<Expr> },
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A parsed REPL input: an expression, optionally bound to a name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub binding: Option<String>,
+    pub expr: Expr,
+}
+
+/// Result of evaluating a statement.
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub binding: Option<String>,
+    pub value: ScVal,
+}
+
+/// Evaluates expressions and keeps `let` bindings between them.
+#[derive(Debug, Default)]
+pub struct Evaluator {
+    variables: BTreeMap<String, ScVal>,
+}
+
+impl Evaluator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn variables(&self) -> &BTreeMap<String, ScVal> {
+        &self.variables
+    }
+
+    /// Parse and evaluate `input` against a fork of the executor's state.
+    pub fn evaluate(&mut self, executor: &ContractExecutor, input: &str) -> Result<Evaluation> {
+        let statement = parse_statement(input)?;
+        let (env, contract) = executor.fork()?;
+        let scope = Scope {
+            env: &env,
+            contract: &contract,
+            parser: ArgumentParser::new(env.clone()),
+            variables: &self.variables,
+        };
+        let val = scope.eval(&statement.expr)?;
+        let value = ScVal::try_from_val(&env, &val)
+            .map_err(|e| eval_error(format!("Cannot convert result: {:?}", e)))?;
+
+        if let Some(name) = &statement.binding {
+            self.variables.insert(name.clone(), value.clone());
+        }
+        Ok(Evaluation {
+            binding: statement.binding,
+            value,
+        })
+    }
+}
+
+struct Scope<'a> {
+    env: &'a Env,
+    contract: &'a Address,
+    parser: ArgumentParser,
+    variables: &'a BTreeMap<String, ScVal>,
+}
+
+impl Scope<'_> {
+    fn eval(&self, expr: &Expr) -> Result<Val> {
+        match expr {
+            Expr::Literal(json) => self
+                .parser
+                .parse_single_value(json)
+                .map_err(|e| eval_error(e.to_string())),
+            Expr::Variable(name) => {
+                let value = self
+                    .variables
+                    .get(name)
+                    .ok_or_else(|| eval_error(format!("Unknown variable '{}'", name)))?;
+                Val::try_from_val(self.env, value)
+                    .map_err(|e| eval_error(format!("Cannot load '{}': {:?}", name, e)))
+            }
+            Expr::List(items) => {
+                let mut vec = SorobanVec::<Val>::new(self.env);
+                for item in items {
+                    vec.push_back(self.eval(item)?);
+                }
+                Ok(vec.into())
+            }
+            Expr::Call { function, args } if STORAGE_BUILTINS.contains(&function.as_str()) => {
+                let [key] = args.as_slice() else {
+                    return Err(eval_error(format!("{}() takes exactly one key", function)));
+                };
+                let key = self.eval(key)?;
+                self.read_storage(function, key)
+            }
+            Expr::Call { function, args } => {
+                let mut vec = SorobanVec::<Val>::new(self.env);
+                for arg in args {
+                    vec.push_back(self.eval(arg)?);
+                }
+                self.call(function, vec)
+            }
+            Expr::Compare(lhs, op, rhs) => {
+                let lhs = self.eval_sc(lhs)?;
+                let rhs = self.eval_sc(rhs)?;
+                Ok(compare(&lhs, *op, &rhs)?.into())
+            }
+            Expr::And(lhs, rhs) => Ok((self.eval_bool(lhs)? && self.eval_bool(rhs)?).into()),
+            Expr::Or(lhs, rhs) => Ok((self.eval_bool(lhs)? || self.eval_bool(rhs)?).into()),
+        }
+    }
+
+    fn eval_sc(&self, expr: &Expr) -> Result<ScVal> {
+        let val = self.eval(expr)?;
+        ScVal::try_from_val(self.env, &val)
+            .map_err(|e| eval_error(format!("Cannot convert value: {:?}", e)))
+    }
+
+    fn eval_bool(&self, expr: &Expr) -> Result<bool> {
+        match self.eval_sc(expr)? {
+            ScVal::Bool(b) => Ok(b),
+            other => Err(eval_error(format!(
+                "Expected a bool but got {}",
+                crate::ui::formatter::Formatter::format_sc_val(&other)
+            ))),
+        }
+    }
+
+    fn call(&self, function: &str, args: SorobanVec<Val>) -> Result<Val> {
+        let symbol = Symbol::try_from_val(self.env, &function)
+            .map_err(|_| eval_error(format!("Invalid function name '{}'", function)))?;
+        match self
+            .env
+            .try_invoke_contract::<Val, InvokeError>(self.contract, &symbol, args)
+        {
+            Ok(Ok(val)) => Ok(val),
+            Ok(Err(e)) => Err(eval_error(format!(
+                "{}() returned an unconvertible value: {:?}",
+                function, e
+            ))),
+            Err(Ok(InvokeError::Contract(code))) => Err(eval_error(format!(
+                "{}() failed with contract error code {}",
+                function, code
+            ))),
+            Err(Ok(InvokeError::Abort)) => Err(eval_error(format!(
+                "{}() aborted (trap, panic, missing function or budget exhaustion)",
+                function
+            ))),
+            Err(Err(e)) => Err(eval_error(format!("{}() failed: {:?}", function, e))),
+        }
+    }
+
+    fn read_storage(&self, kind: &str, key: Val) -> Result<Val> {
+        let env = self.env;
+        let value = env.as_contract(self.contract, || {
+            let storage = env.storage();
+            let instance = || storage.instance().get::<Val, Val>(&key);
+            let persistent = || {
+                storage
+                    .persistent()
+                    .has(&key)
+                    .then(|| storage.persistent().get::<Val, Val>(&key))
+                    .flatten()
+            };
+            let temporary = || {
+                storage
+                    .temporary()
+                    .has(&key)
+                    .then(|| storage.temporary().get::<Val, Val>(&key))
+                    .flatten()
+            };
+            match kind {
+                "instance" => instance(),
+                "persistent" => persistent(),
+                "temporary" => temporary(),
+                _ => instance().or_else(persistent).or_else(temporary),
+            }
+        });
+        value.ok_or_else(|| eval_error(format!("No {} entry for that key", kind)))
+    }
+}
+
+/// Compare two values. Integers compare numerically across widths; other
+/// values must be of the same type to be ordered.
+fn compare(lhs: &ScVal, op: CompareOp, rhs: &ScVal) -> Result<bool> {
+    let ordering = match (as_integer(lhs), as_integer(rhs)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ if std::mem::discriminant(lhs) == std::mem::discriminant(rhs) => lhs.cmp(rhs),
+        _ if matches!(op, CompareOp::Eq | CompareOp::Ne) => Ordering::Less,
+        _ => {
+            return Err(eval_error(format!(
+                "Cannot order {} and {}",
+                crate::ui::formatter::Formatter::format_sc_val(lhs),
+                crate::ui::formatter::Formatter::format_sc_val(rhs)
+            )))
+        }
+    };
+    Ok(match op {
+        CompareOp::Eq => ordering == Ordering::Equal,
+        CompareOp::Ne => ordering != Ordering::Equal,
+        CompareOp::Lt => ordering == Ordering::Less,
+        CompareOp::Le => ordering != Ordering::Greater,
+        CompareOp::Gt => ordering == Ordering::Greater,
+        CompareOp::Ge => ordering != Ordering::Less,
+    })
+}
+
+fn as_integer(value: &ScVal) -> Option<i128> {
+    Some(match value {
+        ScVal::U32(v) => *v as i128,
+        ScVal::I32(v) => *v as i128,
+        ScVal::U64(v) => *v as i128,
+        ScVal::I64(v) => *v as i128,
+        ScVal::U128(parts) => i128::try_from(((parts.hi as u128) << 64) | parts.lo as u128).ok()?,
+        ScVal::I128(parts) => ((parts.hi as i128) << 64) | parts.lo as i128,
+        _ => return None,
+    })
+}
+
+fn eval_error(message: impl Into<String>) -> miette::Report {
+    DebuggerError::ExecutionError(message.into()).into()
+}
+
+/// Parse a REPL input line: `let name = expr` or `expr`.
+pub fn parse_statement(input: &str) -> Result<Statement> {
+    let input = input.trim();
+    let mut binding = None;
+    let mut rest = input;
+    if let Some(after_let) = input.strip_prefix("let ") {
+        let (name, expr) = after_let
+            .split_once('=')
+            .filter(|(_, expr)| !expr.starts_with('='))
+            .ok_or_else(|| parse_error("Expected 'let <name> = <expr>'"))?;
+        let name = name.trim();
+        if !is_identifier(name) {
+            return Err(parse_error(format!("Invalid variable name '{}'", name)));
+        }
+        binding = Some(name.to_string());
+        rest = expr;
+    }
+
+    let mut parser = Parser { src: rest, pos: 0 };
+    let expr = parser.expr()?;
+    parser.skip_ws();
+    if parser.pos < parser.src.len() {
+        return Err(parse_error(format!(
+            "Unexpected '{}' at column {}",
+            &parser.src[parser.pos..],
+            parser.pos + 1
+        )));
+    }
+    Ok(Statement { binding, expr })
+}
+
+fn parse_error(message: impl Into<String>) -> miette::Report {
+    DebuggerError::InvalidArguments(message.into()).into()
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.src.len() - trimmed.len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(parse_error(format!(
+                "Expected '{}' at column {}",
+                token,
+                self.pos + 1
+            )))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.and()?;
+        while self.eat("||") {
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut lhs = self.comparison()?;
+        while self.eat("&&") {
+            lhs = Expr::And(Box::new(lhs), Box::new(self.comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let lhs = self.primary()?;
+        let ops = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ];
+        for (token, op) in ops {
+            if self.eat(token) {
+                let rhs = self.primary()?;
+                return Ok(Expr::Compare(Box::new(lhs), op, Box::new(rhs)));
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn list(&mut self, close: &str) -> Result<Vec<Expr>> {
+        let mut items = Vec::new();
+        if self.eat(close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(self.expr()?);
+            if self.eat(close) {
+                return Ok(items);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        self.skip_ws();
+        let rest = self.rest();
+        let Some(first) = rest.chars().next() else {
+            return Err(parse_error("Unexpected end of expression"));
+        };
+
+        match first {
+            '(' => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            '[' => {
+                self.pos += 1;
+                Ok(Expr::List(self.list("]")?))
+            }
+            '"' | '{' => {
+                let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+                let value = stream
+                    .next()
+                    .ok_or_else(|| parse_error("Expected a JSON value"))?
+                    .map_err(|e| parse_error(format!("Invalid JSON literal: {}", e)))?;
+                self.pos += stream.byte_offset();
+                Ok(Expr::Literal(value))
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let len = rest
+                    .char_indices()
+                    .skip(1)
+                    .find(|(_, c)| !c.is_ascii_digit())
+                    .map(|(i, _)| i)
+                    .unwrap_or(rest.len());
+                let value = serde_json::from_str(&rest[..len])
+                    .map_err(|_| parse_error(format!("Invalid number '{}'", &rest[..len])))?;
+                self.pos += len;
+                Ok(Expr::Literal(value))
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                let name = rest[..len].to_string();
+                self.pos += len;
+                match name.as_str() {
+                    "true" => return Ok(Expr::Literal(Value::Bool(true))),
+                    "false" => return Ok(Expr::Literal(Value::Bool(false))),
+                    "null" => return Ok(Expr::Literal(Value::Null)),
+                    _ => {}
+                }
+                if self.eat("(") {
+                    Ok(Expr::Call {
+                        function: name,
+                        args: self.list(")")?,
+                    })
+                } else {
+                    Ok(Expr::Variable(name))
+                }
+            }
+            other => Err(parse_error(format!(
+                "Unexpected '{}' at column {}",
+                other,
+                self.pos + 1
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(function: &str, args: Vec<Expr>) -> Expr {
+        Expr::Call {
+            function: function.to_string(),
+            args,
+        }
+    }
+
+    #[test]
+    fn parses_calls_and_comparisons() {
+        let stmt = parse_statement("balance(alice) >= 100").unwrap();
+        assert_eq!(stmt.binding, None);
+        assert_eq!(
+            stmt.expr,
+            Expr::Compare(
+                Box::new(call("balance", vec![Expr::Variable("alice".into())])),
+                CompareOp::Ge,
+                Box::new(Expr::Literal(json!(100)))
+            )
+        );
+    }
+
+    #[test]
+    fn parses_let_lists_and_typed_literals() {
+        let stmt = parse_statement(r#"let b = storage(["Balance", {"type": "u32", "value": 7}])"#)
+            .unwrap();
+        assert_eq!(stmt.binding.as_deref(), Some("b"));
+        assert_eq!(
+            stmt.expr,
+            call(
+                "storage",
+                vec![Expr::List(vec![
+                    Expr::Literal(json!("Balance")),
+                    Expr::Literal(json!({"type": "u32", "value": 7})),
+                ])]
+            )
+        );
+    }
+
+    #[test]
+    fn logical_operators_bind_looser_than_comparisons() {
+        let stmt = parse_statement("a() == 1 && b() != -2 || true").unwrap();
+        let Expr::Or(lhs, rhs) = stmt.expr else {
+            panic!("expected ||");
+        };
+        assert!(matches!(*lhs, Expr::And(_, _)));
+        assert_eq!(*rhs, Expr::Literal(json!(true)));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(parse_statement("balance(").is_err());
+        assert!(parse_statement("1 2").is_err());
+        assert!(parse_statement("let 1x = 2").is_err());
+        assert!(parse_statement("let x == 2").is_err());
+    }
+
+    /// A contract with `echo(x) -> x` and `set(v)`, which stores `v` under
+    /// the persistent key `Counter`.
+    fn executor() -> ContractExecutor {
+        use crate::runtime::test_wasm;
+        use walrus::{FunctionBuilder, ValType};
+
+        let (mut module, _) = test_wasm::module();
+        let put_ty = module
+            .types
+            .add(&[ValType::I64, ValType::I64, ValType::I64], &[ValType::I64]);
+        let (put, _) = module.add_import_func("l", "_", put_ty);
+
+        let x = module.locals.add(ValType::I64);
+        let mut echo = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[ValType::I64]);
+        echo.func_body().local_get(x);
+        let echo = echo.finish(vec![x], &mut module.funcs);
+        module.exports.add("echo", echo);
+
+        let v = module.locals.add(ValType::I64);
+        let mut set = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[ValType::I64]);
+        set.func_body()
+            .i64_const(test_wasm::symbol("Counter"))
+            .local_get(v)
+            .i64_const(1) // StorageType::Persistent
+            .call(put);
+        let set = set.finish(vec![v], &mut module.funcs);
+        module.exports.add("set", set);
+
+        ContractExecutor::new(test_wasm::finish(module)).unwrap()
+    }
+
+    fn eval(evaluator: &mut Evaluator, executor: &ContractExecutor, input: &str) -> String {
+        let result = evaluator.evaluate(executor, input).unwrap();
+        crate::ui::formatter::Formatter::format_sc_val(&result.value)
+    }
+
+    #[test]
+    fn evaluates_calls_bindings_and_comparisons() {
+        let executor = executor();
+        let mut evaluator = Evaluator::new();
+
+        assert_eq!(eval(&mut evaluator, &executor, "echo(5)"), "5");
+        assert_eq!(eval(&mut evaluator, &executor, "let x = echo(7)"), "7");
+        assert_eq!(evaluator.variables().len(), 1);
+        assert_eq!(
+            eval(&mut evaluator, &executor, "echo(x) > 6 && x != 8"),
+            "true"
+        );
+        assert_eq!(
+            eval(&mut evaluator, &executor, r#"echo(["a", "b"])"#),
+            "[a, b]"
+        );
+        assert!(evaluator.evaluate(&executor, "echo(y)").is_err());
+        assert!(evaluator.evaluate(&executor, "missing()").is_err());
+    }
+
+    #[test]
+    fn reads_storage_without_mutating_the_session() {
+        let executor = executor();
+        let mut evaluator = Evaluator::new();
+        executor.execute("set", Some("[42]")).unwrap();
+
+        assert_eq!(
+            eval(&mut evaluator, &executor, r#"storage("Counter")"#),
+            "42"
+        );
+        assert_eq!(
+            eval(&mut evaluator, &executor, r#"persistent("Counter") == 42"#),
+            "true"
+        );
+        assert!(evaluator
+            .evaluate(&executor, r#"temporary("Counter")"#)
+            .is_err());
+
+        // Calls run in a fork, so this write is discarded
+        eval(&mut evaluator, &executor, "set(1)");
+        assert_eq!(
+            eval(&mut evaluator, &executor, r#"storage("Counter")"#),
+            "42"
+        );
+    }
+
+    #[test]
+    fn compares_integers_across_widths() {
+        use soroban_env_host::xdr::Int128Parts;
+        let big = ScVal::I128(Int128Parts { hi: 0, lo: 5 });
+        assert!(compare(&ScVal::U32(5), CompareOp::Eq, &big).unwrap());
+        assert!(compare(&ScVal::I64(-1), CompareOp::Lt, &big).unwrap());
+        assert!(compare(&ScVal::Bool(true), CompareOp::Ne, &big).unwrap());
+        assert!(compare(&ScVal::Bool(true), CompareOp::Lt, &big).is_err());
+    }
+}
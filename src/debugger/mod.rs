@@ -1,13 +1,19 @@
 pub mod breakpoint;
 pub mod engine;
 pub mod error_db;
+pub mod evaluator;
 pub mod instruction_pointer;
+pub mod session;
+pub mod source_map;
 pub mod state;
 pub mod stepper;
 
 pub use breakpoint::BreakpointManager;
 pub use engine::DebuggerEngine;
 pub use error_db::{ErrorDatabase, ErrorExplanation};
+pub use evaluator::{Evaluation, Evaluator};
 pub use instruction_pointer::{InstructionPointer, StepMode};
-pub use state::DebugState;
+pub use session::Session;
+pub use source_map::{SourceLocation, SourceMap, SourceVariable, VariableLocation};
+pub use state::{DebugState, ModuleFrame};
 pub use stepper::Stepper;
@@ -0,0 +1,259 @@
+//! Saved debugging sessions.
+//!
+//! A session file is self-contained JSON: the contract bytecode, the full
+//! ledger state (storage, ledger info and other contracts), mocks,
+//! breakpoints, watch expressions and the commands that were entered. It can
+//! be reopened later or on another machine with `interactive --session`.
+
+use crate::config::{Config, DebugConfig};
+use crate::debugger::engine::DebuggerEngine;
+use crate::inspector::storage::StorageState;
+use crate::runtime::ContractExecutor;
+use crate::utils::wasm::compute_wasm_sha256;
+use crate::{DebuggerError, Result};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use soroban_env_host::xdr::ScAddress;
+use soroban_sdk::testutils::Snapshot;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Version written to new session files.
+pub const SESSION_VERSION: u32 = 1;
+
+/// The contract a session was debugging.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionContract {
+    /// Where the WASM was loaded from, for display only
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    pub sha256: String,
+    /// Base64-encoded WASM bytecode
+    pub wasm: String,
+    /// Address the contract ran at, which a network snapshot may have set.
+    /// Older sessions use the address of a freshly registered contract.
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+/// Everything needed to reopen a debugging session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub contract: SessionContract,
+    /// Breakpoints are kept in `config.debug.breakpoints`
+    #[serde(default)]
+    pub config: Config,
+    /// Ledger info, storage and every deployed contract
+    pub state: Snapshot,
+    /// The REPL's tracked storage view
+    #[serde(default)]
+    pub storage: StorageState,
+    #[serde(default)]
+    pub mocks: Vec<String>,
+    #[serde(default)]
+    pub watches: Vec<String>,
+    #[serde(default)]
+    pub history: Vec<String>,
+    #[serde(default)]
+    pub instruction_debug: bool,
+}
+
+impl Session {
+    /// Capture the current state of `engine`.
+    pub fn capture(engine: &mut DebuggerEngine, contract_path: Option<&Path>) -> Self {
+        let mut breakpoints = engine.breakpoints_mut().list();
+        breakpoints.sort();
+        let executor = engine.executor();
+        let wasm = executor.wasm_bytes();
+
+        Self {
+            version: SESSION_VERSION,
+            contract: SessionContract {
+                path: contract_path.map(Path::to_path_buf),
+                sha256: compute_wasm_sha256(wasm),
+                wasm: base64::engine::general_purpose::STANDARD.encode(wasm),
+                address: Some(executor.contract_id()),
+            },
+            config: Config {
+                debug: DebugConfig {
+                    breakpoints,
+                    verbosity: None,
+                },
+                ..Config::default()
+            },
+            state: executor.state_snapshot(),
+            storage: StorageState::default(),
+            mocks: executor.mock_specs().to_vec(),
+            watches: Vec::new(),
+            history: Vec::new(),
+            instruction_debug: engine.is_instruction_debug_enabled(),
+        }
+    }
+
+    /// Write the session as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| DebuggerError::FileError(format!("Failed to serialize session: {}", e)))?;
+        fs::write(path, json).map_err(|e| {
+            DebuggerError::FileError(format!("Failed to write session file {:?}: {}", path, e))
+        })?;
+        Ok(())
+    }
+
+    /// Read a session file and check its embedded contract.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            DebuggerError::FileError(format!("Failed to read session file {:?}: {}", path, e))
+        })?;
+        let session: Session = serde_json::from_str(&contents).map_err(|e| {
+            DebuggerError::FileError(format!("Failed to parse session file {:?}: {}", path, e))
+        })?;
+        if session.version > SESSION_VERSION {
+            return Err(DebuggerError::FileError(format!(
+                "Session file {:?} has version {}; this debugger supports up to {}",
+                path, session.version, SESSION_VERSION
+            ))
+            .into());
+        }
+
+        let actual = compute_wasm_sha256(&session.wasm_bytes()?);
+        if actual != session.contract.sha256 {
+            return Err(DebuggerError::ChecksumMismatch {
+                expected: session.contract.sha256.clone(),
+                actual,
+            }
+            .into());
+        }
+        Ok(session)
+    }
+
+    /// The embedded contract bytecode.
+    pub fn wasm_bytes(&self) -> Result<Vec<u8>> {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.contract.wasm)
+            .map_err(|e| {
+                DebuggerError::FileError(format!("Session contains invalid WASM data: {}", e))
+                    .into()
+            })
+    }
+
+    /// Rebuild a debugger engine with the saved state, mocks and breakpoints.
+    pub fn restore(&self) -> Result<DebuggerEngine> {
+        let wasm = self.wasm_bytes()?;
+        let address = self
+            .contract
+            .address
+            .as_deref()
+            .map(|address| {
+                ScAddress::from_str(address).map_err(|e| {
+                    DebuggerError::FileError(format!(
+                        "Session has an invalid contract address {}: {}",
+                        address, e
+                    ))
+                })
+            })
+            .transpose()?;
+        let mut executor = ContractExecutor::new(wasm.clone())?;
+        executor.restore_snapshot(self.state.clone(), address)?;
+        if !self.mocks.is_empty() {
+            executor.set_mock_specs(&self.mocks)?;
+        }
+
+        let mut engine = DebuggerEngine::new(executor, self.config.debug.breakpoints.clone());
+        if self.instruction_debug {
+            engine.enable_instruction_debug(&wasm)?;
+        }
+        Ok(engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{test_wasm, StorageKind};
+    use crate::simulator::{ContractState, NetworkSnapshot, SnapshotLoader};
+    use serde_json::json;
+
+    fn engine() -> DebuggerEngine {
+        let (module, _) = test_wasm::module();
+        let executor = ContractExecutor::new(test_wasm::finish(module)).unwrap();
+        DebuggerEngine::new(executor, vec!["transfer".to_string()])
+    }
+
+    #[test]
+    fn session_round_trips_state_and_settings() {
+        let mut engine = engine();
+        let executor = engine.executor_mut();
+        executor
+            .storage_set(
+                StorageKind::Persistent,
+                &json!("counter"),
+                &json!({"type": "u32", "value": 41}),
+            )
+            .unwrap();
+        let (sequence, timestamp) = executor.advance_ledger(3, 15);
+
+        let mut session = Session::capture(&mut engine, Some(Path::new("token.wasm")));
+        session.watches = vec!["counter".to_string()];
+        session.history = vec!["call transfer".to_string()];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("debug.session.json");
+        session.save(&path).unwrap();
+
+        let loaded = Session::load(&path).unwrap();
+        assert_eq!(loaded.contract.path, Some(PathBuf::from("token.wasm")));
+        assert_eq!(loaded.watches, session.watches);
+        assert_eq!(loaded.history, session.history);
+
+        let mut restored = loaded.restore().unwrap();
+        assert_eq!(restored.breakpoints_mut().list(), vec!["transfer"]);
+        let executor = restored.executor();
+        assert_eq!(executor.ledger_info(), (sequence, timestamp));
+        assert_eq!(
+            executor
+                .storage_get(StorageKind::Persistent, &json!("counter"))
+                .unwrap(),
+            Some(soroban_env_host::xdr::ScVal::U32(41))
+        );
+    }
+
+    #[test]
+    fn sessions_restore_the_contract_a_network_snapshot_chose() {
+        let mut engine = engine();
+        let wasm = engine.executor().wasm_bytes().to_vec();
+        let mut snapshot = NetworkSnapshot::new(100, "Test Network", 1234567890);
+        let mut contract = ContractState::new("11".repeat(32), compute_wasm_sha256(&wasm));
+        contract
+            .persistent_storage
+            .insert("owner".to_string(), json!({"type": "u32", "value": 9}));
+        snapshot.add_contract(contract).unwrap();
+        let loader = SnapshotLoader::from_json(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        loader.apply_to_environment(engine.executor_mut()).unwrap();
+        let address = engine.executor().contract_id();
+
+        let session = Session::capture(&mut engine, None);
+        let restored = session.restore().unwrap();
+        let executor = restored.executor();
+        assert_eq!(executor.contract_id(), address);
+        assert_eq!(
+            executor
+                .storage_get(StorageKind::Persistent, &json!("owner"))
+                .unwrap(),
+            Some(soroban_env_host::xdr::ScVal::U32(9))
+        );
+    }
+
+    #[test]
+    fn tampered_contract_is_rejected() {
+        let mut session = Session::capture(&mut engine(), None);
+        session.contract.sha256 = "0".repeat(64);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad.json");
+        session.save(&path).unwrap();
+        assert!(Session::load(&path).is_err());
+    }
+}
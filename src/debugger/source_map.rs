@@ -1,10 +1,13 @@
-use crate::{Result, DebuggerError};
+use crate::{DebuggerError, Result};
 use gimli::{Dwarf, EndianSlice, RunTimeEndian};
-use object::{Object, ObjectSection};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
+type DwarfReader<'a> = EndianSlice<'a, RunTimeEndian>;
+
 /// Represents a source code location
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct SourceLocation {
@@ -13,10 +16,56 @@ pub struct SourceLocation {
     pub column: Option<u32>,
 }
 
+impl SourceLocation {
+    /// Whether this is `line` of `file`. DWARF paths and editor paths are
+    /// often relative to different roots, so either may be a suffix of the other.
+    pub fn matches(&self, file: &Path, line: u32) -> bool {
+        self.line == line && (self.file.ends_with(file) || file.ends_with(&self.file))
+    }
+}
+
+/// Where a source-level variable lives at runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VariableLocation {
+    /// A WASM local of the enclosing function
+    Local { index: u32 },
+    /// A WASM global
+    Global { index: u32 },
+    /// A slot on the WASM operand stack
+    Stack { index: u32 },
+    /// An offset from the frame base in linear memory
+    FrameOffset { offset: i64 },
+    /// The variable was optimized out or uses an unsupported expression
+    Unavailable,
+}
+
+/// A Rust-level variable described by DWARF debug info
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceVariable {
+    pub name: String,
+    pub type_name: Option<String>,
+    pub location: VariableLocation,
+    pub function: Option<String>,
+    pub is_param: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ScopedVariable {
+    ranges: Vec<Range<usize>>,
+    variable: SourceVariable,
+}
+
 /// Manages mapping from WASM offsets to source code locations
+///
+/// All offsets are absolute byte offsets into the WASM binary, matching
+/// [`crate::runtime::instruction::Instruction::offset`]. DWARF addresses in
+/// WASM are relative to the code section and are rebased while loading.
 pub struct SourceMap {
     /// Mapping from offset to source location (sorted by offset)
     offsets: BTreeMap<usize, SourceLocation>,
+    /// Variables with the offset ranges in which they are live
+    variables: Vec<ScopedVariable>,
     /// Cache of source file contents
     source_cache: HashMap<PathBuf, String>,
 }
@@ -26,60 +75,41 @@ impl SourceMap {
     pub fn new() -> Self {
         Self {
             offsets: BTreeMap::new(),
+            variables: Vec::new(),
             source_cache: HashMap::new(),
         }
     }
 
     /// Load debug info from WASM bytes and build the mapping
     pub fn load(&mut self, wasm_bytes: &[u8]) -> Result<()> {
-        let obj = object::File::parse(wasm_bytes)
-            .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to parse WASM object file: {}", e)))?;
+        let (sections, code_offset) = collect_dwarf_sections(wasm_bytes)?;
 
         let load_section =
-            |id: gimli::SectionId| -> Result<EndianSlice<RunTimeEndian>, gimli::Error> {
-                let section = obj.section_by_name(id.name()).unwrap_or_else(|| {
-                    obj.section_by_name(&format!(".{}", id.name()))
-                        .unwrap_or_else(|| object::Section {
-                            index: object::SectionIndex(0),
-                            id: object::SectionId(0),
-                            name: id.name(),
-                            segment: None,
-                            address: 0,
-                            size: 0,
-                            align: 0,
-                            data_range: None,
-                            data: &[],
-                            relocations: Vec::new(),
-                            flags: object::SectionFlags::None,
-                            symbol_index: None,
-                            kind: object::SectionKind::Other,
-                        })
-                });
-                Ok(EndianSlice::new(
-                    section.data().unwrap_or(&[]),
-                    RunTimeEndian::Little,
-                ))
+            |id: gimli::SectionId| -> std::result::Result<DwarfReader, gimli::Error> {
+                let data = sections.get(id.name()).copied().unwrap_or(&[]);
+                Ok(EndianSlice::new(data, RunTimeEndian::Little))
             };
 
-        let dwarf = Dwarf::load(&load_section)
-            .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to load DWARF sections: {}", e)))?;
+        let dwarf = Dwarf::load(load_section).map_err(dwarf_error)?;
 
         let mut units = dwarf.units();
-        while let Some(header) = units.next()
-            .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to read DWARF unit: {}", e)))? {
-            let unit = dwarf.unit(header)
-                .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to load DWARF unit: {}", e)))?;
+        while let Some(header) = units.next().map_err(dwarf_error)? {
+            let unit = dwarf.unit(header).map_err(dwarf_error)?;
             if let Some(program) = unit.line_program.clone() {
                 let mut rows = program.rows();
-                while let Some((header, row)) = rows.next_row()
-                    .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to read DWARF line row: {}", e)))? {
+                while let Some((header, row)) = rows.next_row().map_err(dwarf_error)? {
+                    if row.end_sequence() {
+                        continue;
+                    }
                     if let Some(file_path) =
                         self.get_file_path(&dwarf, &unit, header, row.file_index())
                     {
-                        // In WASM, DWARF addresses are usually offsets into the code section
-                        let offset = row.address() as usize;
+                        let offset = code_offset + row.address() as usize;
                         let line = row.line().map(|l| l.get() as u32).unwrap_or(0);
-                        let column = row.column().column().map(|c| c.get() as u32);
+                        let column = match row.column() {
+                            gimli::ColumnType::LeftEdge => None,
+                            gimli::ColumnType::Column(c) => Some(c.get() as u32),
+                        };
 
                         self.offsets.insert(
                             offset,
@@ -92,6 +122,16 @@ impl SourceMap {
                     }
                 }
             }
+
+            let mut tree = unit.entries_tree(None).map_err(dwarf_error)?;
+            let root = tree.root().map_err(dwarf_error)?;
+            let mut collector = VariableCollector {
+                dwarf: &dwarf,
+                unit: &unit,
+                code_offset,
+                variables: &mut self.variables,
+            };
+            collector.walk(root, &[], None).map_err(dwarf_error)?;
         }
 
         Ok(())
@@ -99,9 +139,9 @@ impl SourceMap {
 
     fn get_file_path(
         &self,
-        dwarf: &Dwarf<EndianSlice<RunTimeEndian>>,
-        unit: &gimli::Unit<EndianSlice<RunTimeEndian>>,
-        header: &gimli::LineProgramHeader<EndianSlice<RunTimeEndian>>,
+        dwarf: &Dwarf<DwarfReader>,
+        unit: &gimli::Unit<DwarfReader>,
+        header: &gimli::LineProgramHeader<DwarfReader>,
         file_index: u64,
     ) -> Option<PathBuf> {
         let file = header.file(file_index)?;
@@ -127,11 +167,38 @@ impl SourceMap {
             .map(|(_, loc)| loc.clone())
     }
 
+    /// Whether any code maps to `line` of `file`
+    pub fn contains_line(&self, file: &Path, line: u32) -> bool {
+        self.offsets.values().any(|loc| loc.matches(file, line))
+    }
+
     /// (Internal/Test) Manually add a mapping
     pub fn add_mapping(&mut self, offset: usize, loc: SourceLocation) {
         self.offsets.insert(offset, loc);
     }
 
+    /// (Internal/Test) Manually add a variable that is live over `range`
+    pub fn add_variable(&mut self, range: Range<usize>, variable: SourceVariable) {
+        self.variables.push(ScopedVariable {
+            ranges: vec![range],
+            variable,
+        });
+    }
+
+    /// Variables that are in scope at the given WASM offset
+    pub fn variables_at(&self, offset: usize) -> Vec<SourceVariable> {
+        self.variables
+            .iter()
+            .filter(|v| v.ranges.iter().any(|r| r.contains(&offset)))
+            .map(|v| v.variable.clone())
+            .collect()
+    }
+
+    /// Whether any line or variable information was loaded
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty() && self.variables.is_empty()
+    }
+
     /// Get source code line for a given location
     pub fn get_source_line(&mut self, location: &SourceLocation) -> Option<String> {
         let content = self.get_source_content(&location.file)?;
@@ -158,3 +225,218 @@ impl SourceMap {
         self.source_cache.clear();
     }
 }
+
+impl Default for SourceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn dwarf_error(e: gimli::Error) -> miette::Report {
+    DebuggerError::WasmLoadError(format!("Failed to read DWARF debug info: {}", e)).into()
+}
+
+/// Collect the `.debug_*` custom sections and the code section start offset
+fn collect_dwarf_sections(wasm_bytes: &[u8]) -> Result<(HashMap<&str, &[u8]>, usize)> {
+    use wasmparser::{Parser, Payload};
+
+    let mut sections = HashMap::new();
+    let mut code_offset = 0;
+
+    for payload in Parser::new(0).parse_all(wasm_bytes) {
+        let payload = payload
+            .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to parse WASM: {}", e)))?;
+        match payload {
+            Payload::CodeSectionStart { range, .. } => code_offset = range.start,
+            Payload::CustomSection(reader) if reader.name().starts_with(".debug_") => {
+                sections.insert(reader.name(), reader.data());
+            }
+            _ => {}
+        }
+    }
+
+    Ok((sections, code_offset))
+}
+
+struct VariableCollector<'a, 'd> {
+    dwarf: &'a Dwarf<DwarfReader<'d>>,
+    unit: &'a gimli::Unit<DwarfReader<'d>>,
+    code_offset: usize,
+    variables: &'a mut Vec<ScopedVariable>,
+}
+
+impl<'d> VariableCollector<'_, 'd> {
+    fn walk(
+        &mut self,
+        node: gimli::EntriesTreeNode<DwarfReader<'d>>,
+        scope: &[Range<usize>],
+        function: Option<&str>,
+    ) -> gimli::Result<()> {
+        let entry = node.entry();
+        let tag = entry.tag();
+
+        let mut scope = scope.to_vec();
+        let mut function = function.map(str::to_string);
+
+        match tag {
+            gimli::DW_TAG_subprogram
+            | gimli::DW_TAG_inlined_subroutine
+            | gimli::DW_TAG_lexical_block => {
+                let ranges = self.ranges(entry)?;
+                if !ranges.is_empty() {
+                    scope = ranges;
+                }
+                if tag != gimli::DW_TAG_lexical_block {
+                    if let Some(name) = self.name(entry)? {
+                        function = Some(name);
+                    }
+                }
+            }
+            gimli::DW_TAG_formal_parameter | gimli::DW_TAG_variable if !scope.is_empty() => {
+                self.collect_variable(entry, &scope, function.as_deref())?;
+            }
+            _ => {}
+        }
+
+        let mut children = node.children();
+        while let Some(child) = children.next()? {
+            self.walk(child, &scope, function.as_deref())?;
+        }
+        Ok(())
+    }
+
+    fn collect_variable(
+        &mut self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader<'d>>,
+        scope: &[Range<usize>],
+        function: Option<&str>,
+    ) -> gimli::Result<()> {
+        let Some(name) = self.name(entry)? else {
+            return Ok(());
+        };
+        let type_name = self.type_name(entry, 0)?;
+        let is_param = entry.tag() == gimli::DW_TAG_formal_parameter;
+
+        let make = |location| SourceVariable {
+            name: name.clone(),
+            type_name: type_name.clone(),
+            location,
+            function: function.map(str::to_string),
+            is_param,
+        };
+
+        let Some(attr) = entry.attr_value(gimli::DW_AT_location)? else {
+            self.variables.push(ScopedVariable {
+                ranges: scope.to_vec(),
+                variable: make(VariableLocation::Unavailable),
+            });
+            return Ok(());
+        };
+
+        if let gimli::AttributeValue::Exprloc(expr) = attr {
+            self.variables.push(ScopedVariable {
+                ranges: scope.to_vec(),
+                variable: make(self.location(expr)?),
+            });
+        } else if let Some(mut locations) = self.dwarf.attr_locations(self.unit, attr)? {
+            while let Some(entry) = locations.next()? {
+                let range = self.code_offset + entry.range.begin as usize
+                    ..self.code_offset + entry.range.end as usize;
+                self.variables.push(ScopedVariable {
+                    ranges: vec![range],
+                    variable: make(self.location(entry.data)?),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn location(
+        &self,
+        expr: gimli::Expression<DwarfReader<'d>>,
+    ) -> gimli::Result<VariableLocation> {
+        let mut ops = expr.operations(self.unit.encoding());
+        let location = match ops.next()? {
+            Some(gimli::Operation::WasmLocal { index }) => VariableLocation::Local { index },
+            Some(gimli::Operation::WasmGlobal { index }) => VariableLocation::Global { index },
+            Some(gimli::Operation::WasmStack { index }) => VariableLocation::Stack { index },
+            Some(gimli::Operation::FrameOffset { offset }) => {
+                VariableLocation::FrameOffset { offset }
+            }
+            _ => VariableLocation::Unavailable,
+        };
+        Ok(location)
+    }
+
+    fn ranges(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader<'d>>,
+    ) -> gimli::Result<Vec<Range<usize>>> {
+        let mut ranges = Vec::new();
+        let mut iter = self.dwarf.die_ranges(self.unit, entry)?;
+        while let Some(range) = iter.next()? {
+            if range.begin < range.end {
+                ranges.push(
+                    self.code_offset + range.begin as usize..self.code_offset + range.end as usize,
+                );
+            }
+        }
+        Ok(ranges)
+    }
+
+    /// Resolve `DW_AT_name`, following `DW_AT_abstract_origin` for inlined entries
+    fn name(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader<'d>>,
+    ) -> gimli::Result<Option<String>> {
+        if let Some(attr) = entry.attr_value(gimli::DW_AT_name)? {
+            let name = self.dwarf.attr_string(self.unit, attr)?;
+            return Ok(Some(name.to_string_lossy().into_owned()));
+        }
+        if let Some(gimli::AttributeValue::UnitRef(offset)) =
+            entry.attr_value(gimli::DW_AT_abstract_origin)?
+        {
+            let origin = self.unit.entry(offset)?;
+            return self.name(&origin);
+        }
+        Ok(None)
+    }
+
+    /// Resolve a readable type name for `DW_AT_type`
+    fn type_name(
+        &self,
+        entry: &gimli::DebuggingInformationEntry<DwarfReader<'d>>,
+        depth: usize,
+    ) -> gimli::Result<Option<String>> {
+        if depth > 8 {
+            return Ok(None);
+        }
+        let offset = match entry.attr_value(gimli::DW_AT_type)? {
+            Some(gimli::AttributeValue::UnitRef(offset)) => offset,
+            _ => {
+                if let Some(gimli::AttributeValue::UnitRef(offset)) =
+                    entry.attr_value(gimli::DW_AT_abstract_origin)?
+                {
+                    let origin = self.unit.entry(offset)?;
+                    return self.type_name(&origin, depth + 1);
+                }
+                return Ok(None);
+            }
+        };
+
+        let ty = self.unit.entry(offset)?;
+        let inner = || self.type_name(&ty, depth + 1);
+        let name = match ty.tag() {
+            gimli::DW_TAG_pointer_type => Some(format!(
+                "*{}",
+                inner()?.unwrap_or_else(|| "void".to_string())
+            )),
+            gimli::DW_TAG_reference_type => {
+                Some(format!("&{}", inner()?.unwrap_or_else(|| "?".to_string())))
+            }
+            gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type => inner()?,
+            _ => self.name(&ty)?.or(inner()?),
+        };
+        Ok(name)
+    }
+}
@@ -2,6 +2,31 @@ use crate::debugger::instruction_pointer::{InstructionPointer, StepMode};
 use crate::inspector::stack::CallStackInspector;
 use crate::runtime::instruction::Instruction;
 
+/// The contract whose code is being stepped through. Stepping into a
+/// cross-contract call enters a new module; returning leaves it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleFrame {
+    pub contract_id: Option<String>,
+    /// Exported function the contract was invoked with.
+    pub function: Option<String>,
+    /// Index of that function in the module.
+    pub function_index: Option<u32>,
+    /// Position of this invocation in the traced call list.
+    pub trace_index: Option<usize>,
+    /// Cross-contract calls stepped past or into so far. The next call is
+    /// the invocation's child at this position in the traced call list.
+    pub calls_made: usize,
+}
+
+/// A caller module suspended while stepping through its callee.
+#[derive(Debug, Clone)]
+struct SuspendedModule {
+    frame: ModuleFrame,
+    instructions: Vec<Instruction>,
+    instruction_pointer: InstructionPointer,
+    current_instruction: Option<Instruction>,
+}
+
 /// Represents the current state of the debugger.
 #[derive(Debug, Clone)]
 pub struct DebugState {
@@ -13,6 +38,8 @@ pub struct DebugState {
     instructions: Vec<Instruction>,
     instruction_debug_enabled: bool,
     call_stack: CallStackInspector,
+    module: ModuleFrame,
+    callers: Vec<SuspendedModule>,
 }
 
 impl DebugState {
@@ -27,6 +54,8 @@ impl DebugState {
             instructions: Vec::new(),
             instruction_debug_enabled: false,
             call_stack: CallStackInspector::new(),
+            module: ModuleFrame::default(),
+            callers: Vec::new(),
         }
     }
 
@@ -55,6 +84,8 @@ impl DebugState {
     }
 
     pub fn set_instructions(&mut self, instructions: Vec<Instruction>) {
+        self.callers.clear();
+        self.module = ModuleFrame::default();
         self.instructions = instructions;
         self.current_instruction = self.instructions.first().cloned();
         self.instruction_pointer.reset();
@@ -144,7 +175,76 @@ impl DebugState {
         &mut self.call_stack
     }
 
+    /// The module being stepped through.
+    pub fn module(&self) -> &ModuleFrame {
+        &self.module
+    }
+
+    pub fn module_mut(&mut self) -> &mut ModuleFrame {
+        &mut self.module
+    }
+
+    /// Number of caller modules suspended below the current one.
+    pub fn module_depth(&self) -> usize {
+        self.callers.len()
+    }
+
+    /// Every module on the stack with its current instruction, innermost
+    /// first.
+    pub fn modules(&self) -> Vec<(&ModuleFrame, Option<&Instruction>)> {
+        std::iter::once((&self.module, self.current_instruction.as_ref()))
+            .chain(
+                self.callers
+                    .iter()
+                    .rev()
+                    .map(|c| (&c.frame, c.current_instruction.as_ref())),
+            )
+            .collect()
+    }
+
+    /// Suspend the current module and step through `instructions` of a
+    /// callee instead, starting at index `entry`.
+    pub fn enter_module(
+        &mut self,
+        frame: ModuleFrame,
+        instructions: Vec<Instruction>,
+        entry: usize,
+    ) {
+        let mut pointer = InstructionPointer::new();
+        if self.instruction_pointer.is_stepping() {
+            pointer.start_stepping(self.instruction_pointer.step_mode());
+        }
+        self.callers.push(SuspendedModule {
+            frame: std::mem::replace(&mut self.module, frame),
+            instructions: std::mem::replace(&mut self.instructions, instructions),
+            instruction_pointer: std::mem::replace(&mut self.instruction_pointer, pointer),
+            current_instruction: self.current_instruction.take(),
+        });
+        self.current_instruction = self.instructions.get(entry).cloned();
+        self.instruction_pointer.advance_to(entry);
+        self.instruction_pointer.clear_history();
+    }
+
+    /// Return to the caller module, positioned at its call instruction.
+    /// Returns `false` at the outermost module.
+    pub fn leave_module(&mut self) -> bool {
+        let Some(caller) = self.callers.pop() else {
+            return false;
+        };
+        self.module = caller.frame;
+        self.instructions = caller.instructions;
+        self.instruction_pointer = caller.instruction_pointer;
+        self.current_instruction = caller.current_instruction;
+        true
+    }
+
+    /// Leave every callee module.
+    pub fn reset_modules(&mut self) {
+        while self.leave_module() {}
+    }
+
     pub fn reset(&mut self) {
+        self.reset_modules();
         self.current_function = None;
         self.current_args = None;
         self.step_count = 0;
@@ -0,0 +1,991 @@
+//! Inspection of WASM locals and the operand stack at a paused instruction.
+//!
+//! Instruction stepping walks the parsed instruction list rather than a live
+//! VM, so values are reconstructed by abstractly interpreting the current
+//! function from its entry up to the paused instruction. Constants, copies
+//! between locals and simple integer arithmetic are tracked; anything that
+//! depends on runtime input (parameters, memory, host calls) or that crosses a
+//! control-flow merge point is reported as unknown.
+
+use crate::debugger::source_map::{SourceMap, SourceVariable, VariableLocation};
+use crate::runtime::instruction::Instruction;
+use crate::{DebuggerError, Result};
+use serde::Serialize;
+use soroban_env_host::Val;
+use std::collections::HashMap;
+use std::fmt;
+use wasmparser::{BlockType, Operator, ValType};
+
+/// A concrete WASM value
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum WasmValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl WasmValue {
+    /// Decode an i64 as a Soroban `Val`, if it is a well-formed one
+    pub fn as_soroban_val(&self) -> Option<String> {
+        match self {
+            WasmValue::I64(v) => {
+                let val = Val::from_payload(*v as u64);
+                val.is_good().then(|| format!("{:?}", val))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WasmValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WasmValue::I32(v) => write!(f, "{}", v),
+            WasmValue::I64(v) => match self.as_soroban_val() {
+                Some(val) => write!(f, "{} ({})", v, val),
+                None => write!(f, "{}", v),
+            },
+            WasmValue::F32(v) => write!(f, "{}", v),
+            WasmValue::F64(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// A local (parameter or declared local) of the current function
+#[derive(Debug, Clone, Serialize)]
+pub struct WasmLocal {
+    pub index: u32,
+    pub name: Option<String>,
+    pub value_type: String,
+    pub is_param: bool,
+    pub value: Option<WasmValue>,
+}
+
+/// A single slot on the operand stack
+#[derive(Debug, Clone, Serialize)]
+pub struct StackValue {
+    pub value_type: String,
+    pub value: Option<WasmValue>,
+}
+
+/// A DWARF source variable with its value, when it can be resolved
+#[derive(Debug, Clone, Serialize)]
+pub struct VariableValue {
+    #[serde(flatten)]
+    pub variable: SourceVariable,
+    pub value: Option<WasmValue>,
+}
+
+/// Locals, operand stack and source variables at the paused instruction
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalsSnapshot {
+    pub function_index: u32,
+    pub function_name: Option<String>,
+    pub instruction_offset: usize,
+    pub locals: Vec<WasmLocal>,
+    /// Operand stack, bottom first
+    pub value_stack: Vec<StackValue>,
+    /// False when an instruction with an unmodelled stack effect was passed
+    pub stack_exact: bool,
+    pub variables: Vec<VariableValue>,
+}
+
+#[derive(Debug, Clone)]
+struct GlobalInfo {
+    ty: ValType,
+    mutable: bool,
+    init: Option<WasmValue>,
+}
+
+/// Reconstructs locals and operand stack contents for a parsed module
+pub struct LocalsInspector {
+    types: Vec<(Vec<ValType>, Vec<ValType>)>,
+    function_types: Vec<u32>,
+    imported_functions: u32,
+    globals: Vec<GlobalInfo>,
+    function_locals: Vec<Vec<ValType>>,
+    function_names: HashMap<u32, String>,
+    local_names: HashMap<u32, HashMap<u32, String>>,
+}
+
+impl LocalsInspector {
+    /// Parse type, function, global, code and name sections of a module
+    pub fn new(wasm_bytes: &[u8]) -> Result<Self> {
+        use wasmparser::{CompositeType, Name, NameSectionReader, Parser, Payload, TypeRef};
+
+        let err = |e: wasmparser::BinaryReaderError| {
+            DebuggerError::WasmLoadError(format!("Failed to parse WASM: {}", e))
+        };
+
+        let mut inspector = Self {
+            types: Vec::new(),
+            function_types: Vec::new(),
+            imported_functions: 0,
+            globals: Vec::new(),
+            function_locals: Vec::new(),
+            function_names: HashMap::new(),
+            local_names: HashMap::new(),
+        };
+
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            match payload.map_err(err)? {
+                Payload::TypeSection(reader) => {
+                    for group in reader {
+                        for sub_type in group.map_err(err)?.into_types() {
+                            let signature = match sub_type.composite_type {
+                                CompositeType::Func(ft) => {
+                                    (ft.params().to_vec(), ft.results().to_vec())
+                                }
+                                _ => (Vec::new(), Vec::new()),
+                            };
+                            inspector.types.push(signature);
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        match import.map_err(err)?.ty {
+                            TypeRef::Func(ty) => {
+                                inspector.function_types.push(ty);
+                                inspector.imported_functions += 1;
+                            }
+                            TypeRef::Global(g) => inspector.globals.push(GlobalInfo {
+                                ty: g.content_type,
+                                mutable: g.mutable,
+                                init: None,
+                            }),
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for ty in reader {
+                        inspector.function_types.push(ty.map_err(err)?);
+                    }
+                }
+                Payload::GlobalSection(reader) => {
+                    for global in reader {
+                        let global = global.map_err(err)?;
+                        let init = match global.init_expr.get_operators_reader().read() {
+                            Ok(Operator::I32Const { value }) => Some(WasmValue::I32(value)),
+                            Ok(Operator::I64Const { value }) => Some(WasmValue::I64(value)),
+                            _ => None,
+                        };
+                        inspector.globals.push(GlobalInfo {
+                            ty: global.ty.content_type,
+                            mutable: global.ty.mutable,
+                            init,
+                        });
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut locals = Vec::new();
+                    for entry in body.get_locals_reader().map_err(err)? {
+                        let (count, ty) = entry.map_err(err)?;
+                        locals.extend(std::iter::repeat_n(ty, count as usize));
+                    }
+                    inspector.function_locals.push(locals);
+                }
+                Payload::CustomSection(reader) if reader.name() == "name" => {
+                    let names = NameSectionReader::new(reader.data(), reader.data_offset());
+                    // A malformed name section only costs us names
+                    for name in names.into_iter().flatten() {
+                        match name {
+                            Name::Function(map) => {
+                                for naming in map.into_iter().flatten() {
+                                    inspector
+                                        .function_names
+                                        .insert(naming.index, naming.name.to_string());
+                                }
+                            }
+                            Name::Local(map) => {
+                                for indirect in map.into_iter().flatten() {
+                                    let locals =
+                                        inspector.local_names.entry(indirect.index).or_default();
+                                    for naming in indirect.names.into_iter().flatten() {
+                                        locals.insert(naming.index, naming.name.to_string());
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(inspector)
+    }
+
+    /// Name of a function from the name section
+    pub fn function_name(&self, function_index: u32) -> Option<&str> {
+        self.function_names.get(&function_index).map(String::as_str)
+    }
+
+    /// Reconstruct the state just before `instructions[current]` executes
+    pub fn inspect(
+        &self,
+        instructions: &[Instruction],
+        current: usize,
+        source_map: Option<&SourceMap>,
+    ) -> Option<LocalsSnapshot> {
+        let inst = instructions.get(current)?;
+        let function_index = inst.function_index;
+        let start = current.checked_sub(inst.local_index as usize)?;
+        if instructions[start].function_index != function_index {
+            return None;
+        }
+
+        let (params, results) = self.signature(function_index)?;
+        let declared = self
+            .function_locals
+            .get(function_index.checked_sub(self.imported_functions)? as usize)?;
+
+        let mut local_types = params.clone();
+        local_types.extend(declared.iter().copied());
+
+        let mut machine = Machine {
+            inspector: self,
+            locals: params
+                .iter()
+                .map(|_| None)
+                .chain(declared.iter().map(|ty| Some(zero(*ty))))
+                .map(Option::flatten)
+                .collect(),
+            local_types: local_types.clone(),
+            stack: Vec::new(),
+            frames: vec![Frame {
+                kind: FrameKind::Function,
+                height: 0,
+                params: Vec::new(),
+                results: results.clone(),
+                branched: false,
+                unreachable: false,
+            }],
+            exact: true,
+        };
+
+        for inst in &instructions[start..current] {
+            machine.execute(&inst.operator);
+        }
+
+        let names = self.local_names.get(&function_index);
+        let locals = local_types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| WasmLocal {
+                index: i as u32,
+                name: names.and_then(|n| n.get(&(i as u32))).cloned(),
+                value_type: type_name(*ty).to_string(),
+                is_param: i < params.len(),
+                value: machine.locals[i],
+            })
+            .collect();
+
+        let value_stack = machine
+            .stack
+            .iter()
+            .map(|slot| StackValue {
+                value_type: slot.ty.map(type_name).unwrap_or("?").to_string(),
+                value: slot.value,
+            })
+            .collect();
+
+        let variables = source_map
+            .map(|sm| sm.variables_at(inst.offset))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|variable| {
+                let value = match variable.location {
+                    VariableLocation::Local { index } => {
+                        machine.locals.get(index as usize).copied().flatten()
+                    }
+                    VariableLocation::Global { index } => self
+                        .globals
+                        .get(index as usize)
+                        .filter(|g| !g.mutable)
+                        .and_then(|g| g.init),
+                    VariableLocation::Stack { index } => {
+                        machine.stack.get(index as usize).and_then(|s| s.value)
+                    }
+                    _ => None,
+                };
+                VariableValue { variable, value }
+            })
+            .collect();
+
+        Some(LocalsSnapshot {
+            function_index,
+            function_name: self.function_name(function_index).map(str::to_string),
+            instruction_offset: inst.offset,
+            locals,
+            value_stack,
+            stack_exact: machine.exact,
+            variables,
+        })
+    }
+
+    fn signature(&self, function_index: u32) -> Option<&(Vec<ValType>, Vec<ValType>)> {
+        let ty = self.function_types.get(function_index as usize)?;
+        self.types.get(*ty as usize)
+    }
+
+    fn block_signature(&self, ty: BlockType) -> (Vec<ValType>, Vec<ValType>) {
+        match ty {
+            BlockType::Empty => (Vec::new(), Vec::new()),
+            BlockType::Type(ty) => (Vec::new(), vec![ty]),
+            BlockType::FuncType(idx) => self.types.get(idx as usize).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+fn zero(ty: ValType) -> Option<WasmValue> {
+    match ty {
+        ValType::I32 => Some(WasmValue::I32(0)),
+        ValType::I64 => Some(WasmValue::I64(0)),
+        ValType::F32 => Some(WasmValue::F32(0.0)),
+        ValType::F64 => Some(WasmValue::F64(0.0)),
+        _ => None,
+    }
+}
+
+fn type_name(ty: ValType) -> &'static str {
+    match ty {
+        ValType::I32 => "i32",
+        ValType::I64 => "i64",
+        ValType::F32 => "f32",
+        ValType::F64 => "f64",
+        ValType::V128 => "v128",
+        ValType::Ref(_) => "ref",
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    ty: Option<ValType>,
+    value: Option<WasmValue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameKind {
+    Function,
+    Block,
+    Loop,
+    If,
+}
+
+#[derive(Debug)]
+struct Frame {
+    kind: FrameKind,
+    height: usize,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    /// A branch targets the end of this frame, so its end is a merge point
+    branched: bool,
+    /// The rest of the frame is unreachable (after br, return, unreachable)
+    unreachable: bool,
+}
+
+struct Machine<'a> {
+    inspector: &'a LocalsInspector,
+    locals: Vec<Option<WasmValue>>,
+    local_types: Vec<ValType>,
+    stack: Vec<Slot>,
+    frames: Vec<Frame>,
+    exact: bool,
+}
+
+impl Machine<'_> {
+    fn frame(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("function frame is never popped")
+    }
+
+    fn push(&mut self, ty: ValType, value: Option<WasmValue>) {
+        self.stack.push(Slot {
+            ty: Some(ty),
+            value,
+        });
+    }
+
+    fn pop(&mut self) -> Slot {
+        let frame = self.frames.last().expect("function frame is never popped");
+        if self.stack.len() > frame.height {
+            return self.stack.pop().expect("stack is above frame height");
+        }
+        if !frame.unreachable {
+            self.exact = false;
+        }
+        Slot {
+            ty: None,
+            value: None,
+        }
+    }
+
+    fn pop_n(&mut self, n: usize) -> Vec<Slot> {
+        let mut slots: Vec<Slot> = (0..n).map(|_| self.pop()).collect();
+        slots.reverse();
+        slots
+    }
+
+    /// Forget local values at a control-flow merge point
+    fn invalidate_locals(&mut self) {
+        self.locals.iter_mut().for_each(|v| *v = None);
+    }
+
+    fn mark_branch(&mut self, relative_depth: u32) {
+        let len = self.frames.len();
+        if let Some(frame) = len
+            .checked_sub(relative_depth as usize + 1)
+            .and_then(|i| self.frames.get_mut(i))
+        {
+            frame.branched = true;
+        }
+    }
+
+    fn enter_unreachable(&mut self) {
+        let frame = self.frame();
+        frame.unreachable = true;
+        let height = frame.height;
+        self.stack.truncate(height);
+    }
+
+    fn enter_block(&mut self, kind: FrameKind, ty: BlockType) {
+        let (params, results) = self.inspector.block_signature(ty);
+        let height = self.stack.len().saturating_sub(params.len());
+        if kind == FrameKind::Loop {
+            // Back edges may re-enter with different values
+            self.invalidate_locals();
+            for slot in &mut self.stack[height..] {
+                slot.value = None;
+            }
+        }
+        self.frames.push(Frame {
+            kind,
+            height,
+            params,
+            results,
+            branched: false,
+            unreachable: false,
+        });
+    }
+
+    fn execute(&mut self, op: &Operator<'static>) {
+        use ValType::{F32, F64, I32, I64};
+
+        match op {
+            Operator::Nop => {}
+            Operator::Unreachable | Operator::Return => self.enter_unreachable(),
+            Operator::Block { blockty } => self.enter_block(FrameKind::Block, *blockty),
+            Operator::Loop { blockty } => self.enter_block(FrameKind::Loop, *blockty),
+            Operator::If { blockty } => {
+                self.pop();
+                self.enter_block(FrameKind::If, *blockty);
+            }
+            Operator::Else => {
+                let frame = self.frame();
+                frame.unreachable = false;
+                let height = frame.height;
+                let params = frame.params.clone();
+                self.stack.truncate(height);
+                for ty in params {
+                    self.push(ty, None);
+                }
+                self.invalidate_locals();
+            }
+            Operator::End => {
+                if self.frames.len() == 1 {
+                    return;
+                }
+                let frame = self.frames.pop().expect("checked above");
+                let merge = frame.kind == FrameKind::If
+                    || (frame.kind == FrameKind::Block && frame.branched);
+                let kept = if merge || frame.unreachable {
+                    Vec::new()
+                } else {
+                    let split = self.stack.len().saturating_sub(frame.results.len());
+                    self.stack.split_off(split.max(frame.height))
+                };
+                self.stack.truncate(frame.height);
+                if kept.len() == frame.results.len() {
+                    self.stack.extend(kept);
+                } else {
+                    for ty in frame.results {
+                        self.push(ty, None);
+                    }
+                }
+                if merge {
+                    self.invalidate_locals();
+                }
+            }
+            Operator::Br { relative_depth } => {
+                self.mark_branch(*relative_depth);
+                self.enter_unreachable();
+            }
+            Operator::BrIf { relative_depth } => {
+                self.pop();
+                self.mark_branch(*relative_depth);
+            }
+            Operator::BrTable { .. } => {
+                // Branch targets are not retained after parsing; treat every
+                // enclosing frame as a potential target.
+                self.pop();
+                for frame in &mut self.frames {
+                    frame.branched = true;
+                }
+                self.enter_unreachable();
+            }
+            Operator::Call { function_index } => match self.inspector.signature(*function_index) {
+                Some((params, results)) => {
+                    self.pop_n(params.len());
+                    for ty in results.clone() {
+                        self.push(ty, None);
+                    }
+                }
+                None => self.exact = false,
+            },
+            Operator::CallIndirect { type_index, .. } => {
+                self.pop();
+                match self.inspector.types.get(*type_index as usize) {
+                    Some((params, results)) => {
+                        self.pop_n(params.len());
+                        for ty in results.clone() {
+                            self.push(ty, None);
+                        }
+                    }
+                    None => self.exact = false,
+                }
+            }
+            Operator::Drop => {
+                self.pop();
+            }
+            Operator::Select | Operator::TypedSelect { .. } => {
+                let cond = self.pop();
+                let b = self.pop();
+                let a = self.pop();
+                let chosen = match cond.value {
+                    Some(WasmValue::I32(0)) => b,
+                    Some(WasmValue::I32(_)) => a,
+                    _ => Slot {
+                        ty: a.ty.or(b.ty),
+                        value: None,
+                    },
+                };
+                self.stack.push(chosen);
+            }
+            Operator::LocalGet { local_index } => {
+                let i = *local_index as usize;
+                match self.local_types.get(i) {
+                    Some(ty) => self.push(*ty, self.locals[i]),
+                    None => self.exact = false,
+                }
+            }
+            Operator::LocalSet { local_index } => {
+                let slot = self.pop();
+                if let Some(local) = self.locals.get_mut(*local_index as usize) {
+                    *local = slot.value;
+                }
+            }
+            Operator::LocalTee { local_index } => {
+                let slot = self.pop();
+                if let Some(local) = self.locals.get_mut(*local_index as usize) {
+                    *local = slot.value;
+                }
+                self.stack.push(slot);
+            }
+            Operator::GlobalGet { global_index } => {
+                match self.inspector.globals.get(*global_index as usize) {
+                    Some(g) => {
+                        let value = if g.mutable { None } else { g.init };
+                        self.push(g.ty, value);
+                    }
+                    None => self.exact = false,
+                }
+            }
+            Operator::GlobalSet { .. } => {
+                self.pop();
+            }
+            Operator::I32Load { .. }
+            | Operator::I32Load8S { .. }
+            | Operator::I32Load8U { .. }
+            | Operator::I32Load16S { .. }
+            | Operator::I32Load16U { .. } => {
+                self.pop();
+                self.push(I32, None);
+            }
+            Operator::I64Load { .. }
+            | Operator::I64Load8S { .. }
+            | Operator::I64Load8U { .. }
+            | Operator::I64Load16S { .. }
+            | Operator::I64Load16U { .. }
+            | Operator::I64Load32S { .. }
+            | Operator::I64Load32U { .. } => {
+                self.pop();
+                self.push(I64, None);
+            }
+            Operator::F32Load { .. } => {
+                self.pop();
+                self.push(F32, None);
+            }
+            Operator::F64Load { .. } => {
+                self.pop();
+                self.push(F64, None);
+            }
+            Operator::I32Store { .. }
+            | Operator::I64Store { .. }
+            | Operator::F32Store { .. }
+            | Operator::F64Store { .. }
+            | Operator::I32Store8 { .. }
+            | Operator::I32Store16 { .. }
+            | Operator::I64Store8 { .. }
+            | Operator::I64Store16 { .. }
+            | Operator::I64Store32 { .. } => {
+                self.pop_n(2);
+            }
+            Operator::MemorySize { .. } => self.push(I32, None),
+            Operator::MemoryGrow { .. } => {
+                self.pop();
+                self.push(I32, None);
+            }
+            Operator::MemoryCopy { .. } | Operator::MemoryFill { .. } => {
+                self.pop_n(3);
+            }
+            Operator::I32Const { value } => self.push(I32, Some(WasmValue::I32(*value))),
+            Operator::I64Const { value } => self.push(I64, Some(WasmValue::I64(*value))),
+            Operator::F32Const { value } => {
+                self.push(F32, Some(WasmValue::F32(f32::from_bits(value.bits()))))
+            }
+            Operator::F64Const { value } => {
+                self.push(F64, Some(WasmValue::F64(f64::from_bits(value.bits()))))
+            }
+            op => match numeric_signature(op) {
+                Some((params, result)) => {
+                    let args: Vec<Option<WasmValue>> =
+                        self.pop_n(params.len()).iter().map(|s| s.value).collect();
+                    self.push(result, fold(op, &args));
+                }
+                None => self.exact = false,
+            },
+        }
+    }
+}
+
+/// Operand and result types of numeric instructions
+fn numeric_signature(op: &Operator) -> Option<(&'static [ValType], ValType)> {
+    use ValType::{F32, F64, I32, I64};
+    const I32_1: &[ValType] = &[I32];
+    const I32_2: &[ValType] = &[I32, I32];
+    const I64_1: &[ValType] = &[I64];
+    const I64_2: &[ValType] = &[I64, I64];
+    const F32_1: &[ValType] = &[F32];
+    const F32_2: &[ValType] = &[F32, F32];
+    const F64_1: &[ValType] = &[F64];
+    const F64_2: &[ValType] = &[F64, F64];
+
+    let signature = match op {
+        Operator::I32Eqz
+        | Operator::I32Clz
+        | Operator::I32Ctz
+        | Operator::I32Popcnt
+        | Operator::I32Extend8S
+        | Operator::I32Extend16S => (I32_1, I32),
+        Operator::I32Eq
+        | Operator::I32Ne
+        | Operator::I32LtS
+        | Operator::I32LtU
+        | Operator::I32GtS
+        | Operator::I32GtU
+        | Operator::I32LeS
+        | Operator::I32LeU
+        | Operator::I32GeS
+        | Operator::I32GeU
+        | Operator::I32Add
+        | Operator::I32Sub
+        | Operator::I32Mul
+        | Operator::I32DivS
+        | Operator::I32DivU
+        | Operator::I32RemS
+        | Operator::I32RemU
+        | Operator::I32And
+        | Operator::I32Or
+        | Operator::I32Xor
+        | Operator::I32Shl
+        | Operator::I32ShrS
+        | Operator::I32ShrU
+        | Operator::I32Rotl
+        | Operator::I32Rotr => (I32_2, I32),
+        Operator::I64Eqz => (I64_1, I32),
+        Operator::I64Eq
+        | Operator::I64Ne
+        | Operator::I64LtS
+        | Operator::I64LtU
+        | Operator::I64GtS
+        | Operator::I64GtU
+        | Operator::I64LeS
+        | Operator::I64LeU
+        | Operator::I64GeS
+        | Operator::I64GeU => (I64_2, I32),
+        Operator::I64Clz
+        | Operator::I64Ctz
+        | Operator::I64Popcnt
+        | Operator::I64Extend8S
+        | Operator::I64Extend16S
+        | Operator::I64Extend32S => (I64_1, I64),
+        Operator::I64Add
+        | Operator::I64Sub
+        | Operator::I64Mul
+        | Operator::I64DivS
+        | Operator::I64DivU
+        | Operator::I64RemS
+        | Operator::I64RemU
+        | Operator::I64And
+        | Operator::I64Or
+        | Operator::I64Xor
+        | Operator::I64Shl
+        | Operator::I64ShrS
+        | Operator::I64ShrU
+        | Operator::I64Rotl
+        | Operator::I64Rotr => (I64_2, I64),
+        Operator::F32Eq
+        | Operator::F32Ne
+        | Operator::F32Lt
+        | Operator::F32Gt
+        | Operator::F32Le
+        | Operator::F32Ge => (F32_2, I32),
+        Operator::F64Eq
+        | Operator::F64Ne
+        | Operator::F64Lt
+        | Operator::F64Gt
+        | Operator::F64Le
+        | Operator::F64Ge => (F64_2, I32),
+        Operator::F32Abs
+        | Operator::F32Neg
+        | Operator::F32Ceil
+        | Operator::F32Floor
+        | Operator::F32Trunc
+        | Operator::F32Nearest
+        | Operator::F32Sqrt => (F32_1, F32),
+        Operator::F32Add
+        | Operator::F32Sub
+        | Operator::F32Mul
+        | Operator::F32Div
+        | Operator::F32Min
+        | Operator::F32Max
+        | Operator::F32Copysign => (F32_2, F32),
+        Operator::F64Abs
+        | Operator::F64Neg
+        | Operator::F64Ceil
+        | Operator::F64Floor
+        | Operator::F64Trunc
+        | Operator::F64Nearest
+        | Operator::F64Sqrt => (F64_1, F64),
+        Operator::F64Add
+        | Operator::F64Sub
+        | Operator::F64Mul
+        | Operator::F64Div
+        | Operator::F64Min
+        | Operator::F64Max
+        | Operator::F64Copysign => (F64_2, F64),
+        Operator::I32WrapI64 => (I64_1, I32),
+        Operator::I32TruncF32S
+        | Operator::I32TruncF32U
+        | Operator::I32TruncSatF32S
+        | Operator::I32TruncSatF32U
+        | Operator::I32ReinterpretF32 => (F32_1, I32),
+        Operator::I32TruncF64S
+        | Operator::I32TruncF64U
+        | Operator::I32TruncSatF64S
+        | Operator::I32TruncSatF64U => (F64_1, I32),
+        Operator::I64ExtendI32S | Operator::I64ExtendI32U => (I32_1, I64),
+        Operator::I64TruncF32S
+        | Operator::I64TruncF32U
+        | Operator::I64TruncSatF32S
+        | Operator::I64TruncSatF32U => (F32_1, I64),
+        Operator::I64TruncF64S
+        | Operator::I64TruncF64U
+        | Operator::I64TruncSatF64S
+        | Operator::I64TruncSatF64U
+        | Operator::I64ReinterpretF64 => (F64_1, I64),
+        Operator::F32ConvertI32S | Operator::F32ConvertI32U | Operator::F32ReinterpretI32 => {
+            (I32_1, F32)
+        }
+        Operator::F32ConvertI64S | Operator::F32ConvertI64U => (I64_1, F32),
+        Operator::F32DemoteF64 => (F64_1, F32),
+        Operator::F64ConvertI32S | Operator::F64ConvertI32U => (I32_1, F64),
+        Operator::F64ConvertI64S | Operator::F64ConvertI64U | Operator::F64ReinterpretI64 => {
+            (I64_1, F64)
+        }
+        Operator::F64PromoteF32 => (F32_1, F64),
+        _ => return None,
+    };
+    Some(signature)
+}
+
+/// Constant-fold simple integer operations on known operands
+fn fold(op: &Operator, args: &[Option<WasmValue>]) -> Option<WasmValue> {
+    use WasmValue::{I32, I64};
+
+    let bool32 = |b: bool| Some(I32(b as i32));
+    match (op, args) {
+        (Operator::I32Eqz, [Some(I32(a))]) => bool32(*a == 0),
+        (Operator::I64Eqz, [Some(I64(a))]) => bool32(*a == 0),
+        (Operator::I32WrapI64, [Some(I64(a))]) => Some(I32(*a as i32)),
+        (Operator::I64ExtendI32S, [Some(I32(a))]) => Some(I64(*a as i64)),
+        (Operator::I64ExtendI32U, [Some(I32(a))]) => Some(I64(*a as u32 as i64)),
+        (op, [Some(I32(a)), Some(I32(b))]) => match op {
+            Operator::I32Add => Some(I32(a.wrapping_add(*b))),
+            Operator::I32Sub => Some(I32(a.wrapping_sub(*b))),
+            Operator::I32Mul => Some(I32(a.wrapping_mul(*b))),
+            Operator::I32And => Some(I32(a & b)),
+            Operator::I32Or => Some(I32(a | b)),
+            Operator::I32Xor => Some(I32(a ^ b)),
+            Operator::I32Shl => Some(I32(a.wrapping_shl(*b as u32))),
+            Operator::I32ShrS => Some(I32(a.wrapping_shr(*b as u32))),
+            Operator::I32ShrU => Some(I32((*a as u32).wrapping_shr(*b as u32) as i32)),
+            Operator::I32Eq => bool32(a == b),
+            Operator::I32Ne => bool32(a != b),
+            _ => None,
+        },
+        (op, [Some(I64(a)), Some(I64(b))]) => match op {
+            Operator::I64Add => Some(I64(a.wrapping_add(*b))),
+            Operator::I64Sub => Some(I64(a.wrapping_sub(*b))),
+            Operator::I64Mul => Some(I64(a.wrapping_mul(*b))),
+            Operator::I64And => Some(I64(a & b)),
+            Operator::I64Or => Some(I64(a | b)),
+            Operator::I64Xor => Some(I64(a ^ b)),
+            Operator::I64Shl => Some(I64(a.wrapping_shl(*b as u32))),
+            Operator::I64ShrS => Some(I64(a.wrapping_shr(*b as u32))),
+            Operator::I64ShrU => Some(I64((*a as u64).wrapping_shr(*b as u32) as i64)),
+            Operator::I64Eq => bool32(a == b),
+            Operator::I64Ne => bool32(a != b),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::instruction::InstructionParser;
+
+    fn section(module: &mut Vec<u8>, id: u8, body: &[u8]) {
+        module.push(id);
+        module.push(body.len() as u8);
+        module.extend_from_slice(body);
+    }
+
+    /// One function `(param i64) (result i64)` with one i32 local, a name
+    /// section naming the function `inc` and its locals `x` and `tmp`.
+    fn make_module(code: &[u8]) -> Vec<u8> {
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        section(&mut module, 1, &[0x01, 0x60, 0x01, 0x7e, 0x01, 0x7e]);
+        section(&mut module, 3, &[0x01, 0x00]);
+
+        let mut body = vec![0x01, 0x01, 0x7f];
+        body.extend_from_slice(code);
+        let mut code_section = vec![0x01, body.len() as u8];
+        code_section.extend_from_slice(&body);
+        section(&mut module, 10, &code_section);
+
+        let mut names = b"\x04name".to_vec();
+        names.extend_from_slice(&[0x01, 0x06, 0x01, 0x00, 0x03]);
+        names.extend_from_slice(b"inc");
+        names.extend_from_slice(&[0x02, 0x0b, 0x01, 0x00, 0x02, 0x00, 0x01]);
+        names.extend_from_slice(b"x");
+        names.extend_from_slice(&[0x01, 0x03]);
+        names.extend_from_slice(b"tmp");
+        section(&mut module, 0, &names);
+        module
+    }
+
+    fn snapshot_at(wasm: &[u8], index: usize) -> LocalsSnapshot {
+        let mut parser = InstructionParser::new();
+        let instructions = parser.parse(wasm).unwrap().to_vec();
+        let inspector = LocalsInspector::new(wasm).unwrap();
+        inspector.inspect(&instructions, index, None).unwrap()
+    }
+
+    #[test]
+    fn tracks_constants_and_params() {
+        // i32.const 7; local.set 1; local.get 0; i64.const 2; i64.add; end
+        let wasm = make_module(&[0x41, 0x07, 0x21, 0x01, 0x20, 0x00, 0x42, 0x02, 0x7c, 0x0b]);
+
+        let snapshot = snapshot_at(&wasm, 4);
+        assert_eq!(snapshot.function_name.as_deref(), Some("inc"));
+        assert_eq!(snapshot.locals.len(), 2);
+        assert_eq!(snapshot.locals[0].name.as_deref(), Some("x"));
+        assert!(snapshot.locals[0].is_param);
+        assert_eq!(snapshot.locals[0].value, None);
+        assert_eq!(snapshot.locals[1].name.as_deref(), Some("tmp"));
+        assert_eq!(snapshot.locals[1].value, Some(WasmValue::I32(7)));
+
+        assert!(snapshot.stack_exact);
+        assert_eq!(snapshot.value_stack.len(), 2);
+        assert_eq!(snapshot.value_stack[0].value_type, "i64");
+        assert_eq!(snapshot.value_stack[0].value, None);
+        assert_eq!(snapshot.value_stack[1].value, Some(WasmValue::I64(2)));
+
+        let at_end = snapshot_at(&wasm, 5);
+        assert_eq!(at_end.value_stack.len(), 1);
+    }
+
+    #[test]
+    fn folds_soroban_val_construction() {
+        // i64.const 5; i64.const 32; i64.shl; i64.const 4; i64.or; end
+        let wasm = make_module(&[0x42, 0x05, 0x42, 0x20, 0x86, 0x42, 0x04, 0x84, 0x0b]);
+
+        let snapshot = snapshot_at(&wasm, 5);
+        let value = snapshot.value_stack[0].value.unwrap();
+        assert_eq!(value, WasmValue::I64((5 << 32) | 4));
+        assert_eq!(value.as_soroban_val().as_deref(), Some("U32(5)"));
+    }
+
+    #[test]
+    fn forgets_locals_after_if_merge() {
+        // i32.const 3; local.set 1; local.get 0; i32.wrap_i64; if; i32.const 9;
+        // local.set 1; end; i64.const 0; end
+        let wasm = make_module(&[
+            0x41, 0x03, 0x21, 0x01, 0x20, 0x00, 0xa7, 0x04, 0x40, 0x41, 0x09, 0x21, 0x01, 0x0b,
+            0x42, 0x00, 0x0b,
+        ]);
+
+        let inside = snapshot_at(&wasm, 5);
+        assert_eq!(inside.locals[1].value, Some(WasmValue::I32(3)));
+
+        let after = snapshot_at(&wasm, 8);
+        assert_eq!(after.locals[1].value, None);
+        assert!(after.value_stack.is_empty());
+    }
+
+    #[test]
+    fn resolves_source_variables_from_locals() {
+        let wasm = make_module(&[0x41, 0x07, 0x21, 0x01, 0x42, 0x00, 0x0b]);
+        let mut parser = InstructionParser::new();
+        let instructions = parser.parse(&wasm).unwrap().to_vec();
+        let inspector = LocalsInspector::new(&wasm).unwrap();
+
+        let mut source_map = SourceMap::new();
+        source_map.add_variable(
+            0..usize::MAX,
+            SourceVariable {
+                name: "amount".to_string(),
+                type_name: Some("u32".to_string()),
+                location: VariableLocation::Local { index: 1 },
+                function: Some("inc".to_string()),
+                is_param: false,
+            },
+        );
+
+        let snapshot = inspector
+            .inspect(&instructions, 2, Some(&source_map))
+            .unwrap();
+        assert_eq!(snapshot.variables.len(), 1);
+        assert_eq!(snapshot.variables[0].variable.name, "amount");
+        assert_eq!(snapshot.variables[0].value, Some(WasmValue::I32(7)));
+    }
+}
@@ -0,0 +1,569 @@
+//! Linear memory inspection: hex dumps, pattern search and value decoding.
+
+use crate::runtime::executor::ContractExecutor;
+use crate::runtime::memory_capture::{capture_linear_memory, CapturePoint};
+use crate::ui::formatter::Formatter;
+use crate::{DebuggerError, Result};
+use serde::Serialize;
+use soroban_ledger_snapshot::LedgerSnapshot;
+
+/// Size of a WASM memory page in bytes
+pub const WASM_PAGE_SIZE: usize = 65536;
+
+/// Bytes shown per hex dump row
+pub const DUMP_ROW_WIDTH: usize = 16;
+
+/// A row of a hex dump
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryRow {
+    pub address: u32,
+    pub bytes: Vec<u8>,
+}
+
+impl MemoryRow {
+    /// Printable ASCII rendering of the row, `.` for other bytes
+    pub fn ascii(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|b| {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    *b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect()
+    }
+}
+
+/// Integer layouts that can be decoded from memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntKind {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    U128,
+    I128,
+}
+
+impl IntKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s.to_ascii_lowercase().as_str() {
+            "u8" => IntKind::U8,
+            "i8" => IntKind::I8,
+            "u16" => IntKind::U16,
+            "i16" => IntKind::I16,
+            "u32" => IntKind::U32,
+            "i32" => IntKind::I32,
+            "u64" => IntKind::U64,
+            "i64" => IntKind::I64,
+            "u128" => IntKind::U128,
+            "i128" => IntKind::I128,
+            _ => return None,
+        })
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            IntKind::U8 | IntKind::I8 => 1,
+            IntKind::U16 | IntKind::I16 => 2,
+            IntKind::U32 | IntKind::I32 => 4,
+            IntKind::U64 | IntKind::I64 => 8,
+            IntKind::U128 | IntKind::I128 => 16,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> String {
+        let mut buf = [0u8; 16];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let unsigned = u128::from_le_bytes(buf);
+        match self {
+            IntKind::U8 | IntKind::U16 | IntKind::U32 | IntKind::U64 | IntKind::U128 => {
+                unsigned.to_string()
+            }
+            _ => {
+                // Sign-extend from the encoded width
+                let shift = 128 - self.size() * 8;
+                (((unsigned << shift) as i128) >> shift).to_string()
+            }
+        }
+    }
+}
+
+/// A byte image of linear memory
+#[derive(Debug, Clone, Default)]
+pub struct LinearMemory {
+    bytes: Vec<u8>,
+}
+
+impl LinearMemory {
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    /// Build the memory image a module starts with: its declared initial
+    /// pages with active data segments applied.
+    pub fn from_module(wasm_bytes: &[u8]) -> Result<(Self, MemoryLimits)> {
+        use wasmparser::{DataKind, Operator, Parser, Payload};
+
+        let err = |e: wasmparser::BinaryReaderError| {
+            DebuggerError::WasmLoadError(format!("Failed to parse WASM: {}", e))
+        };
+
+        let mut limits = MemoryLimits::default();
+        let mut segments = Vec::new();
+
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            match payload.map_err(err)? {
+                Payload::MemorySection(reader) => {
+                    if let Some(memory) = reader.into_iter().next() {
+                        let memory = memory.map_err(err)?;
+                        limits.initial_pages = memory.initial as u32;
+                        limits.maximum_pages = memory.maximum.map(|m| m as u32);
+                    }
+                }
+                Payload::DataSection(reader) => {
+                    for data in reader {
+                        let data = data.map_err(err)?;
+                        if let DataKind::Active {
+                            memory_index: 0,
+                            offset_expr,
+                        } = data.kind
+                        {
+                            if let Ok(Operator::I32Const { value }) =
+                                offset_expr.get_operators_reader().read()
+                            {
+                                segments.push((value as u32 as usize, data.data.to_vec()));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut bytes = vec![0u8; limits.initial_pages as usize * WASM_PAGE_SIZE];
+        for (offset, data) in segments {
+            let end = (offset + data.len()).min(bytes.len());
+            if offset < end {
+                bytes[offset..end].copy_from_slice(&data[..end - offset]);
+            }
+        }
+        limits.data_start = limits_data_start(&bytes);
+
+        Ok((Self { bytes }, limits))
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn pages(&self) -> u32 {
+        (self.bytes.len() / WASM_PAGE_SIZE) as u32
+    }
+
+    /// Bytes in `[address, address + len)`, clamped to the memory size
+    pub fn read(&self, address: u32, len: usize) -> Option<&[u8]> {
+        let start = address as usize;
+        if start >= self.bytes.len() {
+            return None;
+        }
+        let end = start.saturating_add(len).min(self.bytes.len());
+        Some(&self.bytes[start..end])
+    }
+
+    /// Hex dump rows covering `[address, address + len)`
+    pub fn dump(&self, address: u32, len: usize) -> Vec<MemoryRow> {
+        let Some(bytes) = self.read(address, len) else {
+            return Vec::new();
+        };
+        bytes
+            .chunks(DUMP_ROW_WIDTH)
+            .enumerate()
+            .map(|(i, chunk)| MemoryRow {
+                address: address + (i * DUMP_ROW_WIDTH) as u32,
+                bytes: chunk.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Hex dump of `radius` bytes either side of `pointer`, row-aligned
+    pub fn dump_around(&self, pointer: u32, radius: usize) -> Vec<MemoryRow> {
+        let start = (pointer as usize).saturating_sub(radius) / DUMP_ROW_WIDTH * DUMP_ROW_WIDTH;
+        let end = (pointer as usize).saturating_add(radius + 1);
+        self.dump(start as u32, end - start)
+    }
+
+    /// Addresses where `pattern` occurs, up to `limit` matches
+    pub fn search(&self, pattern: &[u8], limit: usize) -> Vec<u32> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        self.bytes
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, window)| *window == pattern)
+            .map(|(addr, _)| addr as u32)
+            .take(limit)
+            .collect()
+    }
+
+    /// Decode a UTF-8 string at `address`, stopping at NUL or `max_len`
+    pub fn read_utf8(&self, address: u32, max_len: usize) -> Option<String> {
+        let bytes = self.read(address, max_len)?;
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        let bytes = &bytes[..end];
+        Some(match std::str::from_utf8(bytes) {
+            Ok(s) => s.to_string(),
+            Err(e) => {
+                let valid = &bytes[..e.valid_up_to()];
+                format!(
+                    "{}<invalid UTF-8 at +{}>",
+                    String::from_utf8_lossy(valid),
+                    e.valid_up_to()
+                )
+            }
+        })
+    }
+
+    /// Decode a little-endian integer at `address`
+    pub fn read_int(&self, address: u32, kind: IntKind) -> Option<String> {
+        let bytes = self.read(address, kind.size())?;
+        (bytes.len() == kind.size()).then(|| kind.decode(bytes))
+    }
+}
+
+fn limits_data_start(bytes: &[u8]) -> Option<u32> {
+    bytes.iter().position(|b| *b != 0).map(|p| p as u32)
+}
+
+/// Declared memory limits of a module
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryLimits {
+    pub initial_pages: u32,
+    pub maximum_pages: Option<u32>,
+    /// First non-zero byte of the initial image (start of static data)
+    pub data_start: Option<u32>,
+}
+
+/// Memory size and growth over an invocation
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryInfo {
+    pub declared_initial_pages: u32,
+    pub maximum_pages: Option<u32>,
+    pub pages_at_entry: Option<u32>,
+    pub pages_at_exit: Option<u32>,
+    pub size_bytes: usize,
+    /// Whether the summary describes live memory captured from an invocation
+    pub captured: bool,
+    pub capture_point: Option<CapturePoint>,
+    pub capture_error: Option<String>,
+}
+
+impl MemoryInfo {
+    /// Pages grown during the invocation
+    pub fn growth_pages(&self) -> Option<u32> {
+        Some(self.pages_at_exit?.saturating_sub(self.pages_at_entry?))
+    }
+}
+
+/// Inspects a contract's linear memory, live when a capture succeeded and
+/// otherwise as initialised from the module's data segments.
+pub struct MemoryInspector {
+    initial: LinearMemory,
+    limits: MemoryLimits,
+    captured: Option<(LinearMemory, u32, CapturePoint)>,
+    capture_error: Option<String>,
+}
+
+impl MemoryInspector {
+    pub fn new(wasm_bytes: &[u8]) -> Result<Self> {
+        let (initial, limits) = LinearMemory::from_module(wasm_bytes)?;
+        Ok(Self {
+            initial,
+            limits,
+            captured: None,
+            capture_error: None,
+        })
+    }
+
+    /// Re-run `function` against a fork of the session, from `ledger` if
+    /// given, to capture its linear memory on return or trap. A failed
+    /// capture is remembered and the initial image is used instead.
+    pub fn capture(
+        &mut self,
+        executor: &ContractExecutor,
+        ledger: Option<LedgerSnapshot>,
+        function: &str,
+        args: Option<&str>,
+    ) {
+        match capture_linear_memory(executor, ledger, function, args) {
+            Ok(capture) => {
+                self.captured = Some((
+                    LinearMemory::from_bytes(capture.bytes),
+                    capture.pages_at_entry,
+                    capture.point,
+                ));
+                self.capture_error = None;
+            }
+            Err(e) => {
+                self.captured = None;
+                self.capture_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// The memory image commands operate on
+    pub fn memory(&self) -> &LinearMemory {
+        self.captured
+            .as_ref()
+            .map(|c| &c.0)
+            .unwrap_or(&self.initial)
+    }
+
+    /// The memory image before execution
+    pub fn initial_memory(&self) -> &LinearMemory {
+        &self.initial
+    }
+
+    pub fn limits(&self) -> &MemoryLimits {
+        &self.limits
+    }
+
+    pub fn summary(&self) -> MemoryInfo {
+        MemoryInfo {
+            declared_initial_pages: self.limits.initial_pages,
+            maximum_pages: self.limits.maximum_pages,
+            pages_at_entry: self.captured.as_ref().map(|c| c.1),
+            pages_at_exit: self.captured.as_ref().map(|c| c.0.pages()),
+            size_bytes: self.memory().len(),
+            captured: self.captured.is_some(),
+            capture_point: self.captured.as_ref().map(|c| c.2),
+            capture_error: self.capture_error.clone(),
+        }
+    }
+}
+
+/// Default number of bytes shown by `mem dump`
+const DEFAULT_DUMP_LEN: usize = 128;
+/// Default radius shown by `mem around`
+const DEFAULT_AROUND_RADIUS: usize = 32;
+/// Default maximum length read by `mem str`
+const DEFAULT_STR_LEN: usize = 256;
+/// Maximum matches reported by `mem find`
+const MAX_SEARCH_RESULTS: usize = 32;
+
+/// Usage text for the `memory` REPL command
+pub const MEMORY_COMMAND_HELP: &str = "\
+Memory commands:
+  mem                       Show memory size and growth
+  mem dump <addr> [len]     Hex dump (default 128 bytes)
+  mem around <ptr> [radius] Hex dump around a pointer (default 32 bytes)
+  mem find <pattern>        Search for hex bytes (deadbeef) or \"text\"
+  mem str <addr> [max]      Decode a UTF-8 string
+  mem int <addr> <type>     Decode u8/i8/u16/i16/u32/i32/u64/i64/u128/i128
+Addresses are decimal or 0x-prefixed hex.";
+
+impl MemoryInspector {
+    /// Run a `memory` REPL subcommand and return its output.
+    pub fn run_command(&self, args: &[&str]) -> Result<String> {
+        let memory = self.memory();
+        let arg = |i: usize, what: &str| -> Result<&str> {
+            args.get(i).copied().ok_or_else(|| {
+                DebuggerError::InvalidArguments(format!("Missing {}. See 'mem help'", what)).into()
+            })
+        };
+        let len = |i: usize, default: usize| -> Result<usize> {
+            match args.get(i) {
+                Some(s) => parse_address(s).map(|n| n as usize),
+                None => Ok(default),
+            }
+        };
+
+        match args.first().copied() {
+            None | Some("info") => Ok(Formatter::format_memory_info(&self.summary())),
+            Some("help") => Ok(MEMORY_COMMAND_HELP.to_string()),
+            Some("dump") | Some("x") => {
+                let address = parse_address(arg(1, "address")?)?;
+                Ok(Formatter::format_memory_dump(
+                    &memory.dump(address, len(2, DEFAULT_DUMP_LEN)?),
+                ))
+            }
+            Some("around") => {
+                let pointer = parse_address(arg(1, "pointer")?)?;
+                Ok(Formatter::format_memory_dump(
+                    &memory.dump_around(pointer, len(2, DEFAULT_AROUND_RADIUS)?),
+                ))
+            }
+            Some("find") | Some("search") => {
+                let pattern = parse_pattern(&args[1..].join(" "))?;
+                let matches = memory.search(&pattern, MAX_SEARCH_RESULTS);
+                if matches.is_empty() {
+                    return Ok("No matches".to_string());
+                }
+                Ok(matches
+                    .iter()
+                    .map(|addr| format!("  0x{:08x}", addr))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            Some("str") | Some("string") => {
+                let address = parse_address(arg(1, "address")?)?;
+                memory
+                    .read_utf8(address, len(2, DEFAULT_STR_LEN)?)
+                    .map(|s| format!("{:?}", s))
+                    .ok_or_else(|| out_of_bounds(address))
+            }
+            Some("int") => {
+                let address = parse_address(arg(1, "address")?)?;
+                let kind_name = arg(2, "integer type")?;
+                let kind = IntKind::parse(kind_name).ok_or_else(|| {
+                    DebuggerError::InvalidArguments(format!("Unknown integer type: {}", kind_name))
+                })?;
+                memory
+                    .read_int(address, kind)
+                    .ok_or_else(|| out_of_bounds(address))
+            }
+            Some(other) => Err(DebuggerError::InvalidArguments(format!(
+                "Unknown memory command: {}. See 'mem help'",
+                other
+            ))
+            .into()),
+        }
+    }
+}
+
+fn out_of_bounds(address: u32) -> miette::Report {
+    DebuggerError::InvalidArguments(format!("Address 0x{:x} is out of bounds", address)).into()
+}
+
+/// Parse a memory address given in decimal or `0x` hex
+pub fn parse_address(s: &str) -> Result<u32> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| DebuggerError::InvalidArguments(format!("Invalid address: {}", s)).into())
+}
+
+/// Parse a search pattern: a quoted string (`"text"`) or hex bytes
+/// (`deadbeef`, `de ad be ef`, `0xdeadbeef`)
+pub fn parse_pattern(s: &str) -> Result<Vec<u8>> {
+    let s = s.trim();
+    if let Some(text) = s.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        return Ok(text.as_bytes().to_vec());
+    }
+    let hex: String = s
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    hex::decode(&hex).map_err(|e| {
+        DebuggerError::InvalidArguments(format!("Invalid byte pattern '{}': {}", s, e)).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> LinearMemory {
+        let mut bytes = vec![0u8; 256];
+        bytes[16..21].copy_from_slice(b"hello");
+        bytes[32..36].copy_from_slice(&(-2i32).to_le_bytes());
+        bytes[40..48].copy_from_slice(&0x0102_0304_0506_0708u64.to_le_bytes());
+        LinearMemory::from_bytes(bytes)
+    }
+
+    #[test]
+    fn dump_rows_are_sixteen_bytes() {
+        let rows = memory().dump(16, 20);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].address, 16);
+        assert_eq!(rows[0].ascii(), "hello...........");
+        assert_eq!(rows[1].bytes.len(), 4);
+    }
+
+    #[test]
+    fn dump_around_aligns_to_rows() {
+        let rows = memory().dump_around(20, 8);
+        assert_eq!(rows[0].address, 0);
+        assert_eq!(rows.last().unwrap().address, 16);
+    }
+
+    #[test]
+    fn search_finds_patterns() {
+        let mem = memory();
+        assert_eq!(mem.search(b"llo", 10), vec![18]);
+        assert_eq!(
+            mem.search(&parse_pattern("08 07 06").unwrap(), 10),
+            vec![40]
+        );
+        assert!(mem.search(b"absent", 10).is_empty());
+    }
+
+    #[test]
+    fn decodes_strings_and_integers() {
+        let mem = memory();
+        assert_eq!(mem.read_utf8(16, 64).as_deref(), Some("hello"));
+        assert_eq!(mem.read_int(32, IntKind::I32).as_deref(), Some("-2"));
+        assert_eq!(
+            mem.read_int(32, IntKind::U32).as_deref(),
+            Some("4294967294")
+        );
+        assert_eq!(
+            mem.read_int(40, IntKind::U64).as_deref(),
+            Some("72623859790382856")
+        );
+        assert_eq!(mem.read_int(254, IntKind::U32), None);
+    }
+
+    #[test]
+    fn initial_image_includes_data_segments() {
+        use walrus::{ActiveData, ActiveDataLocation, DataKind, Module, ModuleConfig};
+
+        let mut module = Module::with_config(ModuleConfig::new());
+        let memory = module.memories.add_local(false, 2, Some(4));
+        module.data.add(
+            DataKind::Active(ActiveData {
+                memory,
+                location: ActiveDataLocation::Absolute(0x100),
+            }),
+            b"static".to_vec(),
+        );
+        let inspector = MemoryInspector::new(&module.emit_wasm()).unwrap();
+
+        assert_eq!(inspector.memory().pages(), 2);
+        assert_eq!(inspector.limits().maximum_pages, Some(4));
+        assert_eq!(inspector.limits().data_start, Some(0x100));
+        assert!(!inspector.summary().captured);
+        assert_eq!(
+            inspector.run_command(&["find", "\"static\""]).unwrap(),
+            "  0x00000100"
+        );
+        assert_eq!(
+            inspector.run_command(&["str", "0x100"]).unwrap(),
+            "\"static\""
+        );
+        assert!(inspector.run_command(&["int", "0x100", "f32"]).is_err());
+    }
+
+    #[test]
+    fn parses_addresses_and_patterns() {
+        assert_eq!(parse_address("0x10").unwrap(), 16);
+        assert_eq!(parse_address("42").unwrap(), 42);
+        assert!(parse_address("nope").is_err());
+        assert_eq!(parse_pattern("\"hi\"").unwrap(), b"hi");
+        assert_eq!(parse_pattern("0xdead").unwrap(), vec![0xde, 0xad]);
+        assert!(parse_pattern("xyz").is_err());
+    }
+}
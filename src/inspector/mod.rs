@@ -3,6 +3,8 @@ pub mod budget;
 pub mod events;
 pub mod instructions;
 pub mod ledger;
+pub mod locals;
+pub mod memory;
 pub mod stack;
 pub mod storage;
 
@@ -10,5 +12,9 @@ pub use auth::AuthInspector;
 pub use budget::{BudgetInfo, BudgetInspector, MemorySummary, MemoryTracker};
 pub use instructions::{FunctionInstructionCount, InstructionCounter};
 pub use ledger::LedgerEntryInspector;
+pub use locals::{
+    LocalsInspector, LocalsSnapshot, StackValue, VariableValue, WasmLocal, WasmValue,
+};
+pub use memory::{LinearMemory, MemoryInfo, MemoryInspector, MemoryRow};
 pub use stack::CallStackInspector;
 pub use storage::{StorageFilter, StorageInspector};
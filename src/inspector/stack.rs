@@ -1,3 +1,5 @@
+use crate::ui::formatter::Formatter;
+use soroban_env_host::xdr::{ContractEvent, ContractEventBody, Hash, ScAddress, ScVal};
 use std::time::Duration;
 
 /// Represents a single frame in the call stack
@@ -6,9 +8,61 @@ pub struct CallFrame {
     pub function: String,
     pub contract_id: Option<String>,
     pub duration: Option<Duration>,
+    /// Nesting level, 0 for the top-level invocation.
+    pub depth: usize,
+    pub args: Vec<ScVal>,
+    /// `None` if the frame trapped or has not returned yet.
+    pub return_value: Option<ScVal>,
+    /// CPU instructions consumed by the frame, including nested calls.
+    pub cpu_instructions: Option<u64>,
+    /// Memory bytes consumed by the frame, including nested calls.
+    pub memory_bytes: Option<u64>,
+    /// Whether the budget figures come from replaying the call on its own
+    /// rather than from the invocation itself.
+    pub budget_estimated: bool,
 }
 
-/// Tracks and displays the call stack
+impl CallFrame {
+    pub fn new(function: String, contract_id: Option<String>, depth: usize) -> Self {
+        Self {
+            function,
+            contract_id,
+            duration: None,
+            depth,
+            args: Vec::new(),
+            return_value: None,
+            cpu_instructions: None,
+            memory_bytes: None,
+            budget_estimated: false,
+        }
+    }
+
+    /// One-line description: `name(args) -> value`.
+    pub fn signature(&self) -> String {
+        let args: Vec<String> = self.args.iter().map(Formatter::format_sc_val).collect();
+        let ret = match &self.return_value {
+            Some(value) => format!(" -> {}", Formatter::format_sc_val(value)),
+            None => String::new(),
+        };
+        format!("{}({}){}", self.function, args.join(", "), ret)
+    }
+
+    /// Budget summary, e.g. ` (cpu 1234, mem 567)`, ` (est. cpu 1234,
+    /// mem 567)` for estimates, or ` (cost n/a)` if unknown.
+    pub fn cost(&self) -> String {
+        match (self.cpu_instructions, self.memory_bytes) {
+            (Some(cpu), Some(mem)) if self.budget_estimated => {
+                format!(" (est. cpu {}, mem {})", cpu, mem)
+            }
+            (Some(cpu), Some(mem)) => format!(" (cpu {}, mem {})", cpu, mem),
+            _ => " (cost n/a)".to_string(),
+        }
+    }
+}
+
+/// Tracks and displays the call stack. Only the top-level frame has its
+/// cost measured; nested frames show `n/a` unless the engine estimates
+/// them (`--estimate-frame-budgets`).
 #[derive(Debug, Clone, Default)]
 pub struct CallStackInspector {
     stack: Vec<CallFrame>,
@@ -19,13 +73,63 @@ impl CallStackInspector {
         Self { stack: Vec::new() }
     }
 
+    /// Rebuild the calls of one invocation from the host's `fn_call` and
+    /// `fn_return` diagnostic events, in call order. Frames that trapped
+    /// (or were abandoned by a failing `try_call`) have no return value.
+    ///
+    /// The host packs multiple arguments into a vector, so a call with a
+    /// single vector argument of two or more elements is indistinguishable
+    /// from a multi-argument call and is shown as the latter.
+    pub fn from_events(events: &[ContractEvent]) -> Self {
+        let mut frames: Vec<CallFrame> = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+
+        for event in events {
+            let ContractEventBody::V0(body) = &event.body;
+            match body.topics.first() {
+                Some(ScVal::Symbol(s)) if s.as_slice() == b"fn_call" => {
+                    let contract_id = match body.topics.get(1) {
+                        Some(ScVal::Bytes(bytes)) => <[u8; 32]>::try_from(bytes.as_slice())
+                            .ok()
+                            .map(|id| ScAddress::Contract(Hash(id)).to_string()),
+                        _ => None,
+                    };
+                    let mut frame =
+                        CallFrame::new(symbol_name(body.topics.get(2)), contract_id, open.len());
+                    frame.args = match &body.data {
+                        ScVal::Void => Vec::new(),
+                        ScVal::Vec(Some(items)) if items.len() > 1 => items.to_vec(),
+                        other => vec![other.clone()],
+                    };
+                    open.push(frames.len());
+                    frames.push(frame);
+                }
+                Some(ScVal::Symbol(s)) if s.as_slice() == b"fn_return" => {
+                    let function = symbol_name(body.topics.get(1));
+                    let contract_id = event
+                        .contract_id
+                        .as_ref()
+                        .map(|id| ScAddress::Contract(id.clone()).to_string());
+                    // Frames above the one returning never returned themselves
+                    if let Some(pos) = open.iter().rposition(|&i| {
+                        frames[i].function == function && frames[i].contract_id == contract_id
+                    }) {
+                        frames[open[pos]].return_value = Some(body.data.clone());
+                        open.truncate(pos);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self { stack: frames }
+    }
+
     /// Push a function onto the call stack
     pub fn push(&mut self, function: String, contract_id: Option<String>) {
-        self.stack.push(CallFrame {
-            function,
-            contract_id,
-            duration: None,
-        });
+        let depth = self.stack.len();
+        self.stack
+            .push(CallFrame::new(function, contract_id, depth));
     }
 
     /// Push a frame with duration
@@ -43,6 +147,11 @@ impl CallStackInspector {
         &self.stack
     }
 
+    /// Mutable access to the frames, e.g. to attach budget figures.
+    pub fn frames_mut(&mut self) -> &mut [CallFrame] {
+        &mut self.stack
+    }
+
     /// Display the call stack
     pub fn display(&self) {
         if self.stack.is_empty() {
@@ -52,7 +161,7 @@ impl CallStackInspector {
 
         println!("Call Stack:");
         for (i, frame) in self.stack.iter().enumerate() {
-            let indent = "  ".repeat(i);
+            let indent = "  ".repeat(frame.depth);
             let contract_ctx = if let Some(ref id) = frame.contract_id {
                 format!(" [{}]", id)
             } else {
@@ -65,17 +174,20 @@ impl CallStackInspector {
                 "".to_string()
             };
 
-            if i == self.stack.len() - 1 {
-                println!(
-                    "{}→ {}{}{}",
-                    indent, frame.function, contract_ctx, duration_ctx
-                );
+            let marker = if i == self.stack.len() - 1 {
+                "→"
             } else {
-                println!(
-                    "{}└─ {}{}{}",
-                    indent, frame.function, contract_ctx, duration_ctx
-                );
-            }
+                "└─"
+            };
+            println!(
+                "{}{} {}{}{}{}",
+                indent,
+                marker,
+                frame.signature(),
+                contract_ctx,
+                frame.cost(),
+                duration_ctx
+            );
         }
     }
 
@@ -84,3 +196,107 @@ impl CallStackInspector {
         self.stack.clear();
     }
 }
+
+fn symbol_name(value: Option<&ScVal>) -> String {
+    match value {
+        Some(ScVal::Symbol(s)) => s.to_utf8_string_lossy(),
+        _ => "<unknown>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_env_host::xdr::{ContractEventType, ContractEventV0, ExtensionPoint, ScSymbol};
+
+    fn sym(s: &str) -> ScVal {
+        ScVal::Symbol(ScSymbol(s.try_into().unwrap()))
+    }
+
+    fn event(contract: Option<u8>, topics: Vec<ScVal>, data: ScVal) -> ContractEvent {
+        ContractEvent {
+            ext: ExtensionPoint::V0,
+            contract_id: contract.map(|b| Hash([b; 32])),
+            type_: ContractEventType::Diagnostic,
+            body: ContractEventBody::V0(ContractEventV0 {
+                topics: topics.try_into().unwrap(),
+                data,
+            }),
+        }
+    }
+
+    fn call(caller: Option<u8>, callee: u8, function: &str, data: ScVal) -> ContractEvent {
+        let id = ScVal::Bytes(vec![callee; 32].try_into().unwrap());
+        event(caller, vec![sym("fn_call"), id, sym(function)], data)
+    }
+
+    fn ret(callee: u8, function: &str, value: ScVal) -> ContractEvent {
+        event(Some(callee), vec![sym("fn_return"), sym(function)], value)
+    }
+
+    fn contract(b: u8) -> Option<String> {
+        Some(ScAddress::Contract(Hash([b; 32])).to_string())
+    }
+
+    #[test]
+    fn builds_nested_frames_from_fn_call_and_fn_return() {
+        let args = ScVal::Vec(Some(vec![ScVal::U32(1), ScVal::U32(2)].try_into().unwrap()));
+        let events = vec![
+            call(None, 1, "swap", args),
+            event(Some(1), vec![sym("log")], ScVal::Void),
+            call(Some(1), 2, "transfer", ScVal::U32(5)),
+            ret(2, "transfer", ScVal::Void),
+            call(Some(1), 3, "price", ScVal::Void),
+            ret(3, "price", ScVal::U32(9)),
+            ret(1, "swap", ScVal::Bool(true)),
+        ];
+
+        let stack = CallStackInspector::from_events(&events);
+        let frames = stack.get_stack();
+        assert_eq!(frames.len(), 3);
+
+        assert_eq!(frames[0].function, "swap");
+        assert_eq!(frames[0].contract_id, contract(1));
+        assert_eq!(frames[0].depth, 0);
+        assert_eq!(frames[0].args, vec![ScVal::U32(1), ScVal::U32(2)]);
+        assert_eq!(frames[0].return_value, Some(ScVal::Bool(true)));
+
+        assert_eq!(frames[1].function, "transfer");
+        assert_eq!(frames[1].contract_id, contract(2));
+        assert_eq!(frames[1].depth, 1);
+        assert_eq!(frames[1].args, vec![ScVal::U32(5)]);
+
+        assert_eq!(frames[2].function, "price");
+        assert_eq!(frames[2].depth, 1);
+        assert!(frames[2].args.is_empty());
+        assert_eq!(frames[2].signature(), "price() -> 9");
+    }
+
+    #[test]
+    fn frames_that_trap_have_no_return_value() {
+        let events = vec![
+            call(None, 1, "outer", ScVal::Void),
+            call(Some(1), 2, "inner", ScVal::Void),
+            call(Some(2), 3, "fails", ScVal::Void),
+            // `inner` caught the failure with try_call and returned
+            ret(2, "inner", ScVal::U32(0)),
+            call(Some(1), 3, "again", ScVal::Void),
+        ];
+
+        let stack = CallStackInspector::from_events(&events);
+        let frames = stack.get_stack();
+        let summary: Vec<(&str, usize, bool)> = frames
+            .iter()
+            .map(|f| (f.function.as_str(), f.depth, f.return_value.is_some()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("outer", 0, false),
+                ("inner", 1, true),
+                ("fails", 2, false),
+                ("again", 1, false),
+            ]
+        );
+    }
+}
@@ -5,6 +5,7 @@ pub mod client;
 pub mod codegen;
 pub mod compare;
 pub mod config;
+pub mod dap;
 pub mod debugger;
 pub mod history;
 pub mod inspector;
@@ -61,7 +61,7 @@ fn handle_deprecations(cli: &mut Cli) {
         Some(Commands::Interactive(args)) => {
             if let Some(wasm) = args.wasm.take() {
                 eprintln!("{}", Formatter::warning("Warning: --wasm and --contract-path are deprecated. Please use --contract instead."));
-                args.contract = wasm;
+                args.contract = Some(wasm);
             }
             if let Some(snapshot) = args.snapshot.take() {
                 eprintln!(
@@ -145,6 +145,7 @@ fn main() -> miette::Result<()> {
         }
         Some(Commands::Server(args)) => soroban_debugger::cli::commands::server(args),
         Some(Commands::Remote(args)) => soroban_debugger::cli::commands::remote(args, verbosity),
+        Some(Commands::Dap(args)) => soroban_debugger::cli::commands::dap(args),
         Some(Commands::Analyze(args)) => soroban_debugger::cli::commands::analyze(args, verbosity),
         None => {
             if let Some(path) = cli.list_functions {
@@ -2,6 +2,7 @@ use crate::debugger::engine::DebuggerEngine;
 use crate::inspector::budget::{BudgetInfo, BudgetInspector};
 use crate::logging;
 use crate::runtime::executor::ContractExecutor;
+use crate::simulator::SnapshotLoader;
 use crate::Result;
 use std::time::{Duration, Instant};
 
@@ -155,6 +156,7 @@ pub struct RepeatRunner {
     wasm_bytes: Vec<u8>,
     breakpoints: Vec<String>,
     initial_storage: Option<String>,
+    snapshot: Option<SnapshotLoader>,
 }
 
 impl RepeatRunner {
@@ -167,9 +169,16 @@ impl RepeatRunner {
             wasm_bytes,
             breakpoints,
             initial_storage,
+            snapshot: None,
         }
     }
 
+    /// Run every iteration against the ledger state of `snapshot`
+    pub fn with_snapshot(mut self, snapshot: SnapshotLoader) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
     /// Run the contract function `n` times and return aggregate stats.
     pub fn run(&self, function: &str, args: Option<&str>, n: u32) -> Result<AggregateStats> {
         logging::log_repeat_execution(function, n as usize);
@@ -189,6 +198,9 @@ impl RepeatRunner {
             if let Some(ref storage) = self.initial_storage {
                 executor.set_initial_storage(storage.clone())?;
             }
+            if let Some(snapshot) = &self.snapshot {
+                snapshot.apply_to_environment(&mut executor)?;
+            }
 
             let mut engine = DebuggerEngine::new(executor, self.breakpoints.clone());
 
@@ -0,0 +1,545 @@
+//! Source-mapped backtraces for failed invocations.
+//!
+//! The host reports a trap without the WASM frames that led to it, so the
+//! failing call is re-run in a forked environment with an instrumented copy
+//! of the contract. Every function entry, call site, return and `unreachable`
+//! logs a small marker event; replaying the markers rebuilds the stack that
+//! was live when execution stopped. Frames are symbolized with the name
+//! section and mapped to source lines through DWARF when it is present.
+
+use crate::debugger::source_map::{SourceLocation, SourceMap};
+use crate::runtime::memory_capture::{host_import, u32_val};
+use crate::utils::ArgumentParser;
+use crate::{DebuggerError, Result};
+use soroban_env_host::xdr::{ContractEventBody, ScVal};
+use soroban_env_host::{DiagnosticLevel, SymbolSmall};
+use soroban_sdk::{Address, Bytes, Env, InvokeError, Symbol, Val, Vec as SorobanVec};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use walrus::ir::{Instr, InstrLocId, InstrSeq, LoadKind, MemArg, StoreKind, Value};
+use walrus::{FunctionBuilder, FunctionId, FunctionKind, LocalFunction, Module, ValType};
+
+/// Marker symbol identifying trace log events.
+const TRACE_MARKER: &str = "sdbg_bt";
+
+const ENTER: u32 = 0;
+const CALL: u32 = 1;
+const RETURN: u32 = 2;
+const TRAP: u32 = 3;
+
+/// One frame of a backtrace, innermost first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktraceFrame {
+    pub function_index: u32,
+    pub function_name: Option<String>,
+    /// Code offset of the call or trap site; `None` when unknown
+    pub offset: Option<usize>,
+    pub location: Option<SourceLocation>,
+    /// The frame is a host function called by the contract
+    pub is_host: bool,
+}
+
+/// The WASM call stack at the point an invocation failed.
+#[derive(Debug, Clone, Default)]
+pub struct Backtrace {
+    pub frames: Vec<BacktraceFrame>,
+    /// Messages the contract logged before failing
+    pub logs: Vec<String>,
+    /// Execution stopped inside the contract rather than returning
+    pub trapped: bool,
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for log in &self.logs {
+            writeln!(f, "  Contract log: {}", log)?;
+        }
+        write!(f, "  Backtrace:")?;
+        for (i, frame) in self.frames.iter().enumerate() {
+            let name = frame
+                .function_name
+                .clone()
+                .unwrap_or_else(|| format!("func[{}]", frame.function_index));
+            write!(f, "\n    #{:<2} {}", i, name)?;
+            if frame.is_host {
+                write!(f, " (host)")?;
+            }
+            if let Some(offset) = frame.offset {
+                write!(f, " @ 0x{:08x}", offset)?;
+            }
+            if let Some(loc) = &frame.location {
+                write!(f, "\n         at {}:{}", loc.file.display(), loc.line)?;
+                if let Some(column) = loc.column {
+                    write!(f, ":{}", column)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Re-run `function` in `env` with an instrumented copy of the contract at
+/// `contract` and return the stack at the point it failed. `env` must be a
+/// throwaway fork; its copy of the contract code is replaced.
+pub fn capture_backtrace(
+    env: &Env,
+    contract: &Address,
+    wasm_bytes: &[u8],
+    function: &str,
+    args: Option<&str>,
+) -> Result<Backtrace> {
+    let (instrumented, symbols) = instrument(wasm_bytes)?;
+    let host_err = |e| DebuggerError::ExecutionError(format!("{:?}", e));
+
+    env.cost_estimate().budget().reset_unlimited();
+    let host = env.host();
+    host.set_diagnostic_level(DiagnosticLevel::Debug)
+        .map_err(host_err)?;
+    host.set_shadow_budget_limits(u64::MAX, u64::MAX)
+        .map_err(host_err)?;
+
+    let hash = env
+        .deployer()
+        .upload_contract_wasm(Bytes::from_slice(env, &instrumented));
+    env.as_contract(contract, || {
+        env.deployer().update_current_contract_wasm(hash)
+    });
+
+    let parsed_args = match args {
+        Some(json) => ArgumentParser::new(env.clone())
+            .parse_args_string(json)
+            .map_err(|e| DebuggerError::InvalidArguments(e.to_string()))?,
+        None => Vec::new(),
+    };
+    let args_vec = SorobanVec::<Val>::from_slice(env, &parsed_args);
+    let _ = env.try_invoke_contract::<Val, InvokeError>(
+        contract,
+        &Symbol::new(env, function),
+        args_vec,
+    );
+
+    let events = host.get_diagnostic_events().map_err(host_err)?;
+    let mut trace = Vec::new();
+    let mut logs = Vec::new();
+    for event in events.0.iter() {
+        let ContractEventBody::V0(body) = &event.event.body;
+        match parse_trace_event(&body.data) {
+            Some(marker) => trace.push(marker),
+            None if is_log(&body.topics) => logs.push(format_log(&body.data)),
+            None => {}
+        }
+    }
+
+    let mut source_map = SourceMap::new();
+    // Contracts without DWARF still get symbolized frames
+    let _ = source_map.load(wasm_bytes);
+    let mut backtrace = rebuild(&trace, &symbols, &source_map);
+    backtrace.logs = logs;
+    Ok(backtrace)
+}
+
+/// Function names and call targets recorded while instrumenting.
+#[derive(Default)]
+struct Symbols {
+    names: HashMap<u32, String>,
+    imports: HashSet<u32>,
+    /// Call site offset to the imported function it calls
+    import_calls: HashMap<u32, u32>,
+}
+
+#[derive(Debug)]
+struct OpenFrame {
+    function: u32,
+    site: Option<u32>,
+}
+
+/// Replay trace markers into the stack that was live at the last one.
+fn rebuild(trace: &[(u32, u32, u32)], symbols: &Symbols, source_map: &SourceMap) -> Backtrace {
+    let mut stack: Vec<OpenFrame> = Vec::new();
+    let mut trapped = false;
+
+    for &(kind, function, offset) in trace {
+        match kind {
+            ENTER => stack.push(OpenFrame {
+                function,
+                site: None,
+            }),
+            CALL => {
+                if let Some(top) = stack.last_mut() {
+                    top.site = Some(offset);
+                }
+            }
+            RETURN => {
+                // The innermost frame waiting on this call site made it
+                let caller = stack
+                    .iter()
+                    .rposition(|f| f.function == function && f.site == Some(offset));
+                if let Some(caller) = caller {
+                    stack.truncate(caller + 1);
+                    stack[caller].site = None;
+                }
+            }
+            TRAP => {
+                if let Some(top) = stack.last_mut() {
+                    top.site = Some(offset);
+                }
+                trapped = true;
+            }
+            _ => {}
+        }
+    }
+
+    let mut frames = Vec::new();
+    if let Some(import) = stack
+        .last()
+        .and_then(|top| top.site)
+        .and_then(|site| symbols.import_calls.get(&site))
+    {
+        trapped = true;
+        frames.push(BacktraceFrame {
+            function_index: *import,
+            function_name: symbols.names.get(import).cloned(),
+            offset: None,
+            location: None,
+            is_host: true,
+        });
+    }
+    for frame in stack.iter().rev() {
+        let offset = frame.site.map(|s| s as usize);
+        frames.push(BacktraceFrame {
+            function_index: frame.function,
+            function_name: symbols.names.get(&frame.function).cloned(),
+            offset,
+            location: offset.and_then(|o| source_map.lookup(o)),
+            is_host: false,
+        });
+    }
+    // Frames still open after the export returned mean it never finished
+    trapped |= stack.first().is_some_and(|root| root.site.is_some()) || stack.len() > 1;
+
+    Backtrace {
+        frames,
+        logs: Vec::new(),
+        trapped,
+    }
+}
+
+fn parse_trace_event(data: &ScVal) -> Option<(u32, u32, u32)> {
+    let ScVal::Vec(Some(items)) = data else {
+        return None;
+    };
+    match items.as_slice() {
+        [ScVal::String(_), ScVal::Symbol(marker), ScVal::U32(kind), ScVal::U32(function), ScVal::U32(offset)]
+            if marker.to_utf8_string_lossy() == TRACE_MARKER =>
+        {
+            Some((*kind, *function, *offset))
+        }
+        _ => None,
+    }
+}
+
+/// The message of a `log!` diagnostic event.
+pub(crate) fn log_line(event: &soroban_env_host::xdr::ContractEvent) -> Option<String> {
+    let ContractEventBody::V0(body) = &event.body;
+    is_log(&body.topics).then(|| format_log(&body.data))
+}
+
+fn is_log(topics: &[ScVal]) -> bool {
+    matches!(topics.first(), Some(ScVal::Symbol(s)) if s.as_slice() == b"log")
+}
+
+fn format_log(data: &ScVal) -> String {
+    let text = |v: &ScVal| match v {
+        ScVal::String(s) => s.to_utf8_string_lossy(),
+        other => crate::ui::formatter::Formatter::format_sc_val(other),
+    };
+    match data {
+        ScVal::Vec(Some(items)) => match items.split_first() {
+            Some((message, args)) if !args.is_empty() => format!(
+                "{} {}",
+                text(message),
+                args.iter().map(text).collect::<Vec<_>>().join(", ")
+            ),
+            Some((message, _)) => text(message),
+            None => String::new(),
+        },
+        other => text(other),
+    }
+}
+
+fn display_name(name: &str) -> String {
+    format!("{:#}", rustc_demangle::demangle(name))
+}
+
+/// Instrument every function so entries, call sites, returns and traps are
+/// logged with their original function index and code offset.
+fn instrument(wasm_bytes: &[u8]) -> Result<(Vec<u8>, Symbols)> {
+    let mut module = Module::from_buffer(wasm_bytes)
+        .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to parse WASM: {}", e)))?;
+
+    let memory = module
+        .memories
+        .iter()
+        .next()
+        .map(|m| m.id())
+        .ok_or_else(|| DebuggerError::WasmLoadError("Contract has no linear memory".into()))?;
+
+    let mut symbols = Symbols::default();
+    let mut originals = Vec::new();
+    for func in module.funcs.iter() {
+        let index = func.id().index() as u32;
+        let name = match (&func.name, &func.kind) {
+            (Some(name), _) => Some(display_name(name)),
+            (None, FunctionKind::Import(import)) => {
+                let import = module.imports.get(import.import);
+                Some(format!("{}.{}", import.module, import.name))
+            }
+            _ => None,
+        };
+        if let Some(name) = name {
+            symbols.names.insert(index, name);
+        }
+        match func.kind {
+            FunctionKind::Import(_) => {
+                symbols.imports.insert(index);
+            }
+            FunctionKind::Local(_) => originals.push(func.id()),
+            FunctionKind::Uninitialized(_) => {}
+        }
+    }
+
+    // x._ = log_from_linear_memory
+    let log = host_import(&mut module, "x", "_", 4);
+    let marker = SymbolSmall::try_from_str(TRACE_MARKER)
+        .map_err(|e| DebuggerError::ExecutionError(format!("{:?}", e)))?
+        .to_val()
+        .get_payload() as i64;
+    let arg = |offset| MemArg { align: 8, offset };
+    let i64_load = LoadKind::I64 { atomic: false };
+    let i64_store = StoreKind::I64 { atomic: false };
+
+    // trace(kind, function, offset): the vals array for the log call is
+    // written to address 0 and restored afterwards.
+    let params: Vec<_> = (0..3).map(|_| module.locals.add(ValType::I64)).collect();
+    let saved: Vec<_> = (0..4).map(|_| module.locals.add(ValType::I64)).collect();
+    let mut trace = FunctionBuilder::new(&mut module.types, &[ValType::I64; 3], &[]);
+    {
+        let mut body = trace.func_body();
+        for (i, local) in saved.iter().enumerate() {
+            body.i32_const(0)
+                .load(memory, i64_load, arg(i as u32 * 8))
+                .local_set(*local);
+        }
+        body.i32_const(0)
+            .i64_const(marker)
+            .store(memory, i64_store, arg(0));
+        for (i, param) in params.iter().enumerate() {
+            body.i32_const(0)
+                .local_get(*param)
+                .store(memory, i64_store, arg(8 + i as u32 * 8));
+        }
+        body.i64_const(u32_val(0))
+            .i64_const(u32_val(0))
+            .i64_const(u32_val(0))
+            .i64_const(u32_val(4))
+            .call(log)
+            .drop();
+        for (i, local) in saved.iter().enumerate() {
+            body.i32_const(0)
+                .local_get(*local)
+                .store(memory, i64_store, arg(i as u32 * 8));
+        }
+    }
+    let trace = trace.finish(params, &mut module.funcs);
+
+    for id in originals {
+        let index = id.index() as u32;
+        let imports = &symbols.imports;
+        if let FunctionKind::Local(func) = &mut module.funcs.get_mut(id).kind {
+            let calls = insert_markers(func, trace, index, |callee| {
+                imports.contains(&(callee.index() as u32))
+            });
+            symbols.import_calls.extend(calls);
+        }
+    }
+
+    Ok((module.emit_wasm(), symbols))
+}
+
+/// Add trace calls to `func` and return the offsets of its calls into
+/// imported functions.
+fn insert_markers(
+    func: &mut LocalFunction,
+    trace: FunctionId,
+    index: u32,
+    is_import: impl Fn(FunctionId) -> bool,
+) -> Vec<(u32, u32)> {
+    struct Rewriter<'a> {
+        trace: FunctionId,
+        index: u32,
+        is_import: &'a dyn Fn(FunctionId) -> bool,
+        import_calls: Vec<(u32, u32)>,
+    }
+
+    impl Rewriter<'_> {
+        fn marker(&self, kind: u32, offset: u32) -> [(Instr, InstrLocId); 4] {
+            let konst = |v: u32| {
+                (
+                    walrus::ir::Const {
+                        value: Value::I64(u32_val(v)),
+                    }
+                    .into(),
+                    InstrLocId::default(),
+                )
+            };
+            [
+                konst(kind),
+                konst(self.index),
+                konst(offset),
+                (
+                    walrus::ir::Call { func: self.trace }.into(),
+                    InstrLocId::default(),
+                ),
+            ]
+        }
+    }
+
+    impl walrus::ir::VisitorMut for Rewriter<'_> {
+        fn start_instr_seq_mut(&mut self, seq: &mut InstrSeq) {
+            let instrs = std::mem::take(&mut seq.instrs);
+            for (instr, loc) in instrs {
+                let offset = loc.data();
+                match &instr {
+                    Instr::Call(call) => {
+                        if (self.is_import)(call.func) {
+                            self.import_calls.push((offset, call.func.index() as u32));
+                        }
+                        seq.instrs.extend(self.marker(CALL, offset));
+                        seq.instrs.push((instr, loc));
+                        seq.instrs.extend(self.marker(RETURN, offset));
+                    }
+                    Instr::CallIndirect(_) => {
+                        seq.instrs.extend(self.marker(CALL, offset));
+                        seq.instrs.push((instr, loc));
+                        seq.instrs.extend(self.marker(RETURN, offset));
+                    }
+                    Instr::Unreachable(_) => {
+                        seq.instrs.extend(self.marker(TRAP, offset));
+                        seq.instrs.push((instr, loc));
+                    }
+                    _ => seq.instrs.push((instr, loc)),
+                }
+            }
+        }
+    }
+
+    let entry = func.entry_block();
+    let mut rewriter = Rewriter {
+        trace,
+        index,
+        is_import: &is_import,
+        import_calls: Vec::new(),
+    };
+    walrus::ir::dfs_pre_order_mut(&mut rewriter, func, entry);
+
+    let enter = rewriter.marker(ENTER, 0);
+    let body = &mut func.block_mut(entry).instrs;
+    body.splice(0..0, enter);
+    rewriter.import_calls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use soroban_sdk::testutils::EnvTestConfig;
+
+    /// `boom` calls `inner`, which traps; `fail` calls `fail_with_error`.
+    fn contract() -> Vec<u8> {
+        let (mut module, _) = test_wasm::module();
+        let fail_ty = module.types.add(&[ValType::I64], &[ValType::I64]);
+        let (fail_with_error, _) = module.add_import_func("x", "5", fail_ty);
+
+        let mut inner = FunctionBuilder::new(&mut module.types, &[], &[ValType::I64]);
+        inner.name("inner".to_string());
+        inner.func_body().unreachable();
+        let inner = inner.finish(vec![], &mut module.funcs);
+
+        let mut boom = FunctionBuilder::new(&mut module.types, &[], &[ValType::I64]);
+        boom.name("boom".to_string());
+        boom.func_body()
+            .i64_const(test_wasm::VOID)
+            .drop()
+            .call(inner);
+        let boom = boom.finish(vec![], &mut module.funcs);
+        module.exports.add("boom", boom);
+
+        let mut fail = FunctionBuilder::new(&mut module.types, &[], &[ValType::I64]);
+        fail.name("fail".to_string());
+        fail.func_body()
+            .i64_const((3 << 32) | 3)
+            .call(fail_with_error);
+        let fail = fail.finish(vec![], &mut module.funcs);
+        module.exports.add("fail", fail);
+
+        test_wasm::finish(module)
+    }
+
+    fn run(function: &str) -> Backtrace {
+        let env = Env::new_with_config(EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
+        let wasm = contract();
+        let contract = env.register(wasm.as_slice(), ());
+        capture_backtrace(&env, &contract, &wasm, function, None).unwrap()
+    }
+
+    fn names(trace: &Backtrace) -> Vec<&str> {
+        trace
+            .frames
+            .iter()
+            .map(|f| f.function_name.as_deref().unwrap_or("?"))
+            .collect()
+    }
+
+    #[test]
+    fn backtrace_through_nested_trap() {
+        let trace = run("boom");
+        assert!(trace.trapped);
+        assert_eq!(names(&trace), vec!["inner", "boom"]);
+        assert!(trace.frames.iter().all(|f| f.offset.is_some()));
+        assert_ne!(trace.frames[0].offset, trace.frames[1].offset);
+        let text = trace.to_string();
+        assert!(text.contains("#0  inner @ 0x"));
+        assert!(text.contains("#1  boom @ 0x"));
+    }
+
+    #[test]
+    fn backtrace_ends_in_failing_host_call() {
+        let trace = run("fail");
+        assert!(trace.trapped);
+        assert_eq!(names(&trace), vec!["x.5", "fail"]);
+        assert!(trace.frames[0].is_host);
+    }
+
+    #[test]
+    fn rebuild_pops_returned_recursive_calls() {
+        let symbols = Symbols::default();
+        let trace = [
+            (ENTER, 1, 0),
+            (CALL, 1, 10),
+            (ENTER, 1, 0),
+            (CALL, 1, 10),
+            (ENTER, 1, 0),
+            (RETURN, 1, 10),
+            (RETURN, 1, 10),
+            (CALL, 1, 20),
+            (ENTER, 2, 0),
+            (TRAP, 2, 30),
+        ];
+        let bt = rebuild(&trace, &symbols, &SourceMap::new());
+        let sites: Vec<_> = bt.frames.iter().map(|f| f.offset).collect();
+        assert_eq!(sites, vec![Some(30), Some(20)]);
+        assert!(bt.trapped);
+    }
+}
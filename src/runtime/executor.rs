@@ -1,12 +1,23 @@
+use crate::debugger::error_db::ErrorDatabase;
+use crate::inspector::stack::{CallFrame, CallStackInspector};
+use crate::runtime::backtrace::{capture_backtrace, Backtrace};
 use crate::runtime::mocking::MockRegistry;
 use crate::utils::ArgumentParser;
 use crate::{runtime::mocking::MockCallLogEntry, runtime::mocking::MockContractDispatcher};
 use crate::{DebuggerError, Result};
 
+use soroban_env_host::xdr::{
+    ContractDataDurability, ContractEventBody, ContractExecutable, LedgerEntryData, ScAddress,
+    ScContractInstance, ScError, ScVal,
+};
 use soroban_env_host::{DiagnosticLevel, Host};
-use soroban_sdk::{Address, Env, InvokeError, Symbol, Val, Vec as SorobanVec};
+use soroban_ledger_snapshot::LedgerSnapshot;
+use soroban_sdk::testutils::{EnvTestConfig, Ledger as _, Snapshot};
+use soroban_sdk::{Address, Env, InvokeError, Symbol, TryFromVal, Val, Vec as SorobanVec};
 use std::collections::HashMap;
 use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use tracing::{info, warn};
 
@@ -16,13 +27,43 @@ pub struct StorageSnapshot {
     _contract_address: Address,
 }
 
+/// Which of the contract's storage maps an entry lives in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    Instance,
+    Persistent,
+    Temporary,
+}
+
+impl StorageKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "instance" => Some(Self::Instance),
+            "persistent" => Some(Self::Persistent),
+            "temporary" | "temp" => Some(Self::Temporary),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Instance => "instance",
+            Self::Persistent => "persistent",
+            Self::Temporary => "temporary",
+        }
+    }
+}
+
 /// Executes Soroban contracts in a test environment.
 pub struct ContractExecutor {
     env: Env,
     contract_address: Address,
     mock_registry: Arc<Mutex<MockRegistry>>,
+    mock_specs: Vec<String>,
     wasm_bytes: Vec<u8>,
     timeout_secs: u64,
+    error_db: ErrorDatabase,
+    console_output: bool,
 }
 
 impl ContractExecutor {
@@ -30,26 +71,86 @@ impl ContractExecutor {
     pub fn new(wasm: Vec<u8>) -> Result<Self> {
         info!("Initializing contract executor");
 
-        let env = Env::default();
+        // Never write SDK test snapshots when the environment is dropped
+        let env = Env::new_with_config(EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
         env.host()
             .set_diagnostic_level(DiagnosticLevel::Debug)
             .expect("Failed to set diagnostic level");
 
-        let contract_address = env.register(wasm.as_slice(), ());
+        let contract_address = register_wasm(&env, &wasm)?;
 
         Ok(Self {
             env,
             contract_address,
             mock_registry: Arc::new(Mutex::new(MockRegistry::default())),
+            mock_specs: Vec::new(),
+            error_db: ErrorDatabase::from_wasm(&wasm),
             wasm_bytes: wasm,
             timeout_secs: 30,
+            console_output: true,
         })
     }
 
+    /// The contract's WASM bytecode.
+    pub fn wasm_bytes(&self) -> &[u8] {
+        &self.wasm_bytes
+    }
+
+    /// Address of the contract being debugged.
+    pub fn contract_id(&self) -> String {
+        ScAddress::from(&self.contract_address).to_string()
+    }
+
+    /// Deploy another contract into the environment, for example the callee
+    /// of a cross-contract call, and return its address.
+    pub fn deploy(&mut self, wasm: &[u8]) -> Result<String> {
+        let address = register_wasm(&self.env, wasm)?;
+        Ok(ScAddress::from(&address).to_string())
+    }
+
+    /// WASM code of a deployed contract, looked up through its instance
+    /// entry. `None` for unknown contracts, mocks and built-in contracts.
+    pub fn contract_wasm(&self, contract_id: &str) -> Option<Vec<u8>> {
+        let contract = ScAddress::from_str(contract_id).ok()?;
+        let entries = self.env.to_ledger_snapshot().ledger_entries;
+
+        let hash = entries
+            .iter()
+            .find_map(|(_, (entry, _))| match &entry.data {
+                LedgerEntryData::ContractData(data) if data.contract == contract => match &data.val
+                {
+                    ScVal::ContractInstance(ScContractInstance {
+                        executable: ContractExecutable::Wasm(hash),
+                        ..
+                    }) => Some(hash.clone()),
+                    _ => None,
+                },
+                _ => None,
+            })?;
+        entries
+            .into_iter()
+            .find_map(|(_, (entry, _))| match entry.data {
+                LedgerEntryData::ContractCode(code) if code.hash == hash => {
+                    Some(code.code.to_vec())
+                }
+                _ => None,
+            })
+    }
+
+    /// Exit the process with status 124 if a call runs longer than `secs`
+    /// seconds; 0 waits forever. Long-lived hosts such as the debug server
+    /// turn it off.
     pub fn set_timeout(&mut self, secs: u64) {
         self.timeout_secs = secs;
     }
 
+    /// Whether budget warnings are printed to stdout after each call.
+    pub fn set_console_output(&mut self, enabled: bool) {
+        self.console_output = enabled;
+    }
+
     /// Execute a contract function.
     pub fn execute(&self, function: &str, args: Option<&str>) -> Result<String> {
         info!("Executing function: {}", function);
@@ -94,7 +195,7 @@ impl ContractExecutor {
         }
 
         // Call the contract
-        let res = match self.env.try_invoke_contract::<Val, InvokeError>(
+        let res = match self.env.try_invoke_contract::<Val, soroban_sdk::Error>(
             &self.contract_address,
             &func_symbol,
             args_vec,
@@ -110,22 +211,19 @@ impl ContractExecutor {
                     conv_err
                 )))
             }
-            Err(Ok(inv_err)) => match inv_err {
-                InvokeError::Contract(code) => {
-                    warn!("Contract returned error code: {}", code);
-                    Err(DebuggerError::ExecutionError(format!(
-                        "The contract returned an error code: {}. This typically indicates a business logic failure (e.g. `panic!` or `require!`).",
-                        code
-                    )))
-                }
-                InvokeError::Abort => {
-                    warn!("Contract execution aborted");
-                    Err(DebuggerError::ExecutionError(
-                        "Contract execution was aborted. This could be due to a trap, budget exhaustion, or an explicit abort call."
-                            .to_string(),
-                    ))
+            Err(Ok(err)) => {
+                warn!("Contract invocation failed: {:?}", err);
+                let mut message = self.describe_error(err);
+                match self.backtrace(function, args) {
+                    Ok(trace) if trace.trapped && !trace.frames.is_empty() => {
+                        message.push('\n');
+                        message.push_str(&trace.to_string());
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Could not capture a backtrace: {}", e),
                 }
-            },
+                Err(DebuggerError::ExecutionError(message))
+            }
             Err(Err(inv_err)) => {
                 warn!("Invocation error conversion failed: {:?}", inv_err);
                 Err(DebuggerError::ExecutionError(format!(
@@ -138,7 +236,9 @@ impl ContractExecutor {
         let _ = tx.send(());
 
         // Display budget usage and warnings
-        crate::inspector::BudgetInspector::display(self.env.host());
+        if self.console_output {
+            crate::inspector::BudgetInspector::display(self.env.host());
+        }
 
         Ok(res?)
     }
@@ -151,7 +251,14 @@ impl ContractExecutor {
 
     pub fn set_mock_specs(&mut self, specs: &[String]) -> Result<()> {
         let registry = MockRegistry::from_cli_specs(&self.env, specs)?;
-        self.set_mock_registry(registry)
+        self.set_mock_registry(registry)?;
+        self.mock_specs = specs.to_vec();
+        Ok(())
+    }
+
+    /// The `CONTRACT_ID.function=return_value` specs the mocks were built from.
+    pub fn mock_specs(&self) -> &[String] {
+        &self.mock_specs
     }
 
     pub fn set_mock_registry(&mut self, registry: MockRegistry) -> Result<()> {
@@ -166,6 +273,298 @@ impl ContractExecutor {
         }
     }
 
+    /// A throwaway environment holding a copy of the current ledger state
+    /// and the same mocks, with the contract's address in that environment.
+    /// Anything invoked in it leaves the session, mock call log included,
+    /// untouched.
+    pub fn fork(&self) -> Result<(Env, Address)> {
+        self.fork_from(self.env.to_ledger_snapshot())
+    }
+
+    /// Like [`fork`](Self::fork), from an earlier `ledger` of the session.
+    pub fn fork_from(&self, ledger: LedgerSnapshot) -> Result<(Env, Address)> {
+        let mut env = Env::from_ledger_snapshot(ledger);
+        env.set_config(EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
+        env.cost_estimate().budget().reset_unlimited();
+        if !self.mock_specs.is_empty() {
+            let registry = MockRegistry::from_cli_specs(&env, &self.mock_specs)?;
+            register_mocks(&env, &Arc::new(Mutex::new(registry)))?;
+        }
+
+        let address = ScVal::Address(ScAddress::from(&self.contract_address));
+        let contract = Address::try_from_val(&env, &address).map_err(|e| {
+            DebuggerError::ExecutionError(format!("Failed to fork environment: {:?}", e))
+        })?;
+        Ok((env, contract))
+    }
+
+    /// Re-run a call against a fork of the current state and capture the
+    /// WASM stack at the point it fails.
+    pub fn backtrace(&self, function: &str, args: Option<&str>) -> Result<Backtrace> {
+        let (env, contract) = self.fork()?;
+        capture_backtrace(&env, &contract, &self.wasm_bytes, function, args)
+    }
+
+    /// Read a storage entry of the contract.
+    pub fn storage_get(&self, kind: StorageKind, key: &serde_json::Value) -> Result<Option<ScVal>> {
+        let key = self.parse_value(key)?;
+        let value = self.env.as_contract(&self.contract_address, || {
+            let storage = self.env.storage();
+            match kind {
+                StorageKind::Instance => storage.instance().get::<Val, Val>(&key),
+                StorageKind::Persistent => storage.persistent().get::<Val, Val>(&key),
+                StorageKind::Temporary => storage.temporary().get::<Val, Val>(&key),
+            }
+        });
+        value.map(|v| self.to_sc_val(v)).transpose()
+    }
+
+    /// Every storage entry of the contract, in ledger order.
+    pub fn storage_entries(&self) -> Vec<(StorageKind, ScVal, ScVal)> {
+        let contract = ScAddress::from(&self.contract_address);
+        let mut entries = Vec::new();
+        for (_, (entry, _)) in self.env.to_ledger_snapshot().ledger_entries {
+            let LedgerEntryData::ContractData(data) = entry.data else {
+                continue;
+            };
+            if data.contract != contract {
+                continue;
+            }
+            match (data.key, data.val) {
+                (ScVal::LedgerKeyContractInstance, ScVal::ContractInstance(instance)) => {
+                    if let Some(storage) = instance.storage {
+                        entries.extend(
+                            storage
+                                .iter()
+                                .map(|e| (StorageKind::Instance, e.key.clone(), e.val.clone())),
+                        );
+                    }
+                }
+                (key, val) => {
+                    let kind = match data.durability {
+                        ContractDataDurability::Persistent => StorageKind::Persistent,
+                        ContractDataDurability::Temporary => StorageKind::Temporary,
+                    };
+                    entries.push((kind, key, val));
+                }
+            }
+        }
+        entries
+    }
+
+    /// Write a storage entry of the contract, as if the contract had done so.
+    pub fn storage_set(
+        &mut self,
+        kind: StorageKind,
+        key: &serde_json::Value,
+        value: &serde_json::Value,
+    ) -> Result<()> {
+        let key = self.parse_value(key)?;
+        let value = self.parse_value(value)?;
+        self.env.as_contract(&self.contract_address, || {
+            let storage = self.env.storage();
+            match kind {
+                StorageKind::Instance => storage.instance().set(&key, &value),
+                StorageKind::Persistent => storage.persistent().set(&key, &value),
+                StorageKind::Temporary => storage.temporary().set(&key, &value),
+            }
+        });
+        Ok(())
+    }
+
+    /// Remove a storage entry of the contract. Returns whether it existed.
+    pub fn storage_remove(&mut self, kind: StorageKind, key: &serde_json::Value) -> Result<bool> {
+        let key = self.parse_value(key)?;
+        Ok(self.env.as_contract(&self.contract_address, || {
+            let storage = self.env.storage();
+            match kind {
+                StorageKind::Instance => {
+                    let existed = storage.instance().has(&key);
+                    storage.instance().remove(&key);
+                    existed
+                }
+                StorageKind::Persistent => {
+                    let existed = storage.persistent().has(&key);
+                    storage.persistent().remove(&key);
+                    existed
+                }
+                StorageKind::Temporary => {
+                    let existed = storage.temporary().has(&key);
+                    storage.temporary().remove(&key);
+                    existed
+                }
+            }
+        }))
+    }
+
+    /// Current ledger sequence number and timestamp.
+    pub fn ledger_info(&self) -> (u32, u64) {
+        (self.env.ledger().sequence(), self.env.ledger().timestamp())
+    }
+
+    /// Move the ledger forward by `ledgers` sequence numbers and `seconds`.
+    pub fn advance_ledger(&mut self, ledgers: u32, seconds: u64) -> (u32, u64) {
+        self.env.ledger().with_mut(|li| {
+            li.sequence_number = li.sequence_number.saturating_add(ledgers);
+            li.timestamp = li.timestamp.saturating_add(seconds);
+        });
+        self.ledger_info()
+    }
+
+    /// Write the full environment state (ledger, auth, events) to a JSON file.
+    pub fn save_snapshot(&self, path: &Path) -> Result<()> {
+        self.env.to_snapshot().write_file(path).map_err(|e| {
+            DebuggerError::FileError(format!(
+                "Failed to write snapshot {}: {}",
+                path.display(),
+                e
+            ))
+            .into()
+        })
+    }
+
+    /// Replace the environment with one restored from a snapshot file
+    /// written by [`save_snapshot`](Self::save_snapshot).
+    pub fn load_snapshot(&mut self, path: &Path) -> Result<()> {
+        let snapshot = Snapshot::read_file(path).map_err(|e| {
+            DebuggerError::FileError(format!("Failed to read snapshot {}: {}", path.display(), e))
+        })?;
+        self.restore_snapshot(snapshot, None)
+    }
+
+    /// Replace the environment with one built from `snapshot`. Calls go to
+    /// `contract` from then on, or to the same address as before.
+    pub fn restore_snapshot(
+        &mut self,
+        snapshot: Snapshot,
+        contract: Option<ScAddress>,
+    ) -> Result<()> {
+        let contract = contract.unwrap_or_else(|| ScAddress::from(&self.contract_address));
+        self.replace_env(Env::from_snapshot(snapshot), contract)
+    }
+
+    /// The ledger: its info and every entry.
+    pub fn ledger_snapshot(&self) -> LedgerSnapshot {
+        self.env.to_ledger_snapshot()
+    }
+
+    /// Replace the environment with one holding `ledger`. Calls go to
+    /// `contract` from then on, or to the same address as before.
+    pub fn restore_ledger_snapshot(
+        &mut self,
+        ledger: LedgerSnapshot,
+        contract: Option<ScAddress>,
+    ) -> Result<()> {
+        let contract = contract.unwrap_or_else(|| ScAddress::from(&self.contract_address));
+        self.replace_env(Env::from_ledger_snapshot(ledger), contract)
+    }
+
+    fn replace_env(&mut self, mut env: Env, contract: ScAddress) -> Result<()> {
+        env.set_config(EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
+        env.host()
+            .set_diagnostic_level(DiagnosticLevel::Debug)
+            .expect("Failed to set diagnostic level");
+
+        self.contract_address =
+            Address::try_from_val(&env, &ScVal::Address(contract)).map_err(|e| {
+                DebuggerError::ExecutionError(format!(
+                    "Failed to restore contract address: {:?}",
+                    e
+                ))
+            })?;
+        self.env = env;
+        self.install_mock_dispatchers()
+    }
+
+    /// CPU instructions and memory bytes consumed by the last invocation.
+    pub fn budget_consumed(&self) -> (u64, u64) {
+        let budget = self.env.host().budget_cloned();
+        (
+            budget.get_cpu_insns_consumed().unwrap_or(0),
+            budget.get_mem_bytes_consumed().unwrap_or(0),
+        )
+    }
+
+    /// The full environment state, for later use with
+    /// [`measure_call`](Self::measure_call).
+    pub fn state_snapshot(&self) -> Snapshot {
+        self.env.to_snapshot()
+    }
+
+    /// CPU instructions and memory bytes used by invoking `function` on
+    /// `contract_id` with `args` in a throwaway environment built from
+    /// `state`. Authorization is mocked; returns `None` if the call fails.
+    pub fn measure_call(
+        &self,
+        state: &Snapshot,
+        contract_id: &str,
+        function: &str,
+        args: &[ScVal],
+    ) -> Option<(u64, u64)> {
+        let mut env = Env::from_snapshot(state.clone());
+        env.set_config(EnvTestConfig {
+            capture_snapshot_at_drop: false,
+        });
+        env.mock_all_auths();
+
+        let contract =
+            catch_unwind(AssertUnwindSafe(|| Address::from_str(&env, contract_id))).ok()?;
+        let mut call_args = SorobanVec::<Val>::new(&env);
+        for arg in args {
+            call_args.push_back(Val::try_from_val(&env, arg).ok()?);
+        }
+        let function = catch_unwind(AssertUnwindSafe(|| Symbol::new(&env, function))).ok()?;
+
+        // The host resets the budget when a top-level invocation starts
+        match env.try_invoke_contract::<Val, InvokeError>(&contract, &function, call_args) {
+            Ok(Ok(_)) => {
+                let budget = env.cost_estimate().budget();
+                Some((budget.cpu_instruction_cost(), budget.memory_bytes_cost()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The calls `function` makes, in call order, found by invoking it on a
+    /// fork of the current state. The first frame is
+    /// the invocation itself; the session is left untouched.
+    pub fn trace_calls(&self, function: &str, args: Option<&str>) -> Result<Vec<CallFrame>> {
+        let (env, contract) = self.fork()?;
+        env.host()
+            .set_diagnostic_level(DiagnosticLevel::Debug)
+            .expect("Failed to set diagnostic level");
+
+        let parsed_args = match args {
+            Some(args_json) => ArgumentParser::new(env.clone())
+                .parse_args_string(args_json)
+                .map_err(|e| DebuggerError::InvalidArguments(e.to_string()))?,
+            None => Vec::new(),
+        };
+        let call_args = SorobanVec::from_slice(&env, &parsed_args);
+        let function = catch_unwind(AssertUnwindSafe(|| Symbol::new(&env, function)))
+            .map_err(|_| DebuggerError::InvalidFunction(function.to_string()))?;
+        // Failing calls still leave the frames that ran
+        let _ = env.try_invoke_contract::<Val, InvokeError>(&contract, &function, call_args);
+
+        let events: Vec<_> = env
+            .host()
+            .get_diagnostic_events()
+            .map_err(|e| {
+                DebuggerError::ExecutionError(format!("Failed to get diagnostic events: {}", e))
+            })?
+            .0
+            .into_iter()
+            .map(|he| he.event)
+            .collect();
+        Ok(CallStackInspector::from_events(&events)
+            .get_stack()
+            .to_vec())
+    }
+
     /// Get the host instance.
     pub fn host(&self) -> &Host {
         self.env.host()
@@ -214,6 +613,15 @@ impl ContractExecutor {
             .collect())
     }
 
+    /// Lines the last invocation logged with `log!`.
+    pub fn logs(&self) -> Result<Vec<String>> {
+        Ok(self
+            .get_diagnostic_events()?
+            .iter()
+            .filter_map(crate::runtime::backtrace::log_line)
+            .collect())
+    }
+
     fn parse_args(&self, args_json: &str) -> Result<Vec<Val>> {
         let parser = ArgumentParser::new(self.env.clone());
         parser.parse_args_string(args_json).map_err(|e| {
@@ -222,45 +630,296 @@ impl ContractExecutor {
         })
     }
 
-    fn install_mock_dispatchers(&self) -> Result<()> {
-        let ids = match self.mock_registry.lock() {
-            Ok(registry) => registry.mocked_contract_ids(),
-            Err(_) => {
-                return Err(DebuggerError::ExecutionError(
-                    "Mock registry lock poisoned".to_string(),
-                )
-                .into())
-            }
+    /// Explain a failed invocation. The host narrows every non-contract
+    /// error to `Error(Context, InvalidAction)` at the call boundary, so the
+    /// precise error comes from the first `error` diagnostic event.
+    fn describe_error(&self, err: soroban_sdk::Error) -> String {
+        let returned = match ScError::try_from(err) {
+            Ok(error) => error,
+            Err(_) => return format!("Invocation failed: {:?}", err),
         };
+        if matches!(returned, ScError::Contract(_)) {
+            return self.error_db.describe(&returned);
+        }
 
-        for contract_id in ids {
-            let address = self.parse_contract_address(&contract_id)?;
-            let dispatcher =
-                MockContractDispatcher::new(contract_id.clone(), Arc::clone(&self.mock_registry))
-                    .boxed();
-            self.env
-                .host()
-                .register_test_contract(address.to_object(), dispatcher)
-                .map_err(|e| {
-                    DebuggerError::ExecutionError(format!(
-                        "Failed to register test contract: {}",
-                        e
-                    ))
-                })?;
+        let events = self.get_diagnostic_events().unwrap_or_default();
+        let root_cause = events.iter().find_map(|event| {
+            let ContractEventBody::V0(body) = &event.body;
+            match body.topics.as_slice() {
+                [ScVal::Symbol(s), ScVal::Error(error), ..] if s.as_slice() == b"error" => {
+                    let message = match &body.data {
+                        ScVal::String(m) => Some(m.to_utf8_string_lossy()),
+                        ScVal::Vec(Some(items)) => match items.first() {
+                            Some(ScVal::String(m)) => Some(m.to_utf8_string_lossy()),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    Some((error.clone(), message))
+                }
+                _ => None,
+            }
+        });
+
+        match root_cause {
+            Some((error, Some(message))) => format!(
+                "{}\n  Host message: {}",
+                self.error_db.describe(&error),
+                message
+            ),
+            Some((error, None)) => self.error_db.describe(&error),
+            None => self.error_db.describe(&returned),
         }
+    }
 
-        Ok(())
+    fn parse_value(&self, value: &serde_json::Value) -> Result<Val> {
+        let parser = ArgumentParser::new(self.env.clone());
+        parser
+            .parse_single_value(value)
+            .map_err(|e| DebuggerError::InvalidArguments(e.to_string()).into())
     }
-    fn parse_contract_address(&self, contract_id: &str) -> Result<Address> {
-        let parsed = catch_unwind(AssertUnwindSafe(|| {
-            Address::from_str(&self.env, contract_id)
-        }));
-        match parsed {
-            Ok(addr) => Ok(addr),
-            Err(_) => Err(DebuggerError::InvalidArguments(format!(
-                "Invalid contract id in --mock: {contract_id}"
-            ))
-            .into()),
+
+    fn to_sc_val(&self, value: Val) -> Result<ScVal> {
+        ScVal::try_from_val(&self.env, &value).map_err(|e| {
+            DebuggerError::ExecutionError(format!("Failed to convert value: {:?}", e)).into()
+        })
+    }
+
+    fn install_mock_dispatchers(&self) -> Result<()> {
+        register_mocks(&self.env, &self.mock_registry)
+    }
+}
+
+/// Register `wasm` as a new contract in `env`.
+fn register_wasm(env: &Env, wasm: &[u8]) -> Result<Address> {
+    // The SDK panics when the host rejects the module
+    catch_unwind(AssertUnwindSafe(|| env.register(wasm, ()))).map_err(|panic| {
+        let message = panic
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| panic.downcast_ref::<&str>().copied())
+            .and_then(|m| m.lines().next())
+            .unwrap_or("unknown host error");
+        DebuggerError::WasmLoadError(format!("Host rejected the contract: {}", message)).into()
+    })
+}
+
+/// Route calls to every mocked contract of `registry` through it.
+fn register_mocks(env: &Env, registry: &Arc<Mutex<MockRegistry>>) -> Result<()> {
+    let ids = match registry.lock() {
+        Ok(registry) => registry.mocked_contract_ids(),
+        Err(_) => {
+            return Err(
+                DebuggerError::ExecutionError("Mock registry lock poisoned".to_string()).into(),
+            )
         }
+    };
+
+    for contract_id in ids {
+        let address = parse_contract_address(env, &contract_id)?;
+        let dispatcher =
+            MockContractDispatcher::new(contract_id.clone(), Arc::clone(registry)).boxed();
+        env.host()
+            .register_test_contract(address.to_object(), dispatcher)
+            .map_err(|e| {
+                DebuggerError::ExecutionError(format!("Failed to register test contract: {}", e))
+            })?;
+    }
+
+    Ok(())
+}
+
+fn parse_contract_address(env: &Env, contract_id: &str) -> Result<Address> {
+    let parsed = catch_unwind(AssertUnwindSafe(|| Address::from_str(env, contract_id)));
+    match parsed {
+        Ok(addr) => Ok(addr),
+        Err(_) => Err(DebuggerError::InvalidArguments(format!(
+            "Invalid contract id in --mock: {contract_id}"
+        ))
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use serde_json::json;
+
+    fn executor() -> ContractExecutor {
+        let (module, _) = test_wasm::module();
+        ContractExecutor::new(test_wasm::finish(module)).unwrap()
+    }
+
+    #[test]
+    fn storage_set_get_and_remove() {
+        let mut executor = executor();
+        let key = json!("counter");
+        assert_eq!(
+            executor.storage_get(StorageKind::Persistent, &key).unwrap(),
+            None
+        );
+
+        executor
+            .storage_set(
+                StorageKind::Persistent,
+                &key,
+                &json!({"type": "u32", "value": 7}),
+            )
+            .unwrap();
+        assert_eq!(
+            executor.storage_get(StorageKind::Persistent, &key).unwrap(),
+            Some(ScVal::U32(7))
+        );
+        assert_eq!(
+            executor.storage_get(StorageKind::Temporary, &key).unwrap(),
+            None
+        );
+
+        executor
+            .storage_set(StorageKind::Instance, &json!("admin"), &json!(true))
+            .unwrap();
+        let mut entries = executor.storage_entries();
+        entries.sort_by_key(|(kind, _, _)| kind.name());
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    StorageKind::Instance,
+                    ScVal::Symbol("admin".try_into().unwrap()),
+                    ScVal::Bool(true)
+                ),
+                (
+                    StorageKind::Persistent,
+                    ScVal::Symbol("counter".try_into().unwrap()),
+                    ScVal::U32(7)
+                ),
+            ]
+        );
+
+        assert!(executor
+            .storage_remove(StorageKind::Persistent, &key)
+            .unwrap());
+        assert!(!executor
+            .storage_remove(StorageKind::Persistent, &key)
+            .unwrap());
+    }
+
+    #[test]
+    fn forks_call_mocks_without_logging_to_the_session() {
+        let mut executor = ContractExecutor::new(test_wasm::relay()).unwrap();
+        let callee = ScAddress::Contract(soroban_env_host::xdr::Hash([0x11; 32])).to_string();
+        executor
+            .set_mock_specs(&[format!("{}.ping=true", callee)])
+            .unwrap();
+
+        let logged = executor.get_mock_call_log().len();
+
+        let (env, contract) = executor.fork().unwrap();
+        let arg = Address::from_str(&env, &callee);
+        let result: Val = env.invoke_contract(
+            &contract,
+            &Symbol::new(&env, "relay"),
+            SorobanVec::from_array(&env, [arg.to_val()]),
+        );
+        assert_eq!(
+            ScVal::try_from_val(&env, &result).unwrap(),
+            ScVal::Bool(true)
+        );
+        assert_eq!(executor.get_mock_call_log().len(), logged);
+    }
+
+    #[test]
+    fn measure_call_replays_against_saved_state() {
+        use walrus::{FunctionBuilder, ValType};
+
+        let (mut module, _) = test_wasm::module();
+        let x = module.locals.add(ValType::I64);
+        let mut echo = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[ValType::I64]);
+        echo.func_body().local_get(x);
+        let echo = echo.finish(vec![x], &mut module.funcs);
+        module.exports.add("echo", echo);
+        let executor = ContractExecutor::new(test_wasm::finish(module)).unwrap();
+
+        let state = executor.state_snapshot();
+        let contract = ScAddress::from(&executor.contract_address).to_string();
+        let (cpu, mem) = executor
+            .measure_call(&state, &contract, "echo", &[ScVal::U32(3)])
+            .unwrap();
+        assert!(cpu > 0 && mem > 0);
+        assert!(executor
+            .measure_call(&state, &contract, "missing", &[])
+            .is_none());
+    }
+
+    #[test]
+    fn execution_errors_are_explained() {
+        use walrus::FunctionBuilder;
+
+        let (mut module, _) = test_wasm::module();
+        test_wasm::add_error_spec(
+            &mut module,
+            "TokenError",
+            &[("InsufficientBalance", 7, "The sender's balance is too low.")],
+        );
+        // Error(Contract, #7) as a raw Val: code << 32 | type << 8 | tag
+        let mut fail = FunctionBuilder::new(&mut module.types, &[], &[walrus::ValType::I64]);
+        fail.func_body().i64_const((7 << 32) | 3);
+        let fail = fail.finish(vec![], &mut module.funcs);
+        module.exports.add("fail", fail);
+        let mut trap = FunctionBuilder::new(&mut module.types, &[], &[walrus::ValType::I64]);
+        trap.func_body().unreachable();
+        let trap = trap.finish(vec![], &mut module.funcs);
+        module.exports.add("trap", trap);
+        let executor = ContractExecutor::new(test_wasm::finish(module)).unwrap();
+
+        let err = executor.execute("fail", None).unwrap_err().to_string();
+        assert!(err.contains("Error(Contract, #7): TokenError::InsufficientBalance"));
+        assert!(err.contains("The sender's balance is too low."));
+        // Returning an error is not a trap
+        assert!(!err.contains("Backtrace:"));
+
+        let err = executor.execute("trap", None).unwrap_err().to_string();
+        assert!(err.contains("Error(WasmVm, InvalidAction): WasmTrap"));
+        assert!(err.contains("Backtrace:\n    #0  func["));
+    }
+
+    #[test]
+    fn advance_ledger_moves_sequence_and_time() {
+        let mut executor = executor();
+        let (sequence, timestamp) = executor.ledger_info();
+        assert_eq!(
+            executor.advance_ledger(10, 50),
+            (sequence + 10, timestamp + 50)
+        );
+    }
+
+    #[test]
+    fn snapshot_round_trip_restores_state() {
+        let mut executor = executor();
+        let key = json!("owner");
+        executor
+            .storage_set(StorageKind::Instance, &key, &json!(true))
+            .unwrap();
+        executor.advance_ledger(3, 15);
+
+        let path = std::env::temp_dir().join(format!(
+            "soroban-debug-snapshot-{}.json",
+            std::process::id()
+        ));
+        executor.save_snapshot(&path).unwrap();
+        executor
+            .storage_remove(StorageKind::Instance, &key)
+            .unwrap();
+        let saved_ledger = executor.ledger_info();
+        executor.advance_ledger(1, 1);
+
+        executor.load_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(executor.ledger_info(), saved_ledger);
+        assert_eq!(
+            executor.storage_get(StorageKind::Instance, &key).unwrap(),
+            Some(ScVal::Bool(true))
+        );
     }
 }
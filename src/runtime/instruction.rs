@@ -223,12 +223,27 @@ impl InstructionParser {
 
         let parser = Parser::new(0);
 
+        // Function indices count imported functions first, then code entries
+        let mut imported_functions = 0u32;
+        let mut code_entry = 0u32;
+
         for payload in parser.parse_all(wasm_bytes) {
             let payload = payload.map_err(|e| format!("WASM parsing error: {}", e))?;
 
-            if let Payload::CodeSectionEntry(body) = payload {
-                let function_index = self.instructions.len() as u32; // Simplified function indexing
-                self.parse_function_body(body, function_index)?;
+            match payload {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import.map_err(|e| format!("WASM parsing error: {}", e))?;
+                        if matches!(import.ty, wasmparser::TypeRef::Func(_)) {
+                            imported_functions += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    self.parse_function_body(body, imported_functions + code_entry)?;
+                    code_entry += 1;
+                }
+                _ => {}
             }
         }
 
@@ -0,0 +1,409 @@
+//! Capture of contract linear memory.
+//!
+//! The host does not expose guest memory, so the call is re-run in a fork
+//! of the session, with the contract's code swapped for an instrumented
+//! copy. The instrumentation copies the whole of linear memory into a
+//! `Bytes` object and emits it as a diagnostic `log` event when the invoked
+//! export returns and just before any `unreachable` trap (which is how Rust
+//! panics surface).
+
+use crate::runtime::executor::ContractExecutor;
+use crate::utils::ArgumentParser;
+use crate::{DebuggerError, Result};
+use soroban_env_host::xdr::{ContractEventBody, ScVal};
+use soroban_env_host::{DiagnosticLevel, SymbolSmall, U32Val};
+use soroban_ledger_snapshot::LedgerSnapshot;
+use soroban_sdk::{Bytes, InvokeError, Symbol, Val, Vec as SorobanVec};
+use walrus::ir::{BinaryOp, Instr, InstrLocId, InstrSeq, LoadKind, MemArg, StoreKind, UnaryOp};
+use walrus::{FunctionBuilder, FunctionId, InitExpr, LocalFunction, Module, ValType};
+
+/// Marker symbol identifying snapshot log events.
+const SNAPSHOT_MARKER: &str = "sdbg_mem";
+
+/// Why a memory snapshot was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapturePoint {
+    /// The invoked function returned normally
+    Return,
+    /// The contract was about to execute `unreachable`
+    Trap,
+}
+
+/// Linear memory captured at the end of an invocation.
+#[derive(Debug, Clone)]
+pub struct MemoryCapture {
+    pub bytes: Vec<u8>,
+    pub pages_at_entry: u32,
+    pub pages_at_exit: u32,
+    pub point: CapturePoint,
+}
+
+/// Re-run `function` with instrumentation and return its final linear
+/// memory. The call runs against a fork of the executor's state, or of
+/// `ledger` if given, with the session's mocks.
+pub fn capture_linear_memory(
+    executor: &ContractExecutor,
+    ledger: Option<LedgerSnapshot>,
+    function: &str,
+    args: Option<&str>,
+) -> Result<MemoryCapture> {
+    let instrumented = instrument(executor.wasm_bytes(), function)?;
+    let (env, contract) = match ledger {
+        Some(ledger) => executor.fork_from(ledger)?,
+        None => executor.fork()?,
+    };
+
+    let host = env.host();
+    host.set_diagnostic_level(DiagnosticLevel::Debug)
+        .map_err(|e| DebuggerError::ExecutionError(format!("{:?}", e)))?;
+    // Diagnostics run under the shadow budget; a full memory copy needs room
+    host.set_shadow_budget_limits(u64::MAX, u64::MAX)
+        .map_err(|e| DebuggerError::ExecutionError(format!("{:?}", e)))?;
+
+    // Same address and storage, instrumented code
+    let hash = env
+        .deployer()
+        .upload_contract_wasm(Bytes::from_slice(&env, &instrumented));
+    env.as_contract(&contract, || {
+        env.deployer().update_current_contract_wasm(hash)
+    });
+
+    let parsed_args = match args {
+        Some(json) => ArgumentParser::new(env.clone())
+            .parse_args_string(json)
+            .map_err(|e| DebuggerError::InvalidArguments(e.to_string()))?,
+        None => Vec::new(),
+    };
+    let args_vec = SorobanVec::<Val>::from_slice(&env, &parsed_args);
+
+    // Traps are expected when debugging panics; the snapshot is taken first
+    let _ = env.try_invoke_contract::<Val, InvokeError>(
+        &contract,
+        &Symbol::new(&env, function),
+        args_vec,
+    );
+
+    let events = host
+        .get_diagnostic_events()
+        .map_err(|e| DebuggerError::ExecutionError(format!("{:?}", e)))?;
+
+    events
+        .0
+        .iter()
+        .rev()
+        .find_map(|e| parse_snapshot_event(&e.event.body))
+        .ok_or_else(|| {
+            DebuggerError::ExecutionError(format!(
+                "No memory snapshot was recorded for '{}' (the host aborted before the contract returned or trapped)",
+                function
+            ))
+            .into()
+        })
+}
+
+fn parse_snapshot_event(body: &ContractEventBody) -> Option<MemoryCapture> {
+    let ContractEventBody::V0(body) = body;
+    let ScVal::Vec(Some(items)) = &body.data else {
+        return None;
+    };
+    match items.as_slice() {
+        [ScVal::String(_), ScVal::Symbol(marker), ScVal::Bytes(bytes), ScVal::U32(entry), ScVal::U32(point)]
+            if marker.to_utf8_string_lossy() == SNAPSHOT_MARKER =>
+        {
+            let bytes = bytes.to_vec();
+            Some(MemoryCapture {
+                pages_at_exit: (bytes.len() / crate::inspector::memory::WASM_PAGE_SIZE) as u32,
+                bytes,
+                pages_at_entry: *entry,
+                point: if *point == 0 {
+                    CapturePoint::Return
+                } else {
+                    CapturePoint::Trap
+                },
+            })
+        }
+        _ => None,
+    }
+}
+
+pub(super) fn u32_val(v: u32) -> i64 {
+    U32Val::from(v).to_val().get_payload() as i64
+}
+
+/// Find an existing host function import or add one.
+pub(super) fn host_import(
+    module: &mut Module,
+    import_module: &str,
+    name: &str,
+    arity: usize,
+) -> FunctionId {
+    let existing = module.imports.iter().find_map(|i| match i.kind {
+        walrus::ImportKind::Function(f) if i.module == import_module && i.name == name => Some(f),
+        _ => None,
+    });
+    existing.unwrap_or_else(|| {
+        let params = vec![ValType::I64; arity];
+        let ty = module.types.add(&params, &[ValType::I64]);
+        module.add_import_func(import_module, name, ty).0
+    })
+}
+
+/// Instrument `function` so that linear memory is logged on return and trap.
+fn instrument(wasm_bytes: &[u8], function: &str) -> Result<Vec<u8>> {
+    let mut module = Module::from_buffer(wasm_bytes)
+        .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to parse WASM: {}", e)))?;
+
+    let memory = module
+        .memories
+        .iter()
+        .next()
+        .map(|m| m.id())
+        .ok_or_else(|| DebuggerError::WasmLoadError("Contract has no linear memory".into()))?;
+
+    let (export_id, target) = module
+        .exports
+        .iter()
+        .find_map(|e| match e.item {
+            walrus::ExportItem::Function(f) if e.name == function => Some((e.id(), f)),
+            _ => None,
+        })
+        .ok_or_else(|| DebuggerError::InvalidFunction(function.to_string()))?;
+
+    // b.3 = bytes_new_from_linear_memory, x._ = log_from_linear_memory
+    let bytes_new = host_import(&mut module, "b", "3", 2);
+    let log = host_import(&mut module, "x", "_", 4);
+
+    let entry_pages = module.globals.add_local(
+        ValType::I32,
+        true,
+        InitExpr::Value(walrus::ir::Value::I32(0)),
+    );
+
+    let marker = SymbolSmall::try_from_str(SNAPSHOT_MARKER)
+        .map_err(|e| DebuggerError::ExecutionError(format!("{:?}", e)))?
+        .to_val()
+        .get_payload() as i64;
+    let u32_tag = u32_val(0);
+    let arg = |offset| MemArg { align: 8, offset };
+    let i64_load = LoadKind::I64 { atomic: false };
+    let i64_store = StoreKind::I64 { atomic: false };
+
+    // snapshot(point: i64): the vals array for the log call is written to
+    // address 0 and restored afterwards, once memory has been copied.
+    let point = module.locals.add(ValType::I64);
+    let bytes = module.locals.add(ValType::I64);
+    let saved: Vec<_> = (0..4).map(|_| module.locals.add(ValType::I64)).collect();
+    let mut snapshot = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[]);
+    {
+        let mut body = snapshot.func_body();
+        for (i, local) in saved.iter().enumerate() {
+            body.i32_const(0)
+                .load(memory, i64_load, arg(i as u32 * 8))
+                .local_set(*local);
+        }
+        body.i64_const(u32_tag)
+            .memory_size(memory)
+            .unop(UnaryOp::I64ExtendUI32)
+            .i64_const(48)
+            .binop(BinaryOp::I64Shl)
+            .i64_const(u32_tag)
+            .binop(BinaryOp::I64Or)
+            .call(bytes_new)
+            .local_set(bytes);
+        body.i32_const(0)
+            .i64_const(marker)
+            .store(memory, i64_store, arg(0));
+        body.i32_const(0)
+            .local_get(bytes)
+            .store(memory, i64_store, arg(8));
+        body.i32_const(0)
+            .global_get(entry_pages)
+            .unop(UnaryOp::I64ExtendUI32)
+            .i64_const(32)
+            .binop(BinaryOp::I64Shl)
+            .i64_const(u32_tag)
+            .binop(BinaryOp::I64Or)
+            .store(memory, i64_store, arg(16));
+        body.i32_const(0)
+            .local_get(point)
+            .store(memory, i64_store, arg(24));
+        body.i64_const(u32_val(0))
+            .i64_const(u32_val(0))
+            .i64_const(u32_val(0))
+            .i64_const(u32_val(4))
+            .call(log)
+            .drop();
+        for (i, local) in saved.iter().enumerate() {
+            body.i32_const(0)
+                .local_get(*local)
+                .store(memory, i64_store, arg(i as u32 * 8));
+        }
+    }
+    let snapshot = snapshot.finish(vec![point], &mut module.funcs);
+
+    // Record memory size before any trap in the existing code
+    for (id, func) in module.funcs.iter_local_mut() {
+        if id != snapshot {
+            insert_before_unreachable(func, snapshot, u32_val(1));
+        }
+    }
+
+    // Wrap the export: record entry pages, call through, snapshot on return
+    let ty = module.funcs.get(target).ty();
+    let (params, results) = {
+        let ty = module.types.get(ty);
+        (ty.params().to_vec(), ty.results().to_vec())
+    };
+    let args: Vec<_> = params.iter().map(|t| module.locals.add(*t)).collect();
+    let outs: Vec<_> = results.iter().map(|t| module.locals.add(*t)).collect();
+    let mut wrapper = FunctionBuilder::new(&mut module.types, &params, &results);
+    {
+        let mut body = wrapper.func_body();
+        body.memory_size(memory).global_set(entry_pages);
+        for a in &args {
+            body.local_get(*a);
+        }
+        body.call(target);
+        for out in outs.iter().rev() {
+            body.local_set(*out);
+        }
+        body.i64_const(u32_val(0)).call(snapshot);
+        for out in &outs {
+            body.local_get(*out);
+        }
+    }
+    let wrapper = wrapper.finish(args, &mut module.funcs);
+    module.exports.get_mut(export_id).item = walrus::ExportItem::Function(wrapper);
+
+    Ok(module.emit_wasm())
+}
+
+fn insert_before_unreachable(func: &mut LocalFunction, snapshot: FunctionId, point: i64) {
+    struct Rewriter {
+        snapshot: FunctionId,
+        point: i64,
+    }
+
+    impl walrus::ir::VisitorMut for Rewriter {
+        fn start_instr_seq_mut(&mut self, seq: &mut InstrSeq) {
+            if !seq
+                .instrs
+                .iter()
+                .any(|(i, _)| matches!(i, Instr::Unreachable(_)))
+            {
+                return;
+            }
+            let instrs = std::mem::take(&mut seq.instrs);
+            for (instr, loc) in instrs {
+                if matches!(instr, Instr::Unreachable(_)) {
+                    seq.instrs.push((
+                        walrus::ir::Const {
+                            value: walrus::ir::Value::I64(self.point),
+                        }
+                        .into(),
+                        InstrLocId::default(),
+                    ));
+                    seq.instrs.push((
+                        walrus::ir::Call {
+                            func: self.snapshot,
+                        }
+                        .into(),
+                        InstrLocId::default(),
+                    ));
+                }
+                seq.instrs.push((instr, loc));
+            }
+        }
+    }
+
+    let entry = func.entry_block();
+    walrus::ir::dfs_pre_order_mut(&mut Rewriter { snapshot, point }, func, entry);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use walrus::{ActiveData, ActiveDataLocation, DataKind};
+
+    /// A minimal contract with a data segment, a function that writes to
+    /// memory and returns, and one that writes and then traps.
+    fn contract() -> Vec<u8> {
+        let (mut module, memory) = test_wasm::module();
+        module.data.add(
+            DataKind::Active(ActiveData {
+                memory,
+                location: ActiveDataLocation::Absolute(1024),
+            }),
+            b"soroban!".to_vec(),
+        );
+
+        for (name, trap) in [("run", false), ("boom", true)] {
+            let mut func = FunctionBuilder::new(&mut module.types, &[], &[ValType::I64]);
+            let mut body = func.func_body();
+            body.i32_const(2048).i32_const(0x11223344).store(
+                memory,
+                StoreKind::I32 { atomic: false },
+                MemArg {
+                    align: 4,
+                    offset: 0,
+                },
+            );
+            if trap {
+                body.unreachable();
+            } else {
+                body.i64_const(test_wasm::VOID);
+            }
+            let id = func.finish(vec![], &mut module.funcs);
+            module.exports.add(name, id);
+        }
+
+        test_wasm::finish(module)
+    }
+
+    fn capture(wasm: &[u8], function: &str, args: Option<&str>) -> Result<MemoryCapture> {
+        let executor = ContractExecutor::new(wasm.to_vec())?;
+        capture_linear_memory(&executor, None, function, args)
+    }
+
+    #[test]
+    fn captures_memory_on_return() {
+        let capture = capture(&contract(), "run", None).unwrap();
+        assert_eq!(capture.point, CapturePoint::Return);
+        assert_eq!(capture.pages_at_entry, 1);
+        assert_eq!(capture.pages_at_exit, 1);
+        assert_eq!(&capture.bytes[1024..1032], b"soroban!");
+        assert_eq!(&capture.bytes[2048..2052], &0x11223344u32.to_le_bytes());
+        // The scratch area used for the log call is restored
+        assert_eq!(&capture.bytes[..32], &[0u8; 32]);
+    }
+
+    #[test]
+    fn captures_memory_before_trap() {
+        let capture = capture(&contract(), "boom", None).unwrap();
+        assert_eq!(capture.point, CapturePoint::Trap);
+        assert_eq!(&capture.bytes[2048..2052], &0x11223344u32.to_le_bytes());
+    }
+
+    #[test]
+    fn unknown_function_is_rejected() {
+        assert!(capture(&contract(), "missing", None).is_err());
+    }
+
+    #[test]
+    fn captures_against_the_session_state_and_mocks() {
+        let mut executor = ContractExecutor::new(test_wasm::relay()).unwrap();
+        let callee =
+            soroban_env_host::xdr::ScAddress::Contract(soroban_env_host::xdr::Hash([0x11; 32]))
+                .to_string();
+        executor
+            .set_mock_specs(&[format!("{}.ping=true", callee)])
+            .unwrap();
+
+        // Without the mock the call fails before the contract returns
+        let args = format!("[\"{}\"]", callee);
+        let capture = capture_linear_memory(&executor, None, "relay", Some(args.as_str())).unwrap();
+        assert_eq!(capture.point, CapturePoint::Return);
+    }
+}
@@ -1,10 +1,16 @@
+pub mod backtrace;
 pub mod env;
 pub mod executor;
 pub mod instruction;
 pub mod instrumentation;
+pub mod memory_capture;
 pub mod mocking;
+#[cfg(test)]
+pub(crate) mod test_wasm;
 
+pub use backtrace::{capture_backtrace, Backtrace, BacktraceFrame};
 pub use env::DebugEnv;
-pub use executor::ContractExecutor;
+pub use executor::{ContractExecutor, StorageKind};
 pub use instruction::{Instruction, InstructionParser};
 pub use instrumentation::{InstructionHook, Instrumenter};
+pub use memory_capture::{capture_linear_memory, CapturePoint, MemoryCapture};
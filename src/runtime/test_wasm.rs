@@ -0,0 +1,140 @@
+//! Hand-built Soroban contracts for unit tests.
+
+use soroban_env_host::xdr::{
+    Limits, ScEnvMetaEntry, ScEnvMetaEntryInterfaceVersion, ScSpecEntry, ScSpecUdtErrorEnumCaseV0,
+    ScSpecUdtErrorEnumV0, WriteXdr,
+};
+use soroban_env_host::SymbolSmall;
+use walrus::{FunctionBuilder, MemoryId, Module, ModuleConfig, ValType};
+
+/// Payload of the `Void` value.
+pub(crate) const VOID: i64 = 2;
+
+/// An empty module with one page of exported linear memory.
+pub(crate) fn module() -> (Module, MemoryId) {
+    let mut module = Module::with_config(ModuleConfig::new());
+    let memory = module.memories.add_local(false, 1, None);
+    module.exports.add("memory", memory);
+    (module, memory)
+}
+
+/// Add the environment metadata the host requires and emit the module.
+pub(crate) fn finish(mut module: Module) -> Vec<u8> {
+    let meta = ScEnvMetaEntry::ScEnvMetaKindInterfaceVersion(ScEnvMetaEntryInterfaceVersion {
+        protocol: 22,
+        pre_release: 0,
+    })
+    .to_xdr(Limits::none())
+    .expect("env meta encodes");
+    module.customs.add(walrus::RawCustomSection {
+        name: "contractenvmetav0".to_string(),
+        data: meta,
+    });
+    module.emit_wasm()
+}
+
+/// Payload of a small symbol value.
+pub(crate) fn symbol(name: &str) -> i64 {
+    SymbolSmall::try_from_str(name)
+        .expect("symbol fits in a small symbol")
+        .to_val()
+        .get_payload() as i64
+}
+
+/// Declare a `#[contracterror]` enum with `(name, value, doc)` cases in the
+/// module's `contractspecv0` section.
+pub(crate) fn add_error_spec(module: &mut Module, name: &str, cases: &[(&str, u32, &str)]) {
+    let spec = ScSpecEntry::UdtErrorEnumV0(ScSpecUdtErrorEnumV0 {
+        doc: "".try_into().unwrap(),
+        lib: "".try_into().unwrap(),
+        name: name.try_into().unwrap(),
+        cases: cases
+            .iter()
+            .map(|(name, value, doc)| ScSpecUdtErrorEnumCaseV0 {
+                doc: (*doc).try_into().unwrap(),
+                name: (*name).try_into().unwrap(),
+                value: *value,
+            })
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap(),
+    });
+    // Every entry goes in the one section, as the SDK writes them
+    let mut data = module
+        .customs
+        .remove_raw("contractspecv0")
+        .map(|section| section.data)
+        .unwrap_or_default();
+    data.extend(spec.to_xdr(Limits::none()).expect("spec encodes"));
+    module.customs.add(walrus::RawCustomSection {
+        name: "contractspecv0".to_string(),
+        data,
+    });
+}
+
+/// A contract with `relay(addr)`, which returns `addr.ping()`.
+pub(crate) fn relay() -> Vec<u8> {
+    let (mut module, _) = module();
+    let call_ty = module
+        .types
+        .add(&[ValType::I64, ValType::I64, ValType::I64], &[ValType::I64]);
+    let (call, _) = module.add_import_func("d", "_", call_ty);
+    let vec_new_ty = module.types.add(&[], &[ValType::I64]);
+    let (vec_new, _) = module.add_import_func("v", "_", vec_new_ty);
+
+    let addr = module.locals.add(ValType::I64);
+    let mut relay = FunctionBuilder::new(&mut module.types, &[ValType::I64], &[ValType::I64]);
+    relay
+        .func_body()
+        .local_get(addr)
+        .i64_const(symbol("ping"))
+        .call(vec_new)
+        .call(call);
+    let relay = relay.finish(vec![addr], &mut module.funcs);
+    module.exports.add("relay", relay);
+    finish(module)
+}
+
+/// A contract with `relay_both(a, b)`, which calls `a.ping()` and then
+/// returns `b.ping()`.
+pub(crate) fn relay_both() -> Vec<u8> {
+    let (mut module, _) = module();
+    let call_ty = module
+        .types
+        .add(&[ValType::I64, ValType::I64, ValType::I64], &[ValType::I64]);
+    let (call, _) = module.add_import_func("d", "_", call_ty);
+    let vec_new_ty = module.types.add(&[], &[ValType::I64]);
+    let (vec_new, _) = module.add_import_func("v", "_", vec_new_ty);
+
+    let a = module.locals.add(ValType::I64);
+    let b = module.locals.add(ValType::I64);
+    let mut relay = FunctionBuilder::new(
+        &mut module.types,
+        &[ValType::I64, ValType::I64],
+        &[ValType::I64],
+    );
+    relay
+        .func_body()
+        .local_get(a)
+        .i64_const(symbol("ping"))
+        .call(vec_new)
+        .call(call)
+        .drop()
+        .local_get(b)
+        .i64_const(symbol("ping"))
+        .call(vec_new)
+        .call(call);
+    let relay = relay.finish(vec![a, b], &mut module.funcs);
+    module.exports.add("relay_both", relay);
+    finish(module)
+}
+
+/// A contract whose `ping()` returns `()`.
+pub(crate) fn ping() -> Vec<u8> {
+    let (mut module, _) = module();
+    let mut ping = FunctionBuilder::new(&mut module.types, &[], &[ValType::I64]);
+    ping.func_body().i64_const(VOID);
+    let ping = ping.finish(vec![], &mut module.funcs);
+    module.exports.add("ping", ping);
+    finish(module)
+}
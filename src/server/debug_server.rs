@@ -1,29 +1,250 @@
 use crate::debugger::engine::DebuggerEngine;
+use crate::debugger::instruction_pointer::StepMode;
+use crate::inspector::locals::WasmValue;
+use crate::inspector::memory::{parse_pattern, IntKind, MemoryInspector};
 use crate::runtime::executor::ContractExecutor;
-use crate::server::protocol::{DebugMessage, DebugRequest, DebugResponse};
+use crate::runtime::executor::StorageKind;
+use crate::server::framing::{self, Frame, FrameReader, Framing};
+use crate::server::protocol::{
+    Capabilities, ContractEventInfo, DebugEvent, DebugMessage, DebugRequest, DebugResponse,
+    InstructionLine, InstructionPosition, Scope, StepKind, StorageChange, ValueInfo,
+    MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+use crate::server::session::{Session, SessionManager};
+use crate::server::tls;
+use crate::server::transcript::{TranscriptEntry, TranscriptWriter};
+use crate::server::websocket;
 use crate::simulator::SnapshotLoader;
+use crate::ui::formatter::Formatter;
+use crate::utils::wasm::{compute_wasm_sha256, verify_wasm_hash};
 use crate::{DebuggerError, Result};
-use std::io::{BufRead, BufReader, Write};
-use std::net::{TcpListener, TcpStream};
+use base64::Engine as _;
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use soroban_env_host::xdr::ScVal;
+use std::collections::{HashMap, VecDeque};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tracing::{error, info};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Maximum addresses returned for a memory search
+const MAX_MEMORY_SEARCH_RESULTS: usize = 256;
+
+/// Default time after which an unused, detached session is destroyed
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// How often idle sessions are looked for
+const IDLE_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a connection with requests in progress checks for new ones,
+/// such as a `Cancel`
+const REQUEST_POLL_INTERVAL: Duration = Duration::from_millis(20);
 
 /// Debug server that handles remote debugging connections
 pub struct DebugServer {
     port: u16,
     token: Option<String>,
+    observer_tokens: Vec<String>,
     tls_cert: Option<PathBuf>,
     tls_key: Option<PathBuf>,
+    tls_client_ca: Option<PathBuf>,
+    idle_timeout: Option<Duration>,
+    max_message_size: usize,
+    websocket_port: Option<u16>,
+    transcript: Option<PathBuf>,
 }
 
-/// Session state for a connected client
-struct Session {
-    #[allow(clippy::arc_with_non_send_sync)]
-    engine: Option<Arc<Mutex<DebuggerEngine>>>,
-    authenticated: bool,
-    #[allow(dead_code)]
-    message_id: u64,
+/// A client connection, plain or TLS
+pub(crate) trait Peer: Read + Write {
+    fn socket(&self) -> &TcpStream;
+
+    fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket().peer_addr()
+    }
+}
+
+impl Peer for TcpStream {
+    fn socket(&self) -> &TcpStream {
+        self
+    }
+}
+
+impl Peer for StreamOwned<ServerConnection, TcpStream> {
+    fn socket(&self) -> &TcpStream {
+        &self.sock
+    }
+}
+
+/// Work for the thread that keeps track of sessions and connections
+enum Command {
+    Request {
+        connection: u64,
+        message: Box<DebugMessage>,
+        progress: Arc<Progress>,
+        reply: mpsc::Sender<Outgoing>,
+    },
+    /// A session's thread answered a connection's request
+    Finished {
+        connection: u64,
+        id: u64,
+        response: DebugResponse,
+        events: Vec<DebugEvent>,
+    },
+    Disconnected {
+        connection: u64,
+    },
+}
+
+/// Messages from the session thread to a connection
+enum Outgoing {
+    /// The response to the oldest pending request, then its events
+    Reply(Vec<DebugMessage>),
+    /// Events from a session the connection observes
+    Events(Vec<DebugMessage>),
+}
+
+/// A request sent to the server and not yet answered
+struct Pending<T> {
+    id: u64,
+    tag: T,
+    progress: Arc<Progress>,
+}
+
+/// Whether a request has started, shared by its connection, which may
+/// cancel it, and the thread that runs it
+#[derive(Default)]
+struct Progress(AtomicU8);
+
+impl Progress {
+    const QUEUED: u8 = 0;
+    const STARTED: u8 = 1;
+    const CANCELLED: u8 = 2;
+
+    /// Mark the request started, unless it was cancelled first
+    fn start(&self) -> bool {
+        self.0
+            .compare_exchange(
+                Self::QUEUED,
+                Self::STARTED,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst) == Self::CANCELLED
+    }
+
+    /// Cancel the request, unless it already started
+    fn cancel(&self) -> bool {
+        self.0
+            .compare_exchange(
+                Self::QUEUED,
+                Self::CANCELLED,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_ok()
+    }
+}
+
+/// What a client speaks
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    /// [`DebugMessage`]s over TCP, see [`framing`]
+    Tcp,
+    /// JSON-RPC 2.0 over WebSocket, see [`websocket`]
+    WebSocket,
+}
+
+/// Everything a listener needs to hand connections to the session thread
+#[derive(Clone)]
+struct Listeners {
+    commands: mpsc::Sender<Command>,
+    tls: Option<Arc<ServerConfig>>,
+    next_connection: Arc<AtomicU64>,
+    max_message_size: usize,
+}
+
+/// Reads requests from one client and writes the replies
+pub(crate) trait Codec {
+    /// What a reply needs to know about its request, like its framing
+    type Tag;
+
+    fn socket(&self) -> &TcpStream;
+
+    /// The next valid request. Invalid ones are answered here.
+    fn read(&mut self) -> Result<Incoming<Self::Tag>>;
+
+    /// Write a response and the events that follow it
+    fn write(&mut self, tag: &Self::Tag, messages: &[DebugMessage]) -> Result<()>;
+
+    /// Write events that don't follow a response of this connection's
+    fn push(&mut self, events: &[DebugMessage]) -> Result<()>;
+}
+
+pub(crate) enum Incoming<T> {
+    Request(Box<DebugMessage>, T),
+    /// The read timed out; try again
+    Timeout,
+    /// The client hung up
+    Closed,
+}
+
+/// [`DebugMessage`]s in either [`Framing`]
+struct FramedCodec<S> {
+    stream: S,
+    reader: FrameReader,
+    max_message_size: usize,
+    /// Framing of the last request, used for pushed events
+    framing: Framing,
+}
+
+/// A request waiting for the ones before it from the same connection
+struct Queued {
+    message: Box<DebugMessage>,
+    progress: Arc<Progress>,
+    reply: mpsc::Sender<Outgoing>,
+}
+
+/// A request running on its session's thread
+struct InProgress {
+    /// The request, if a transcript is kept
+    request: Option<DebugMessage>,
+    reply: mpsc::Sender<Outgoing>,
+    /// The connection's later requests, answered in order once it finishes
+    queued: VecDeque<Queued>,
+}
+
+/// State of one client connection
+struct Connection {
+    /// What the connection may do; `None` until it authenticates
+    scope: Option<Scope>,
+    /// Agreed in the handshake; none until then
+    capabilities: Capabilities,
+    /// Session the connection is attached to
+    session: Option<String>,
+}
+
+/// Sessions and the connections using them. Requests for a session run on
+/// that session's thread; only bookkeeping and routing happen here.
+struct ServerState {
+    token: Option<String>,
+    observer_tokens: Vec<String>,
+    sessions: SessionManager,
+    connections: HashMap<u64, Connection>,
+    /// Where to push events for each connection
+    outboxes: HashMap<u64, mpsc::Sender<Outgoing>>,
+    /// Connections with a request on a session's thread
+    in_progress: HashMap<u64, InProgress>,
+    transcript: Option<TranscriptWriter>,
+    /// Handed to session threads to report back
+    commands: mpsc::Sender<Command>,
+    receiver: mpsc::Receiver<Command>,
 }
 
 impl DebugServer {
@@ -32,11 +253,47 @@ impl DebugServer {
         Self {
             port,
             token,
+            observer_tokens: Vec::new(),
             tls_cert: None,
             tls_key: None,
+            tls_client_ca: None,
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+            max_message_size: framing::DEFAULT_MAX_MESSAGE_SIZE,
+            websocket_port: None,
+            transcript: None,
         }
     }
 
+    /// Reject messages larger than `bytes`
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Destroy detached sessions unused for `timeout`; `None` keeps them forever
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Let clients authenticating with `token` observe sessions read-only
+    pub fn with_observer_token(mut self, token: String) -> Self {
+        self.observer_tokens.push(token);
+        self
+    }
+
+    /// Also accept JSON-RPC over WebSocket on `port`
+    pub fn with_websocket(mut self, port: u16) -> Self {
+        self.websocket_port = Some(port);
+        self
+    }
+
+    /// Append every request and its answer to the transcript at `path`
+    pub fn with_transcript(mut self, path: PathBuf) -> Self {
+        self.transcript = Some(path);
+        self
+    }
+
     /// Set TLS certificate and key paths
     pub fn with_tls(mut self, cert: PathBuf, key: PathBuf) -> Self {
         self.tls_cert = Some(cert);
@@ -44,149 +301,117 @@ impl DebugServer {
         self
     }
 
+    /// Require TLS clients to present a certificate signed by this CA
+    pub fn with_client_ca(mut self, ca: PathBuf) -> Self {
+        self.tls_client_ca = Some(ca);
+        self
+    }
+
     /// Start the debug server and listen for connections
     pub fn start(&self) -> Result<()> {
-        let addr = format!("0.0.0.0:{}", self.port);
-        let listener = TcpListener::bind(&addr)
-            .map_err(|e| DebuggerError::FileError(format!("Failed to bind to {}: {}", addr, e)))?;
+        let bind = |port: u16| {
+            let addr = format!("0.0.0.0:{}", port);
+            TcpListener::bind(&addr)
+                .map_err(|e| DebuggerError::FileError(format!("Failed to bind to {}: {}", addr, e)))
+        };
+        let listener = bind(self.port)?;
+        info!("Debug server listening on 0.0.0.0:{}", self.port);
+        let websocket = self.websocket_port.map(bind).transpose()?;
+        if let Some(port) = self.websocket_port {
+            info!("WebSocket (JSON-RPC) listening on 0.0.0.0:{}", port);
+        }
+        self.serve_with_websocket(listener, websocket)
+    }
+
+    /// Accept connections on `listener` until it fails
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        self.serve_with_websocket(listener, None)
+    }
 
-        info!("Debug server listening on {}", addr);
+    /// Accept TCP connections on `listener`, and JSON-RPC over WebSocket
+    /// connections on `websocket`. Both share the same sessions.
+    pub fn serve_with_websocket(
+        &self,
+        listener: TcpListener,
+        websocket: Option<TcpListener>,
+    ) -> Result<()> {
+        let tls = match (&self.tls_cert, &self.tls_key) {
+            (Some(cert), Some(key)) => Some(tls::server_config(
+                cert,
+                key,
+                self.tls_client_ca.as_deref(),
+            )?),
+            _ => None,
+        };
         if self.token.is_some() {
             info!("Token authentication enabled");
+            if tls.is_none() {
+                warn!("TLS is not enabled; authentication tokens are sent in plain text");
+            }
         }
-        if self.tls_cert.is_some() {
+        if tls.is_some() {
             info!("TLS enabled");
-        }
-
-        for stream in listener.incoming() {
-            match stream {
-                Ok(stream) => {
-                    let token = self.token.clone();
-                    let tls_cert = self.tls_cert.clone();
-                    let tls_key = self.tls_key.clone();
-
-                    // Handle each connection in a separate thread
-                    std::thread::spawn(move || {
-                        if let Err(e) = Self::handle_client(stream, token, tls_cert, tls_key) {
-                            error!("Error handling client: {}", e);
-                        }
-                    });
-                }
-                Err(e) => {
-                    error!("Failed to accept connection: {}", e);
-                }
+            if self.tls_client_ca.is_some() {
+                info!("Client certificates required");
             }
         }
 
-        Ok(())
-    }
-
-    fn handle_client(
-        stream: TcpStream,
-        token: Option<String>,
-        _tls_cert: Option<PathBuf>,
-        _tls_key: Option<PathBuf>,
-    ) -> Result<()> {
-        let peer_addr = stream
-            .peer_addr()
-            .map_err(|e| DebuggerError::FileError(format!("Failed to get peer address: {}", e)))?;
-        info!("New client connected from {}", peer_addr);
-
-        let mut session = Session {
-            engine: None,
-            authenticated: token.is_none(), // Auto-authenticate if no token required
-            message_id: 0,
-        };
-
-        let reader = BufReader::new(
-            stream
-                .try_clone()
-                .map_err(|e| DebuggerError::FileError(format!("Failed to clone stream: {}", e)))?,
-        );
-        let mut writer = stream;
-
-        for line in reader.lines() {
-            let line =
-                line.map_err(|e| DebuggerError::FileError(format!("Failed to read line: {}", e)))?;
-            if line.is_empty() {
-                continue;
+        if !self.observer_tokens.is_empty() {
+            info!("Observer tokens enabled");
+        }
+        let transcript = match &self.transcript {
+            Some(path) => {
+                info!("Recording a transcript to {:?}", path);
+                Some(TranscriptWriter::create(path)?)
             }
+            None => None,
+        };
+        let mut state = ServerState::new(self.token.clone(), self.idle_timeout);
+        state.observer_tokens = self.observer_tokens.clone();
+        state.transcript = transcript;
+        let commands = state.commands.clone();
+        std::thread::spawn(move || Self::run_sessions(state));
 
-            let message: DebugMessage = serde_json::from_str(&line).map_err(|e| {
-                DebuggerError::FileError(format!("Failed to parse message: {}: {}", line, e))
-            })?;
-
-            let response = Self::handle_request(&mut session, message, &token)?;
-
-            let response_json = serde_json::to_string(&response).map_err(|e| {
-                DebuggerError::FileError(format!("Failed to serialize response: {}", e))
-            })?;
-            writeln!(writer, "{}", response_json).map_err(|e| {
-                DebuggerError::FileError(format!("Failed to write response: {}", e))
-            })?;
-            writer
-                .flush()
-                .map_err(|e| DebuggerError::FileError(format!("Failed to flush stream: {}", e)))?;
+        let listeners = Listeners {
+            commands,
+            tls,
+            next_connection: Arc::new(AtomicU64::new(1)),
+            max_message_size: self.max_message_size,
+        };
+        if let Some(websocket) = websocket {
+            let listeners = listeners.clone();
+            std::thread::spawn(move || listeners.accept(websocket, Transport::WebSocket));
         }
+        listeners.accept(listener, Transport::Tcp);
 
-        info!("Client {} disconnected", peer_addr);
         Ok(())
     }
 
-    #[allow(clippy::arc_with_non_send_sync)]
-    fn handle_request(
-        session: &mut Session,
-        message: DebugMessage,
-        expected_token: &Option<String>,
-    ) -> Result<DebugMessage> {
-        let request = message
-            .request
-            .ok_or_else(|| DebuggerError::ExecutionError("Message has no request".to_string()))?;
-
-        // Check authentication for all requests except Authenticate and Ping
-        match &request {
-            DebugRequest::Authenticate { .. } | DebugRequest::Ping => {}
-            _ => {
-                if !session.authenticated {
-                    return Ok(DebugMessage::response(
-                        message.id,
-                        DebugResponse::Error {
-                            message: "Not authenticated. Send Authenticate request first."
-                                .to_string(),
-                        },
-                    ));
-                }
+    /// Route requests to their sessions and answers back to the clients
+    fn run_sessions(mut state: ServerState) {
+        loop {
+            match state.receiver.recv_timeout(IDLE_SWEEP_INTERVAL) {
+                Ok(command) => state.dispatch(command),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            for id in state.sessions.expire_idle() {
+                info!("Session {} expired after being idle", id);
             }
         }
+    }
 
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn handle_request(session: &mut Session, request: DebugRequest) -> Result<DebugResponse> {
         let response = match request {
-            DebugRequest::Authenticate { token } => {
-                if let Some(expected) = expected_token {
-                    let success = token == *expected;
-                    session.authenticated = success;
-                    DebugResponse::Authenticated {
-                        success,
-                        message: if success {
-                            "Authentication successful".to_string()
-                        } else {
-                            "Invalid token".to_string()
-                        },
-                    }
-                } else {
-                    session.authenticated = true;
-                    DebugResponse::Authenticated {
-                        success: true,
-                        message: "No authentication required".to_string(),
-                    }
-                }
-            }
-
-            DebugRequest::Ping => DebugResponse::Pong,
-
             DebugRequest::LoadContract { contract_path } => {
-                match Self::load_contract(&contract_path) {
-                    Ok((engine, size)) => {
+                match Self::load_contract(&contract_path).and_then(|loaded| {
+                    Self::with_session_snapshot(session.snapshot.as_ref(), loaded)
+                }) {
+                    Ok((engine, size, hash)) => {
                         session.engine = Some(Arc::new(Mutex::new(engine)));
+                        session.contract = Some(contract_path);
+                        session.wasm_sha256 = Some(hash);
                         DebugResponse::ContractLoaded { size }
                     }
                     Err(e) => DebugResponse::Error {
@@ -195,16 +420,43 @@ impl DebugServer {
                 }
             }
 
+            DebugRequest::UploadContract {
+                wasm_base64,
+                sha256,
+            } => match Self::upload_contract(&wasm_base64, sha256.as_ref())
+                .and_then(|loaded| Self::with_session_snapshot(session.snapshot.as_ref(), loaded))
+            {
+                Ok((engine, size, hash)) => {
+                    session.engine = Some(Arc::new(Mutex::new(engine)));
+                    session.contract = Some(format!("uploaded {}", &hash[..16]));
+                    session.wasm_sha256 = Some(hash);
+                    DebugResponse::ContractLoaded { size }
+                }
+                Err(e) => DebugResponse::Error {
+                    message: format!("Failed to load contract: {}", e),
+                },
+            },
+
+            DebugRequest::UploadSnapshot {
+                snapshot_json,
+                sha256,
+            } => {
+                let loaded = verify_wasm_hash(
+                    &compute_wasm_sha256(snapshot_json.as_bytes()),
+                    sha256.as_ref(),
+                )
+                .and_then(|_| SnapshotLoader::from_json(&snapshot_json));
+                match loaded {
+                    Ok(loader) => Self::install_snapshot(session, loader),
+                    Err(e) => DebugResponse::Error {
+                        message: format!("Failed to load snapshot: {}", e),
+                    },
+                }
+            }
+
             DebugRequest::LoadSnapshot { snapshot_path } => {
                 match SnapshotLoader::from_file(&snapshot_path) {
-                    Ok(loader) => match loader.apply_to_environment() {
-                        Ok(snapshot) => DebugResponse::SnapshotLoaded {
-                            summary: snapshot.format_summary(),
-                        },
-                        Err(e) => DebugResponse::Error {
-                            message: format!("Failed to apply snapshot: {}", e),
-                        },
-                    },
+                    Ok(loader) => Self::install_snapshot(session, loader),
                     Err(e) => DebugResponse::Error {
                         message: format!("Failed to load snapshot: {}", e),
                     },
@@ -248,7 +500,34 @@ impl DebugServer {
                         DebuggerError::ExecutionError(format!("Failed to lock engine: {}", e))
                     })?;
 
-                    match engine_guard.execute(&function, args.as_deref()) {
+                    if engine_guard.breakpoints_mut().should_break(&function) {
+                        session.events.push(DebugEvent::Stopped {
+                            reason: "breakpoint".to_string(),
+                            function: Some(function.clone()),
+                        });
+                    }
+                    let storage_before = engine_guard.executor().storage_entries();
+                    let result = engine_guard.execute(&function, args.as_deref());
+
+                    let executor = engine_guard.executor();
+                    for line in executor.logs().unwrap_or_default() {
+                        session.events.push(DebugEvent::Output {
+                            category: "log".to_string(),
+                            output: line,
+                        });
+                    }
+                    session.events.push(DebugEvent::ExecutionComplete {
+                        function: function.clone(),
+                        success: result.is_ok(),
+                        output: result.as_ref().ok().cloned(),
+                        error: result.as_ref().err().map(|e| e.to_string()),
+                    });
+                    let changes = storage_changes(&storage_before, &executor.storage_entries());
+                    if !changes.is_empty() {
+                        session.events.push(DebugEvent::StorageChanged { changes });
+                    }
+
+                    match result {
                         Ok(output) => DebugResponse::ExecutionResult {
                             success: true,
                             output,
@@ -283,6 +562,10 @@ impl DebugServer {
                                 ))
                             })?;
 
+                            session.events.push(DebugEvent::Stopped {
+                                reason: "step".to_string(),
+                                function: state_guard.current_function().map(str::to_string),
+                            });
                             DebugResponse::StepResult {
                                 paused: engine.is_paused(),
                                 current_function: state_guard
@@ -330,6 +613,161 @@ impl DebugServer {
                 }
             }
 
+            DebugRequest::StartInstructionStepping { function, args } => {
+                Self::with_engine(session, |engine, events| {
+                    let started = (|| {
+                        if !engine.is_instruction_debug_enabled() {
+                            let wasm = engine.executor().wasm_bytes().to_vec();
+                            engine.enable_instruction_debug(&wasm)?;
+                        }
+                        engine.start_instruction_stepping(StepMode::StepInto)?;
+                        engine.prepare_invocation(&function, args.as_deref())
+                    })();
+                    match started {
+                        Ok(()) => {
+                            let position = instruction_position(engine);
+                            events.push(DebugEvent::Stopped {
+                                reason: "entry".to_string(),
+                                function: Some(function.clone()),
+                            });
+                            DebugResponse::InstructionStepped {
+                                stepped: position.is_some(),
+                                position,
+                            }
+                        }
+                        Err(e) => DebugResponse::Error {
+                            message: format!("Failed to start instruction stepping: {}", e),
+                        },
+                    }
+                })?
+            }
+
+            DebugRequest::StepInstruction { kind } => {
+                Self::with_engine(session, |engine, events| {
+                    let stepped = match kind {
+                        StepKind::Into => engine.step_into(),
+                        StepKind::Over => engine.step_over(),
+                        StepKind::Out => engine.step_out(),
+                        StepKind::Block => engine.step_block(),
+                        StepKind::Back => engine.step_back(),
+                    };
+                    match stepped {
+                        Ok(stepped) => {
+                            let position = instruction_position(engine);
+                            if stepped {
+                                events.push(DebugEvent::Stopped {
+                                    reason: "step".to_string(),
+                                    function: position.as_ref().and_then(|p| p.function.clone()),
+                                });
+                            }
+                            DebugResponse::InstructionStepped { stepped, position }
+                        }
+                        Err(e) => DebugResponse::Error {
+                            message: format!("Step failed: {}", e),
+                        },
+                    }
+                })?
+            }
+
+            DebugRequest::GetInstructionContext { context_size } => {
+                Self::with_engine(session, |engine, _| DebugResponse::InstructionContext {
+                    instructions: engine
+                        .get_instruction_context(context_size)
+                        .into_iter()
+                        .map(|(index, instruction, current)| InstructionLine {
+                            index,
+                            instruction: instruction.to_string(),
+                            current,
+                        })
+                        .collect(),
+                })?
+            }
+
+            DebugRequest::GetLocals => {
+                Self::with_engine(session, |engine, _| match engine.inspect_locals() {
+                    Ok(snapshot) => {
+                        let value = |value: Option<WasmValue>| value.map(|v| v.to_string());
+                        DebugResponse::Locals {
+                            function: snapshot.function_name,
+                            locals: snapshot
+                                .locals
+                                .into_iter()
+                                .map(|local| ValueInfo {
+                                    name: Some(
+                                        local.name.unwrap_or_else(|| format!("${}", local.index)),
+                                    ),
+                                    value_type: Some(local.value_type),
+                                    value: value(local.value),
+                                })
+                                .collect(),
+                            value_stack: snapshot
+                                .value_stack
+                                .into_iter()
+                                .map(|slot| ValueInfo {
+                                    name: None,
+                                    value_type: Some(slot.value_type),
+                                    value: value(slot.value),
+                                })
+                                .collect(),
+                            stack_exact: snapshot.stack_exact,
+                            variables: snapshot
+                                .variables
+                                .into_iter()
+                                .map(|variable| ValueInfo {
+                                    name: Some(variable.variable.name),
+                                    value_type: variable.variable.type_name,
+                                    value: value(variable.value),
+                                })
+                                .collect(),
+                        }
+                    }
+                    Err(e) => DebugResponse::Error {
+                        message: format!("Cannot inspect locals: {}", e),
+                    },
+                })?
+            }
+
+            DebugRequest::GetStorageEntry { storage, key } => {
+                Self::with_engine(session, |engine, _| {
+                    let Some(kind) = StorageKind::parse(&storage) else {
+                        return DebugResponse::Error {
+                            message: format!(
+                                "Unknown storage '{}'; use instance, persistent or temporary",
+                                storage
+                            ),
+                        };
+                    };
+                    match engine.executor().storage_get(kind, &key) {
+                        Ok(value) => DebugResponse::StorageValue {
+                            storage: kind.name().to_string(),
+                            key,
+                            value: value.map(|v| Formatter::format_sc_val(&v)),
+                        },
+                        Err(e) => DebugResponse::Error {
+                            message: format!("Failed to read storage: {}", e),
+                        },
+                    }
+                })?
+            }
+
+            DebugRequest::GetContractEvents => {
+                Self::with_engine(session, |engine, _| match engine.executor().get_events() {
+                    Ok(events) => DebugResponse::ContractEvents {
+                        events: events
+                            .into_iter()
+                            .map(|event| ContractEventInfo {
+                                contract_id: event.contract_id,
+                                topics: event.topics,
+                                data: event.data,
+                            })
+                            .collect(),
+                    },
+                    Err(e) => DebugResponse::Error {
+                        message: format!("Failed to read events: {}", e),
+                    },
+                })?
+            }
+
             DebugRequest::Inspect => {
                 if let Some(engine) = &session.engine {
                     let engine_guard = engine.lock().map_err(|e| {
@@ -360,24 +798,24 @@ impl DebugServer {
                 }
             }
 
-            DebugRequest::GetStorage => {
-                if let Some(engine) = &session.engine {
-                    // Get storage from the executor's host
-                    let engine_guard = engine.lock().map_err(|e| {
-                        DebuggerError::ExecutionError(format!("Failed to lock engine: {}", e))
-                    })?;
-                    let _host = engine_guard.executor().host();
-
-                    // This is a simplified version - in practice, you'd serialize the actual storage
-                    DebugResponse::StorageState {
-                        storage_json: "{}".to_string(), // Placeholder
-                    }
-                } else {
-                    DebugResponse::Error {
-                        message: "No contract loaded".to_string(),
+            DebugRequest::GetStorage => Self::with_engine(session, |engine, _| {
+                // {"persistent": {"<key>": "<value>", ...}, ...}
+                let mut storage = serde_json::Map::new();
+                for (kind, key, value) in engine.executor().storage_entries() {
+                    if let serde_json::Value::Object(entries) = storage
+                        .entry(kind.name())
+                        .or_insert_with(|| serde_json::Value::Object(Default::default()))
+                    {
+                        entries.insert(
+                            Formatter::format_sc_val(&key),
+                            Formatter::format_sc_val(&value).into(),
+                        );
                     }
                 }
-            }
+                DebugResponse::StorageState {
+                    storage_json: serde_json::Value::Object(storage).to_string(),
+                }
+            })?,
 
             DebugRequest::GetStack => {
                 if let Some(engine) = &session.engine {
@@ -432,6 +870,9 @@ impl DebugServer {
                         DebuggerError::ExecutionError(format!("Failed to lock engine: {}", e))
                     })?;
                     engine.breakpoints_mut().add(&function);
+                    session.events.push(DebugEvent::BreakpointChanged {
+                        breakpoints: engine.breakpoints_mut().list(),
+                    });
                     DebugResponse::BreakpointSet { function }
                 } else {
                     DebugResponse::Error {
@@ -446,6 +887,9 @@ impl DebugServer {
                         DebuggerError::ExecutionError(format!("Failed to lock engine: {}", e))
                     })?;
                     engine.breakpoints_mut().remove(&function);
+                    session.events.push(DebugEvent::BreakpointChanged {
+                        breakpoints: engine.breakpoints_mut().list(),
+                    });
                     DebugResponse::BreakpointCleared { function }
                 } else {
                     DebugResponse::Error {
@@ -468,13 +912,165 @@ impl DebugServer {
                 }
             }
 
-            DebugRequest::Disconnect => DebugResponse::Disconnected,
+            DebugRequest::InspectMemory { address, length } => {
+                Self::with_memory(session, |memory| {
+                    match memory.memory().read(address, length) {
+                        Some(bytes) => DebugResponse::MemoryContents {
+                            address,
+                            hex: hex::encode(bytes),
+                        },
+                        None => DebugResponse::Error {
+                            message: format!("Address 0x{:x} is out of bounds", address),
+                        },
+                    }
+                })?
+            }
+
+            DebugRequest::SearchMemory { pattern } => match parse_pattern(&pattern) {
+                Ok(bytes) => {
+                    Self::with_memory(session, |memory| DebugResponse::MemorySearchResult {
+                        addresses: memory.memory().search(&bytes, MAX_MEMORY_SEARCH_RESULTS),
+                    })?
+                }
+                Err(e) => DebugResponse::Error {
+                    message: format!("{}", e),
+                },
+            },
+
+            DebugRequest::DecodeMemory {
+                address,
+                kind,
+                max_len,
+            } => Self::with_memory(session, |memory| {
+                let memory = memory.memory();
+                let value = match kind.as_str() {
+                    "utf8" | "str" | "string" => memory.read_utf8(address, max_len.unwrap_or(256)),
+                    other => match IntKind::parse(other) {
+                        Some(int_kind) => memory.read_int(address, int_kind),
+                        None => {
+                            return DebugResponse::Error {
+                                message: format!("Unknown memory value kind: {}", other),
+                            }
+                        }
+                    },
+                };
+                match value {
+                    Some(value) => DebugResponse::MemoryValue {
+                        address,
+                        kind: kind.clone(),
+                        value,
+                    },
+                    None => DebugResponse::Error {
+                        message: format!("Address 0x{:x} is out of bounds", address),
+                    },
+                }
+            })?,
+
+            DebugRequest::GetMemoryInfo => Self::with_memory(session, |memory| {
+                let info = memory.summary();
+                DebugResponse::MemoryInfo {
+                    size_bytes: info.size_bytes,
+                    declared_initial_pages: info.declared_initial_pages,
+                    maximum_pages: info.maximum_pages,
+                    pages_at_entry: info.pages_at_entry,
+                    pages_at_exit: info.pages_at_exit,
+                    captured: info.captured,
+                    capture_error: info.capture_error,
+                }
+            })?,
+
+            DebugRequest::Handshake { .. }
+            | DebugRequest::Authenticate { .. }
+            | DebugRequest::CreateSession { .. }
+            | DebugRequest::AttachSession { .. }
+            | DebugRequest::ListSessions
+            | DebugRequest::DestroySession { .. }
+            | DebugRequest::Cancel { .. }
+            | DebugRequest::Ping
+            | DebugRequest::Disconnect => DebugResponse::Error {
+                message: "Not a session request".to_string(),
+            },
+        };
+
+        Ok(response)
+    }
+
+    /// Run `f` against the session's engine, with the events to push after
+    /// the response.
+    fn with_engine(
+        session: &mut Session,
+        f: impl FnOnce(&mut DebuggerEngine, &mut Vec<DebugEvent>) -> DebugResponse,
+    ) -> Result<DebugResponse> {
+        let Some(engine) = &session.engine else {
+            return Ok(DebugResponse::Error {
+                message: "No contract loaded".to_string(),
+            });
+        };
+        let mut engine = engine
+            .lock()
+            .map_err(|e| DebuggerError::ExecutionError(format!("Failed to lock engine: {}", e)))?;
+        Ok(f(&mut engine, &mut session.events))
+    }
+
+    /// Run `f` against the session's memory inspector.
+    fn with_memory(
+        session: &Session,
+        f: impl FnOnce(&MemoryInspector) -> DebugResponse,
+    ) -> Result<DebugResponse> {
+        let Some(engine) = &session.engine else {
+            return Ok(DebugResponse::Error {
+                message: "No contract loaded".to_string(),
+            });
+        };
+        let mut engine = engine
+            .lock()
+            .map_err(|e| DebuggerError::ExecutionError(format!("Failed to lock engine: {}", e)))?;
+        Ok(match engine.memory_inspector() {
+            Ok(inspector) => f(inspector),
+            Err(e) => DebugResponse::Error {
+                message: format!("Failed to inspect memory: {}", e),
+            },
+        })
+    }
+
+    /// Apply a snapshot to the session's contract, if any, and keep it for
+    /// contracts loaded later
+    fn install_snapshot(session: &mut Session, loader: SnapshotLoader) -> DebugResponse {
+        let applied = match &session.engine {
+            Some(engine) => engine
+                .lock()
+                .map_err(|e| DebuggerError::ExecutionError(format!("Failed to lock engine: {}", e)))
+                .map_err(Into::into)
+                .and_then(|mut engine| loader.apply_to_environment(engine.executor_mut())),
+            None => Ok(loader.summary()),
         };
+        match applied {
+            Ok(snapshot) => {
+                session.snapshot = Some(loader);
+                DebugResponse::SnapshotLoaded {
+                    summary: snapshot.format_summary(),
+                }
+            }
+            Err(e) => DebugResponse::Error {
+                message: format!("Failed to apply snapshot: {}", e),
+            },
+        }
+    }
 
-        Ok(DebugMessage::response(message.id, response))
+    /// Install the session's snapshot, if any, in a freshly loaded contract
+    fn with_session_snapshot(
+        snapshot: Option<&SnapshotLoader>,
+        (mut engine, size, hash): (DebuggerEngine, usize, String),
+    ) -> Result<(DebuggerEngine, usize, String)> {
+        if let Some(loader) = snapshot {
+            loader.apply_to_environment(engine.executor_mut())?;
+        }
+        Ok((engine, size, hash))
     }
 
-    fn load_contract(contract_path: &str) -> Result<(DebuggerEngine, usize)> {
+    /// Load a contract from the server's disk. Returns the engine, the size
+    /// and the SHA-256 of the WASM.
+    fn load_contract(contract_path: &str) -> Result<(DebuggerEngine, usize, String)> {
         use std::fs;
         let wasm_bytes = fs::read(contract_path).map_err(|e| {
             DebuggerError::WasmLoadError(format!(
@@ -483,9 +1079,31 @@ impl DebugServer {
             ))
         })?;
         let size = wasm_bytes.len();
-        let executor = ContractExecutor::new(wasm_bytes)?;
-        let engine = DebuggerEngine::new(executor, vec![]);
-        Ok((engine, size))
+        let hash = compute_wasm_sha256(&wasm_bytes);
+        Ok((Self::session_engine(wasm_bytes)?, size, hash))
+    }
+
+    /// Decode an uploaded contract and check it against `expected_hash`.
+    /// Returns the engine, the size and the SHA-256 of the WASM.
+    fn upload_contract(
+        wasm_base64: &str,
+        expected_hash: Option<&String>,
+    ) -> Result<(DebuggerEngine, usize, String)> {
+        let wasm_bytes = base64::engine::general_purpose::STANDARD
+            .decode(wasm_base64)
+            .map_err(|e| DebuggerError::WasmLoadError(format!("Invalid base64 WASM: {}", e)))?;
+        let hash = compute_wasm_sha256(&wasm_bytes);
+        verify_wasm_hash(&hash, expected_hash)?;
+        let size = wasm_bytes.len();
+        Ok((Self::session_engine(wasm_bytes)?, size, hash))
+    }
+
+    /// An engine for a session. The executor's timeout exits the process, so
+    /// it is off: one slow `Execute` must not take every session down.
+    fn session_engine(wasm_bytes: Vec<u8>) -> Result<DebuggerEngine> {
+        let mut executor = ContractExecutor::new(wasm_bytes)?;
+        executor.set_timeout(0);
+        Ok(DebuggerEngine::new(executor, vec![]))
     }
 
     fn parse_storage(_storage_json: &str) -> Result<String> {
@@ -497,3 +1115,1457 @@ impl DebugServer {
         Ok(_storage_json.to_string())
     }
 }
+
+impl Listeners {
+    /// Serve each connection on `listener` in a thread of its own
+    fn accept(&self, listener: TcpListener, transport: Transport) {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let listeners = self.clone();
+                    let connection = self.next_connection.fetch_add(1, Ordering::SeqCst);
+                    std::thread::spawn(move || {
+                        let result = match listeners.tls.clone() {
+                            Some(config) => ServerConnection::new(config)
+                                .map_err(|e| {
+                                    DebuggerError::FileError(format!("TLS setup failed: {}", e))
+                                        .into()
+                                })
+                                .and_then(|tls| {
+                                    listeners.handle_client(
+                                        StreamOwned::new(tls, stream),
+                                        transport,
+                                        connection,
+                                    )
+                                }),
+                            None => listeners.handle_client(stream, transport, connection),
+                        };
+                        if let Err(e) = result {
+                            error!("Error handling client: {}", e);
+                        }
+                        let _ = listeners
+                            .commands
+                            .send(Command::Disconnected { connection });
+                    });
+                }
+                Err(e) => {
+                    error!("Failed to accept connection: {}", e);
+                }
+            }
+        }
+    }
+
+    fn handle_client(
+        &self,
+        stream: impl Peer,
+        transport: Transport,
+        connection: u64,
+    ) -> Result<()> {
+        let peer_addr = stream
+            .peer_addr()
+            .map_err(|e| DebuggerError::FileError(format!("Failed to get peer address: {}", e)))?;
+        info!("New {:?} client connected from {}", transport, peer_addr);
+
+        match transport {
+            Transport::Tcp => self.serve_connection(
+                FramedCodec {
+                    stream,
+                    reader: FrameReader::new(self.max_message_size),
+                    max_message_size: self.max_message_size,
+                    framing: Framing::Line,
+                },
+                connection,
+            )?,
+            Transport::WebSocket => self.serve_connection(
+                websocket::JsonRpcCodec::accept(stream, self.max_message_size)?,
+                connection,
+            )?,
+        }
+
+        info!("Client {} disconnected", peer_addr);
+        Ok(())
+    }
+
+    /// Pass requests from one client to the server. While requests
+    /// are in progress the client can still send more, so a `Cancel`
+    /// reaches a request waiting its turn, and a heartbeat `Ping` is
+    /// answered during a long `Execute`.
+    fn serve_connection<C: Codec>(&self, mut codec: C, connection: u64) -> Result<()> {
+        let (reply, replies) = mpsc::channel();
+        let mut pending: VecDeque<Pending<C::Tag>> = VecDeque::new();
+        codec
+            .socket()
+            .set_read_timeout(Some(REQUEST_POLL_INTERVAL))
+            .map_err(|e| DebuggerError::FileError(format!("Failed to set timeout: {}", e)))?;
+        loop {
+            // Replies come back in request order
+            let wait = if pending.is_empty() {
+                Duration::ZERO
+            } else {
+                REQUEST_POLL_INTERVAL
+            };
+            match replies.recv_timeout(wait) {
+                Ok(Outgoing::Reply(messages)) => {
+                    let request = pending.pop_front().expect("a pending request");
+                    codec.write(&request.tag, &messages)?;
+                    continue;
+                }
+                Ok(Outgoing::Events(events)) => {
+                    codec.push(&events)?;
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Err(DebuggerError::ExecutionError(
+                        "Server is shutting down".to_string(),
+                    )
+                    .into())
+                }
+            }
+
+            let (message, tag) = match codec.read()? {
+                Incoming::Request(message, tag) => (message, tag),
+                Incoming::Timeout => continue,
+                Incoming::Closed => return Ok(()),
+            };
+
+            if let Some(DebugRequest::Cancel { request_id }) = message.request {
+                let response = match pending.iter().find(|p| p.id == request_id) {
+                    Some(request) if request.progress.cancel() => {
+                        DebugResponse::CancelAccepted { request_id }
+                    }
+                    // The host can't stop an invocation part-way
+                    Some(_) => DebugResponse::Error {
+                        message: format!("Request {} is already running", request_id),
+                    },
+                    None => DebugResponse::Error {
+                        message: format!("Request {} is not in progress", request_id),
+                    },
+                };
+                codec.write(&tag, &[DebugMessage::response(message.id, response)])?;
+                continue;
+            }
+            if let Some(DebugRequest::Ping) = message.request {
+                codec.write(
+                    &tag,
+                    &[DebugMessage::response(message.id, DebugResponse::Pong)],
+                )?;
+                continue;
+            }
+
+            let progress = Arc::new(Progress::default());
+            pending.push_back(Pending {
+                id: message.id,
+                tag,
+                progress: progress.clone(),
+            });
+            self.commands
+                .send(Command::Request {
+                    connection,
+                    message,
+                    progress,
+                    reply: reply.clone(),
+                })
+                .map_err(|_| {
+                    DebuggerError::ExecutionError("Server is shutting down".to_string())
+                })?;
+        }
+    }
+}
+
+impl<S: Peer> Codec for FramedCodec<S> {
+    type Tag = Framing;
+
+    fn socket(&self) -> &TcpStream {
+        self.stream.socket()
+    }
+
+    fn read(&mut self) -> Result<Incoming<Framing>> {
+        loop {
+            let (framing, frame) = match self.reader.next_frame(&mut self.stream) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return Ok(Incoming::Closed),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(Incoming::Timeout)
+                }
+                // A TLS client that hangs up without a close_notify
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(Incoming::Closed),
+                Err(e) => {
+                    return Err(
+                        DebuggerError::FileError(format!("Failed to read message: {}", e)).into(),
+                    )
+                }
+            };
+            match parse_frame(frame, self.max_message_size) {
+                Ok(message) => {
+                    self.framing = framing;
+                    return Ok(Incoming::Request(Box::new(message), framing));
+                }
+                Err(reply) => self.write(&framing, &[*reply])?,
+            }
+        }
+    }
+
+    fn write(&mut self, framing: &Framing, messages: &[DebugMessage]) -> Result<()> {
+        for message in messages {
+            let json = serde_json::to_string(message).map_err(|e| {
+                DebuggerError::FileError(format!("Failed to serialize response: {}", e))
+            })?;
+            framing::write_frame(&mut self.stream, *framing, &json).map_err(|e| {
+                DebuggerError::FileError(format!("Failed to write response: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, events: &[DebugMessage]) -> Result<()> {
+        let framing = self.framing;
+        self.write(&framing, events)
+    }
+}
+
+impl ServerState {
+    fn new(token: Option<String>, idle_timeout: Option<Duration>) -> Self {
+        let (commands, receiver) = mpsc::channel();
+        Self {
+            token,
+            observer_tokens: Vec::new(),
+            sessions: SessionManager::new(idle_timeout),
+            connections: HashMap::new(),
+            outboxes: HashMap::new(),
+            in_progress: HashMap::new(),
+            transcript: None,
+            commands,
+            receiver,
+        }
+    }
+
+    fn dispatch(&mut self, command: Command) {
+        match command {
+            Command::Request {
+                connection,
+                message,
+                progress,
+                reply,
+            } => {
+                self.outboxes
+                    .entry(connection)
+                    .or_insert_with(|| reply.clone());
+                let request = Queued {
+                    message,
+                    progress,
+                    reply,
+                };
+                // Replies go out in request order, so wait for an earlier
+                // request still running on a session's thread
+                match self.in_progress.get_mut(&connection) {
+                    Some(in_progress) => in_progress.queued.push_back(request),
+                    None => self.serve(connection, request),
+                }
+            }
+            Command::Finished {
+                connection,
+                id,
+                response,
+                events,
+            } => self.finish(connection, id, response, events),
+            Command::Disconnected { connection } => self.disconnect(connection),
+        }
+    }
+
+    /// Answer a request, or pass it to its session's thread
+    fn serve(&mut self, connection: u64, request: Queued) {
+        let Queued {
+            message,
+            progress,
+            reply,
+        } = request;
+        let id = message.id;
+        let request = self.transcript.is_some().then(|| (*message).clone());
+        // Session requests start on their session's thread
+        let cancelled = match &message.request {
+            Some(request) if request.uses_session() => progress.is_cancelled(),
+            _ => !progress.start(),
+        };
+        let messages = if cancelled {
+            vec![DebugMessage::response(id, DebugResponse::Cancelled)]
+        } else {
+            match self.handle(connection, *message, progress) {
+                Ok(Some(messages)) => messages,
+                Ok(None) => {
+                    self.in_progress.insert(
+                        connection,
+                        InProgress {
+                            request,
+                            reply,
+                            queued: VecDeque::new(),
+                        },
+                    );
+                    return;
+                }
+                // Errors answer the request; the connection stays up
+                Err(e) => vec![DebugMessage::response(
+                    id,
+                    DebugResponse::Error {
+                        message: e.to_string(),
+                    },
+                )],
+            }
+        };
+        if let Some(request) = request {
+            self.record(connection, request, &messages);
+        }
+        let _ = reply.send(Outgoing::Reply(messages));
+    }
+
+    /// Deliver a session thread's answer, then serve the requests that
+    /// waited for it
+    fn finish(
+        &mut self,
+        connection: u64,
+        id: u64,
+        response: DebugResponse,
+        events: Vec<DebugEvent>,
+    ) {
+        let Some(in_progress) = self.in_progress.remove(&connection) else {
+            return;
+        };
+        let (session, observer, send_events) = match self.connections.get(&connection) {
+            Some(state) => (
+                state.session.clone(),
+                state.scope == Some(Scope::Observer),
+                state.capabilities.events,
+            ),
+            None => (None, false, false),
+        };
+        let events: Vec<DebugMessage> = events.into_iter().map(DebugMessage::event).collect();
+        if let Some(session) = session.filter(|_| !observer && !events.is_empty()) {
+            self.notify_observers(&session, &events);
+        }
+        let mut messages = vec![DebugMessage::response(id, response)];
+        if send_events {
+            messages.extend(events);
+        }
+        if let Some(request) = in_progress.request {
+            self.record(connection, request, &messages);
+        }
+        let _ = in_progress.reply.send(Outgoing::Reply(messages));
+
+        let mut queued = in_progress.queued;
+        while let Some(request) = queued.pop_front() {
+            self.serve(connection, request);
+            if let Some(in_progress) = self.in_progress.get_mut(&connection) {
+                in_progress.queued = queued;
+                break;
+            }
+        }
+    }
+
+    /// Handle one request. Returns the response, or `None` if it was passed
+    /// to a session's thread, which answers with [`Command::Finished`].
+    fn handle(
+        &mut self,
+        connection_id: u64,
+        message: DebugMessage,
+        progress: Arc<Progress>,
+    ) -> Result<Option<Vec<DebugMessage>>> {
+        let request = message
+            .request
+            .ok_or_else(|| DebuggerError::ExecutionError("Message has no request".to_string()))?;
+        let token = &self.token;
+        let connection = self
+            .connections
+            .entry(connection_id)
+            .or_insert_with(|| Connection {
+                // Auto-authenticate if no token required
+                scope: token.is_none().then_some(Scope::Controller),
+                capabilities: Capabilities::default(),
+                session: None,
+            });
+
+        // Check authentication for all requests except Handshake, Authenticate and Ping
+        let denied = match (&request, connection.scope) {
+            (
+                DebugRequest::Handshake { .. }
+                | DebugRequest::Authenticate { .. }
+                | DebugRequest::Ping,
+                _,
+            ) => None,
+            (_, None) => Some("Not authenticated. Send Authenticate request first."),
+            (
+                DebugRequest::AttachSession { .. } | DebugRequest::Disconnect,
+                Some(Scope::Observer),
+            ) => None,
+            (request, Some(Scope::Observer)) if !request.is_read_only() => {
+                Some("Observers can only inspect sessions, not change them")
+            }
+            _ => None,
+        };
+        if let Some(denied) = denied {
+            return Ok(Some(vec![DebugMessage::response(
+                message.id,
+                DebugResponse::Error {
+                    message: denied.to_string(),
+                },
+            )]));
+        }
+
+        let observer = connection.scope == Some(Scope::Observer);
+        let response = match request {
+            DebugRequest::Handshake {
+                protocol_version,
+                capabilities,
+            } => {
+                if protocol_version < MIN_PROTOCOL_VERSION {
+                    DebugResponse::Error {
+                        message: format!(
+                            "Protocol version {} is not supported; this server speaks {} to {}",
+                            protocol_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+                        ),
+                    }
+                } else {
+                    connection.capabilities = capabilities.intersect(Capabilities::current());
+                    DebugResponse::HandshakeResult {
+                        protocol_version: protocol_version.min(PROTOCOL_VERSION),
+                        server_version: env!("CARGO_PKG_VERSION").to_string(),
+                        capabilities: connection.capabilities,
+                    }
+                }
+            }
+
+            DebugRequest::Authenticate { token } => {
+                let (scope, message) = if self.observer_tokens.contains(&token) {
+                    (Some(Scope::Observer), "Authenticated as an observer")
+                } else {
+                    match &self.token {
+                        Some(expected) if token == *expected => {
+                            (Some(Scope::Controller), "Authentication successful")
+                        }
+                        Some(_) => (None, "Invalid token"),
+                        None => (Some(Scope::Controller), "No authentication required"),
+                    }
+                };
+                if scope != connection.scope {
+                    // Sessions are held under the previous scope
+                    self.sessions.release(connection_id);
+                    connection.session = None;
+                    connection.scope = scope;
+                }
+                DebugResponse::Authenticated {
+                    success: scope.is_some(),
+                    message: message.to_string(),
+                    scope,
+                }
+            }
+
+            DebugRequest::Ping => DebugResponse::Pong,
+
+            DebugRequest::CreateSession { name } => {
+                self.sessions.release(connection_id);
+                connection.session = None;
+                match self.sessions.create(name, connection_id, false) {
+                    Ok(session_id) => {
+                        info!("Session {} created", session_id);
+                        connection.session = Some(session_id.clone());
+                        DebugResponse::SessionCreated { session_id }
+                    }
+                    Err(e) => DebugResponse::Error {
+                        message: e.to_string(),
+                    },
+                }
+            }
+
+            DebugRequest::AttachSession { session_id } => {
+                if connection.session.as_ref() != Some(&session_id) {
+                    self.sessions.release(connection_id);
+                    connection.session = None;
+                }
+                let attached = if observer {
+                    self.sessions.observe(&session_id, connection_id)
+                } else {
+                    self.sessions.attach(&session_id, connection_id)
+                };
+                match attached {
+                    Ok(session) => {
+                        let summary = session.summary();
+                        if observer {
+                            info!("Session {} observed", session_id);
+                        } else {
+                            info!("Session {} attached", session_id);
+                        }
+                        connection.session = Some(session_id.clone());
+                        DebugResponse::SessionAttached {
+                            session_id,
+                            contract: summary.contract,
+                            paused: summary.paused,
+                            function: summary.function,
+                        }
+                    }
+                    Err(e) => DebugResponse::Error {
+                        message: e.to_string(),
+                    },
+                }
+            }
+
+            DebugRequest::ListSessions => DebugResponse::SessionList {
+                sessions: self.sessions.list(),
+            },
+
+            DebugRequest::DestroySession { session_id } => {
+                if self.sessions.destroy(&session_id) {
+                    info!("Session {} destroyed", session_id);
+                    DebugResponse::SessionDestroyed { session_id }
+                } else {
+                    DebugResponse::Error {
+                        message: format!("No session named '{}'", session_id),
+                    }
+                }
+            }
+
+            DebugRequest::Disconnect => {
+                self.disconnect(connection_id);
+                DebugResponse::Disconnected
+            }
+
+            request => {
+                // Clients that never ask for a session get a private one
+                let session_id = match &connection.session {
+                    Some(id) => id.clone(),
+                    None if observer => {
+                        return Ok(Some(vec![DebugMessage::response(
+                            message.id,
+                            DebugResponse::Error {
+                                message: "Attach to a session to observe it".to_string(),
+                            },
+                        )]));
+                    }
+                    None => {
+                        let id = self.sessions.create(None, connection_id, true)?;
+                        connection.session = Some(id.clone());
+                        id
+                    }
+                };
+                let session = if observer {
+                    self.sessions.get_observed(&session_id, connection_id)
+                } else {
+                    self.sessions.get_mut(&session_id, connection_id)
+                };
+                match session {
+                    Ok(session) => {
+                        let id = message.id;
+                        let done = self.commands.clone();
+                        session.run(Box::new(move |session| {
+                            // A `Cancel` may come while earlier work runs
+                            let response = if !progress.start() {
+                                DebugResponse::Cancelled
+                            } else {
+                                DebugServer::handle_request(session, request).unwrap_or_else(|e| {
+                                    DebugResponse::Error {
+                                        message: e.to_string(),
+                                    }
+                                })
+                            };
+                            let finished = Command::Finished {
+                                connection: connection_id,
+                                id,
+                                response,
+                                events: std::mem::take(&mut session.events),
+                            };
+                            Box::new(move || {
+                                let _ = done.send(finished);
+                            })
+                        }))?;
+                        return Ok(None);
+                    }
+                    Err(e) => DebugResponse::Error {
+                        message: e.to_string(),
+                    },
+                }
+            }
+        };
+
+        Ok(Some(vec![DebugMessage::response(message.id, response)]))
+    }
+
+    /// Push a controller's events to the connections observing its session
+    fn notify_observers(&self, session_id: &str, events: &[DebugMessage]) {
+        for observer in self.sessions.observers(session_id) {
+            let wants_events = self
+                .connections
+                .get(&observer)
+                .is_some_and(|connection| connection.capabilities.events);
+            if let (true, Some(outbox)) = (wants_events, self.outboxes.get(&observer)) {
+                let _ = outbox.send(Outgoing::Events(events.to_vec()));
+            }
+        }
+    }
+
+    /// Append a handled request to the transcript, if one is being kept.
+    fn record(&mut self, connection_id: u64, request: DebugMessage, responses: &[DebugMessage]) {
+        let wasm_sha256 = self
+            .connections
+            .get(&connection_id)
+            .and_then(|connection| connection.session.as_deref())
+            .and_then(|id| self.sessions.summary(id))
+            .and_then(|summary| summary.wasm_sha256);
+        let Some(transcript) = &mut self.transcript else {
+            return;
+        };
+        let entry = TranscriptEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            connection: connection_id,
+            request,
+            responses: responses.to_vec(),
+            wasm_sha256,
+        };
+        if let Err(e) = transcript.record(entry) {
+            warn!("Failed to record transcript: {}", e);
+        }
+    }
+
+    /// Forget a connection, leaving its named session to be reattached.
+    fn disconnect(&mut self, connection_id: u64) {
+        self.outboxes.remove(&connection_id);
+        self.in_progress.remove(&connection_id);
+        if self.connections.remove(&connection_id).is_some() {
+            self.sessions.release(connection_id);
+        }
+    }
+}
+
+/// Where instruction stepping is paused, if it is
+fn instruction_position(engine: &DebuggerEngine) -> Option<InstructionPosition> {
+    let state = engine.state();
+    let state = state.lock().ok()?;
+    let instruction = state.current_instruction()?;
+    let module = state.module();
+    Some(InstructionPosition {
+        index: state.instruction_pointer().current_index(),
+        function_index: instruction.function_index,
+        instruction: instruction.to_string(),
+        function: module
+            .function
+            .clone()
+            .or_else(|| state.current_function().map(str::to_string)),
+        contract_id: module.contract_id.clone(),
+        depth: state.module_depth(),
+    })
+}
+
+/// Decode a frame into a message, or the error reply to send instead
+fn parse_frame(
+    frame: Frame,
+    max_message_size: usize,
+) -> std::result::Result<DebugMessage, Box<DebugMessage>> {
+    let error =
+        |id, message| Box::new(DebugMessage::response(id, DebugResponse::Error { message }));
+    let bytes = match frame {
+        Frame::Message(bytes) => bytes,
+        Frame::TooLarge(size) => {
+            return Err(error(
+                0,
+                format!(
+                    "Message of {} bytes exceeds the {} byte limit",
+                    size, max_message_size
+                ),
+            ))
+        }
+        Frame::InvalidHeader(header) => {
+            return Err(error(0, format!("Invalid header: {}", header)))
+        }
+    };
+    let text = String::from_utf8(bytes)
+        .map_err(|e| error(0, format!("Message is not valid UTF-8: {}", e)))?;
+    serde_json::from_str(&text).map_err(|e| {
+        // Answer requests from newer clients instead of rejecting them
+        invalid_request_reply(&text, &e)
+            .map(Box::new)
+            .unwrap_or_else(|| {
+                let id = serde_json::from_str::<serde_json::Value>(&text)
+                    .ok()
+                    .and_then(|value| value.get("id")?.as_u64())
+                    .unwrap_or(0);
+                error(id, format!("Malformed message: {}", e))
+            })
+    })
+}
+
+/// Reply to a request that failed to parse, if it has an ID and a type:
+/// `Unsupported` for a request type this server doesn't know, otherwise an
+/// error.
+fn invalid_request_reply(line: &str, error: &serde_json::Error) -> Option<DebugMessage> {
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let id = value.get("id")?.as_u64()?;
+    let kind = value.get("request")?.get("type")?.as_str()?;
+    let response = if error.to_string().starts_with("unknown variant") {
+        DebugResponse::Unsupported {
+            request: kind.to_string(),
+        }
+    } else {
+        DebugResponse::Error {
+            message: format!("Invalid {} request: {}", kind, error),
+        }
+    };
+    Some(DebugMessage::response(id, response))
+}
+
+/// Entries that differ between two listings from
+/// [`ContractExecutor::storage_entries`], in the order of `after` followed by
+/// removed entries.
+fn storage_changes(
+    before: &[(StorageKind, ScVal, ScVal)],
+    after: &[(StorageKind, ScVal, ScVal)],
+) -> Vec<StorageChange> {
+    let find = |entries: &[(StorageKind, ScVal, ScVal)], kind: StorageKind, key: &ScVal| {
+        entries
+            .iter()
+            .find(|(k, key2, _)| *k == kind && key2 == key)
+            .map(|(_, _, value)| value.clone())
+    };
+    let change = |kind: StorageKind, key: &ScVal, before: Option<ScVal>, after: Option<ScVal>| {
+        StorageChange {
+            storage: kind.name().to_string(),
+            key: Formatter::format_sc_val(key),
+            before: before.as_ref().map(Formatter::format_sc_val),
+            after: after.as_ref().map(Formatter::format_sc_val),
+        }
+    };
+
+    let mut changes = Vec::new();
+    for (kind, key, value) in after {
+        let old = find(before, *kind, key);
+        if old.as_ref() != Some(value) {
+            changes.push(change(*kind, key, old, Some(value.clone())));
+        }
+    }
+    for (kind, key, value) in before {
+        if find(after, *kind, key).is_none() {
+            changes.push(change(*kind, key, Some(value.clone()), None));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use crate::simulator::{ContractState, NetworkSnapshot};
+
+    /// Send `request` from `connection` without waiting for the answer.
+    fn send(
+        state: &mut ServerState,
+        connection: u64,
+        id: u64,
+        request: DebugRequest,
+        progress: Arc<Progress>,
+    ) -> mpsc::Receiver<Outgoing> {
+        let (reply, replies) = mpsc::channel();
+        state.dispatch(Command::Request {
+            connection,
+            message: Box::new(DebugMessage::request(id, request)),
+            progress,
+            reply,
+        });
+        replies
+    }
+
+    /// Route commands, such as answers from session threads, until
+    /// `replies` has the next reply.
+    fn reply(state: &mut ServerState, replies: &mpsc::Receiver<Outgoing>) -> Vec<DebugMessage> {
+        loop {
+            match replies.try_recv() {
+                Ok(Outgoing::Reply(messages)) => return messages,
+                Ok(Outgoing::Events(_)) => {}
+                Err(_) => {
+                    let command = state.receiver.recv().unwrap();
+                    state.dispatch(command);
+                }
+            }
+        }
+    }
+
+    /// Send `request` from `connection`, returning the response and events.
+    fn request(
+        state: &mut ServerState,
+        connection: u64,
+        request: DebugRequest,
+    ) -> (DebugResponse, Vec<DebugEvent>) {
+        let replies = send(state, connection, 1, request, Arc::default());
+        let mut messages = reply(state, &replies).into_iter();
+        let response = messages.next().unwrap().response.unwrap();
+        (response, messages.map(|m| m.event.unwrap()).collect())
+    }
+
+    fn ping_contract(dir: &tempfile::TempDir) -> DebugRequest {
+        let path = dir.path().join("ping.wasm");
+        std::fs::write(&path, test_wasm::ping()).unwrap();
+        DebugRequest::LoadContract {
+            contract_path: path.to_string_lossy().into_owned(),
+        }
+    }
+
+    #[test]
+    fn requests_queue_events_for_the_client() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = ServerState::new(None, None);
+        let (_, events) = request(&mut state, 1, ping_contract(&dir));
+        assert!(events.is_empty());
+
+        // Clients that didn't ask for events don't get them
+        let set_breakpoint = || DebugRequest::SetBreakpoint {
+            function: "ping".to_string(),
+        };
+        let (_, events) = request(&mut state, 1, set_breakpoint());
+        assert!(events.is_empty());
+        let (response, _) = request(
+            &mut state,
+            1,
+            DebugRequest::Handshake {
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: Capabilities {
+                    events: true,
+                    ..Capabilities::default()
+                },
+            },
+        );
+        let DebugResponse::HandshakeResult { capabilities, .. } = response else {
+            panic!("unexpected response: {:?}", response);
+        };
+        assert!(capabilities.events);
+        assert!(!capabilities.memory_inspection);
+
+        let (_, events) = request(&mut state, 1, set_breakpoint());
+        assert!(matches!(
+            events.as_slice(),
+            [DebugEvent::BreakpointChanged { breakpoints }] if breakpoints == &["ping"]
+        ));
+
+        let (_, events) = request(
+            &mut state,
+            1,
+            DebugRequest::Execute {
+                function: "ping".to_string(),
+                args: None,
+            },
+        );
+        assert!(matches!(
+            events.first(),
+            Some(DebugEvent::Stopped { reason, .. }) if reason == "breakpoint"
+        ));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            DebugEvent::ExecutionComplete { function, success: true, .. } if function == "ping"
+        )));
+    }
+
+    #[test]
+    fn named_sessions_can_be_reattached_after_a_disconnect() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = ServerState::new(Some("secret".to_string()), None);
+        let authenticate = || DebugRequest::Authenticate {
+            token: "secret".to_string(),
+        };
+        let list = |state: &mut ServerState, connection| match request(
+            state,
+            connection,
+            DebugRequest::ListSessions,
+        )
+        .0
+        {
+            DebugResponse::SessionList { sessions } => sessions,
+            other => panic!("unexpected response: {:?}", other),
+        };
+
+        assert!(matches!(
+            request(&mut state, 1, DebugRequest::ListSessions).0,
+            DebugResponse::Error { .. }
+        ));
+        request(&mut state, 1, authenticate());
+        let (response, _) = request(
+            &mut state,
+            1,
+            DebugRequest::CreateSession {
+                name: Some("ci".to_string()),
+            },
+        );
+        assert!(
+            matches!(response, DebugResponse::SessionCreated { session_id } if session_id == "ci")
+        );
+        request(&mut state, 1, ping_contract(&dir));
+        request(
+            &mut state,
+            1,
+            DebugRequest::SetBreakpoint {
+                function: "ping".to_string(),
+            },
+        );
+
+        // A client without a session gets a private one that dies with it
+        request(&mut state, 2, authenticate());
+        request(&mut state, 2, ping_contract(&dir));
+        assert_eq!(list(&mut state, 2).len(), 2);
+        state.disconnect(2);
+        state.disconnect(1);
+
+        request(&mut state, 3, authenticate());
+        let sessions = list(&mut state, 3);
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "ci");
+        assert!(!sessions[0].attached);
+
+        let (response, _) = request(
+            &mut state,
+            3,
+            DebugRequest::AttachSession {
+                session_id: "ci".to_string(),
+            },
+        );
+        assert!(matches!(
+            response,
+            DebugResponse::SessionAttached {
+                contract: Some(_),
+                ..
+            }
+        ));
+        let (response, _) = request(&mut state, 3, DebugRequest::ListBreakpoints);
+        assert!(
+            matches!(response, DebugResponse::BreakpointsList { breakpoints } if breakpoints == ["ping"])
+        );
+
+        let (response, _) = request(
+            &mut state,
+            3,
+            DebugRequest::DestroySession {
+                session_id: "ci".to_string(),
+            },
+        );
+        assert!(matches!(response, DebugResponse::SessionDestroyed { .. }));
+        let (response, _) = request(&mut state, 3, DebugRequest::ListBreakpoints);
+        assert!(matches!(response, DebugResponse::Error { .. }));
+    }
+
+    #[test]
+    fn observers_watch_sessions_without_changing_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = ServerState::new(Some("secret".to_string()), None);
+        state.observer_tokens = vec!["watch".to_string()];
+        let with_events = || DebugRequest::Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Capabilities {
+                events: true,
+                ..Capabilities::default()
+            },
+        };
+        let attach = || DebugRequest::AttachSession {
+            session_id: "ci".to_string(),
+        };
+        let execute = || DebugRequest::Execute {
+            function: "ping".to_string(),
+            args: None,
+        };
+
+        request(
+            &mut state,
+            1,
+            DebugRequest::Authenticate {
+                token: "secret".to_string(),
+            },
+        );
+        request(
+            &mut state,
+            1,
+            DebugRequest::CreateSession {
+                name: Some("ci".to_string()),
+            },
+        );
+        request(&mut state, 1, ping_contract(&dir));
+
+        let (response, _) = request(
+            &mut state,
+            2,
+            DebugRequest::Authenticate {
+                token: "watch".to_string(),
+            },
+        );
+        assert!(matches!(
+            response,
+            DebugResponse::Authenticated {
+                success: true,
+                scope: Some(Scope::Observer),
+                ..
+            }
+        ));
+        request(&mut state, 2, with_events());
+        let (outbox, events) = mpsc::channel();
+        state.outboxes.insert(2, outbox);
+
+        // Observers need an existing session and can't change it
+        assert!(matches!(
+            request(&mut state, 2, DebugRequest::Inspect).0,
+            DebugResponse::Error { .. }
+        ));
+        assert!(matches!(
+            request(&mut state, 2, attach()).0,
+            DebugResponse::SessionAttached {
+                contract: Some(_),
+                ..
+            }
+        ));
+        for denied in [execute(), DebugRequest::Step, DebugRequest::Continue] {
+            assert!(matches!(
+                request(&mut state, 2, denied).0,
+                DebugResponse::Error { .. }
+            ));
+        }
+        assert!(matches!(
+            request(&mut state, 2, DebugRequest::GetBudget).0,
+            DebugResponse::BudgetInfo { .. }
+        ));
+
+        // The controller keeps the session and its events reach the observer
+        let (response, _) = request(&mut state, 1, execute());
+        assert!(matches!(response, DebugResponse::ExecutionResult { .. }));
+        let Ok(Outgoing::Events(pushed)) = events.try_recv() else {
+            panic!("observer got no events");
+        };
+        assert!(pushed.iter().any(|message| matches!(
+            message.event,
+            Some(DebugEvent::ExecutionComplete { success: true, .. })
+        )));
+        let (response, _) = request(&mut state, 2, DebugRequest::Inspect);
+        assert!(matches!(response, DebugResponse::InspectionResult { .. }));
+        let DebugResponse::SessionList { sessions } =
+            request(&mut state, 2, DebugRequest::ListSessions).0
+        else {
+            panic!("expected a session list");
+        };
+        assert_eq!(sessions[0].observers, 1);
+        assert!(sessions[0].attached);
+    }
+
+    #[test]
+    fn uploads_are_checked_against_their_hash() {
+        use base64::Engine as _;
+
+        let mut state = ServerState::new(None, None);
+        let wasm = test_wasm::ping();
+        let upload = |sha256: &str| DebugRequest::UploadContract {
+            wasm_base64: base64::engine::general_purpose::STANDARD.encode(&wasm),
+            sha256: Some(sha256.to_string()),
+        };
+        let (response, _) = request(&mut state, 1, upload(&"0".repeat(64)));
+        assert!(
+            matches!(&response, DebugResponse::Error { message } if message.contains("checksum mismatch")),
+            "{:?}",
+            response
+        );
+        let (response, _) = request(&mut state, 1, upload(&compute_wasm_sha256(&wasm)));
+        assert!(matches!(response, DebugResponse::ContractLoaded { size } if size == wasm.len()));
+
+        let snapshot_json =
+            serde_json::to_string(&NetworkSnapshot::new(100, "Test Network", 1234567890)).unwrap();
+        let (response, _) = request(
+            &mut state,
+            1,
+            DebugRequest::UploadSnapshot {
+                sha256: Some(compute_wasm_sha256(snapshot_json.as_bytes())),
+                snapshot_json,
+            },
+        );
+        assert!(
+            matches!(response, DebugResponse::SnapshotLoaded { .. }),
+            "{:?}",
+            response
+        );
+    }
+
+    #[test]
+    fn snapshots_are_installed_in_contracts_loaded_later() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = ServerState::new(None, None);
+        let mut snapshot = NetworkSnapshot::new(100, "Test Network", 1234567890);
+        let mut contract =
+            ContractState::new("11".repeat(32), compute_wasm_sha256(&test_wasm::ping()));
+        contract.persistent_storage.insert(
+            "owner".to_string(),
+            serde_json::json!({"type": "u32", "value": 9}),
+        );
+        snapshot.add_contract(contract).unwrap();
+        let path = dir.path().join("snapshot.json");
+        std::fs::write(&path, serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+        let (response, _) = request(
+            &mut state,
+            1,
+            DebugRequest::LoadSnapshot {
+                snapshot_path: path.to_string_lossy().into_owned(),
+            },
+        );
+        assert!(
+            matches!(response, DebugResponse::SnapshotLoaded { .. }),
+            "{:?}",
+            response
+        );
+        let (response, _) = request(&mut state, 1, ping_contract(&dir));
+        assert!(
+            matches!(response, DebugResponse::ContractLoaded { .. }),
+            "{:?}",
+            response
+        );
+        let (response, _) = request(&mut state, 1, DebugRequest::GetStorage);
+        assert!(
+            matches!(&response, DebugResponse::StorageState { storage_json }
+                if storage_json.contains("persistent") && storage_json.contains("owner")),
+            "{:?}",
+            response
+        );
+    }
+
+    #[test]
+    fn unknown_requests_are_answered_as_unsupported() {
+        let reply = |line: &str| {
+            let error = serde_json::from_str::<DebugMessage>(line).unwrap_err();
+            invalid_request_reply(line, &error).map(|m| (m.id, m.response.unwrap()))
+        };
+        assert!(matches!(
+            reply(r#"{"id":4,"request":{"type":"Rewind","steps":3}}"#),
+            Some((4, DebugResponse::Unsupported { request })) if request == "Rewind"
+        ));
+        assert!(matches!(
+            reply(r#"{"id":5,"request":{"type":"LoadContract"}}"#),
+            Some((5, DebugResponse::Error { .. }))
+        ));
+        assert!(reply("not json").is_none());
+    }
+
+    #[test]
+    fn bad_messages_are_answered_without_dropping_the_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = DebugServer::new(0, None).with_max_message_size(100);
+        std::thread::spawn(move || server.serve(listener));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let json =
+            |id, request| serde_json::to_string(&DebugMessage::request(id, request)).unwrap();
+        for (framing, text) in [
+            (Framing::Line, "{not json".to_string()),
+            (Framing::Line, "x".repeat(200)),
+            (
+                Framing::ContentLength,
+                r#"{"id":7,"request":"Ping"}"#.to_string(),
+            ),
+            (
+                Framing::Line,
+                json(8, DebugRequest::Cancel { request_id: 3 }),
+            ),
+            (Framing::ContentLength, json(9, DebugRequest::Ping)),
+        ] {
+            framing::write_frame(&mut stream, framing, &text).unwrap();
+        }
+
+        let mut reader = FrameReader::new(1024);
+        let mut replies = Vec::new();
+        while replies.len() < 5 {
+            let (framing, frame) = reader.next_frame(&mut stream).unwrap().unwrap();
+            let Frame::Message(bytes) = frame else {
+                panic!("unexpected frame: {:?}", frame);
+            };
+            let message: DebugMessage = serde_json::from_slice(&bytes).unwrap();
+            replies.push((framing, message.id, message.response.unwrap()));
+        }
+        let error = |reply: &(Framing, u64, DebugResponse), framing, id, text: &str| {
+            matches!(reply, (f, i, DebugResponse::Error { message })
+                if *f == framing && *i == id && message.contains(text))
+        };
+        assert!(
+            error(&replies[0], Framing::Line, 0, "Malformed message"),
+            "{:?}",
+            replies[0]
+        );
+        assert!(
+            error(&replies[1], Framing::Line, 0, "exceeds the 100 byte limit"),
+            "{:?}",
+            replies[1]
+        );
+        assert!(
+            error(&replies[2], Framing::ContentLength, 7, "Malformed message"),
+            "{:?}",
+            replies[2]
+        );
+        assert!(
+            error(&replies[3], Framing::Line, 8, "not in progress"),
+            "{:?}",
+            replies[3]
+        );
+        assert!(matches!(
+            replies[4],
+            (Framing::ContentLength, 9, DebugResponse::Pong)
+        ));
+    }
+
+    #[test]
+    fn pings_are_answered_while_requests_wait() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Nothing serves the requests, like a session stuck in `Execute`
+        let (commands, _requests) = mpsc::channel();
+        let listeners = Listeners {
+            commands,
+            tls: None,
+            next_connection: Arc::new(AtomicU64::new(1)),
+            max_message_size: framing::DEFAULT_MAX_MESSAGE_SIZE,
+        };
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            listeners.handle_client(stream, Transport::Tcp, 1)
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        for (id, request) in [
+            (
+                1,
+                DebugRequest::Execute {
+                    function: "slow".to_string(),
+                    args: None,
+                },
+            ),
+            (2, DebugRequest::Ping),
+        ] {
+            let json = serde_json::to_string(&DebugMessage::request(id, request)).unwrap();
+            framing::write_frame(&mut stream, Framing::Line, &json).unwrap();
+        }
+        let (_, frame) = FrameReader::new(1024)
+            .next_frame(&mut stream)
+            .unwrap()
+            .unwrap();
+        let Frame::Message(bytes) = frame else {
+            panic!("unexpected frame: {:?}", frame);
+        };
+        let message: DebugMessage = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(message.id, 2);
+        assert!(matches!(message.response, Some(DebugResponse::Pong)));
+    }
+
+    #[test]
+    fn cancelled_requests_are_not_run() {
+        let state = ServerState::new(None, None);
+        let commands = state.commands.clone();
+        std::thread::spawn(move || DebugServer::run_sessions(state));
+
+        let (reply, replies) = mpsc::channel();
+        for (id, cancelled) in [(1, true), (2, false)] {
+            let progress = Arc::new(Progress::default());
+            if cancelled {
+                assert!(progress.cancel());
+            }
+            commands
+                .send(Command::Request {
+                    connection: 1,
+                    message: Box::new(DebugMessage::request(id, DebugRequest::Ping)),
+                    progress,
+                    reply: reply.clone(),
+                })
+                .unwrap();
+        }
+        let response = |outgoing| match outgoing {
+            Outgoing::Reply(messages) => messages[0].response.clone().unwrap(),
+            Outgoing::Events(_) => panic!("unexpected events"),
+        };
+        assert!(matches!(
+            response(replies.recv().unwrap()),
+            DebugResponse::Cancelled
+        ));
+        assert!(matches!(
+            response(replies.recv().unwrap()),
+            DebugResponse::Pong
+        ));
+    }
+
+    #[test]
+    fn only_requests_that_have_not_started_can_be_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = ServerState::new(None, None);
+        request(
+            &mut state,
+            1,
+            DebugRequest::CreateSession {
+                name: Some("ci".to_string()),
+            },
+        );
+        // Hold the session so the next request waits its turn
+        let (release, released) = mpsc::channel::<()>();
+        state
+            .sessions
+            .get_mut("ci", 1)
+            .unwrap()
+            .run(Box::new(move |_| {
+                let _ = released.recv();
+                Box::new(|| {})
+            }))
+            .unwrap();
+        let waiting = Arc::new(Progress::default());
+        let replies = send(&mut state, 1, 1, ping_contract(&dir), waiting.clone());
+        assert!(waiting.cancel());
+        release.send(()).unwrap();
+        assert!(matches!(
+            reply(&mut state, &replies)[0].response,
+            Some(DebugResponse::Cancelled)
+        ));
+
+        let ran = Arc::new(Progress::default());
+        let replies = send(&mut state, 1, 2, ping_contract(&dir), ran.clone());
+        assert!(matches!(
+            reply(&mut state, &replies)[0].response,
+            Some(DebugResponse::ContractLoaded { .. })
+        ));
+        assert!(!ran.cancel());
+        // Requests answered without a session start too
+        let pinged = Arc::new(Progress::default());
+        let replies = send(&mut state, 1, 3, DebugRequest::Ping, pinged.clone());
+        assert!(matches!(
+            reply(&mut state, &replies)[0].response,
+            Some(DebugResponse::Pong)
+        ));
+        assert!(!pinged.cancel());
+    }
+
+    #[test]
+    fn a_busy_session_does_not_hold_up_other_clients() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = ServerState::new(None, None);
+        request(
+            &mut state,
+            1,
+            DebugRequest::CreateSession {
+                name: Some("slow".to_string()),
+            },
+        );
+        // Stand in for a long `Execute` on the first session
+        let (release, released) = mpsc::channel::<()>();
+        state
+            .sessions
+            .get_mut("slow", 1)
+            .unwrap()
+            .run(Box::new(move |_| {
+                let _ = released.recv();
+                Box::new(|| {})
+            }))
+            .unwrap();
+        let load = send(&mut state, 1, 1, ping_contract(&dir), Arc::default());
+        let ping = send(&mut state, 1, 2, DebugRequest::Ping, Arc::default());
+
+        let (response, _) = request(&mut state, 2, ping_contract(&dir));
+        assert!(matches!(response, DebugResponse::ContractLoaded { .. }));
+        let (response, _) = request(&mut state, 3, DebugRequest::ListSessions);
+        assert!(matches!(response, DebugResponse::SessionList { .. }));
+        // The first client's requests wait for the session, in order
+        assert!(ping.try_recv().is_err());
+
+        release.send(()).unwrap();
+        assert!(matches!(
+            reply(&mut state, &load)[0].response,
+            Some(DebugResponse::ContractLoaded { .. })
+        ));
+        assert!(matches!(
+            reply(&mut state, &ping)[0].response,
+            Some(DebugResponse::Pong)
+        ));
+    }
+
+    #[test]
+    fn instruction_stepping_and_queries_work_over_the_protocol() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = ServerState::new(None, None);
+        request(&mut state, 1, ping_contract(&dir));
+
+        let (response, _) = request(
+            &mut state,
+            1,
+            DebugRequest::StartInstructionStepping {
+                function: "ping".to_string(),
+                args: None,
+            },
+        );
+        let DebugResponse::InstructionStepped {
+            stepped: true,
+            position: Some(start),
+        } = response
+        else {
+            panic!("unexpected response: {:?}", response);
+        };
+
+        let step = |state: &mut ServerState, kind| match request(
+            state,
+            1,
+            DebugRequest::StepInstruction { kind },
+        )
+        .0
+        {
+            DebugResponse::InstructionStepped { stepped, position } => (stepped, position),
+            other => panic!("unexpected response: {:?}", other),
+        };
+        let (stepped, position) = step(&mut state, StepKind::Into);
+        assert!(stepped);
+        assert_ne!(position.unwrap().index, start.index);
+        let (stepped, position) = step(&mut state, StepKind::Back);
+        assert!(stepped);
+        assert_eq!(position.unwrap().index, start.index);
+
+        let (response, _) = request(
+            &mut state,
+            1,
+            DebugRequest::GetInstructionContext { context_size: 2 },
+        );
+        assert!(matches!(
+            response,
+            DebugResponse::InstructionContext { instructions }
+                if instructions.iter().filter(|line| line.current).count() == 1
+        ));
+        let (response, _) = request(&mut state, 1, DebugRequest::GetLocals);
+        assert!(matches!(response, DebugResponse::Locals { .. }));
+
+        let (response, _) = request(
+            &mut state,
+            1,
+            DebugRequest::GetStorageEntry {
+                storage: "cold".to_string(),
+                key: serde_json::json!("counter"),
+            },
+        );
+        assert!(matches!(response, DebugResponse::Error { .. }));
+        let (response, _) = request(&mut state, 1, DebugRequest::GetContractEvents);
+        assert!(matches!(response, DebugResponse::ContractEvents { .. }));
+        let (response, _) = request(&mut state, 1, DebugRequest::GetStorage);
+        let DebugResponse::StorageState { storage_json } = response else {
+            panic!("unexpected response: {:?}", response);
+        };
+        assert!(serde_json::from_str::<serde_json::Value>(&storage_json)
+            .unwrap()
+            .is_object());
+    }
+
+    #[test]
+    fn storage_changes_report_added_updated_and_removed_entries() {
+        let entry =
+            |key: u32, value: u32| (StorageKind::Persistent, ScVal::U32(key), ScVal::U32(value));
+        let before = [entry(1, 10), entry(2, 20)];
+        let after = [entry(1, 11), entry(3, 30)];
+
+        let changes: Vec<_> = storage_changes(&before, &after)
+            .into_iter()
+            .map(|c| (c.storage, c.key, c.before, c.after))
+            .collect();
+        let persistent = StorageKind::Persistent.name().to_string();
+        let val = |v: u32| Formatter::format_sc_val(&ScVal::U32(v));
+        assert_eq!(
+            changes,
+            vec![
+                (persistent.clone(), val(1), Some(val(10)), Some(val(11))),
+                (persistent.clone(), val(3), None, Some(val(30))),
+                (persistent, val(2), Some(val(20)), None),
+            ]
+        );
+    }
+}
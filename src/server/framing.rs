@@ -0,0 +1,327 @@
+//! Message framing for the debug protocol.
+//!
+//! Each message is one JSON object, either on a line of its own or preceded
+//! by `Content-Length` headers as in the Debug Adapter Protocol:
+//!
+//! ```text
+//! Content-Length: 34\r\n
+//! \r\n
+//! {"id":1,"request":{"type":"Ping"}}
+//! ```
+//!
+//! Readers accept both, message by message. The server answers each
+//! request in the framing it arrived in.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Largest message accepted by default, enough for base64-encoded contracts
+/// and snapshots
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// How a message is delimited on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /// One message per line
+    Line,
+    /// `Content-Length` headers, a blank line, then the message
+    ContentLength,
+}
+
+/// What [`FrameReader`] found next on the wire
+#[derive(Debug, PartialEq, Eq)]
+pub enum Frame {
+    /// A complete message
+    Message(Vec<u8>),
+    /// A message of this many bytes, over the limit and skipped
+    TooLarge(usize),
+    /// Headers that don't give a valid length; the reader resumes after them
+    InvalidHeader(String),
+}
+
+#[derive(Debug)]
+enum State {
+    /// Between messages
+    Idle,
+    /// Reading headers, with the length seen so far
+    Headers(Option<usize>),
+    /// Waiting for a body of this length
+    Body(usize),
+    /// Discarding an oversized line, with its length so far
+    SkipLine(usize),
+    /// Discarding an oversized body
+    SkipBody { remaining: usize, total: usize },
+}
+
+/// Splits a byte stream into messages. The reader keeps partial messages
+/// across calls, so a read that times out can simply be retried.
+#[derive(Debug)]
+pub struct FrameReader {
+    buffer: Vec<u8>,
+    state: State,
+    max_size: usize,
+}
+
+impl FrameReader {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            state: State::Idle,
+            max_size,
+        }
+    }
+
+    /// Read the next frame from `source`. Returns `None` once the stream
+    /// ends; timeouts and other read errors are returned as they are.
+    pub fn next_frame(&mut self, source: &mut impl Read) -> io::Result<Option<(Framing, Frame)>> {
+        let mut chunk = [0; 8192];
+        loop {
+            if let Some(frame) = self.parse() {
+                return Ok(Some(frame));
+            }
+            match source.read(&mut chunk) {
+                Ok(0) => return Ok(None),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Add bytes read elsewhere, e.g. from an async stream
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next complete frame among the bytes read so far
+    pub fn buffered_frame(&mut self) -> Option<(Framing, Frame)> {
+        self.parse()
+    }
+
+    /// Take the next frame out of the buffer, if it holds one
+    fn parse(&mut self) -> Option<(Framing, Frame)> {
+        loop {
+            match self.state {
+                State::Idle => {
+                    let Some(line) = self.take_line() else {
+                        if self.buffer.len() > self.max_size {
+                            self.state = State::SkipLine(self.buffer.len());
+                            self.buffer.clear();
+                        }
+                        return None;
+                    };
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if let Some(value) = content_length(text) {
+                        match value.parse::<usize>() {
+                            Ok(length) => self.state = State::Headers(Some(length)),
+                            Err(_) => {
+                                self.state = State::Headers(None);
+                                return Some((
+                                    Framing::ContentLength,
+                                    Frame::InvalidHeader(text.to_string()),
+                                ));
+                            }
+                        }
+                        continue;
+                    }
+                    if line.len() > self.max_size {
+                        return Some((Framing::Line, Frame::TooLarge(line.len())));
+                    }
+                    return Some((Framing::Line, Frame::Message(line)));
+                }
+
+                State::Headers(length) => {
+                    let Some(line) = self.take_line() else {
+                        if self.buffer.len() > self.max_size {
+                            // Headers never end; drop them and start over
+                            self.buffer.clear();
+                            self.state = State::Idle;
+                            return Some((
+                                Framing::ContentLength,
+                                Frame::InvalidHeader("Headers too long".to_string()),
+                            ));
+                        }
+                        return None;
+                    };
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim();
+                    if !text.is_empty() {
+                        if let Some(value) = content_length(text) {
+                            self.state = State::Headers(value.parse().ok());
+                        }
+                        continue;
+                    }
+                    self.state = match length {
+                        Some(length) if length > self.max_size => State::SkipBody {
+                            remaining: length,
+                            total: length,
+                        },
+                        Some(length) => State::Body(length),
+                        // The error for the bad header was already returned
+                        None => State::Idle,
+                    };
+                }
+
+                State::Body(length) => {
+                    if self.buffer.len() < length {
+                        return None;
+                    }
+                    let body = self.buffer.drain(..length).collect();
+                    self.state = State::Idle;
+                    return Some((Framing::ContentLength, Frame::Message(body)));
+                }
+
+                State::SkipLine(skipped) => match self.buffer.iter().position(|&b| b == b'\n') {
+                    Some(end) => {
+                        self.buffer.drain(..=end);
+                        self.state = State::Idle;
+                        return Some((Framing::Line, Frame::TooLarge(skipped + end)));
+                    }
+                    None => {
+                        self.state = State::SkipLine(skipped + self.buffer.len());
+                        self.buffer.clear();
+                        return None;
+                    }
+                },
+
+                State::SkipBody { remaining, total } => {
+                    let skip = remaining.min(self.buffer.len());
+                    self.buffer.drain(..skip);
+                    if skip < remaining {
+                        self.state = State::SkipBody {
+                            remaining: remaining - skip,
+                            total,
+                        };
+                        return None;
+                    }
+                    self.state = State::Idle;
+                    return Some((Framing::ContentLength, Frame::TooLarge(total)));
+                }
+            }
+        }
+    }
+
+    /// Remove and return the next line, without its line ending
+    fn take_line(&mut self) -> Option<Vec<u8>> {
+        let end = self.buffer.iter().position(|&b| b == b'\n')?;
+        let mut line: Vec<u8> = self.buffer.drain(..=end).collect();
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Some(line)
+    }
+}
+
+/// The value of a `Content-Length` header line
+fn content_length(line: &str) -> Option<&str> {
+    let (name, value) = line.split_once(':')?;
+    name.trim()
+        .eq_ignore_ascii_case("Content-Length")
+        .then(|| value.trim())
+}
+
+/// Write one message in `framing` and flush it
+pub fn write_frame(writer: &mut impl Write, framing: Framing, json: &str) -> io::Result<()> {
+    match framing {
+        Framing::Line => writeln!(writer, "{}", json)?,
+        Framing::ContentLength => write!(writer, "Content-Length: {}\r\n\r\n{}", json.len(), json)?,
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frames(input: &[u8], max_size: usize) -> Vec<(Framing, Frame)> {
+        let mut reader = FrameReader::new(max_size);
+        let mut source = Cursor::new(input);
+        std::iter::from_fn(|| reader.next_frame(&mut source).unwrap()).collect()
+    }
+
+    #[test]
+    fn line_and_content_length_messages_can_be_mixed() {
+        let mut input = Vec::new();
+        write_frame(&mut input, Framing::Line, r#"{"id":1}"#).unwrap();
+        write_frame(&mut input, Framing::ContentLength, "{\"id\":\n2}").unwrap();
+        input.extend_from_slice(b"\r\ncontent-length: 8\r\nContent-Type: json\r\n\r\n{\"id\":3}");
+
+        assert_eq!(
+            frames(&input, 1024),
+            vec![
+                (Framing::Line, Frame::Message(br#"{"id":1}"#.to_vec())),
+                (
+                    Framing::ContentLength,
+                    Frame::Message(b"{\"id\":\n2}".to_vec())
+                ),
+                (
+                    Framing::ContentLength,
+                    Frame::Message(br#"{"id":3}"#.to_vec())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_and_invalid_frames_are_skipped() {
+        let long = "x".repeat(100);
+        let input = format!(
+            "{long}\nContent-Length: 100\r\n\r\n{long}Content-Length: lots\r\n\r\n{{}}\nok\n"
+        );
+        assert_eq!(
+            frames(input.as_bytes(), 10),
+            vec![
+                (Framing::Line, Frame::TooLarge(100)),
+                (Framing::ContentLength, Frame::TooLarge(100)),
+                (
+                    Framing::ContentLength,
+                    Frame::InvalidHeader("Content-Length: lots".to_string())
+                ),
+                (Framing::Line, Frame::Message(b"{}".to_vec())),
+                (Framing::Line, Frame::Message(b"ok".to_vec())),
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_messages_survive_timeouts() {
+        struct Trickle(Vec<&'static [u8]>);
+        impl Read for Trickle {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.first_mut() {
+                    None => Ok(0),
+                    Some(&mut []) => {
+                        self.0.remove(0);
+                        Err(ErrorKind::WouldBlock.into())
+                    }
+                    Some(chunk) => {
+                        let n = chunk.len().min(buf.len());
+                        buf[..n].copy_from_slice(&chunk[..n]);
+                        *chunk = &chunk[n..];
+                        Ok(n)
+                    }
+                }
+            }
+        }
+
+        let mut source = Trickle(vec![b"Content-Len", b"", b"gth: 2\r\n\r\n{", b"", b"}"]);
+        let mut reader = FrameReader::new(1024);
+        let mut results = Vec::new();
+        loop {
+            match reader.next_frame(&mut source) {
+                Ok(Some(frame)) => results.push(frame),
+                Ok(None) => break,
+                Err(e) => assert_eq!(e.kind(), ErrorKind::WouldBlock),
+            }
+        }
+        assert_eq!(
+            results,
+            vec![(Framing::ContentLength, Frame::Message(b"{}".to_vec()))]
+        );
+    }
+}
@@ -1,5 +1,13 @@
 pub mod debug_server;
+pub mod framing;
 pub mod protocol;
+mod session;
+pub mod tls;
+pub mod transcript;
+pub mod websocket;
 
 pub use debug_server::DebugServer;
-pub use protocol::{DebugMessage, DebugRequest, DebugResponse};
+pub use protocol::{
+    ContractEventInfo, DebugEvent, DebugMessage, DebugRequest, DebugResponse, InstructionLine,
+    InstructionPosition, Scope, SessionInfo, StepKind, StorageChange, ValueInfo,
+};
@@ -1,9 +1,111 @@
+//! Wire protocol for remote debugging.
+//!
+//! # Compatibility
+//!
+//! Clients open with a [`DebugRequest::Handshake`] carrying their
+//! [`PROTOCOL_VERSION`] and [`Capabilities`]; the server answers with the
+//! version both will speak and the capabilities both support.
+//!
+//! - Adding a request, response, event or optional field is not a breaking
+//!   change and keeps the version. Anything a peer has to opt into, like
+//!   unsolicited events, gets a capability instead.
+//! - Removing or renaming a message or field, or changing what one means,
+//!   bumps [`PROTOCOL_VERSION`]. Servers keep accepting versions back to
+//!   [`MIN_PROTOCOL_VERSION`].
+//! - Unknown fields are ignored. A request the server doesn't know gets a
+//!   [`DebugResponse::Unsupported`] reply instead of closing the connection,
+//!   and clients skip events they don't know.
+//! - Clients that never send a handshake are treated as version 1 with no
+//!   capabilities, so they never receive messages they didn't ask for.
+//!
+//! # Errors and cancellation
+//!
+//! A message that can't be read or parsed gets a [`DebugResponse::Error`]
+//! carrying its ID, or ID 0 when it has none, and the connection stays open.
+//! See [`framing`](super::framing) for how messages are delimited and sized.
+//!
+//! [`DebugRequest::Cancel`] drops a request that is still waiting its turn,
+//! which then answers [`DebugResponse::Cancelled`]. A request already running
+//! finishes and answers normally; the Soroban host can't be interrupted
+//! mid-invocation.
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Protocol version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest protocol version this build still accepts
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Optional features a peer supports. Missing fields deserialize as
+/// unsupported, so older peers simply don't advertise newer features.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Capabilities {
+    pub conditional_breakpoints: bool,
+    pub time_travel: bool,
+    pub memory_inspection: bool,
+    pub sessions: bool,
+    /// Unsolicited [`DebugEvent`] messages
+    pub events: bool,
+    /// `Content-Length` framing for requests, see [`framing`](super::framing)
+    pub content_length: bool,
+    /// Instruction stepping, locals and instruction context requests
+    pub instruction_stepping: bool,
+}
+
+impl Capabilities {
+    /// Everything this build supports
+    pub fn current() -> Self {
+        Self {
+            conditional_breakpoints: false,
+            time_travel: false,
+            memory_inspection: true,
+            sessions: true,
+            events: true,
+            content_length: true,
+            instruction_stepping: true,
+        }
+    }
+
+    /// Capabilities supported by both `self` and `other`
+    pub fn intersect(self, other: Self) -> Self {
+        Self {
+            conditional_breakpoints: self.conditional_breakpoints && other.conditional_breakpoints,
+            time_travel: self.time_travel && other.time_travel,
+            memory_inspection: self.memory_inspection && other.memory_inspection,
+            sessions: self.sessions && other.sessions,
+            events: self.events && other.events,
+            content_length: self.content_length && other.content_length,
+            instruction_stepping: self.instruction_stepping && other.instruction_stepping,
+        }
+    }
+}
+
+/// What an authenticated connection may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Full control of its sessions
+    Controller,
+    /// Watch a session other clients control: receive its events and send
+    /// [read-only](DebugRequest::is_read_only) requests
+    Observer,
+}
 
 /// Wire protocol messages for remote debugging
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum DebugRequest {
+    /// Agree on a protocol version and capabilities
+    Handshake {
+        protocol_version: u32,
+        #[serde(default)]
+        capabilities: Capabilities,
+    },
+
     /// Authenticate with the server
     Authenticate { token: String },
 
@@ -22,12 +124,38 @@ pub enum DebugRequest {
     /// Continue execution
     Continue,
 
+    /// Enable instruction stepping and stop at the first instruction of
+    /// `function`
+    StartInstructionStepping {
+        function: String,
+        args: Option<String>,
+    },
+
+    /// Step by instruction
+    StepInstruction { kind: StepKind },
+
+    /// Instructions around the current one
+    GetInstructionContext { context_size: usize },
+
+    /// Locals, operand stack and source variables at the current instruction
+    GetLocals,
+
     /// Inspect current state
     Inspect,
 
     /// Get storage state
     GetStorage,
 
+    /// Read one storage entry; `storage` is `instance`, `persistent` or
+    /// `temporary`
+    GetStorageEntry {
+        storage: String,
+        key: serde_json::Value,
+    },
+
+    /// Contract events emitted by the last invocation
+    GetContractEvents,
+
     /// Get call stack
     GetStack,
 
@@ -49,6 +177,50 @@ pub enum DebugRequest {
     /// Load network snapshot
     LoadSnapshot { snapshot_path: String },
 
+    /// Load a contract sent by the client, for servers that don't share its
+    /// filesystem. `sha256` is checked against the decoded bytes.
+    UploadContract {
+        wasm_base64: String,
+        sha256: Option<String>,
+    },
+
+    /// Load a network snapshot sent by the client, checked against `sha256`
+    UploadSnapshot {
+        snapshot_json: String,
+        sha256: Option<String>,
+    },
+
+    /// Read a range of linear memory
+    InspectMemory { address: u32, length: usize },
+
+    /// Search linear memory for a hex byte or quoted string pattern
+    SearchMemory { pattern: String },
+
+    /// Decode a value in linear memory (`utf8` or an integer type like `u32`)
+    DecodeMemory {
+        address: u32,
+        kind: String,
+        max_len: Option<usize>,
+    },
+
+    /// Get linear memory size and growth
+    GetMemoryInfo,
+
+    /// Create a named session and attach to it
+    CreateSession { name: Option<String> },
+
+    /// Attach to an existing session, e.g. after reconnecting
+    AttachSession { session_id: String },
+
+    /// List sessions on the server
+    ListSessions,
+
+    /// Destroy a session
+    DestroySession { session_id: String },
+
+    /// Cancel an earlier request on this connection that hasn't started yet
+    Cancel { request_id: u64 },
+
     /// Ping to check connection
     Ping,
 
@@ -56,12 +228,64 @@ pub enum DebugRequest {
     Disconnect,
 }
 
+impl DebugRequest {
+    /// Whether the request only reads server state, so observers may send it
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Self::Inspect
+                | Self::GetStorage
+                | Self::GetStorageEntry { .. }
+                | Self::GetContractEvents
+                | Self::GetInstructionContext { .. }
+                | Self::GetLocals
+                | Self::GetStack
+                | Self::GetBudget
+                | Self::ListBreakpoints
+                | Self::InspectMemory { .. }
+                | Self::SearchMemory { .. }
+                | Self::DecodeMemory { .. }
+                | Self::GetMemoryInfo
+                | Self::ListSessions
+                | Self::Ping
+        )
+    }
+
+    /// Whether the server runs the request in the connection's session
+    pub fn uses_session(&self) -> bool {
+        !matches!(
+            self,
+            Self::Handshake { .. }
+                | Self::Authenticate { .. }
+                | Self::Ping
+                | Self::Cancel { .. }
+                | Self::CreateSession { .. }
+                | Self::AttachSession { .. }
+                | Self::ListSessions
+                | Self::DestroySession { .. }
+                | Self::Disconnect
+        )
+    }
+}
+
 /// Response messages from the server
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum DebugResponse {
-    /// Authentication result
-    Authenticated { success: bool, message: String },
+    /// Agreed protocol version and capabilities
+    HandshakeResult {
+        protocol_version: u32,
+        server_version: String,
+        capabilities: Capabilities,
+    },
+
+    /// Authentication result, with what the token allows
+    Authenticated {
+        success: bool,
+        message: String,
+        #[serde(default)]
+        scope: Option<Scope>,
+    },
 
     /// Contract loaded
     ContractLoaded { size: usize },
@@ -87,6 +311,28 @@ pub enum DebugResponse {
         error: Option<String>,
     },
 
+    /// Result of an instruction step; `stepped` is false when there was
+    /// nowhere to step to
+    InstructionStepped {
+        stepped: bool,
+        position: Option<InstructionPosition>,
+    },
+
+    /// Instructions around the current one
+    InstructionContext { instructions: Vec<InstructionLine> },
+
+    /// Values at the current instruction; unknown values are `None`
+    Locals {
+        function: Option<String>,
+        locals: Vec<ValueInfo>,
+        /// Operand stack, bottom first
+        value_stack: Vec<ValueInfo>,
+        /// False when the stack could not be modelled exactly
+        stack_exact: bool,
+        /// DWARF source variables
+        variables: Vec<ValueInfo>,
+    },
+
     /// Inspection result
     InspectionResult {
         function: Option<String>,
@@ -98,6 +344,16 @@ pub enum DebugResponse {
     /// Storage state
     StorageState { storage_json: String },
 
+    /// One storage entry, `None` when it isn't set
+    StorageValue {
+        storage: String,
+        key: serde_json::Value,
+        value: Option<String>,
+    },
+
+    /// Contract events emitted by the last invocation
+    ContractEvents { events: Vec<ContractEventInfo> },
+
     /// Call stack
     CallStack { stack: Vec<String> },
 
@@ -119,9 +375,59 @@ pub enum DebugResponse {
     /// Snapshot loaded
     SnapshotLoaded { summary: String },
 
+    /// Linear memory contents, hex encoded
+    MemoryContents { address: u32, hex: String },
+
+    /// Addresses matching a memory search
+    MemorySearchResult { addresses: Vec<u32> },
+
+    /// Decoded memory value
+    MemoryValue {
+        address: u32,
+        kind: String,
+        value: String,
+    },
+
+    /// Linear memory size and growth
+    MemoryInfo {
+        size_bytes: usize,
+        declared_initial_pages: u32,
+        maximum_pages: Option<u32>,
+        pages_at_entry: Option<u32>,
+        pages_at_exit: Option<u32>,
+        captured: bool,
+        capture_error: Option<String>,
+    },
+
     /// Error response
     Error { message: String },
 
+    /// The server doesn't know this kind of request
+    Unsupported { request: String },
+
+    /// The request will be answered with `Cancelled` if it hadn't started
+    CancelAccepted { request_id: u64 },
+
+    /// The request was cancelled before it ran
+    Cancelled,
+
+    /// Session created and attached
+    SessionCreated { session_id: String },
+
+    /// Attached to a session, which may be paused mid-execution
+    SessionAttached {
+        session_id: String,
+        contract: Option<String>,
+        paused: bool,
+        function: Option<String>,
+    },
+
+    /// Sessions on the server
+    SessionList { sessions: Vec<SessionInfo> },
+
+    /// Session destroyed
+    SessionDestroyed { session_id: String },
+
     /// Pong response
     Pong,
 
@@ -129,12 +435,167 @@ pub enum DebugResponse {
     Disconnected,
 }
 
+/// Messages the server sends without being asked, after the response to
+/// the request that caused them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type")]
+pub enum DebugEvent {
+    /// Execution paused, e.g. at a breakpoint or after a step
+    Stopped {
+        reason: String,
+        function: Option<String>,
+    },
+
+    /// A line of output; `category` is `log` for the contract's `log!` lines
+    Output { category: String, output: String },
+
+    /// The set of breakpoints changed
+    BreakpointChanged { breakpoints: Vec<String> },
+
+    /// An invocation finished
+    ExecutionComplete {
+        function: String,
+        success: bool,
+        output: Option<String>,
+        error: Option<String>,
+    },
+
+    /// Contract storage entries changed
+    StorageChanged { changes: Vec<StorageChange> },
+}
+
+/// Summary of a session on the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SessionInfo {
+    pub id: String,
+    pub contract: Option<String>,
+    /// Whether a client is connected to the session
+    pub attached: bool,
+    /// Number of observers watching the session
+    #[serde(default)]
+    pub observers: usize,
+    pub paused: bool,
+    /// Seconds since the session was last used
+    pub idle_secs: u64,
+}
+
+/// How [`DebugRequest::StepInstruction`] moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StepKind {
+    /// To the next instruction, following calls into other contracts
+    Into,
+    /// To the next instruction in this function
+    Over,
+    /// Out of the current function
+    Out,
+    /// To the next basic block
+    Block,
+    /// Back to the previous instruction
+    Back,
+}
+
+/// Where instruction stepping is paused
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct InstructionPosition {
+    /// Index into the module's instructions
+    pub index: usize,
+    pub function_index: u32,
+    /// Offset and disassembly, e.g. `0000002a: local.get 0`
+    pub instruction: String,
+    pub function: Option<String>,
+    /// Contract being stepped through, which is a callee when `depth` > 0
+    pub contract_id: Option<String>,
+    pub depth: usize,
+}
+
+/// One instruction of [`DebugResponse::InstructionContext`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct InstructionLine {
+    pub index: usize,
+    pub instruction: String,
+    pub current: bool,
+}
+
+/// A local, stack slot or source variable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ValueInfo {
+    pub name: Option<String>,
+    pub value_type: Option<String>,
+    pub value: Option<String>,
+}
+
+/// A contract event, with topics and data formatted as values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ContractEventInfo {
+    pub contract_id: Option<String>,
+    pub topics: Vec<String>,
+    pub data: String,
+}
+
+/// One storage entry that changed; `before` or `after` is `None` when the
+/// entry was created or removed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct StorageChange {
+    pub storage: String,
+    pub key: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl fmt::Display for DebugEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stopped { reason, function } => match function {
+                Some(function) => write!(f, "Stopped ({}) in {}", reason, function),
+                None => write!(f, "Stopped ({})", reason),
+            },
+            Self::Output { category, output } => write!(f, "[{}] {}", category, output),
+            Self::BreakpointChanged { breakpoints } => {
+                write!(f, "Breakpoints: {}", breakpoints.join(", "))
+            }
+            Self::ExecutionComplete {
+                function,
+                success,
+                output,
+                error,
+            } => match (success, output, error) {
+                (true, Some(output), _) => write!(f, "{} returned {}", function, output),
+                (false, _, Some(error)) => write!(f, "{} failed: {}", function, error),
+                _ => write!(f, "{} finished", function),
+            },
+            Self::StorageChanged { changes } => {
+                write!(f, "Storage changed:")?;
+                for change in changes {
+                    let before = change.before.as_deref().unwrap_or("-");
+                    let after = change.after.as_deref().unwrap_or("-");
+                    write!(
+                        f,
+                        "\n  {} {}: {} -> {}",
+                        change.storage, change.key, before, after
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 /// Message wrapper for the protocol
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DebugMessage {
     pub id: u64,
     pub request: Option<DebugRequest>,
     pub response: Option<DebugResponse>,
+    /// Set on server-pushed events, which have no request; their `id` is 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<DebugEvent>,
+}
+
+/// JSON schema of [`DebugMessage`] and everything in it, as published in
+/// `docs/remote-protocol.schema.json`
+pub fn json_schema() -> schemars::schema::RootSchema {
+    schemars::schema_for!(DebugMessage)
 }
 
 impl DebugMessage {
@@ -143,6 +604,7 @@ impl DebugMessage {
             id,
             request: Some(request),
             response: None,
+            event: None,
         }
     }
 
@@ -151,6 +613,79 @@ impl DebugMessage {
             id,
             request: None,
             response: Some(response),
+            event: None,
+        }
+    }
+
+    pub fn event(event: DebugEvent) -> Self {
+        Self {
+            id: 0,
+            request: None,
+            response: None,
+            event: Some(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_round_trip_and_older_messages_still_parse() {
+        let message = DebugMessage::event(DebugEvent::Stopped {
+            reason: "breakpoint".to_string(),
+            function: Some("transfer".to_string()),
+        });
+        let json = serde_json::to_string(&message).unwrap();
+        assert!(json.contains(r#""type":"Stopped""#));
+        let parsed: DebugMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.event, message.event);
+
+        let json = serde_json::to_string(&DebugMessage::response(3, DebugResponse::Pong)).unwrap();
+        assert!(!json.contains("event"));
+        let parsed: DebugMessage =
+            serde_json::from_str(r#"{"id":1,"request":{"type":"Ping"},"response":null}"#).unwrap();
+        assert!(parsed.event.is_none());
+    }
+
+    #[test]
+    fn capabilities_missing_from_older_peers_are_unsupported() {
+        let request: DebugRequest = serde_json::from_str(
+            r#"{"type":"Handshake","protocol_version":1,"capabilities":{"events":true,"future":true}}"#,
+        )
+        .unwrap();
+        let DebugRequest::Handshake { capabilities, .. } = request else {
+            panic!("expected a handshake");
+        };
+        assert!(capabilities.events);
+        assert!(!capabilities.memory_inspection);
+
+        let agreed = capabilities.intersect(Capabilities::current());
+        assert_eq!(
+            agreed,
+            Capabilities {
+                events: true,
+                ..Capabilities::default()
+            }
+        );
+    }
+
+    #[test]
+    fn the_published_schema_is_up_to_date() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/docs/remote-protocol.schema.json"
+        );
+        let schema = serde_json::to_string_pretty(&json_schema()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(path, &schema).unwrap();
         }
+        let published = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            published == schema,
+            "{} is out of date; rerun with UPDATE_SCHEMA=1",
+            path
+        );
     }
 }
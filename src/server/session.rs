@@ -0,0 +1,372 @@
+use crate::debugger::engine::DebuggerEngine;
+use crate::server::protocol::{DebugEvent, SessionInfo};
+use crate::simulator::SnapshotLoader;
+use crate::{DebuggerError, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A debugging session: a loaded contract and its engine
+#[derive(Default)]
+pub(crate) struct Session {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) engine: Option<Arc<Mutex<DebuggerEngine>>>,
+    /// Path of the loaded contract, or the hash of an uploaded one
+    pub(crate) contract: Option<String>,
+    /// SHA-256 of the loaded contract
+    pub(crate) wasm_sha256: Option<String>,
+    /// Network snapshot installed in every contract loaded into the session
+    pub(crate) snapshot: Option<SnapshotLoader>,
+    /// Events to push after the current response
+    pub(crate) events: Vec<DebugEvent>,
+}
+
+impl Session {
+    /// Whether execution is paused, and in which function
+    pub(crate) fn position(&self) -> Result<(bool, Option<String>)> {
+        let Some(engine) = &self.engine else {
+            return Ok((false, None));
+        };
+        let engine = engine
+            .lock()
+            .map_err(|e| DebuggerError::ExecutionError(format!("Failed to lock engine: {}", e)))?;
+        let state = engine.state();
+        let state = state
+            .lock()
+            .map_err(|e| DebuggerError::ExecutionError(format!("Failed to lock state: {}", e)))?;
+        Ok((
+            engine.is_paused(),
+            state.current_function().map(str::to_string),
+        ))
+    }
+
+    fn summary(&self) -> SessionSummary {
+        let (paused, function) = self.position().unwrap_or((false, None));
+        SessionSummary {
+            contract: self.contract.clone(),
+            wasm_sha256: self.wasm_sha256.clone(),
+            paused,
+            function,
+        }
+    }
+}
+
+/// What other threads see of a session, as of its last request
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SessionSummary {
+    pub(crate) contract: Option<String>,
+    pub(crate) wasm_sha256: Option<String>,
+    pub(crate) paused: bool,
+    pub(crate) function: Option<String>,
+}
+
+/// Work for a session's thread. It returns how to answer, which is done
+/// once the session's summary is up to date.
+pub(crate) type Job = Box<dyn FnOnce(&mut Session) -> Box<dyn FnOnce() + Send> + Send>;
+
+/// A thread that owns one session. Engines are not `Send`, so the session
+/// lives and runs its requests there, and a long one only holds up its own
+/// session. The thread ends once its jobs are done and the worker dropped.
+pub(crate) struct SessionWorker {
+    jobs: mpsc::Sender<Job>,
+    summary: Arc<Mutex<SessionSummary>>,
+}
+
+impl SessionWorker {
+    fn spawn() -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let summary = Arc::new(Mutex::new(SessionSummary::default()));
+        let published = summary.clone();
+        std::thread::spawn(move || {
+            let mut session = Session::default();
+            for job in queue {
+                let answer = job(&mut session);
+                if let Ok(mut summary) = published.lock() {
+                    *summary = session.summary();
+                }
+                answer();
+            }
+        });
+        Self { jobs, summary }
+    }
+
+    /// Queue `job` behind the session's earlier ones
+    pub(crate) fn run(&self, job: Job) -> Result<()> {
+        self.jobs.send(job).map_err(|_| {
+            DebuggerError::ExecutionError("Session thread has stopped".to_string()).into()
+        })
+    }
+
+    pub(crate) fn summary(&self) -> SessionSummary {
+        self.summary
+            .lock()
+            .map(|summary| summary.clone())
+            .unwrap_or_default()
+    }
+}
+
+struct ManagedSession {
+    session: SessionWorker,
+    /// Connection the session is attached to
+    owner: Option<u64>,
+    /// Read-only connections watching the session
+    observers: BTreeSet<u64>,
+    /// Created implicitly for a client that never asked for a session;
+    /// destroyed when that client disconnects
+    ephemeral: bool,
+    last_active: Instant,
+}
+
+/// Named sessions that outlive the connections using them
+pub(crate) struct SessionManager {
+    sessions: BTreeMap<String, ManagedSession>,
+    idle_timeout: Option<Duration>,
+    next_id: u64,
+}
+
+impl SessionManager {
+    pub(crate) fn new(idle_timeout: Option<Duration>) -> Self {
+        Self {
+            sessions: BTreeMap::new(),
+            idle_timeout,
+            next_id: 1,
+        }
+    }
+
+    /// Create a session attached to `owner`. Without a name, an ID like
+    /// `session-1` is generated.
+    pub(crate) fn create(
+        &mut self,
+        name: Option<String>,
+        owner: u64,
+        ephemeral: bool,
+    ) -> Result<String> {
+        let id = match name {
+            Some(name) if name.is_empty() => {
+                return Err(miette::miette!("Session name must not be empty"))
+            }
+            Some(name) if self.sessions.contains_key(&name) => {
+                return Err(miette::miette!("Session '{}' already exists", name))
+            }
+            Some(name) => name,
+            None => loop {
+                let id = format!("session-{}", self.next_id);
+                self.next_id += 1;
+                if !self.sessions.contains_key(&id) {
+                    break id;
+                }
+            },
+        };
+        self.sessions.insert(
+            id.clone(),
+            ManagedSession {
+                session: SessionWorker::spawn(),
+                owner: Some(owner),
+                observers: BTreeSet::new(),
+                ephemeral,
+                last_active: Instant::now(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Attach `owner` to a session, taking it over from any other
+    /// connection (which may be a dead tunnel that never closed).
+    pub(crate) fn attach(&mut self, id: &str, owner: u64) -> Result<&SessionWorker> {
+        let managed = self
+            .sessions
+            .get_mut(id)
+            .ok_or_else(|| miette::miette!("No session named '{}'", id))?;
+        managed.owner = Some(owner);
+        managed.ephemeral = false;
+        managed.last_active = Instant::now();
+        Ok(&managed.session)
+    }
+
+    /// The session `owner` is attached to, marking it active.
+    pub(crate) fn get_mut(&mut self, id: &str, owner: u64) -> Result<&SessionWorker> {
+        let managed = self
+            .sessions
+            .get_mut(id)
+            .ok_or_else(|| miette::miette!("Session '{}' no longer exists", id))?;
+        if managed.owner != Some(owner) {
+            return Err(miette::miette!(
+                "Session '{}' was attached by another client",
+                id
+            ));
+        }
+        managed.last_active = Instant::now();
+        Ok(&managed.session)
+    }
+
+    /// Add `observer` to a session's observers, leaving its owner in control.
+    pub(crate) fn observe(&mut self, id: &str, observer: u64) -> Result<&SessionWorker> {
+        let managed = self
+            .sessions
+            .get_mut(id)
+            .ok_or_else(|| miette::miette!("No session named '{}'", id))?;
+        managed.observers.insert(observer);
+        Ok(&managed.session)
+    }
+
+    /// The session `observer` is watching. Observing doesn't count as
+    /// activity, so an abandoned session still expires.
+    pub(crate) fn get_observed(&mut self, id: &str, observer: u64) -> Result<&SessionWorker> {
+        let managed = self
+            .sessions
+            .get_mut(id)
+            .ok_or_else(|| miette::miette!("Session '{}' no longer exists", id))?;
+        if !managed.observers.contains(&observer) {
+            return Err(miette::miette!("Not observing session '{}'", id));
+        }
+        Ok(&managed.session)
+    }
+
+    /// Connections observing a session
+    pub(crate) fn observers(&self, id: &str) -> Vec<u64> {
+        self.sessions
+            .get(id)
+            .map(|managed| managed.observers.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// A session's summary, without touching its owner or activity
+    pub(crate) fn summary(&self, id: &str) -> Option<SessionSummary> {
+        self.sessions
+            .get(id)
+            .map(|managed| managed.session.summary())
+    }
+
+    pub(crate) fn destroy(&mut self, id: &str) -> bool {
+        self.sessions.remove(id).is_some()
+    }
+
+    /// Detach `owner` from the sessions it owns or observes, destroying the
+    /// ephemeral ones it owns.
+    pub(crate) fn release(&mut self, owner: u64) {
+        self.sessions
+            .retain(|_, managed| !(managed.ephemeral && managed.owner == Some(owner)));
+        for managed in self.sessions.values_mut() {
+            managed.observers.remove(&owner);
+            if managed.owner == Some(owner) {
+                managed.owner = None;
+                managed.last_active = Instant::now();
+            }
+        }
+    }
+
+    /// Destroy detached sessions idle for longer than the timeout, returning
+    /// their IDs. An attached client may just be thinking.
+    pub(crate) fn expire_idle(&mut self) -> Vec<String> {
+        let Some(timeout) = self.idle_timeout else {
+            return Vec::new();
+        };
+        let expired: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|(_, managed)| {
+                managed.owner.is_none() && managed.last_active.elapsed() > timeout
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            self.sessions.remove(id);
+        }
+        expired
+    }
+
+    pub(crate) fn list(&self) -> Vec<SessionInfo> {
+        self.sessions
+            .iter()
+            .map(|(id, managed)| {
+                let summary = managed.session.summary();
+                SessionInfo {
+                    id: id.clone(),
+                    contract: summary.contract,
+                    attached: managed.owner.is_some(),
+                    observers: managed.observers.len(),
+                    paused: summary.paused,
+                    idle_secs: managed.last_active.elapsed().as_secs(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_survive_their_connection_and_can_be_taken_over() {
+        let mut sessions = SessionManager::new(None);
+        let named = sessions.create(Some("ci".to_string()), 1, false).unwrap();
+        let implicit = sessions.create(None, 1, true).unwrap();
+        assert_eq!(implicit, "session-1");
+        assert!(sessions.create(Some("ci".to_string()), 2, false).is_err());
+
+        sessions.release(1);
+        let list = sessions.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].id, "ci");
+        assert!(!list[0].attached);
+
+        assert!(sessions.get_mut(&named, 2).is_err());
+        sessions.attach(&named, 2).unwrap();
+        assert!(sessions.get_mut(&named, 2).is_ok());
+
+        // A new connection takes the session over from a stale one
+        sessions.attach(&named, 3).unwrap();
+        assert!(sessions.get_mut(&named, 2).is_err());
+        assert!(sessions.get_mut(&named, 3).is_ok());
+
+        assert!(sessions.destroy(&named));
+        assert!(sessions.attach(&named, 3).is_err());
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let mut sessions = SessionManager::new(Some(Duration::ZERO));
+        sessions.create(Some("ci".to_string()), 1, false).unwrap();
+        sessions.release(1);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(sessions.expire_idle(), vec!["ci".to_string()]);
+        assert!(sessions.list().is_empty());
+
+        let mut sessions = SessionManager::new(None);
+        sessions.create(Some("ci".to_string()), 1, false).unwrap();
+        assert!(sessions.expire_idle().is_empty());
+    }
+
+    #[test]
+    fn idle_sessions_with_an_owner_are_kept() {
+        let mut sessions = SessionManager::new(Some(Duration::ZERO));
+        let id = sessions.create(Some("ci".to_string()), 1, false).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(sessions.expire_idle().is_empty());
+        assert!(sessions.get_mut(&id, 1).is_ok());
+
+        // Once its owner is gone, it expires like any other
+        sessions.release(1);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(sessions.expire_idle(), vec![id]);
+    }
+
+    #[test]
+    fn observers_watch_without_taking_over() {
+        let mut sessions = SessionManager::new(None);
+        let id = sessions.create(Some("ci".to_string()), 1, false).unwrap();
+        assert!(sessions.get_observed(&id, 2).is_err());
+
+        sessions.observe(&id, 2).unwrap();
+        assert!(sessions.get_observed(&id, 2).is_ok());
+        assert!(sessions.get_mut(&id, 2).is_err());
+        assert!(sessions.get_mut(&id, 1).is_ok());
+        assert_eq!(sessions.observers(&id), vec![2]);
+        assert_eq!(sessions.list()[0].observers, 1);
+
+        sessions.release(2);
+        assert!(sessions.observers(&id).is_empty());
+        assert!(sessions.get_mut(&id, 1).is_ok());
+    }
+}
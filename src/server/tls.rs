@@ -0,0 +1,282 @@
+//! TLS for the debug server and `RemoteClient`.
+
+use crate::{DebuggerError, Result};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// How `RemoteClient` verifies the server, and the certificate it presents
+/// when the server requires one
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// Trust only this CA (PEM) instead of the system roots
+    pub ca: Option<PathBuf>,
+    /// Accept only a server certificate with this SHA-256 fingerprint (hex)
+    pub pin: Option<String>,
+    /// Client certificate (PEM) for mutual TLS
+    pub cert: Option<PathBuf>,
+    /// Private key (PEM) for `cert`
+    pub key: Option<PathBuf>,
+}
+
+/// Server configuration for `cert` and `key`. With `client_ca`, clients
+/// must present a certificate signed by it.
+pub fn server_config(
+    cert: &Path,
+    key: &Path,
+    client_ca: Option<&Path>,
+) -> Result<Arc<ServerConfig>> {
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match client_ca {
+        Some(ca) => builder
+            .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(load_roots(ca)?).boxed()),
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder
+        .with_single_cert(load_certs(cert)?, load_key(key)?)
+        .map_err(|e| tls_error(format!("Invalid server certificate or key: {}", e)))?;
+    Ok(Arc::new(config))
+}
+
+/// Client configuration for `options`
+pub fn client_config(options: &TlsOptions) -> Result<Arc<ClientConfig>> {
+    let verifier: Arc<dyn ServerCertVerifier> = match (&options.pin, &options.ca) {
+        (Some(pin), _) => {
+            let fingerprint = hex::decode(pin.trim().replace(':', ""))
+                .ok()
+                .filter(|bytes| bytes.len() == 32)
+                .ok_or_else(|| tls_error(format!("Invalid SHA-256 fingerprint: {}", pin)))?;
+            Arc::new(PinnedCertVerifier { fingerprint })
+        }
+        (None, Some(ca)) => Arc::new(WebPkiVerifier::new(load_roots(ca)?, None)),
+        (None, None) => Arc::new(WebPkiVerifier::new(native_roots()?, None)),
+    };
+    let builder = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(verifier);
+    let config = match (&options.cert, &options.key) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(load_certs(cert)?, load_key(key)?)
+            .map_err(|e| tls_error(format!("Invalid client certificate or key: {}", e)))?,
+        (None, None) => builder.with_no_client_auth(),
+        _ => {
+            return Err(tls_error(
+                "A client certificate needs both a certificate and a key".to_string(),
+            ))
+        }
+    };
+    Ok(Arc::new(config))
+}
+
+/// The name to check the server's certificate against, from `host:port`
+pub fn server_name(addr: &str) -> Result<ServerName> {
+    let host = addr
+        .rsplit_once(':')
+        .map_or(addr, |(host, _)| host)
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    ServerName::try_from(host)
+        .map_err(|e| tls_error(format!("Invalid server name {}: {}", host, e)))
+}
+
+/// Hex SHA-256 fingerprint of a DER certificate, as used by [`TlsOptions::pin`]
+pub fn fingerprint(cert: &[u8]) -> String {
+    hex::encode(Sha256::digest(cert))
+}
+
+/// Accepts exactly one server certificate, whoever signed it
+struct PinnedCertVerifier {
+    fingerprint: Vec<u8>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        if Sha256::digest(&end_entity.0).as_slice() == self.fingerprint.as_slice() {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(format!(
+                "Server certificate fingerprint {} does not match the pinned one",
+                fingerprint(&end_entity.0)
+            )))
+        }
+    }
+}
+
+fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let certs = rustls_pemfile::certs(&mut open(path)?).map_err(|e| {
+        tls_error(format!(
+            "Failed to read certificates from {:?}: {}",
+            path, e
+        ))
+    })?;
+    if certs.is_empty() {
+        return Err(tls_error(format!("No certificates found in {:?}", path)));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(path: &Path) -> Result<PrivateKey> {
+    let mut reader = open(path)?;
+    loop {
+        let item = rustls_pemfile::read_one(&mut reader)
+            .map_err(|e| tls_error(format!("Failed to read private key from {:?}: {}", path, e)))?;
+        match item {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => {}
+            None => return Err(tls_error(format!("No private key found in {:?}", path))),
+        }
+    }
+}
+
+fn load_roots(path: &Path) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots
+            .add(&cert)
+            .map_err(|e| tls_error(format!("Invalid CA certificate in {:?}: {}", path, e)))?;
+    }
+    Ok(roots)
+}
+
+fn native_roots() -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    let certs = rustls_native_certs::load_native_certs()
+        .map_err(|e| tls_error(format!("Failed to load system certificates: {}", e)))?;
+    for cert in certs {
+        // Skip system certificates webpki can't parse
+        let _ = roots.add(&Certificate(cert.0));
+    }
+    Ok(roots)
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| DebuggerError::FileError(format!("Failed to open {:?}: {}", path, e)).into())
+}
+
+fn tls_error(message: String) -> miette::Report {
+    miette::miette!("{}", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::RemoteClient;
+    use crate::server::DebugServer;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa};
+    use std::net::TcpListener;
+
+    struct Pki {
+        dir: tempfile::TempDir,
+    }
+
+    impl Pki {
+        /// A CA with a `localhost` server certificate and a client
+        /// certificate signed by it
+        fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let mut ca_params = CertificateParams::new(vec![]);
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = rcgen::Certificate::from_params(ca_params).unwrap();
+            std::fs::write(dir.path().join("ca.pem"), ca.serialize_pem().unwrap()).unwrap();
+            for (name, san) in [("server", "localhost"), ("client", "client")] {
+                let cert =
+                    rcgen::Certificate::from_params(CertificateParams::new(vec![san.to_string()]))
+                        .unwrap();
+                let pem = cert.serialize_pem_with_signer(&ca).unwrap();
+                std::fs::write(dir.path().join(format!("{}.pem", name)), pem).unwrap();
+                std::fs::write(
+                    dir.path().join(format!("{}.key", name)),
+                    cert.serialize_private_key_pem(),
+                )
+                .unwrap();
+            }
+            Self { dir }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.path().join(name)
+        }
+
+        /// Start a TLS server and return its address
+        fn serve(&self, client_ca: bool) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = format!("localhost:{}", listener.local_addr().unwrap().port());
+            let mut server = DebugServer::new(0, None)
+                .with_tls(self.path("server.pem"), self.path("server.key"));
+            if client_ca {
+                server = server.with_client_ca(self.path("ca.pem"));
+            }
+            std::thread::spawn(move || server.serve(listener));
+            addr
+        }
+    }
+
+    #[test]
+    fn clients_verify_the_server_by_ca_or_pinned_fingerprint() {
+        let pki = Pki::new();
+        let addr = pki.serve(false);
+
+        let with_ca = TlsOptions {
+            ca: Some(pki.path("ca.pem")),
+            ..TlsOptions::default()
+        };
+        RemoteClient::connect_tls(&addr, None, &with_ca)
+            .unwrap()
+            .ping()
+            .unwrap();
+
+        let server_cert = &load_certs(&pki.path("server.pem")).unwrap()[0];
+        let pinned = TlsOptions {
+            pin: Some(fingerprint(&server_cert.0)),
+            ..TlsOptions::default()
+        };
+        RemoteClient::connect_tls(&addr, None, &pinned)
+            .unwrap()
+            .ping()
+            .unwrap();
+
+        let wrong_pin = TlsOptions {
+            pin: Some("00".repeat(32)),
+            ..TlsOptions::default()
+        };
+        assert!(RemoteClient::connect_tls(&addr, None, &wrong_pin).is_err());
+        assert!(RemoteClient::connect(&addr, None).is_err());
+    }
+
+    #[test]
+    fn servers_can_require_client_certificates() {
+        let pki = Pki::new();
+        let addr = pki.serve(true);
+
+        let mut options = TlsOptions {
+            ca: Some(pki.path("ca.pem")),
+            ..TlsOptions::default()
+        };
+        assert!(RemoteClient::connect_tls(&addr, None, &options).is_err());
+
+        options.cert = Some(pki.path("client.pem"));
+        options.key = Some(pki.path("client.key"));
+        RemoteClient::connect_tls(&addr, None, &options)
+            .unwrap()
+            .ping()
+            .unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+//! Transcripts of debug server traffic.
+//!
+//! With `--transcript`, the server appends one JSON line per request: when
+//! it was handled, the connection it came from, the request, the messages
+//! sent back and the SHA-256 of the contract loaded in the session.
+//! `soroban-debug remote --replay` sends the requests to another server and
+//! compares the answers.
+
+use crate::server::protocol::{DebugMessage, DebugRequest};
+use crate::{DebuggerError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Placeholder for tokens, which are never written to a transcript
+pub const REDACTED: &str = "<redacted>";
+
+/// One request and what the server answered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    /// When the request was handled (RFC 3339)
+    pub timestamp: String,
+    /// Server-assigned connection number
+    pub connection: u64,
+    pub request: DebugMessage,
+    /// The response followed by any events
+    pub responses: Vec<DebugMessage>,
+    /// SHA-256 of the contract loaded in the session afterwards
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm_sha256: Option<String>,
+}
+
+/// Appends entries to a transcript file
+pub(crate) struct TranscriptWriter {
+    file: BufWriter<File>,
+}
+
+impl TranscriptWriter {
+    pub(crate) fn create(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                DebuggerError::FileError(format!("Failed to open transcript {:?}: {}", path, e))
+            })?;
+        Ok(Self {
+            file: BufWriter::new(file),
+        })
+    }
+
+    /// Write one entry and flush it, so a crash loses nothing
+    pub(crate) fn record(&mut self, mut entry: TranscriptEntry) -> Result<()> {
+        if let Some(DebugRequest::Authenticate { token }) = &mut entry.request.request {
+            *token = REDACTED.to_string();
+        }
+        let json = serde_json::to_string(&entry).map_err(|e| {
+            DebuggerError::FileError(format!("Failed to serialize transcript entry: {}", e))
+        })?;
+        writeln!(self.file, "{}", json)
+            .and_then(|_| self.file.flush())
+            .map_err(|e| {
+                DebuggerError::FileError(format!("Failed to write transcript: {}", e)).into()
+            })
+    }
+}
+
+/// Read every entry of a transcript
+pub fn read_transcript(path: &Path) -> Result<Vec<TranscriptEntry>> {
+    let file = File::open(path).map_err(|e| {
+        DebuggerError::FileError(format!("Failed to open transcript {:?}: {}", path, e))
+    })?;
+    let mut entries = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| {
+            DebuggerError::FileError(format!("Failed to read transcript {:?}: {}", path, e))
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| {
+            DebuggerError::FileError(format!(
+                "Invalid transcript entry on line {} of {:?}: {}",
+                index + 1,
+                path,
+                e
+            ))
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::protocol::DebugResponse;
+
+    #[test]
+    fn entries_round_trip_without_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transcript.jsonl");
+        let mut writer = TranscriptWriter::create(&path).unwrap();
+        for (id, request) in [
+            (
+                1,
+                DebugRequest::Authenticate {
+                    token: "secret".to_string(),
+                },
+            ),
+            (2, DebugRequest::Ping),
+        ] {
+            writer
+                .record(TranscriptEntry {
+                    timestamp: "2026-01-01T00:00:00+00:00".to_string(),
+                    connection: 1,
+                    request: DebugMessage::request(id, request),
+                    responses: vec![DebugMessage::response(id, DebugResponse::Pong)],
+                    wasm_sha256: None,
+                })
+                .unwrap();
+        }
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("secret"));
+        let entries = read_transcript(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(
+            &entries[0].request.request,
+            Some(DebugRequest::Authenticate { token }) if token == REDACTED
+        ));
+        assert!(matches!(
+            entries[1].request.request,
+            Some(DebugRequest::Ping)
+        ));
+    }
+}
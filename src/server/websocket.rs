@@ -0,0 +1,379 @@
+//! JSON-RPC 2.0 over WebSocket, for browser frontends.
+//!
+//! Each [`DebugRequest`] is a method named after its `type`, with its other
+//! fields as named params. Successful responses are the [`DebugResponse`]
+//! as the `result`; `Error`, `Unsupported` and `Cancelled` responses become
+//! JSON-RPC errors. Events are `event` notifications whose params are the
+//! [`DebugEvent`](crate::server::DebugEvent).
+//!
+//! `Cancel` takes the JSON-RPC ID of the request to cancel as `request_id`.
+
+use crate::server::debug_server::{Codec, Incoming, Peer};
+use crate::server::protocol::{DebugMessage, DebugRequest, DebugResponse};
+use crate::{DebuggerError, Result};
+use serde_json::{json, Map, Value};
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use tracing::warn;
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::{CloseFrame, WebSocketConfig};
+use tungstenite::{Message, WebSocket};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Any error the debugger reports
+const SERVER_ERROR: i64 = -32000;
+/// As in the Language Server Protocol
+const REQUEST_CANCELLED: i64 = -32800;
+
+/// A JSON-RPC error to send instead of running a request
+type RpcError = (Value, i64, String);
+
+/// JSON-RPC messages on a WebSocket. Requests get internal message IDs;
+/// replies carry the client's JSON-RPC ID, or none for notifications.
+pub(crate) struct JsonRpcCodec<S: Peer> {
+    socket: WebSocket<S>,
+    next_id: u64,
+    /// JSON-RPC IDs of requests in progress, by internal ID
+    in_flight: Vec<(u64, Value)>,
+}
+
+impl<S: Peer> JsonRpcCodec<S> {
+    /// Complete the WebSocket handshake on `stream`
+    pub(crate) fn accept(stream: S, max_message_size: usize) -> Result<Self> {
+        let config = WebSocketConfig {
+            max_message_size: Some(max_message_size),
+            max_frame_size: Some(max_message_size),
+            ..WebSocketConfig::default()
+        };
+        let socket = tungstenite::accept_with_config(stream, Some(config))
+            .map_err(|e| DebuggerError::FileError(format!("WebSocket handshake failed: {}", e)))?;
+        Ok(Self {
+            socket,
+            next_id: 0,
+            in_flight: Vec::new(),
+        })
+    }
+
+    /// Turn a JSON-RPC request into a [`DebugMessage`]
+    fn decode(
+        &mut self,
+        text: &str,
+    ) -> std::result::Result<(DebugMessage, Option<Value>), RpcError> {
+        let value: Value = serde_json::from_str(text)
+            .map_err(|e| (Value::Null, PARSE_ERROR, format!("Parse error: {}", e)))?;
+        let mut object = match value {
+            Value::Object(object) => object,
+            Value::Array(_) => {
+                return Err((
+                    Value::Null,
+                    INVALID_REQUEST,
+                    "Batch requests are not supported".to_string(),
+                ))
+            }
+            _ => {
+                return Err((
+                    Value::Null,
+                    INVALID_REQUEST,
+                    "Request must be an object".to_string(),
+                ))
+            }
+        };
+        // Requests without an ID are notifications and get no response
+        let id = object.remove("id");
+        let reply_id = id.clone().unwrap_or(Value::Null);
+        if object.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Err((
+                reply_id,
+                INVALID_REQUEST,
+                "jsonrpc must be \"2.0\"".to_string(),
+            ));
+        }
+        let Some(Value::String(method)) = object.remove("method") else {
+            return Err((
+                reply_id,
+                INVALID_REQUEST,
+                "method must be a string".to_string(),
+            ));
+        };
+        let mut params = match object.remove("params") {
+            None | Some(Value::Null) => Map::new(),
+            Some(Value::Object(params)) => params,
+            Some(Value::Array(params)) if params.is_empty() => Map::new(),
+            Some(_) => {
+                return Err((
+                    reply_id,
+                    INVALID_PARAMS,
+                    "params must be an object".to_string(),
+                ))
+            }
+        };
+
+        if method == "Cancel" {
+            let target = params.get("request_id").cloned().unwrap_or(Value::Null);
+            let internal = self
+                .in_flight
+                .iter()
+                .find(|(_, id)| *id == target)
+                .map_or(0, |(internal, _)| *internal);
+            params.insert("request_id".to_string(), json!(internal));
+        }
+        params.insert("type".to_string(), Value::String(method.clone()));
+        let request: DebugRequest = serde_json::from_value(Value::Object(params)).map_err(|e| {
+            if e.to_string().starts_with("unknown variant") {
+                (
+                    reply_id.clone(),
+                    METHOD_NOT_FOUND,
+                    format!("Method not found: {}", method),
+                )
+            } else {
+                (
+                    reply_id.clone(),
+                    INVALID_PARAMS,
+                    format!("Invalid params for {}: {}", method, e),
+                )
+            }
+        })?;
+
+        self.next_id += 1;
+        if let Some(id) = &id {
+            self.in_flight.push((self.next_id, id.clone()));
+        }
+        Ok((DebugMessage::request(self.next_id, request), id))
+    }
+
+    fn send(&mut self, value: &Value) -> Result<()> {
+        self.socket
+            .send(Message::Text(value.to_string()))
+            .map_err(|e| {
+                DebuggerError::FileError(format!("Failed to write response: {}", e)).into()
+            })
+    }
+}
+
+impl<S: Peer> Codec for JsonRpcCodec<S> {
+    type Tag = Option<Value>;
+
+    fn socket(&self) -> &TcpStream {
+        self.socket.get_ref().socket()
+    }
+
+    fn read(&mut self) -> Result<Incoming<Option<Value>>> {
+        loop {
+            let text = match self.socket.read() {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        let message = format!("Message is not valid UTF-8: {}", e);
+                        self.send(&error_response(Value::Null, PARSE_ERROR, &message))?;
+                        continue;
+                    }
+                },
+                // Pings and closes are answered by tungstenite
+                Ok(_) => continue,
+                Err(tungstenite::Error::Io(e))
+                    if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                {
+                    return Ok(Incoming::Timeout)
+                }
+                Err(tungstenite::Error::Capacity(e)) => {
+                    // The rest of the frame is still on the wire, so the
+                    // connection can't be resynchronized
+                    warn!("Closing WebSocket connection: {}", e);
+                    let _ = self.socket.close(Some(CloseFrame {
+                        code: CloseCode::Size,
+                        reason: e.to_string().into(),
+                    }));
+                    let _ = self.socket.flush();
+                    return Ok(Incoming::Closed);
+                }
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed)
+                | Err(tungstenite::Error::Protocol(
+                    tungstenite::error::ProtocolError::ResetWithoutClosingHandshake,
+                )) => return Ok(Incoming::Closed),
+                Err(tungstenite::Error::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(Incoming::Closed)
+                }
+                Err(e) => {
+                    return Err(
+                        DebuggerError::FileError(format!("WebSocket read failed: {}", e)).into(),
+                    )
+                }
+            };
+            match self.decode(&text) {
+                Ok((message, id)) => return Ok(Incoming::Request(Box::new(message), id)),
+                Err((id, code, message)) => self.send(&error_response(id, code, &message))?,
+            }
+        }
+    }
+
+    fn write(&mut self, id: &Option<Value>, messages: &[DebugMessage]) -> Result<()> {
+        for message in messages {
+            let value = match (&message.event, &message.response) {
+                (Some(event), _) => json!({"jsonrpc": "2.0", "method": "event", "params": event}),
+                (None, Some(response)) => {
+                    self.in_flight
+                        .retain(|(internal, _)| *internal != message.id);
+                    match id {
+                        Some(id) => rpc_response(id.clone(), response),
+                        None => continue,
+                    }
+                }
+                (None, None) => continue,
+            };
+            self.send(&value)?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, events: &[DebugMessage]) -> Result<()> {
+        self.write(&None, events)
+    }
+}
+
+fn rpc_response(id: Value, response: &DebugResponse) -> Value {
+    match response {
+        DebugResponse::Error { message } => error_response(id, SERVER_ERROR, message),
+        DebugResponse::Unsupported { request } => error_response(
+            id,
+            METHOD_NOT_FOUND,
+            &format!("Method not found: {}", request),
+        ),
+        DebugResponse::Cancelled => error_response(id, REQUEST_CANCELLED, "Request cancelled"),
+        response => json!({"jsonrpc": "2.0", "id": id, "result": response}),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::RemoteClient;
+    use crate::runtime::test_wasm;
+    use crate::server::DebugServer;
+    use std::net::TcpListener;
+
+    struct Client {
+        socket: WebSocket<tungstenite::stream::MaybeTlsStream<TcpStream>>,
+    }
+
+    impl Client {
+        fn send(&mut self, text: &str) {
+            self.socket.send(Message::Text(text.to_string())).unwrap();
+        }
+
+        fn call(&mut self, id: u64, method: &str, params: Value) -> Value {
+            let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+            self.send(&request.to_string());
+            self.receive()
+        }
+
+        fn receive(&mut self) -> Value {
+            match self.socket.read().unwrap() {
+                Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+    }
+
+    /// Start a server with both listeners, returning the TCP and WebSocket
+    /// addresses
+    fn serve() -> (String, String) {
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let websocket = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addrs = (
+            tcp.local_addr().unwrap().to_string(),
+            websocket.local_addr().unwrap().to_string(),
+        );
+        std::thread::spawn(move || {
+            DebugServer::new(0, None).serve_with_websocket(tcp, Some(websocket))
+        });
+        addrs
+    }
+
+    fn connect(addr: &str) -> Client {
+        let (socket, _) = tungstenite::connect(format!("ws://{}", addr)).unwrap();
+        Client { socket }
+    }
+
+    #[test]
+    fn requests_map_to_json_rpc_methods() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ping.wasm");
+        std::fs::write(&path, test_wasm::ping()).unwrap();
+        let (_, addr) = serve();
+        let mut client = connect(&addr);
+
+        let reply = client.call(
+            1,
+            "Handshake",
+            json!({"protocol_version": 1, "capabilities": {"events": true}}),
+        );
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["type"], "HandshakeResult");
+        assert_eq!(reply["result"]["capabilities"]["events"], true);
+
+        let reply = client.call(2, "LoadContract", json!({"contract_path": path}));
+        assert_eq!(reply["result"]["type"], "ContractLoaded");
+
+        let reply = client.call(3, "Execute", json!({"function": "ping", "args": null}));
+        assert_eq!(reply["result"]["success"], true, "{}", reply);
+        let event = client.receive();
+        assert_eq!(event["method"], "event");
+        assert_eq!(event["params"]["type"], "ExecutionComplete");
+
+        // Notifications run without a response
+        client.send(r#"{"jsonrpc":"2.0","method":"SetBreakpoint","params":{"function":"ping"}}"#);
+        let event = client.receive();
+        assert_eq!(event["params"]["type"], "BreakpointChanged");
+        let reply = client.call(4, "ListBreakpoints", Value::Null);
+        assert_eq!(reply["result"]["breakpoints"], json!(["ping"]));
+    }
+
+    #[test]
+    fn invalid_requests_get_json_rpc_errors() {
+        let (_, addr) = serve();
+        let mut client = connect(&addr);
+
+        client.send("{not json");
+        let reply = client.receive();
+        assert_eq!(reply["error"]["code"], PARSE_ERROR);
+        assert_eq!(reply["id"], Value::Null);
+
+        let code = |reply: Value| reply["error"]["code"].as_i64().unwrap();
+        assert_eq!(code(client.call(1, "Rewind", json!({}))), METHOD_NOT_FOUND);
+        assert_eq!(
+            code(client.call(2, "Execute", json!({"function": 3}))),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(client.call(3, "Execute", json!({"function": "f"}))),
+            SERVER_ERROR
+        );
+        assert_eq!(
+            code(client.call(4, "Cancel", json!({"request_id": 3}))),
+            SERVER_ERROR
+        );
+        assert_eq!(client.call(5, "Ping", json!([]))["result"]["type"], "Pong");
+    }
+
+    #[test]
+    fn websocket_and_tcp_clients_share_sessions() {
+        let (tcp, websocket) = serve();
+        let mut client = connect(&websocket);
+        let reply = client.call(1, "CreateSession", json!({"name": "web"}));
+        assert_eq!(reply["result"]["session_id"], "web");
+
+        let mut remote = RemoteClient::connect(&tcp, None).unwrap();
+        let sessions = remote.list_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "web");
+        assert!(sessions[0].attached);
+    }
+}
@@ -0,0 +1,844 @@
+//! Conversion to and from ledger snapshots
+//!
+//! `stellar snapshot create` and soroban-sdk's `test_snapshots` store ledger
+//! state as a [`LedgerSnapshot`] of XDR entries. Importing one describes
+//! accounts, contract instances and persistent storage with the fields of
+//! [`NetworkSnapshot`] wherever those reproduce the entry exactly, and keeps
+//! every other entry, contract code included, in `ledger_entries`. Exporting
+//! it again gives back the same entries and TTLs.
+
+use super::state::{AccountState, ContractState, LedgerEntryXdr, NetworkSnapshot, SimulatorError};
+use crate::utils::ArgumentParser;
+use crate::{DebuggerError, Result};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use soroban_env_host::xdr::{
+    AccountEntry, AccountEntryExt, ContractCodeEntry, ContractCodeEntryExt, ContractDataDurability,
+    ContractDataEntry, ContractExecutable, DataEntry, DataEntryExt, ExtensionPoint, Hash,
+    LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyAccount,
+    LedgerKeyContractCode, LedgerKeyContractData, LedgerKeyData, Limits, ReadXdr, ScAddress,
+    ScContractInstance, ScMap, ScMapEntry, ScVal, SequenceNumber, String64, StringM, Thresholds,
+    WriteXdr,
+};
+use soroban_ledger_snapshot::LedgerSnapshot;
+use soroban_sdk::testutils::EnvTestConfig;
+use soroban_sdk::{Env, TryFromVal};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// A ledger entry with its TTL, as stored in a ledger snapshot
+pub(crate) type Entry = (Box<LedgerKey>, (Box<LedgerEntry>, Option<u32>));
+
+/// Passphrases of the public networks, to name imported network IDs
+const KNOWN_NETWORKS: &[&str] = &[
+    "Public Global Stellar Network ; September 2015",
+    "Test SDF Network ; September 2015",
+    "Test SDF Future Network ; October 2022",
+    "Standalone Network ; February 2017",
+];
+
+/// Parse snapshot JSON in either format: a [`NetworkSnapshot`], a
+/// [`LedgerSnapshot`], or a soroban-sdk test snapshot that holds one under
+/// `ledger`
+pub fn parse_snapshot(contents: &str) -> Result<NetworkSnapshot> {
+    let parse_error = |e: serde_json::Error| -> miette::Report {
+        DebuggerError::FileError(format!("Failed to parse snapshot JSON: {}", e)).into()
+    };
+    let value: Value = serde_json::from_str(contents).map_err(parse_error)?;
+    let is_ledger = |value: &Value| {
+        value.get("sequence_number").is_some() && value.get("ledger_entries").is_some()
+    };
+    let ledger = if is_ledger(&value) {
+        Some(value.clone())
+    } else {
+        value
+            .get("ledger")
+            .filter(|ledger| is_ledger(ledger))
+            .cloned()
+    };
+    match ledger {
+        Some(ledger) => from_ledger_snapshot(&serde_json::from_value(ledger).map_err(parse_error)?),
+        None => serde_json::from_value(value).map_err(parse_error),
+    }
+}
+
+/// Describe a ledger snapshot as a network snapshot
+pub fn from_ledger_snapshot(ledger: &LedgerSnapshot) -> Result<NetworkSnapshot> {
+    let passphrase = KNOWN_NETWORKS
+        .iter()
+        .find(|passphrase| network_id(passphrase) == ledger.network_id);
+    let mut snapshot = NetworkSnapshot::new(
+        ledger.sequence_number,
+        passphrase.copied().unwrap_or_default(),
+        ledger.timestamp,
+    );
+    let metadata = &mut snapshot.ledger;
+    if passphrase.is_none() {
+        metadata.network_id = Some(hex::encode(ledger.network_id));
+    }
+    metadata.protocol_version = Some(ledger.protocol_version);
+    metadata.base_reserve = Some(ledger.base_reserve);
+    metadata.min_persistent_entry_ttl = Some(ledger.min_persistent_entry_ttl);
+    metadata.min_temp_entry_ttl = Some(ledger.min_temp_entry_ttl);
+    metadata.max_entry_ttl = Some(ledger.max_entry_ttl);
+
+    let env = scratch_env();
+    let entries = &ledger.ledger_entries;
+    let mut described = vec![false; entries.len()];
+
+    // Indices of the entries that may belong to each account or contract
+    let mut account_data = BTreeMap::<_, Vec<usize>>::new();
+    let mut contract_data = BTreeMap::<_, Vec<usize>>::new();
+    for (index, (_, (entry, live_until))) in entries.iter().enumerate() {
+        match &entry.data {
+            LedgerEntryData::Data(data) => account_data
+                .entry(&data.account_id)
+                .or_default()
+                .push(index),
+            LedgerEntryData::ContractData(data)
+                if data.durability == ContractDataDurability::Persistent
+                    && live_until.is_some() =>
+            {
+                contract_data.entry(&data.contract).or_default().push(index)
+            }
+            _ => {}
+        }
+    }
+
+    for (index, (_, (entry, _))) in entries.iter().enumerate() {
+        let LedgerEntryData::Account(account) = &entry.data else {
+            continue;
+        };
+        let data = account_data
+            .get(&account.account_id)
+            .map_or(&[][..], Vec::as_slice);
+        if let Some(state) = describe_account(entries, index, account, data) {
+            described[index] = true;
+            data.iter().for_each(|&index| described[index] = true);
+            snapshot.accounts.push(state);
+        }
+    }
+
+    for (index, (_, (entry, live_until))) in entries.iter().enumerate() {
+        let LedgerEntryData::ContractData(data) = &entry.data else {
+            continue;
+        };
+        let ScVal::ContractInstance(instance) = &data.val else {
+            continue;
+        };
+        if data.key != ScVal::LedgerKeyContractInstance
+            || data.durability != ContractDataDurability::Persistent
+        {
+            continue;
+        }
+        let (ContractExecutable::Wasm(hash), Some(_)) = (&instance.executable, live_until) else {
+            continue;
+        };
+        let mut contract = ContractState::new(data.contract.to_string(), hex::encode(hash.0));
+        contract.live_until_ledger = *live_until;
+        contract.last_modified_ledger = Some(entry.last_modified_ledger_seq);
+        for item in instance.storage.iter().flat_map(|map| map.iter()) {
+            contract.storage.insert(
+                readable_key(&env, &item.key),
+                readable_value(&env, &item.val),
+            );
+        }
+        if instance_entry(&env, &contract, data.contract.clone(), hash.clone())
+            .ok()
+            .as_ref()
+            != Some(&entries[index])
+        {
+            continue;
+        }
+        described[index] = true;
+
+        for &index in contract_data.get(&data.contract).into_iter().flatten() {
+            let original = &entries[index];
+            let LedgerEntryData::ContractData(data_entry) = &original.1 .0.data else {
+                continue;
+            };
+            if described[index] {
+                continue;
+            }
+            let (key, val) = (
+                readable_key(&env, &data_entry.key),
+                readable_value(&env, &data_entry.val),
+            );
+            let last_modified = original.1 .0.last_modified_ledger_seq;
+            let regenerated = persistent_entry(
+                &env,
+                data.contract.clone(),
+                (&key, &val),
+                last_modified,
+                original.1 .1,
+            );
+            if regenerated.ok().as_ref() != Some(original) {
+                continue;
+            }
+            described[index] = true;
+            if last_modified != entry.last_modified_ledger_seq {
+                contract
+                    .persistent_last_modified
+                    .insert(key.clone(), last_modified);
+            }
+            if let Some(live_until) = original.1 .1.filter(|&ttl| Some(ttl) != *live_until) {
+                contract
+                    .persistent_live_until
+                    .insert(key.clone(), live_until);
+            }
+            contract.persistent_storage.insert(key, val);
+        }
+        snapshot.contracts.push(contract);
+    }
+
+    for (index, (key, (entry, live_until))) in entries.iter().enumerate() {
+        if !described[index] {
+            snapshot.ledger_entries.push(LedgerEntryXdr {
+                key: to_base64(key.as_ref())?,
+                entry: to_base64(entry.as_ref())?,
+                live_until: *live_until,
+            });
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Install `snapshot` on top of `base`: its ledger metadata, and its entries
+/// in place of any with the same key. `code` provides the code of each
+/// contract.
+pub(crate) fn overlay(
+    snapshot: &NetworkSnapshot,
+    mut base: LedgerSnapshot,
+    mut code: impl FnMut(&ContractState) -> Result<Vec<u8>>,
+) -> Result<LedgerSnapshot> {
+    let metadata = &snapshot.ledger;
+    base.sequence_number = metadata.sequence;
+    base.timestamp = metadata.timestamp;
+    base.network_id = match &metadata.network_id {
+        Some(id) => hex::decode(id)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| -> miette::Report {
+                SimulatorError::ValidationError(format!("Invalid network ID: {}", id)).into()
+            })?,
+        None => network_id(&metadata.network_passphrase),
+    };
+    base.protocol_version = metadata.protocol_version.unwrap_or(base.protocol_version);
+    base.base_reserve = metadata.base_reserve.unwrap_or(base.base_reserve);
+    base.min_persistent_entry_ttl = metadata
+        .min_persistent_entry_ttl
+        .unwrap_or(base.min_persistent_entry_ttl);
+    base.min_temp_entry_ttl = metadata
+        .min_temp_entry_ttl
+        .unwrap_or(base.min_temp_entry_ttl);
+    base.max_entry_ttl = metadata.max_entry_ttl.unwrap_or(base.max_entry_ttl);
+    let default_live_until = metadata
+        .sequence
+        .saturating_add(base.max_entry_ttl.saturating_sub(1));
+
+    let mut entries = Vec::new();
+    for account in &snapshot.accounts {
+        entries.extend(account_entries(account, metadata.sequence)?);
+    }
+    let env = scratch_env();
+    for contract in &snapshot.contracts {
+        let address = contract_address(&contract.contract_id)?;
+        let wasm = code(contract)?;
+        let last_modified = contract.last_modified_ledger.unwrap_or(metadata.sequence);
+        let live_until = contract.live_until_ledger.unwrap_or(default_live_until);
+        let hash = Hash(Sha256::digest(&wasm).into());
+        let code = wasm.try_into().map_err(|_| -> miette::Report {
+            SimulatorError::ValidationError(format!(
+                "Code of {} is too large",
+                contract.contract_id
+            ))
+            .into()
+        })?;
+        entries.push(entry(
+            LedgerKey::ContractCode(LedgerKeyContractCode { hash: hash.clone() }),
+            LedgerEntryData::ContractCode(ContractCodeEntry {
+                ext: ContractCodeEntryExt::V0,
+                hash: hash.clone(),
+                code,
+            }),
+            last_modified,
+            Some(live_until),
+        ));
+        let mut contract = contract.clone();
+        contract.last_modified_ledger = Some(last_modified);
+        contract.live_until_ledger = Some(live_until);
+        entries.push(instance_entry(&env, &contract, address.clone(), hash)?);
+        entries.extend(persistent_entries(
+            &env,
+            &contract,
+            address,
+            last_modified,
+            live_until,
+        )?);
+    }
+    for raw in &snapshot.ledger_entries {
+        entries.push((
+            Box::new(from_base64(&raw.key)?),
+            (Box::new(from_base64(&raw.entry)?), raw.live_until),
+        ));
+    }
+
+    let mut merged: BTreeMap<_, _> = std::mem::take(&mut base.ledger_entries)
+        .into_iter()
+        .collect();
+    merged.extend(entries);
+    base.ledger_entries = merged.into_iter().collect();
+    Ok(base)
+}
+
+/// The code stored in `snapshot.ledger_entries` under `wasm_hash`, if any
+pub(crate) fn stored_code(snapshot: &NetworkSnapshot, wasm_hash: &str) -> Result<Option<Vec<u8>>> {
+    for raw in &snapshot.ledger_entries {
+        if let LedgerEntryData::ContractCode(code) = from_base64::<LedgerEntry>(&raw.entry)?.data {
+            if hex::encode(code.hash.0) == wasm_hash {
+                return Ok(Some(code.code.to_vec()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// A readable name for the base64 `LedgerKey` of an entry in
+/// `ledger_entries`
+pub(crate) fn describe_key(key: &str) -> String {
+    match from_base64::<LedgerKey>(key) {
+        Ok(LedgerKey::ContractData(data)) => {
+            let durability = match data.durability {
+                ContractDataDurability::Temporary => "temporary",
+                ContractDataDurability::Persistent => "persistent",
+            };
+            format!(
+                "{} {} of {}",
+                durability,
+                readable_key(&scratch_env(), &data.key),
+                data.contract
+            )
+        }
+        Ok(LedgerKey::ContractCode(code)) => format!("code {}", hex::encode(code.hash.0)),
+        Ok(LedgerKey::Account(account)) => {
+            format!("account {}", ScAddress::Account(account.account_id))
+        }
+        Ok(LedgerKey::Data(data)) => format!(
+            "data {} of {}",
+            data.data_name.0.to_utf8_string_lossy(),
+            ScAddress::Account(data.account_id)
+        ),
+        _ => key.to_string(),
+    }
+}
+
+/// Ledger metadata of a fresh host, for snapshots that don't set it
+pub(crate) fn default_ledger() -> LedgerSnapshot {
+    scratch_env().to_ledger_snapshot()
+}
+
+/// A contract ID as a strkey (`C...`) or 32 bytes of hex
+pub(crate) fn contract_address(contract_id: &str) -> Result<ScAddress> {
+    if let Ok(address @ ScAddress::Contract(_)) = ScAddress::from_str(contract_id) {
+        return Ok(address);
+    }
+    hex::decode(contract_id.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(|bytes| ScAddress::Contract(Hash(bytes)))
+        .ok_or_else(|| {
+            SimulatorError::InvalidContractId(format!(
+                "Not a contract address or 32-byte hex ID: {}",
+                contract_id
+            ))
+            .into()
+        })
+}
+
+fn network_id(passphrase: &str) -> [u8; 32] {
+    Sha256::digest(passphrase.as_bytes()).into()
+}
+
+/// Values are converted in an environment of their own
+fn scratch_env() -> Env {
+    let env = Env::new_with_config(EnvTestConfig {
+        capture_snapshot_at_drop: false,
+    });
+    env.cost_estimate().budget().reset_unlimited();
+    env
+}
+
+/// The account at `index` with the data entries at `data`, if the account
+/// fields reproduce them
+fn describe_account(
+    entries: &[Entry],
+    index: usize,
+    account: &AccountEntry,
+    data: &[usize],
+) -> Option<AccountState> {
+    let mut state = AccountState::new(
+        ScAddress::Account(account.account_id.clone()).to_string(),
+        account.balance.to_string(),
+        u64::try_from(account.seq_num.0).ok()?,
+    );
+    state.flags = (account.flags != 0).then_some(account.flags);
+    state.last_modified_ledger = Some(entries[index].1 .0.last_modified_ledger_seq);
+    for &index in data {
+        let LedgerEntryData::Data(data) = &entries[index].1 .0.data else {
+            return None;
+        };
+        state.set_data(
+            data.data_name.0.to_utf8_string().ok()?,
+            String::from_utf8(data.data_value.to_vec()).ok()?,
+        );
+    }
+
+    let mut regenerated = account_entries(&state, 0).ok()?;
+    let mut original: Vec<Entry> = std::iter::once(index)
+        .chain(data.iter().copied())
+        .map(|index| entries[index].clone())
+        .collect();
+    regenerated.sort_by(|a, b| a.0.cmp(&b.0));
+    original.sort_by(|a, b| a.0.cmp(&b.0));
+    (regenerated == original).then_some(state)
+}
+
+/// The account entry of `account` and one entry per data item
+pub(crate) fn account_entries(account: &AccountState, sequence: u32) -> Result<Vec<Entry>> {
+    let invalid =
+        |message: String| -> miette::Report { SimulatorError::InvalidAddress(message).into() };
+    let account_id = match ScAddress::from_str(&account.address) {
+        Ok(ScAddress::Account(account_id)) => account_id,
+        _ => {
+            return Err(invalid(format!(
+                "Not a Stellar account address: {}",
+                account.address
+            )))
+        }
+    };
+    let balance = account
+        .balance
+        .parse::<i64>()
+        .map_err(|_| -> miette::Report {
+            SimulatorError::InvalidBalance(format!(
+                "Balance of {} does not fit in 64 bits: {}",
+                account.address, account.balance
+            ))
+            .into()
+        })?;
+    let seq_num = i64::try_from(account.sequence).map_err(|_| -> miette::Report {
+        SimulatorError::ValidationError(format!(
+            "Sequence number of {} is too large: {}",
+            account.address, account.sequence
+        ))
+        .into()
+    })?;
+    let last_modified = account.last_modified_ledger.unwrap_or(sequence);
+    let data = account.data.clone().unwrap_or_default();
+
+    let mut entries = vec![entry(
+        LedgerKey::Account(LedgerKeyAccount {
+            account_id: account_id.clone(),
+        }),
+        LedgerEntryData::Account(AccountEntry {
+            account_id: account_id.clone(),
+            balance,
+            seq_num: SequenceNumber(seq_num),
+            num_sub_entries: data.len() as u32,
+            inflation_dest: None,
+            flags: account.flags.unwrap_or(0),
+            home_domain: Default::default(),
+            thresholds: Thresholds([1, 0, 0, 0]),
+            signers: Default::default(),
+            ext: AccountEntryExt::V0,
+        }),
+        last_modified,
+        None,
+    )];
+    for (name, value) in data {
+        let data_name: String64 = StringM::<64>::try_from(name.as_bytes().to_vec())
+            .map(String64::from)
+            .map_err(|_| {
+                invalid(format!(
+                    "Data name of {} is over 64 bytes: {}",
+                    account.address, name
+                ))
+            })?;
+        let data_value = value.into_bytes().try_into().map_err(|_| {
+            invalid(format!(
+                "Data value {} of {} is over 64 bytes",
+                name, account.address
+            ))
+        })?;
+        entries.push(entry(
+            LedgerKey::Data(LedgerKeyData {
+                account_id: account_id.clone(),
+                data_name: data_name.clone(),
+            }),
+            LedgerEntryData::Data(DataEntry {
+                account_id: account_id.clone(),
+                data_name,
+                data_value,
+                ext: DataEntryExt::V0,
+            }),
+            last_modified,
+            None,
+        ));
+    }
+    Ok(entries)
+}
+
+/// The instance entry of `contract`, running the code with `hash`. A
+/// contract without instance storage has none, as when it's deployed.
+fn instance_entry(
+    env: &Env,
+    contract: &ContractState,
+    address: ScAddress,
+    hash: Hash,
+) -> Result<Entry> {
+    let mut storage = contract
+        .storage
+        .iter()
+        .map(|(key, val)| {
+            Ok(ScMapEntry {
+                key: storage_value(env, &storage_key(key))?,
+                val: storage_value(env, val)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    storage.sort_by(|a, b| a.key.cmp(&b.key));
+    let storage = ScMap::sorted_from(storage).map_err(|e| -> miette::Report {
+        SimulatorError::ValidationError(format!(
+            "Invalid instance storage of {}: {}",
+            contract.contract_id, e
+        ))
+        .into()
+    })?;
+
+    let (key, data) = contract_data(
+        address,
+        ScVal::LedgerKeyContractInstance,
+        ScVal::ContractInstance(ScContractInstance {
+            executable: ContractExecutable::Wasm(hash),
+            storage: (!storage.is_empty()).then_some(storage),
+        }),
+    );
+    Ok(entry(
+        key,
+        data,
+        contract.last_modified_ledger.unwrap_or_default(),
+        contract.live_until_ledger,
+    ))
+}
+
+/// The persistent storage entries of `contract`. The defaults apply when
+/// the contract doesn't set `last_modified_ledger` or `live_until_ledger`.
+fn persistent_entries(
+    env: &Env,
+    contract: &ContractState,
+    address: ScAddress,
+    default_last_modified: u32,
+    default_live_until: u32,
+) -> Result<Vec<Entry>> {
+    let last_modified = contract
+        .last_modified_ledger
+        .unwrap_or(default_last_modified);
+    let live_until = contract.live_until_ledger.unwrap_or(default_live_until);
+    contract
+        .persistent_storage
+        .iter()
+        .map(|(key, val)| {
+            let live_until = contract
+                .persistent_live_until
+                .get(key)
+                .copied()
+                .unwrap_or(live_until);
+            let last_modified = contract
+                .persistent_last_modified
+                .get(key)
+                .copied()
+                .unwrap_or(last_modified);
+            persistent_entry(
+                env,
+                address.clone(),
+                (key, val),
+                last_modified,
+                Some(live_until),
+            )
+        })
+        .collect()
+}
+
+fn persistent_entry(
+    env: &Env,
+    address: ScAddress,
+    (key, val): (&str, &Value),
+    last_modified: u32,
+    live_until: Option<u32>,
+) -> Result<Entry> {
+    let (ledger_key, data) = contract_data(
+        address,
+        storage_value(env, &storage_key(key))?,
+        storage_value(env, val)?,
+    );
+    Ok(entry(ledger_key, data, last_modified, live_until))
+}
+
+fn contract_data(address: ScAddress, key: ScVal, val: ScVal) -> (LedgerKey, LedgerEntryData) {
+    (
+        LedgerKey::ContractData(LedgerKeyContractData {
+            contract: address.clone(),
+            key: key.clone(),
+            durability: ContractDataDurability::Persistent,
+        }),
+        LedgerEntryData::ContractData(ContractDataEntry {
+            ext: ExtensionPoint::V0,
+            contract: address,
+            key,
+            durability: ContractDataDurability::Persistent,
+            val,
+        }),
+    )
+}
+
+fn entry(
+    key: LedgerKey,
+    data: LedgerEntryData,
+    last_modified: u32,
+    live_until: Option<u32>,
+) -> Entry {
+    (
+        Box::new(key),
+        (
+            Box::new(LedgerEntry {
+                last_modified_ledger_seq: last_modified,
+                data,
+                ext: LedgerEntryExt::V0,
+            }),
+            live_until,
+        ),
+    )
+}
+
+/// Storage keys are JSON when they parse as JSON, and plain strings
+/// otherwise
+pub(crate) fn storage_key(key: &str) -> Value {
+    serde_json::from_str(key).unwrap_or_else(|_| Value::String(key.to_string()))
+}
+
+/// Convert a JSON value, bare or type-annotated, as arguments are
+pub(crate) fn storage_value(env: &Env, value: &Value) -> Result<ScVal> {
+    let val = ArgumentParser::new(env.clone())
+        .parse_single_value(value)
+        .map_err(|e| DebuggerError::InvalidArguments(e.to_string()))?;
+    ScVal::try_from_val(env, &val).map_err(|e| {
+        DebuggerError::ExecutionError(format!("Failed to convert storage value: {:?}", e)).into()
+    })
+}
+
+/// `val` as a storage key that [`storage_key`] reads back exactly
+fn readable_key(env: &Env, val: &ScVal) -> String {
+    let value = readable_value(env, val);
+    match &value {
+        Value::String(text)
+            if storage_value(env, &storage_key(text)).ok().as_ref() == Some(val) =>
+        {
+            text.clone()
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// `val` as the JSON [`storage_value`] reads back exactly: bare or typed
+/// where possible, and base64 XDR otherwise
+fn readable_value(env: &Env, val: &ScVal) -> Value {
+    let typed = |kind: &str, value: Value| json!({ "type": kind, "value": value });
+    let candidate = match val {
+        ScVal::Void => Some(Value::Null),
+        ScVal::Bool(b) => Some(json!(b)),
+        ScVal::U32(n) => Some(typed("u32", json!(n))),
+        ScVal::I32(n) => Some(typed("i32", json!(n))),
+        ScVal::U64(n) => Some(typed("u64", json!(n))),
+        ScVal::I64(n) => Some(typed("i64", json!(n))),
+        ScVal::U128(parts) if parts.hi == 0 => Some(typed("u128", json!(parts.lo))),
+        ScVal::I128(parts) => i64::try_from(((parts.hi as i128) << 64) | parts.lo as i128)
+            .ok()
+            .map(|n| json!(n)),
+        ScVal::Symbol(symbol) => Some(json!(symbol.0.to_utf8_string_lossy())),
+        ScVal::String(string) => Some(typed("string", json!(string.0.to_utf8_string_lossy()))),
+        ScVal::Address(address) => Some(typed("address", json!(address.to_string()))),
+        ScVal::Bytes(bytes) => Some(typed(
+            "bytes",
+            json!(format!("0x{}", hex::encode(bytes.as_slice()))),
+        )),
+        ScVal::Vec(Some(items)) => Some(Value::Array(
+            items.iter().map(|item| readable_value(env, item)).collect(),
+        )),
+        // Objects become maps with symbol keys
+        ScVal::Map(Some(map)) => map
+            .iter()
+            .map(|item| match &item.key {
+                ScVal::Symbol(symbol) => Some((
+                    symbol.0.to_utf8_string_lossy(),
+                    readable_value(env, &item.val),
+                )),
+                _ => None,
+            })
+            .collect::<Option<serde_json::Map<_, _>>>()
+            .map(Value::Object),
+        _ => None,
+    };
+    candidate
+        .filter(|value| storage_value(env, value).ok().as_ref() == Some(val))
+        .unwrap_or_else(|| {
+            typed(
+                "xdr",
+                json!(val.to_xdr_base64(Limits::none()).unwrap_or_default()),
+            )
+        })
+}
+
+fn to_base64(value: &impl WriteXdr) -> Result<String> {
+    value.to_xdr_base64(Limits::none()).map_err(|e| {
+        SimulatorError::ValidationError(format!("Failed to encode ledger entry: {}", e)).into()
+    })
+}
+
+fn from_base64<T: ReadXdr>(text: &str) -> Result<T> {
+    T::from_xdr_base64(text, Limits::none()).map_err(|e| {
+        SimulatorError::ValidationError(format!("Invalid ledger entry XDR: {}", e)).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_wasm;
+    use crate::simulator::SnapshotLoader;
+    use crate::utils::wasm::compute_wasm_sha256;
+    use soroban_env_host::xdr::{ScSymbol, UInt128Parts};
+
+    const ACCOUNT: &str = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF";
+
+    /// A ledger with entries the network snapshot fields describe, and
+    /// entries only XDR can hold
+    fn sample_ledger() -> LedgerSnapshot {
+        let mut snapshot =
+            NetworkSnapshot::new(500, "Test SDF Network ; September 2015", 1700000000);
+        let mut account = AccountState::new(ACCOUNT, "1000000", 7);
+        account.set_data("app".to_string(), "1.0".to_string());
+        snapshot.add_account(account).unwrap();
+        let mut contract =
+            ContractState::new("11".repeat(32), compute_wasm_sha256(&test_wasm::ping()));
+        contract.set_storage("counter", json!({"type": "u32", "value": 5}));
+        contract.set_storage(
+            "config",
+            json!({"admin": {"type": "address", "value": ACCOUNT}, "paused": false}),
+        );
+        contract
+            .persistent_storage
+            .insert("owner".to_string(), json!({"type": "u32", "value": 9}));
+        contract
+            .persistent_live_until
+            .insert("owner".to_string(), 900);
+        snapshot.add_contract(contract).unwrap();
+        let mut ledger = overlay(&snapshot, default_ledger(), |_| Ok(test_wasm::ping())).unwrap();
+
+        let address = contract_address(&"11".repeat(32)).unwrap();
+        let symbol = |name: &str| ScVal::Symbol(ScSymbol(name.try_into().unwrap()));
+        let (key, data) = contract_data(
+            address.clone(),
+            symbol("big"),
+            ScVal::U128(UInt128Parts { hi: 1, lo: 2 }),
+        );
+        ledger
+            .ledger_entries
+            .push(entry(key, data, 500, Some(1000)));
+
+        let (mut key, mut data) = contract_data(address, symbol("nonce"), ScVal::U32(1));
+        if let (LedgerKey::ContractData(key), LedgerEntryData::ContractData(data)) =
+            (&mut key, &mut data)
+        {
+            key.durability = ContractDataDurability::Temporary;
+            data.durability = ContractDataDurability::Temporary;
+        }
+        ledger.ledger_entries.push(entry(key, data, 480, Some(520)));
+
+        let other = "GAV5QBWJP4HABLY2D7BTFD5HMOUSNFZDZDNY7LCPSOXXDWYYNVXJBKNV";
+        let (key, (mut other, _)) = account_entries(&AccountState::new(other, "5", 1), 400)
+            .unwrap()
+            .remove(0);
+        if let LedgerEntryData::Account(account) = &mut other.data {
+            account.home_domain = StringM::<32>::try_from("example.com").unwrap().into();
+        }
+        ledger.ledger_entries.push((key, (other, None)));
+
+        ledger.ledger_entries.sort_by(|a, b| a.0.cmp(&b.0));
+        ledger
+    }
+
+    #[test]
+    fn ledger_snapshots_round_trip_through_network_snapshots() {
+        let ledger = sample_ledger();
+        let snapshot = from_ledger_snapshot(&ledger).unwrap();
+
+        assert_eq!(snapshot.ledger.sequence, 500);
+        assert_eq!(snapshot.ledger.network_id, None);
+        assert_eq!(snapshot.accounts.len(), 1);
+        assert_eq!(snapshot.accounts[0].get_data("app").unwrap(), "1.0");
+        let contract = &snapshot.contracts[0];
+        assert_eq!(
+            contract.get_storage("counter"),
+            Some(&json!({"type": "u32", "value": 5}))
+        );
+        assert_eq!(contract.get_storage("config").unwrap()["paused"], false);
+        assert_eq!(contract.persistent_storage["big"]["type"], "xdr");
+        assert_eq!(contract.persistent_live_until["owner"], 900);
+        // The code, the temporary entry and the account with a home domain
+        assert_eq!(snapshot.ledger_entries.len(), 3);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let exported = SnapshotLoader::from_json(&json)
+            .unwrap()
+            .to_ledger_snapshot()
+            .unwrap();
+        assert_eq!(exported, ledger);
+    }
+
+    #[test]
+    fn overlaid_entries_replace_those_with_the_same_key() {
+        let base = sample_ledger();
+        let mut snapshot = from_ledger_snapshot(&base).unwrap();
+        snapshot.contracts[0].set_storage("counter", json!({"type": "u32", "value": 6}));
+        // Entries only in the base are kept
+        snapshot.ledger_entries.clear();
+
+        let ledger = overlay(&snapshot, base.clone(), |_| Ok(test_wasm::ping())).unwrap();
+        assert_eq!(ledger.ledger_entries.len(), base.ledger_entries.len());
+        assert!(ledger.ledger_entries.windows(2).all(|w| w[0].0 < w[1].0));
+        let updated = from_ledger_snapshot(&ledger).unwrap();
+        assert_eq!(
+            updated.contracts[0].get_storage("counter"),
+            Some(&json!({"type": "u32", "value": 6}))
+        );
+    }
+
+    #[test]
+    fn test_snapshots_and_unknown_networks_are_imported() {
+        let mut ledger = sample_ledger();
+        ledger.network_id = [7; 32];
+        let test_snapshot = json!({ "generators": {}, "ledger": ledger, "events": [] });
+
+        let snapshot = parse_snapshot(&test_snapshot.to_string()).unwrap();
+        assert_eq!(snapshot.ledger.network_passphrase, "");
+        assert_eq!(snapshot.ledger.network_id, Some("07".repeat(32)));
+        snapshot.validate().unwrap();
+        let exported = overlay(&snapshot, default_ledger(), |_| Ok(test_wasm::ping())).unwrap();
+        assert_eq!(exported, ledger);
+
+        // Our own format still parses
+        let ours = NetworkSnapshot::new(1, "Test Network", 2);
+        let parsed = parse_snapshot(&serde_json::to_string(&ours).unwrap()).unwrap();
+        assert_eq!(parsed.ledger.network_passphrase, "Test Network");
+    }
+}
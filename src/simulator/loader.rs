@@ -3,15 +3,22 @@
 //! This module handles loading network snapshots from files and applying them
 //! to the Soroban debugger environment.
 
-use super::state::{AccountState, ContractState, NetworkSnapshot};
-use crate::Result;
+use super::ledger;
+use super::state::{AccountState, ContractState, NetworkSnapshot, SimulatorError};
+use crate::runtime::executor::ContractExecutor;
+use crate::utils::wasm::{compute_wasm_sha256, load_wasm};
+use crate::{DebuggerError, Result};
+use soroban_ledger_snapshot::LedgerSnapshot;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
 /// Loads and applies network snapshots to a debug environment
+#[derive(Debug, Clone)]
 pub struct SnapshotLoader {
     snapshot: NetworkSnapshot,
+    /// Directory that relative `wasm_ref` paths start from
+    base_dir: Option<PathBuf>,
 }
 
 impl SnapshotLoader {
@@ -28,10 +35,15 @@ impl SnapshotLoader {
             ))
         })?;
 
-        // Parse JSON
-        let snapshot: NetworkSnapshot = serde_json::from_str(&contents).map_err(|e| {
-            crate::DebuggerError::FileError(format!("Failed to parse snapshot JSON: {}", e))
-        })?;
+        let mut loader = Self::from_json(&contents)?;
+        loader.base_dir = path.parent().map(Path::to_path_buf);
+        Ok(loader)
+    }
+
+    /// Load a snapshot from JSON text, in our format or as a ledger
+    /// snapshot from stellar-cli or soroban-sdk
+    pub fn from_json(contents: &str) -> Result<Self> {
+        let snapshot = ledger::parse_snapshot(contents)?;
 
         // Validate the snapshot
         snapshot.validate()?;
@@ -43,13 +55,19 @@ impl SnapshotLoader {
             snapshot.ledger.sequence
         );
 
-        Ok(Self { snapshot })
+        Ok(Self {
+            snapshot,
+            base_dir: None,
+        })
     }
 
     /// Create a snapshot from a NetworkSnapshot struct directly
     pub fn from_snapshot(snapshot: NetworkSnapshot) -> Result<Self> {
         snapshot.validate()?;
-        Ok(Self { snapshot })
+        Ok(Self {
+            snapshot,
+            base_dir: None,
+        })
     }
 
     /// Get reference to the underlying snapshot
@@ -62,28 +80,103 @@ impl SnapshotLoader {
         &mut self.snapshot
     }
 
-    /// Apply snapshot state to the debugger environment
-    ///
-    /// This prepares the environment with the accounts and contracts
-    /// defined in the snapshot. The exact behavior may be limited by
-    /// the Soroban SDK's test environment capabilities.
-    pub fn apply_to_environment(&self) -> Result<LoadedSnapshot> {
+    /// Install the snapshot's ledger state in `executor`'s environment:
+    /// the ledger sequence, timestamp and network, every account with its
+    /// data entries, and every contract with its code, instance and
+    /// persistent storage and TTLs. If a snapshot contract runs the same
+    /// code as the contract being debugged, calls go to that contract.
+    pub fn apply_to_environment(&self, executor: &mut ContractExecutor) -> Result<LoadedSnapshot> {
         info!("Applying snapshot to environment");
+        let wasm = executor.wasm_bytes().to_vec();
+        let ledger = ledger::overlay(&self.snapshot, executor.ledger_snapshot(), |contract| {
+            self.contract_wasm(contract, Some(&wasm))
+        })?;
 
-        let snapshot_info = SnapshotInfo {
-            ledger_sequence: self.snapshot.ledger.sequence,
-            ledger_timestamp: self.snapshot.ledger.timestamp,
-            network_passphrase: self.snapshot.ledger.network_passphrase.clone(),
-            account_count: self.snapshot.accounts.len(),
-            contract_count: self.snapshot.contracts.len(),
-        };
+        let debugged_hash = compute_wasm_sha256(&wasm);
+        let debugged = self
+            .snapshot
+            .contracts
+            .iter()
+            .find(|contract| wasm_hash(contract) == debugged_hash)
+            .map(|contract| ledger::contract_address(&contract.contract_id))
+            .transpose()?;
+        executor.restore_ledger_snapshot(ledger, debugged.clone())?;
+
+        let loaded = self.loaded(debugged.map(|address| address.to_string()));
+        debug!("Snapshot info: {:?}", loaded.info);
+        Ok(loaded)
+    }
+
+    /// The snapshot as a ledger snapshot, as `stellar snapshot create`
+    /// writes them
+    pub fn to_ledger_snapshot(&self) -> Result<LedgerSnapshot> {
+        ledger::overlay(&self.snapshot, ledger::default_ledger(), |contract| {
+            self.contract_wasm(contract, None)
+        })
+    }
+
+    /// Resolve relative `wasm_ref` paths from `dir`
+    pub fn with_base_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = Some(dir.into());
+        self
+    }
 
-        debug!("Snapshot info: {:?}", snapshot_info);
+    /// Describe the snapshot without installing it anywhere
+    pub fn summary(&self) -> LoadedSnapshot {
+        self.loaded(None)
+    }
 
-        Ok(LoadedSnapshot {
+    fn loaded(&self, contract_id: Option<String>) -> LoadedSnapshot {
+        let metadata = &self.snapshot.ledger;
+        LoadedSnapshot {
             snapshot: self.snapshot.clone(),
-            info: snapshot_info,
-        })
+            info: SnapshotInfo {
+                ledger_sequence: metadata.sequence,
+                ledger_timestamp: metadata.timestamp,
+                network_passphrase: metadata.network_passphrase.clone(),
+                account_count: self.snapshot.accounts.len(),
+                contract_count: self.snapshot.contracts.len(),
+                contract_id,
+            },
+        }
+    }
+
+    /// The code of `contract`: from `wasm_ref`, the snapshot's ledger
+    /// entries, the contract being debugged if it has the same hash, or
+    /// `<wasm_hash>.wasm` next to the snapshot
+    fn contract_wasm(&self, contract: &ContractState, debugged: Option<&[u8]>) -> Result<Vec<u8>> {
+        let expected = wasm_hash(contract);
+        let resolve = |path: &str| match &self.base_dir {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+
+        let stored = match &contract.wasm_ref {
+            Some(_) => None,
+            None => ledger::stored_code(&self.snapshot, &expected)?,
+        };
+        let wasm = match (&contract.wasm_ref, stored, debugged) {
+            (Some(wasm_ref), _, _) => load_wasm(resolve(wasm_ref))?.bytes,
+            (None, Some(code), _) => code,
+            (None, None, Some(wasm)) if compute_wasm_sha256(wasm) == expected => wasm.to_vec(),
+            (None, None, _) => {
+                let path = resolve(&format!("{}.wasm", expected));
+                if !path.exists() {
+                    return Err(SimulatorError::ValidationError(format!(
+                        "No code for contract {}: set wasm_ref or put {:?} next to the snapshot",
+                        contract.contract_id, path
+                    ))
+                    .into());
+                }
+                load_wasm(path)?.bytes
+            }
+        };
+
+        let actual = compute_wasm_sha256(&wasm);
+        if actual != expected {
+            return Err(DebuggerError::ChecksumMismatch { expected, actual }.into());
+        }
+        Ok(wasm)
     }
 
     /// Validate the snapshot without applying it
@@ -179,9 +272,14 @@ impl LoadedSnapshot {
         &mut self.snapshot
     }
 
+    /// The snapshot contract calls go to, if one runs the debugged code
+    pub fn contract_id(&self) -> Option<&str> {
+        self.info.contract_id.as_deref()
+    }
+
     /// Format snapshot info for display
     pub fn format_summary(&self) -> String {
-        format!(
+        let mut summary = format!(
             "Network Snapshot:\n  \
             Ledger Sequence: {}\n  \
             Timestamp: {}\n  \
@@ -193,10 +291,22 @@ impl LoadedSnapshot {
             self.info.network_passphrase,
             self.info.account_count,
             self.info.contract_count
-        )
+        );
+        if let Some(contract_id) = &self.info.contract_id {
+            summary.push_str(&format!("\n  Debugging: {}", contract_id));
+        }
+        summary
     }
 }
 
+/// The code hash of `contract`, as lowercase hex
+fn wasm_hash(contract: &ContractState) -> String {
+    contract
+        .wasm_hash
+        .trim_start_matches("0x")
+        .to_ascii_lowercase()
+}
+
 /// Summary information about a snapshot
 #[derive(Debug, Clone)]
 pub struct SnapshotInfo {
@@ -205,11 +315,16 @@ pub struct SnapshotInfo {
     network_passphrase: String,
     account_count: usize,
     contract_count: usize,
+    contract_id: Option<String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime::executor::StorageKind;
+    use crate::runtime::test_wasm;
+    use serde_json::json;
+    use soroban_env_host::xdr::{LedgerKey, ScVal};
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -236,10 +351,116 @@ mod tests {
     fn test_apply_to_environment() {
         let snapshot = NetworkSnapshot::new(100, "Test Network", 1234567890);
         let loader = SnapshotLoader::from_snapshot(snapshot).unwrap();
-        let loaded = loader.apply_to_environment().unwrap();
+        let mut executor = ContractExecutor::new(test_wasm::ping()).unwrap();
+        let loaded = loader.apply_to_environment(&mut executor).unwrap();
 
         assert_eq!(loaded.ledger_sequence(), 100);
         assert_eq!(loaded.network_passphrase(), "Test Network");
+        assert_eq!(executor.ledger_info(), (100, 1234567890));
+        assert_eq!(loaded.contract_id(), None);
+    }
+
+    #[test]
+    fn test_apply_installs_accounts_contracts_and_storage() {
+        let account = "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF";
+        let contract_id = "11".repeat(32);
+        let mut snapshot = NetworkSnapshot::new(500, "Test Network", 1700000000);
+        snapshot
+            .add_account(AccountState::new(account, "1000000", 7))
+            .unwrap();
+        let mut contract =
+            ContractState::new(&contract_id, compute_wasm_sha256(&test_wasm::ping()));
+        contract
+            .storage
+            .insert("counter".to_string(), json!({"type": "u32", "value": 5}));
+        contract
+            .persistent_storage
+            .insert("owner".to_string(), json!({"type": "u32", "value": 9}));
+        snapshot.add_contract(contract).unwrap();
+
+        let loader = SnapshotLoader::from_snapshot(snapshot).unwrap();
+        let mut executor = ContractExecutor::new(test_wasm::ping()).unwrap();
+        let loaded = loader.apply_to_environment(&mut executor).unwrap();
+
+        let expected_id = ledger::contract_address(&contract_id).unwrap().to_string();
+        assert_eq!(loaded.contract_id(), Some(expected_id.as_str()));
+        assert_eq!(executor.ledger_info(), (500, 1700000000));
+        assert_eq!(
+            executor
+                .storage_get(StorageKind::Instance, &json!("counter"))
+                .unwrap(),
+            Some(ScVal::U32(5))
+        );
+        assert_eq!(
+            executor
+                .storage_get(StorageKind::Persistent, &json!("owner"))
+                .unwrap(),
+            Some(ScVal::U32(9))
+        );
+        assert!(executor
+            .ledger_snapshot()
+            .ledger_entries
+            .iter()
+            .any(|(key, _)| matches!(**key, LedgerKey::Account(_))));
+        assert_eq!(
+            executor.execute("ping", None).unwrap(),
+            ContractExecutor::new(test_wasm::ping())
+                .unwrap()
+                .execute("ping", None)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_apply_ledger_snapshot_file() {
+        let contract_id = "11".repeat(32);
+        let mut snapshot = NetworkSnapshot::new(500, "Test Network", 1700000000);
+        let mut contract =
+            ContractState::new(&contract_id, compute_wasm_sha256(&test_wasm::ping()));
+        contract.set_storage("counter", json!({"type": "u32", "value": 5}));
+        snapshot.add_contract(contract).unwrap();
+        let mut executor = ContractExecutor::new(test_wasm::ping()).unwrap();
+        SnapshotLoader::from_snapshot(snapshot)
+            .unwrap()
+            .apply_to_environment(&mut executor)
+            .unwrap();
+
+        // As `stellar snapshot create` would write it
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.json");
+        executor.ledger_snapshot().write_file(&path).unwrap();
+
+        let loader = SnapshotLoader::from_file(&path).unwrap();
+        let expected_id = ledger::contract_address(&contract_id).unwrap().to_string();
+        assert!(loader.list_contracts().contains(&expected_id.as_str()));
+        let mut executor = ContractExecutor::new(test_wasm::ping()).unwrap();
+        let loaded = loader.apply_to_environment(&mut executor).unwrap();
+        assert_eq!(loaded.contract_id(), Some(expected_id.as_str()));
+        assert_eq!(executor.ledger_info(), (500, 1700000000));
+        assert_eq!(
+            executor
+                .storage_get(StorageKind::Instance, &json!("counter"))
+                .unwrap(),
+            Some(ScVal::U32(5))
+        );
+    }
+
+    #[test]
+    fn test_apply_rejects_code_with_the_wrong_hash() {
+        let mut snapshot = NetworkSnapshot::new(100, "Test Network", 1234567890);
+        let mut contract = ContractState::new("22".repeat(32), "ab".repeat(32));
+        contract.wasm_ref = Some("ping.wasm".to_string());
+        snapshot.add_contract(contract).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ping.wasm"), test_wasm::ping()).unwrap();
+        let path = dir.path().join("snapshot.json");
+        std::fs::write(&path, serde_json::to_string(&snapshot).unwrap()).unwrap();
+
+        let loader = SnapshotLoader::from_file(&path).unwrap();
+        let mut executor = ContractExecutor::new(test_wasm::ping()).unwrap();
+        let err = loader.apply_to_environment(&mut executor).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"), "{}", err);
     }
 
     #[test]
@@ -7,10 +7,13 @@
 //! - Pre-deploy contract instances with populated storage
 //! - Save and restore ledger state for iterative debugging
 
+pub mod ledger;
 pub mod loader;
 pub mod snapshot;
 pub mod state;
 
 pub use loader::{LoadedSnapshot, SnapshotLoader};
-pub use snapshot::{AccountDiff, ContractDiff, SnapshotDiff, SnapshotManager};
-pub use state::{AccountState, ContractState, LedgerMetadata, NetworkSnapshot, SimulatorError};
+pub use snapshot::{AccountDiff, ContractDiff, SnapshotDiff, SnapshotFormat, SnapshotManager};
+pub use state::{
+    AccountState, ContractState, LedgerEntryXdr, LedgerMetadata, NetworkSnapshot, SimulatorError,
+};
@@ -4,18 +4,64 @@
 //! allowing users to capture the state of a ledger after debugging and
 //! restore it later for continued work.
 
+use super::ledger;
+use super::loader::SnapshotLoader;
 use super::state::NetworkSnapshot;
-use crate::Result;
+use crate::runtime::executor::ContractExecutor;
+use crate::{DebuggerError, Result};
 use std::fs;
 use std::path::Path;
 use tracing::info;
 
+/// File format of a saved snapshot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// Our [`NetworkSnapshot`] JSON
+    #[default]
+    Network,
+    /// The `LedgerSnapshot` JSON of stellar-cli and soroban-sdk
+    Ledger,
+}
+
+impl std::str::FromStr for SnapshotFormat {
+    type Err = DebuggerError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "network" => Ok(Self::Network),
+            "ledger" => Ok(Self::Ledger),
+            _ => Err(DebuggerError::InvalidArguments(format!(
+                "Unknown snapshot format '{}': expected 'network' or 'ledger'",
+                s
+            ))),
+        }
+    }
+}
+
 /// Manages snapshot persistence (save and restore operations)
 pub struct SnapshotManager;
 
 impl SnapshotManager {
     /// Save a snapshot to a JSON file
     pub fn save<P: AsRef<Path>>(snapshot: &NetworkSnapshot, path: P) -> Result<()> {
+        Self::save_as(snapshot, path, SnapshotFormat::Network)
+    }
+
+    /// Capture the complete ledger state of `executor`: every account,
+    /// contract, storage entry and TTL, and the ledger sequence. Loading the
+    /// result as a network snapshot continues from that state.
+    pub fn capture(executor: &ContractExecutor) -> Result<NetworkSnapshot> {
+        ledger::from_ledger_snapshot(&executor.ledger_snapshot())
+    }
+
+    /// Save a snapshot to a JSON file in `format`. Contract code for a
+    /// ledger snapshot is found as when applying the snapshot, with
+    /// `wasm_ref` paths relative to `path`.
+    pub fn save_as<P: AsRef<Path>>(
+        snapshot: &NetworkSnapshot,
+        path: P,
+        format: SnapshotFormat,
+    ) -> Result<()> {
         let path = path.as_ref();
         info!("Saving snapshot to: {:?}", path);
 
@@ -23,7 +69,17 @@ impl SnapshotManager {
         snapshot.validate()?;
 
         // Serialize to pretty JSON
-        let json = serde_json::to_string_pretty(snapshot).map_err(|e| {
+        let json = match format {
+            SnapshotFormat::Network => serde_json::to_string_pretty(snapshot),
+            SnapshotFormat::Ledger => {
+                let mut loader = SnapshotLoader::from_snapshot(snapshot.clone())?;
+                if let Some(dir) = path.parent() {
+                    loader = loader.with_base_dir(dir);
+                }
+                serde_json::to_string_pretty(&loader.to_ledger_snapshot()?)
+            }
+        }
+        .map_err(|e| {
             crate::DebuggerError::FileError(format!("Failed to serialize snapshot: {}", e))
         })?;
 
@@ -40,7 +96,7 @@ impl SnapshotManager {
         Ok(())
     }
 
-    /// Load a snapshot from a JSON file
+    /// Load a snapshot from a JSON file in either format
     pub fn load<P: AsRef<Path>>(path: P) -> Result<NetworkSnapshot> {
         let path = path.as_ref();
         info!("Loading snapshot from: {:?}", path);
@@ -54,9 +110,7 @@ impl SnapshotManager {
         })?;
 
         // Parse JSON
-        let snapshot: NetworkSnapshot = serde_json::from_str(&contents).map_err(|e| {
-            crate::DebuggerError::FileError(format!("Failed to parse snapshot JSON: {}", e))
-        })?;
+        let snapshot = ledger::parse_snapshot(&contents)?;
 
         // Validate loaded snapshot
         snapshot.validate()?;
@@ -105,6 +159,11 @@ pub struct SnapshotDiff {
     pub contracts_added: Vec<String>,
     pub contracts_removed: Vec<String>,
     pub contracts_modified: Vec<ContractDiff>,
+
+    /// Changes to entries kept as XDR, temporary storage among them, by key
+    pub ledger_entries_added: Vec<String>,
+    pub ledger_entries_removed: Vec<String>,
+    pub ledger_entries_modified: Vec<String>,
 }
 
 impl SnapshotDiff {
@@ -123,6 +182,9 @@ impl SnapshotDiff {
             contracts_added: Vec::new(),
             contracts_removed: Vec::new(),
             contracts_modified: Vec::new(),
+            ledger_entries_added: Vec::new(),
+            ledger_entries_removed: Vec::new(),
+            ledger_entries_modified: Vec::new(),
         };
 
         // Compute account changes
@@ -190,7 +252,10 @@ impl SnapshotDiff {
             let before_contract = before.get_contract(id).unwrap();
             let after_contract = after.get_contract(id).unwrap();
 
-            let storage_changed = before_contract.storage != after_contract.storage;
+            let storage_changed = before_contract.storage != after_contract.storage
+                || before_contract.persistent_storage != after_contract.persistent_storage
+                || before_contract.live_until_ledger != after_contract.live_until_ledger
+                || before_contract.persistent_live_until != after_contract.persistent_live_until;
 
             if storage_changed {
                 diff.contracts_modified.push(ContractDiff {
@@ -200,6 +265,34 @@ impl SnapshotDiff {
             }
         }
 
+        // Compute changes to entries kept as XDR
+        let before_entries: std::collections::BTreeMap<_, _> = before
+            .ledger_entries
+            .iter()
+            .map(|raw| (raw.key.as_str(), raw))
+            .collect();
+        let after_entries: std::collections::BTreeMap<_, _> = after
+            .ledger_entries
+            .iter()
+            .map(|raw| (raw.key.as_str(), raw))
+            .collect();
+
+        for (key, before_entry) in &before_entries {
+            match after_entries.get(key) {
+                None => diff.ledger_entries_removed.push(ledger::describe_key(key)),
+                Some(after_entry) if after_entry != before_entry => {
+                    diff.ledger_entries_modified.push(ledger::describe_key(key))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for key in after_entries.keys() {
+            if !before_entries.contains_key(key) {
+                diff.ledger_entries_added.push(ledger::describe_key(key));
+            }
+        }
+
         diff
     }
 
@@ -287,6 +380,17 @@ impl SnapshotDiff {
             ));
         }
 
+        // Ledger entry changes
+        for (label, keys) in [
+            ("added", &self.ledger_entries_added),
+            ("removed", &self.ledger_entries_removed),
+            ("modified", &self.ledger_entries_modified),
+        ] {
+            if !keys.is_empty() {
+                output.push_str(&format!("Ledger entries {}: {}\n", label, keys.join(", ")));
+            }
+        }
+
         if output.is_empty() {
             output.push_str("No changes detected between snapshots");
         }
@@ -304,6 +408,9 @@ impl SnapshotDiff {
             || !self.contracts_added.is_empty()
             || !self.contracts_removed.is_empty()
             || !self.contracts_modified.is_empty()
+            || !self.ledger_entries_added.is_empty()
+            || !self.ledger_entries_removed.is_empty()
+            || !self.ledger_entries_modified.is_empty()
     }
 }
 
@@ -332,6 +439,162 @@ mod tests {
     use crate::simulator::state::AccountState;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_save_as_ledger_snapshot() {
+        let mut snapshot = NetworkSnapshot::new(100, "Test Network", 1234567890);
+        snapshot
+            .add_account(AccountState::new(
+                "GAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAWHF",
+                "1000000",
+                1,
+            ))
+            .unwrap();
+
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("ledger.json");
+        SnapshotManager::save_as(&snapshot, &path, SnapshotFormat::Ledger).unwrap();
+
+        let ledger = soroban_ledger_snapshot::LedgerSnapshot::read_file(&path).unwrap();
+        assert_eq!(ledger.sequence_number, 100);
+        assert_eq!(ledger.ledger_entries.len(), 1);
+        let loaded = SnapshotManager::load(&path).unwrap();
+        assert_eq!(loaded.accounts[0].address, snapshot.accounts[0].address);
+        assert_eq!(loaded.accounts[0].balance, "1000000");
+    }
+
+    #[test]
+    fn test_captured_state_chains_into_the_next_run() {
+        use crate::runtime::executor::StorageKind;
+        use crate::runtime::test_wasm;
+        use crate::simulator::SnapshotLoader;
+        use serde_json::json;
+
+        let mut executor = ContractExecutor::new(test_wasm::ping()).unwrap();
+        let before = SnapshotManager::capture(&executor).unwrap();
+        executor.advance_ledger(10, 50);
+        executor
+            .storage_set(
+                StorageKind::Persistent,
+                &json!("counter"),
+                &json!({"type": "u32", "value": 1}),
+            )
+            .unwrap();
+        executor.execute("ping", None).unwrap();
+        let after = SnapshotManager::capture(&executor).unwrap();
+
+        let diff = SnapshotManager::diff_snapshots(&before, &after);
+        assert!(diff.ledger_sequence_changed);
+        assert_eq!(diff.contracts_modified.len(), 1);
+        let contract = &after.contracts[0];
+        assert_eq!(
+            contract.persistent_storage["counter"],
+            json!({"type": "u32", "value": 1})
+        );
+        assert_eq!(contract.persistent_live_until["counter"], 4105);
+
+        let tmpdir = TempDir::new().unwrap();
+        let path = tmpdir.path().join("after.json");
+        SnapshotManager::save(&after, &path).unwrap();
+
+        let mut next = ContractExecutor::new(test_wasm::ping()).unwrap();
+        SnapshotLoader::from_file(&path)
+            .unwrap()
+            .apply_to_environment(&mut next)
+            .unwrap();
+        assert_eq!(next.ledger_info(), executor.ledger_info());
+        assert_eq!(
+            next.storage_get(StorageKind::Persistent, &json!("counter"))
+                .unwrap(),
+            Some(soroban_env_host::xdr::ScVal::U32(1))
+        );
+        let resumed = SnapshotManager::capture(&next).unwrap();
+        assert!(!SnapshotManager::diff_snapshots(&after, &resumed).has_changes());
+    }
+
+    #[test]
+    fn test_runs_chain_from_a_snapshot_modified_in_an_earlier_ledger() {
+        use crate::runtime::executor::StorageKind;
+        use crate::runtime::test_wasm;
+        use crate::simulator::{ContractState, SnapshotLoader};
+        use crate::utils::wasm::compute_wasm_sha256;
+        use serde_json::json;
+
+        let mut snapshot = NetworkSnapshot::new(500, "Test Network", 1234567890);
+        let mut contract =
+            ContractState::new("11".repeat(32), compute_wasm_sha256(&test_wasm::ping()));
+        contract.last_modified_ledger = Some(400);
+        contract
+            .persistent_storage
+            .insert("owner".to_string(), json!({"type": "u32", "value": 9}));
+        snapshot.add_contract(contract).unwrap();
+        let mut executor = ContractExecutor::new(test_wasm::ping()).unwrap();
+        SnapshotLoader::from_snapshot(snapshot)
+            .unwrap()
+            .apply_to_environment(&mut executor)
+            .unwrap();
+        let before = SnapshotManager::capture(&executor).unwrap();
+
+        executor
+            .storage_set(
+                StorageKind::Persistent,
+                &json!("counter"),
+                &json!({"type": "u32", "value": 1}),
+            )
+            .unwrap();
+        executor
+            .storage_set(
+                StorageKind::Temporary,
+                &json!("nonce"),
+                &json!({"type": "u32", "value": 2}),
+            )
+            .unwrap();
+        let after = SnapshotManager::capture(&executor).unwrap();
+
+        let contract = &after.contracts[0];
+        assert_eq!(contract.last_modified_ledger, Some(400));
+        assert_eq!(
+            contract.persistent_storage["counter"],
+            json!({"type": "u32", "value": 1})
+        );
+        assert_eq!(contract.persistent_last_modified["counter"], 0);
+        let diff = SnapshotManager::diff_snapshots(&before, &after);
+        assert_eq!(diff.contracts_modified.len(), 1);
+        assert_eq!(diff.ledger_entries_added.len(), 1);
+        assert!(diff.ledger_entries_added[0].starts_with("temporary nonce of C"));
+        assert!(diff
+            .format_summary()
+            .contains("Ledger entries added: temporary nonce"));
+
+        let mut next = ContractExecutor::new(test_wasm::ping()).unwrap();
+        SnapshotLoader::from_snapshot(after.clone())
+            .unwrap()
+            .apply_to_environment(&mut next)
+            .unwrap();
+        assert_eq!(
+            next.storage_get(StorageKind::Persistent, &json!("counter"))
+                .unwrap(),
+            Some(soroban_env_host::xdr::ScVal::U32(1))
+        );
+        assert_eq!(
+            next.storage_get(StorageKind::Temporary, &json!("nonce"))
+                .unwrap(),
+            Some(soroban_env_host::xdr::ScVal::U32(2))
+        );
+        let resumed = SnapshotManager::capture(&next).unwrap();
+        assert!(!SnapshotManager::diff_snapshots(&after, &resumed).has_changes());
+
+        next.storage_set(
+            StorageKind::Temporary,
+            &json!("nonce"),
+            &json!({"type": "u32", "value": 3}),
+        )
+        .unwrap();
+        let changed = SnapshotManager::capture(&next).unwrap();
+        let diff = SnapshotManager::diff_snapshots(&after, &changed);
+        assert_eq!(diff.ledger_entries_modified.len(), 1);
+        assert!(diff.ledger_entries_added.is_empty());
+    }
+
     #[test]
     fn test_save_and_load_snapshot() {
         let mut snapshot = NetworkSnapshot::new(100, "Test Network", 1234567890);
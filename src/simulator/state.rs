@@ -55,6 +55,10 @@ pub struct NetworkSnapshot {
 
     /// Deployed contracts
     pub contracts: Vec<ContractState>,
+
+    /// Ledger entries kept as XDR, for state the fields above can't describe
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ledger_entries: Vec<LedgerEntryXdr>,
 }
 
 impl NetworkSnapshot {
@@ -65,9 +69,16 @@ impl NetworkSnapshot {
                 sequence,
                 timestamp,
                 network_passphrase: network_passphrase.into(),
+                network_id: None,
+                protocol_version: None,
+                base_reserve: None,
+                min_persistent_entry_ttl: None,
+                min_temp_entry_ttl: None,
+                max_entry_ttl: None,
             },
             accounts: Vec::new(),
             contracts: Vec::new(),
+            ledger_entries: Vec::new(),
         }
     }
 
@@ -172,6 +183,31 @@ pub struct LedgerMetadata {
 
     /// Network passphrase (e.g., "Test SDF Network ; September 2015")
     pub network_passphrase: String,
+
+    /// Network ID as hex, for networks whose passphrase isn't known.
+    /// Defaults to the SHA-256 of `network_passphrase`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_id: Option<String>,
+
+    /// Protocol version; defaults to the host's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<u32>,
+
+    /// Base reserve in stroops
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_reserve: Option<u32>,
+
+    /// Minimum TTL of new persistent entries, in ledgers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_persistent_entry_ttl: Option<u32>,
+
+    /// Minimum TTL of new temporary entries, in ledgers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_temp_entry_ttl: Option<u32>,
+
+    /// Maximum TTL of any entry, in ledgers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entry_ttl: Option<u32>,
 }
 
 impl LedgerMetadata {
@@ -183,13 +219,23 @@ impl LedgerMetadata {
             );
         }
 
-        if self.network_passphrase.is_empty() {
+        if self.network_passphrase.is_empty() && self.network_id.is_none() {
             return Err(SimulatorError::ValidationError(
                 "Network passphrase cannot be empty".to_string(),
             )
             .into());
         }
 
+        if let Some(network_id) = &self.network_id {
+            if network_id.len() != 64 || !network_id.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(SimulatorError::ValidationError(format!(
+                    "Network ID must be 32 bytes of hex: {}",
+                    network_id
+                ))
+                .into());
+            }
+        }
+
         Ok(())
     }
 }
@@ -213,6 +259,10 @@ pub struct AccountState {
     /// Optional account data (string key-value pairs)
     #[serde(default)]
     pub data: Option<BTreeMap<String, String>>,
+
+    /// Ledger in which the account last changed; defaults to the snapshot's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified_ledger: Option<u32>,
 }
 
 impl AccountState {
@@ -224,6 +274,7 @@ impl AccountState {
             sequence,
             flags: None,
             data: None,
+            last_modified_ledger: None,
         }
     }
 
@@ -290,6 +341,29 @@ pub struct ContractState {
     /// Contract instance storage (key-value pairs as JSON values)
     #[serde(default)]
     pub storage: BTreeMap<String, serde_json::Value>,
+
+    /// Persistent storage entries (key-value pairs as JSON values)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub persistent_storage: BTreeMap<String, serde_json::Value>,
+
+    /// Ledger up to which the contract's code and instance stay live
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_until_ledger: Option<u32>,
+
+    /// Ledger up to which each persistent entry stays live, by key. Entries
+    /// not listed share `live_until_ledger`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub persistent_live_until: BTreeMap<String, u32>,
+
+    /// Ledger in which the instance and persistent entries last changed;
+    /// defaults to the snapshot's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified_ledger: Option<u32>,
+
+    /// Ledger in which each persistent entry last changed, by key. Entries
+    /// not listed share `last_modified_ledger`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub persistent_last_modified: BTreeMap<String, u32>,
 }
 
 impl ContractState {
@@ -300,6 +374,11 @@ impl ContractState {
             wasm_hash: wasm_hash.into(),
             wasm_ref: None,
             storage: BTreeMap::new(),
+            persistent_storage: BTreeMap::new(),
+            live_until_ledger: None,
+            persistent_live_until: BTreeMap::new(),
+            last_modified_ledger: None,
+            persistent_last_modified: BTreeMap::new(),
         }
     }
 
@@ -350,6 +429,20 @@ impl ContractState {
     }
 }
 
+/// A ledger entry as base64 XDR, with its TTL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntryXdr {
+    /// `LedgerKey`
+    pub key: String,
+
+    /// `LedgerEntry`
+    pub entry: String,
+
+    /// Ledger up to which the entry stays live, for contract data and code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub live_until: Option<u32>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
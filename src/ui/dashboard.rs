@@ -1,5 +1,7 @@
 use crate::debugger::engine::DebuggerEngine;
 use crate::inspector::budget::BudgetInfo;
+use crate::inspector::locals::LocalsSnapshot;
+use crate::inspector::memory::{LinearMemory, MemoryInfo, DUMP_ROW_WIDTH};
 use crate::inspector::stack::CallFrame;
 use crate::{DebuggerError, Result};
 use crossterm::{
@@ -47,6 +49,7 @@ pub enum ActivePane {
     Storage,
     Budget,
     Log,
+    Locals,
 }
 
 impl ActivePane {
@@ -55,13 +58,15 @@ impl ActivePane {
             ActivePane::CallStack => ActivePane::Storage,
             ActivePane::Storage => ActivePane::Budget,
             ActivePane::Budget => ActivePane::Log,
-            ActivePane::Log => ActivePane::CallStack,
+            ActivePane::Log => ActivePane::Locals,
+            ActivePane::Locals => ActivePane::CallStack,
         }
     }
 
     fn prev(self) -> Self {
         match self {
-            ActivePane::CallStack => ActivePane::Log,
+            ActivePane::CallStack => ActivePane::Locals,
+            ActivePane::Locals => ActivePane::Log,
             ActivePane::Storage => ActivePane::CallStack,
             ActivePane::Budget => ActivePane::Storage,
             ActivePane::Log => ActivePane::Budget,
@@ -74,6 +79,7 @@ impl ActivePane {
             ActivePane::Storage => "Storage",
             ActivePane::Budget => "Budget Meters",
             ActivePane::Log => "Execution Log",
+            ActivePane::Locals => "Locals",
         }
     }
 }
@@ -97,6 +103,14 @@ pub struct DashboardApp {
     budget_history_cpu: VecDeque<f64>,
     budget_history_mem: VecDeque<f64>,
 
+    // Locals pane
+    locals: Option<LocalsSnapshot>,
+    locals_scroll: usize,
+    // Memory view shown in place of the locals pane
+    show_memory: bool,
+    memory: Option<(LinearMemory, MemoryInfo)>,
+    memory_scroll: usize,
+
     // Log pane
     log_entries: Vec<LogEntry>,
     log_scroll: usize,
@@ -164,6 +178,11 @@ impl DashboardApp {
             },
             budget_history_cpu: VecDeque::with_capacity(60),
             budget_history_mem: VecDeque::with_capacity(60),
+            locals: None,
+            locals_scroll: 0,
+            show_memory: false,
+            memory: None,
+            memory_scroll: 0,
             log_entries: Vec::new(),
             log_scroll: 0,
             log_scroll_state,
@@ -270,9 +289,39 @@ impl DashboardApp {
         let slen = self.storage_entries.len();
         self.storage_scroll_state = self.storage_scroll_state.content_length(slen);
 
+        // ── Locals ─────────────────────────────────────────────────────
+        self.locals = self.engine.inspect_locals().ok();
+
         self.last_refresh = Instant::now();
     }
 
+    // ── Memory view toggle ───────────────────────────────────────────────────
+    fn toggle_memory(&mut self) {
+        self.show_memory = !self.show_memory;
+        if !self.show_memory || self.memory.is_some() {
+            return;
+        }
+        let loaded = self.engine.memory_inspector().map(|inspector| {
+            let start = inspector.limits().data_start.unwrap_or(0) as usize;
+            (inspector.memory().clone(), inspector.summary(), start)
+        });
+        match loaded {
+            Ok((memory, info, data_start)) => {
+                // Start at the contract's static data rather than page zero
+                self.memory_scroll = data_start / DUMP_ROW_WIDTH;
+                if let Some(err) = &info.capture_error {
+                    let message = format!("Memory capture failed, showing initial image: {}", err);
+                    self.push_log(LogLevel::Warn, message);
+                }
+                self.memory = Some((memory, info));
+            }
+            Err(e) => {
+                self.show_memory = false;
+                self.push_log(LogLevel::Error, format!("Cannot inspect memory: {}", e));
+            }
+        }
+    }
+
     // ── Step action ──────────────────────────────────────────────────────────
     fn do_step(&mut self) {
         match self.engine.step() {
@@ -332,6 +381,12 @@ impl DashboardApp {
                 self.log_scroll = (self.log_scroll + 1).min(len.saturating_sub(1));
                 self.log_scroll_state = self.log_scroll_state.position(self.log_scroll);
             }
+            ActivePane::Locals if self.show_memory => {
+                self.memory_scroll = self.memory_scroll.saturating_add(1);
+            }
+            ActivePane::Locals => {
+                self.locals_scroll = self.locals_scroll.saturating_add(1);
+            }
             ActivePane::Budget => {}
         }
     }
@@ -352,6 +407,12 @@ impl DashboardApp {
                 self.log_scroll = self.log_scroll.saturating_sub(1);
                 self.log_scroll_state = self.log_scroll_state.position(self.log_scroll);
             }
+            ActivePane::Locals if self.show_memory => {
+                self.memory_scroll = self.memory_scroll.saturating_sub(1);
+            }
+            ActivePane::Locals => {
+                self.locals_scroll = self.locals_scroll.saturating_sub(1);
+            }
             ActivePane::Budget => {}
         }
     }
@@ -442,6 +503,7 @@ fn run_app<B: ratatui::backend::Backend>(
                     KeyCode::Char('2') => app.active_pane = ActivePane::Storage,
                     KeyCode::Char('3') => app.active_pane = ActivePane::Budget,
                     KeyCode::Char('4') => app.active_pane = ActivePane::Log,
+                    KeyCode::Char('5') => app.active_pane = ActivePane::Locals,
 
                     // ── Scroll ────────────────────────────────────
                     KeyCode::Down | KeyCode::Char('j') => {
@@ -458,6 +520,10 @@ fn run_app<B: ratatui::backend::Backend>(
                     KeyCode::Char('c') => {
                         app.do_continue();
                     }
+                    KeyCode::Char('m') | KeyCode::Char('M') => {
+                        app.toggle_memory();
+                        app.active_pane = ActivePane::Locals;
+                    }
                     KeyCode::Char('r') | KeyCode::Char('R') => {
                         app.refresh_state();
                         app.push_log(LogLevel::Info, "Manually refreshed state.".to_string());
@@ -555,9 +621,9 @@ fn render_header(f: &mut Frame, app: &DashboardApp, area: Rect) {
     f.render_widget(header, area);
 }
 
-// ─── Body (4 panes) ─────────────────────────────────────────────────────
+// ─── Body (5 panes) ─────────────────────────────────────────────────────
 fn render_body(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
-    // Split body into left column (top=call stack, bottom=budget) and right column (top=storage, bottom=log)
+    // Split body into left column (top=call stack, bottom=budget) and right column (storage, locals, log)
     let columns = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
@@ -570,19 +636,34 @@ fn render_body(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
 
     let right_column = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .constraints([
+            Constraint::Percentage(35),
+            Constraint::Percentage(30),
+            Constraint::Percentage(35),
+        ])
         .split(columns[1]);
 
     render_call_stack(f, app, left_column[0]);
     render_budget(f, app, left_column[1]);
     render_storage(f, app, right_column[0]);
-    render_log(f, app, right_column[1]);
+    render_locals(f, app, right_column[1]);
+    render_log(f, app, right_column[2]);
 }
 
 // ─── Call Stack pane ──────────────────────────────────────────────────────
 fn render_call_stack(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
     let is_active = app.active_pane == ActivePane::CallStack;
-    let block = pane_block("  Call Stack", "1", is_active);
+    // Nested frames are only costed with --estimate-frame-budgets
+    let uncosted = app
+        .call_stack_frames
+        .iter()
+        .any(|frame| frame.depth > 0 && frame.cpu_instructions.is_none());
+    let title = if uncosted {
+        "  Call Stack  (nested cost: --estimate-frame-budgets)"
+    } else {
+        "  Call Stack"
+    };
+    let block = pane_block(title, "1", is_active);
 
     let inner = block.inner(area);
     f.render_widget(block, area);
@@ -604,7 +685,7 @@ fn render_call_stack(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
         .enumerate()
         .map(|(i, frame)| {
             let is_top = i == depth - 1;
-            let indent = "  ".repeat(i);
+            let indent = "  ".repeat(frame.depth);
             let arrow = if is_top { "→ " } else { "└─ " };
 
             let contract_ctx = frame
@@ -617,6 +698,7 @@ fn render_call_stack(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
                 .duration
                 .map(|d| format!(" ({:.2}ms)", d.as_secs_f64() * 1000.0))
                 .unwrap_or_default();
+            let cost_ctx = frame.cost();
 
             let func_color = if is_top { COLOR_ACCENT } else { COLOR_TEXT };
             let frame_style = if is_top {
@@ -633,8 +715,9 @@ fn render_call_stack(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
                     format!("{}{}", indent, arrow),
                     Style::default().fg(COLOR_TEXT_DIM),
                 ),
-                Span::styled(frame.function.clone(), frame_style),
+                Span::styled(frame.signature(), frame_style),
                 Span::styled(contract_ctx, Style::default().fg(COLOR_PURPLE)),
+                Span::styled(cost_ctx, Style::default().fg(COLOR_TEXT_DIM)),
                 Span::styled(dur_ctx, Style::default().fg(COLOR_TEXT_DIM)),
             ]))
         })
@@ -724,6 +807,169 @@ fn render_storage(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
     );
 }
 
+// ─── Locals pane ──────────────────────────────────────────────────────────
+fn render_locals(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
+    if app.show_memory {
+        render_memory(f, app, area);
+        return;
+    }
+    let is_active = app.active_pane == ActivePane::Locals;
+    let title = match &app.locals {
+        Some(snapshot) => format!(
+            "  Locals  ({})",
+            snapshot
+                .function_name
+                .clone()
+                .unwrap_or_else(|| format!("func_{}", snapshot.function_index))
+        ),
+        None => "  Locals".to_string(),
+    };
+    let block = pane_block(&title, "5", is_active);
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let Some(snapshot) = &app.locals else {
+        let msg = Paragraph::new(Line::from(vec![Span::styled(
+            "  (start with --instruction-debug to inspect locals)",
+            Style::default().fg(COLOR_TEXT_DIM),
+        )]))
+        .style(Style::default().bg(COLOR_SURFACE))
+        .wrap(Wrap { trim: false });
+        f.render_widget(msg, inner);
+        return;
+    };
+
+    let value_span = |value: Option<String>| match value {
+        Some(v) => Span::styled(v, Style::default().fg(COLOR_TEXT)),
+        None => Span::styled("?", Style::default().fg(COLOR_TEXT_DIM)),
+    };
+
+    let mut lines: Vec<Line> = Vec::new();
+    for local in &snapshot.locals {
+        let name = local
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("${}", local.index));
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(" {:<14}", truncate(&name, 14)),
+                Style::default().fg(if local.is_param {
+                    COLOR_PURPLE
+                } else {
+                    COLOR_CYAN
+                }),
+            ),
+            Span::styled(
+                format!("{:<4} ", local.value_type),
+                Style::default().fg(COLOR_TEXT_DIM),
+            ),
+            value_span(local.value.map(|v| v.to_string())),
+        ]));
+    }
+
+    let stack_title = if snapshot.stack_exact {
+        format!(" ── stack ({})", snapshot.value_stack.len())
+    } else {
+        format!(" ── stack ({}, approximate)", snapshot.value_stack.len())
+    };
+    lines.push(Line::from(Span::styled(
+        stack_title,
+        Style::default().fg(COLOR_ACCENT),
+    )));
+    for (depth, slot) in snapshot.value_stack.iter().rev().enumerate() {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(" [{:<2}]          ", depth),
+                Style::default().fg(COLOR_YELLOW),
+            ),
+            Span::styled(
+                format!("{:<4} ", slot.value_type),
+                Style::default().fg(COLOR_TEXT_DIM),
+            ),
+            value_span(slot.value.map(|v| v.to_string())),
+        ]));
+    }
+
+    if !snapshot.variables.is_empty() {
+        lines.push(Line::from(Span::styled(
+            " ── source variables",
+            Style::default().fg(COLOR_ACCENT),
+        )));
+        for var in &snapshot.variables {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!(" {:<14}", truncate(&var.variable.name, 14)),
+                    Style::default().fg(COLOR_GREEN),
+                ),
+                Span::styled(
+                    format!("{} ", var.variable.type_name.as_deref().unwrap_or("?")),
+                    Style::default().fg(COLOR_TEXT_DIM),
+                ),
+                value_span(var.value.map(|v| v.to_string())),
+            ]));
+        }
+    }
+
+    app.locals_scroll = app.locals_scroll.min(lines.len().saturating_sub(1));
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().bg(COLOR_SURFACE))
+        .scroll((app.locals_scroll as u16, 0));
+    f.render_widget(paragraph, inner);
+}
+
+// ─── Memory view ──────────────────────────────────────────────────────────
+fn render_memory(f: &mut Frame, app: &mut DashboardApp, area: Rect) {
+    let is_active = app.active_pane == ActivePane::Locals;
+    let title = match &app.memory {
+        Some((memory, info)) => format!(
+            "  Memory  ({} pages{})",
+            memory.pages(),
+            match info.growth_pages() {
+                Some(grown) if grown > 0 => format!(", +{} grown", grown),
+                _ => String::new(),
+            }
+        ),
+        None => "  Memory".to_string(),
+    };
+    let block = pane_block(&title, "5", is_active);
+
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let Some((memory, _)) = &app.memory else {
+        return;
+    };
+
+    let total_rows = memory.len().div_ceil(DUMP_ROW_WIDTH);
+    app.memory_scroll = app.memory_scroll.min(total_rows.saturating_sub(1));
+    let rows = memory.dump(
+        (app.memory_scroll * DUMP_ROW_WIDTH) as u32,
+        inner.height as usize * DUMP_ROW_WIDTH,
+    );
+
+    let lines: Vec<Line> = rows
+        .iter()
+        .map(|row| {
+            let hex: Vec<String> = row.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:08x} ", row.address),
+                    Style::default().fg(COLOR_YELLOW),
+                ),
+                Span::styled(
+                    format!("{:<47} ", hex.join(" ")),
+                    Style::default().fg(COLOR_TEXT),
+                ),
+                Span::styled(row.ascii(), Style::default().fg(COLOR_TEXT_DIM)),
+            ])
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines).style(Style::default().bg(COLOR_SURFACE));
+    f.render_widget(paragraph, inner);
+}
+
 // ─── Budget pane ──────────────────────────────────────────────────────────
 fn render_budget(f: &mut Frame, app: &DashboardApp, area: Rect) {
     let is_active = app.active_pane == ActivePane::Budget;
@@ -972,7 +1218,7 @@ fn render_status_bar(f: &mut Frame, app: &DashboardApp, area: Rect) {
             Style::default().fg(msg_color).bg(COLOR_SURFACE),
         ),
         Span::styled(
-            " │ Tab=next pane  ↑↓/jk=scroll  s=step  c=continue  r=refresh  q=quit ",
+            " │ Tab=next pane  ↑↓/jk=scroll  s=step  c=continue  m=memory  r=refresh  q=quit ",
             Style::default().fg(COLOR_TEXT_DIM).bg(COLOR_SURFACE),
         ),
     ]);
@@ -1010,7 +1256,7 @@ fn render_help_overlay(f: &mut Frame, area: Rect) {
                 .add_modifier(Modifier::BOLD),
         )]),
         bind("Tab / Shift+Tab", "Cycle panes forward / backward"),
-        bind("1 / 2 / 3 / 4", "Jump directly to pane"),
+        bind("1 / 2 / 3 / 4 / 5", "Jump directly to pane"),
         bind("↑ / k", "Scroll active pane up"),
         bind("↓ / j", "Scroll active pane down"),
         Line::from(""),
@@ -1023,6 +1269,7 @@ fn render_help_overlay(f: &mut Frame, area: Rect) {
         bind("s / S", "Step (one instruction)"),
         bind("c", "Continue execution"),
         bind("r / R", "Refresh state manually"),
+        bind("m / M", "Toggle memory view in the locals pane"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "  General",
@@ -1,6 +1,10 @@
 use crate::debugger::instruction_pointer::StepMode;
+use crate::inspector::locals::LocalsSnapshot;
+use crate::inspector::memory::{MemoryInfo, MemoryRow, DUMP_ROW_WIDTH};
 use crate::runtime::instruction::Instruction;
+use crate::runtime::memory_capture::CapturePoint;
 use crossterm::style::Stylize;
+use soroban_env_host::xdr::ScVal;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Pretty printing utilities for debugger output
@@ -130,6 +134,172 @@ impl Formatter {
         )
     }
 
+    /// Format WASM locals, the value stack and source variables.
+    pub fn format_locals(snapshot: &LocalsSnapshot) -> String {
+        let unknown = || "<unknown>".to_string();
+        let function = snapshot
+            .function_name
+            .clone()
+            .unwrap_or_else(|| format!("func_{}", snapshot.function_index));
+
+        let mut lines = vec![format!(
+            "Locals in {} @ {:08x}",
+            function, snapshot.instruction_offset
+        )];
+        if snapshot.locals.is_empty() {
+            lines.push("  (none)".to_string());
+        }
+        for local in &snapshot.locals {
+            let name = local
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("${}", local.index));
+            lines.push(format!(
+                "  {:<3} {:<16} {:<4} {}{}",
+                local.index,
+                name,
+                local.value_type,
+                local.value.map(|v| v.to_string()).unwrap_or_else(unknown),
+                if local.is_param { "  [param]" } else { "" }
+            ));
+        }
+
+        lines.push(format!(
+            "Value Stack ({}){}",
+            snapshot.value_stack.len(),
+            if snapshot.stack_exact {
+                ""
+            } else {
+                " [approximate]"
+            }
+        ));
+        if snapshot.value_stack.is_empty() {
+            lines.push("  (empty)".to_string());
+        }
+        for (depth, slot) in snapshot.value_stack.iter().rev().enumerate() {
+            lines.push(format!(
+                "  [{}] {:<4} {}",
+                depth,
+                slot.value_type,
+                slot.value.map(|v| v.to_string()).unwrap_or_else(unknown)
+            ));
+        }
+
+        if !snapshot.variables.is_empty() {
+            lines.push("Source Variables".to_string());
+            for var in &snapshot.variables {
+                lines.push(format!(
+                    "  {}: {} = {}",
+                    var.variable.name,
+                    var.variable.type_name.as_deref().unwrap_or("?"),
+                    var.value.map(|v| v.to_string()).unwrap_or_else(unknown)
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// Format a Soroban value compactly, e.g. `[Balance, GABC…]` or `{a: 1}`.
+    pub fn format_sc_val(value: &ScVal) -> String {
+        let join = |items: Vec<String>| items.join(", ");
+        match value {
+            ScVal::Bool(b) => b.to_string(),
+            ScVal::Void => "()".to_string(),
+            ScVal::U32(v) => v.to_string(),
+            ScVal::I32(v) => v.to_string(),
+            ScVal::U64(v) => v.to_string(),
+            ScVal::I64(v) => v.to_string(),
+            ScVal::Timepoint(v) => format!("timepoint({})", v.0),
+            ScVal::Duration(v) => format!("duration({})", v.0),
+            ScVal::U128(p) => (((p.hi as u128) << 64) | p.lo as u128).to_string(),
+            ScVal::I128(p) => (((p.hi as i128) << 64) | p.lo as i128).to_string(),
+            ScVal::Bytes(b) => format!("0x{}", hex::encode(b.as_slice())),
+            ScVal::String(s) => format!("{:?}", s.to_utf8_string_lossy()),
+            ScVal::Symbol(s) => s.to_utf8_string_lossy(),
+            ScVal::Address(a) => a.to_string(),
+            ScVal::Vec(Some(items)) => {
+                format!(
+                    "[{}]",
+                    join(items.iter().map(Self::format_sc_val).collect())
+                )
+            }
+            ScVal::Map(Some(entries)) => format!(
+                "{{{}}}",
+                join(
+                    entries
+                        .iter()
+                        .map(|e| format!(
+                            "{}: {}",
+                            Self::format_sc_val(&e.key),
+                            Self::format_sc_val(&e.val)
+                        ))
+                        .collect()
+                )
+            ),
+            other => format!("{:?}", other),
+        }
+    }
+
+    /// Format rows of a linear memory hex dump.
+    pub fn format_memory_dump(rows: &[MemoryRow]) -> String {
+        if rows.is_empty() {
+            return "  (address out of bounds)".to_string();
+        }
+        rows.iter()
+            .map(|row| {
+                let hex: Vec<String> = row.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                format!(
+                    "  {:08x}  {:<width$}  |{}|",
+                    row.address,
+                    hex.join(" "),
+                    row.ascii(),
+                    width = DUMP_ROW_WIDTH * 3 - 1
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Format linear memory size and growth.
+    pub fn format_memory_info(info: &MemoryInfo) -> String {
+        let mut lines = vec![format!(
+            "Linear Memory: {} bytes ({} pages)",
+            info.size_bytes,
+            info.size_bytes / crate::inspector::memory::WASM_PAGE_SIZE
+        )];
+        lines.push(format!(
+            "  declared: {} pages initial, {}",
+            info.declared_initial_pages,
+            info.maximum_pages
+                .map(|m| format!("{} max", m))
+                .unwrap_or_else(|| "no max".to_string())
+        ));
+        if let (Some(entry), Some(exit)) = (info.pages_at_entry, info.pages_at_exit) {
+            lines.push(format!(
+                "  invocation: {} pages at entry, {} at exit (+{})",
+                entry,
+                exit,
+                exit.saturating_sub(entry)
+            ));
+        }
+        match (info.capture_point, &info.capture_error) {
+            (Some(CapturePoint::Return), _) => {
+                lines.push("  source: captured when the function returned".to_string())
+            }
+            (Some(CapturePoint::Trap), _) => {
+                lines.push("  source: captured just before the contract trapped".to_string())
+            }
+            (None, Some(err)) => {
+                lines.push(format!("  source: initial image (capture failed: {})", err))
+            }
+            (None, None) => {
+                lines.push("  source: initial image (nothing executed yet)".to_string())
+            }
+        }
+        lines.join("\n")
+    }
+
     /// Format stepping help.
     pub fn format_stepping_help() -> String {
         [
@@ -143,6 +313,8 @@ impl Formatter {
             "  c, continue   Continue execution",
             "  i, info       Show instruction state",
             "  ctx, context  Show instruction context",
+            "  l, locals     Show locals and value stack",
+            "  m, memory     Inspect linear memory (mem help)",
             "  h, help       Show this help",
             "  q, quit       Exit stepping mode",
         ]
@@ -1,5 +1,6 @@
 pub mod dashboard;
 pub mod formatter;
+pub mod repl;
 pub mod tui;
 
 pub use dashboard::run_dashboard;
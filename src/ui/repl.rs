@@ -0,0 +1,312 @@
+//! Line editing support for the interactive REPL: persistent history and
+//! tab completion of commands, exported functions and argument templates
+//! built from the contract spec.
+
+use crate::utils::wasm::{parse_function_signatures, parse_functions, FunctionSignature};
+use crate::{DebuggerError, Result};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Top-level REPL commands offered for completion.
+pub const COMMANDS: &[&str] = &[
+    "step",
+    "continue",
+    "inspect",
+    "call",
+    "storage",
+    "ledger",
+    "snapshot",
+    "stack",
+    "budget",
+    "locals",
+    "memory",
+    "eval",
+    "vars",
+    "watch",
+    "unwatch",
+    "session",
+    "break",
+    "list-breaks",
+    "clear",
+    "history",
+    "help",
+    "quit",
+];
+
+const STORAGE_SUBCOMMANDS: &[&str] = &["get", "set", "del"];
+const STORAGE_KINDS: &[&str] = &["instance", "persistent", "temporary"];
+const LEDGER_SUBCOMMANDS: &[&str] = &["info", "advance"];
+const SNAPSHOT_SUBCOMMANDS: &[&str] = &["save", "load"];
+const SESSION_SUBCOMMANDS: &[&str] = &["save", "load"];
+const MEMORY_SUBCOMMANDS: &[&str] = &["info", "dump", "around", "find", "str", "int", "help"];
+
+/// Completion helper driven by the contract's exports and spec.
+pub struct ReplHelper {
+    functions: Vec<String>,
+    signatures: Vec<FunctionSignature>,
+    filenames: FilenameCompleter,
+}
+
+impl ReplHelper {
+    pub fn new(wasm: &[u8]) -> Self {
+        Self {
+            functions: parse_functions(wasm).unwrap_or_default(),
+            signatures: parse_function_signatures(wasm).unwrap_or_default(),
+            filenames: FilenameCompleter::new(),
+        }
+    }
+
+    pub fn signature(&self, function: &str) -> Option<&FunctionSignature> {
+        self.signatures.iter().find(|s| s.name == function)
+    }
+
+    fn function_candidates(&self, word: &str) -> Vec<Pair> {
+        self.functions
+            .iter()
+            .filter(|f| f.starts_with(word))
+            .map(|f| Pair {
+                display: match self.signature(f) {
+                    Some(sig) => format_signature(sig),
+                    None => f.clone(),
+                },
+                replacement: f.clone(),
+            })
+            .collect()
+    }
+
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<Pair>) {
+        let prefix = &line[..pos];
+        let start = prefix
+            .rfind(char::is_whitespace)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let word = &prefix[start..];
+        let previous: Vec<&str> = prefix[..start].split_whitespace().collect();
+
+        let candidates = match previous.as_slice() {
+            [] => words(COMMANDS, word),
+            ["call"] | ["break"] | ["clear"] => self.function_candidates(word),
+            ["call", function] if word.is_empty() => match self.signature(function) {
+                Some(sig) if !sig.params.is_empty() => vec![Pair {
+                    display: format_signature(sig),
+                    replacement: argument_template(sig),
+                }],
+                _ => Vec::new(),
+            },
+            ["storage"] => words(STORAGE_SUBCOMMANDS, word),
+            ["storage", "get" | "set" | "del"] => words(STORAGE_KINDS, word),
+            ["ledger"] => words(LEDGER_SUBCOMMANDS, word),
+            ["snapshot"] => words(SNAPSHOT_SUBCOMMANDS, word),
+            ["session"] => words(SESSION_SUBCOMMANDS, word),
+            ["memory" | "mem"] => words(MEMORY_SUBCOMMANDS, word),
+            _ => Vec::new(),
+        };
+        (start, candidates)
+    }
+}
+
+fn words(options: &[&str], word: &str) -> Vec<Pair> {
+    options
+        .iter()
+        .filter(|o| o.starts_with(word))
+        .map(|o| Pair {
+            display: o.to_string(),
+            replacement: o.to_string(),
+        })
+        .collect()
+}
+
+/// Render a signature as `name(param: Type, ...) -> Type`.
+pub fn format_signature(sig: &FunctionSignature) -> String {
+    let params: Vec<String> = sig
+        .params
+        .iter()
+        .map(|p| format!("{}: {}", p.name, p.type_name))
+        .collect();
+    match &sig.return_type {
+        Some(ret) => format!("{}({}) -> {}", sig.name, params.join(", "), ret),
+        None => format!("{}({})", sig.name, params.join(", ")),
+    }
+}
+
+/// A JSON argument array for `sig` with a placeholder per parameter.
+pub fn argument_template(sig: &FunctionSignature) -> String {
+    let values: Vec<String> = sig
+        .params
+        .iter()
+        .map(|p| placeholder(&p.name, &p.type_name))
+        .collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn placeholder(name: &str, type_name: &str) -> String {
+    match type_name {
+        "U32" | "I32" | "U64" | "I64" | "U128" | "I128" => {
+            format!(r#"{{"type": "{}", "value": 0}}"#, type_name.to_lowercase())
+        }
+        "Bool" => "false".to_string(),
+        "Symbol" | "String" | "Address" => format!(
+            r#"{{"type": "{}", "value": "<{}>"}}"#,
+            type_name.to_lowercase(),
+            name
+        ),
+        t if t.starts_with("Vec<") => "[]".to_string(),
+        t if t.starts_with("Option<") => "null".to_string(),
+        _ => format!(r#""<{}>""#, name),
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let previous: Vec<&str> = line[..pos].split_whitespace().collect();
+        let completing_path = matches!(
+            previous.as_slice(),
+            ["snapshot" | "session", "save" | "load", ..]
+        ) && (previous.len() > 2
+            || line[..pos].ends_with(char::is_whitespace));
+        if completing_path {
+            return self.filenames.complete_path(line, pos);
+        }
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Where REPL history is persisted between sessions.
+pub fn history_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".soroban-debug").join("repl_history"))
+}
+
+/// Split command arguments into JSON values. Tokens starting with `[`, `{`
+/// or `"` are read as JSON (and may contain spaces); other tokens are
+/// parsed as JSON when possible and kept as plain strings otherwise.
+pub fn split_args(input: &str) -> Result<Vec<Value>> {
+    let mut values = Vec::new();
+    let mut rest = input.trim_start();
+
+    while !rest.is_empty() {
+        if rest.starts_with(['[', '{', '"']) {
+            let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+            let value = match stream.next() {
+                Some(Ok(value)) => value,
+                Some(Err(e)) => {
+                    return Err(DebuggerError::InvalidArguments(format!(
+                        "Invalid JSON argument '{}': {}",
+                        rest, e
+                    ))
+                    .into())
+                }
+                None => break,
+            };
+            rest = &rest[stream.byte_offset()..];
+            values.push(value);
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let token = &rest[..end];
+            values.push(
+                serde_json::from_str(token).unwrap_or_else(|_| Value::String(token.to_string())),
+            );
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::wasm::FunctionParam;
+    use serde_json::json;
+
+    fn helper() -> ReplHelper {
+        ReplHelper {
+            functions: vec!["transfer".into(), "total".into(), "init".into()],
+            signatures: vec![FunctionSignature {
+                name: "transfer".into(),
+                params: vec![
+                    FunctionParam {
+                        name: "to".into(),
+                        type_name: "Address".into(),
+                    },
+                    FunctionParam {
+                        name: "amount".into(),
+                        type_name: "I128".into(),
+                    },
+                ],
+                return_type: None,
+            }],
+            filenames: FilenameCompleter::new(),
+        }
+    }
+
+    fn replacements(line: &str) -> (usize, Vec<String>) {
+        let (start, pairs) = helper().candidates(line, line.len());
+        (start, pairs.into_iter().map(|p| p.replacement).collect())
+    }
+
+    #[test]
+    fn completes_commands_and_functions() {
+        assert_eq!(replacements("sto"), (0, vec!["storage".to_string()]));
+        assert_eq!(
+            replacements("call t"),
+            (5, vec!["transfer".to_string(), "total".to_string()])
+        );
+        assert_eq!(
+            replacements("storage set p"),
+            (12, vec!["persistent".to_string()])
+        );
+    }
+
+    #[test]
+    fn completes_argument_template_from_spec() {
+        let (start, values) = replacements("call transfer ");
+        assert_eq!(start, 14);
+        assert_eq!(
+            values,
+            vec![r#"[{"type": "address", "value": "<to>"}, {"type": "i128", "value": 0}]"#]
+        );
+        let template: Value = serde_json::from_str(&values[0]).unwrap();
+        assert!(template.is_array());
+        assert!(replacements("call init ").1.is_empty());
+    }
+
+    #[test]
+    fn split_args_reads_json_and_bare_tokens() {
+        let args = split_args(r#"instance counter 5 {"type": "u32", "value": 7} "a b""#).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                json!("instance"),
+                json!("counter"),
+                json!(5),
+                json!({"type": "u32", "value": 7}),
+                json!("a b"),
+            ]
+        );
+        assert!(split_args("[1, 2").is_err());
+        assert!(split_args("   ").unwrap().is_empty());
+    }
+}
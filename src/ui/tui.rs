@@ -1,12 +1,31 @@
 use crate::debugger::engine::DebuggerEngine;
+use crate::debugger::evaluator::Evaluator;
+use crate::debugger::session::Session;
+use crate::inspector::storage::StorageState;
 use crate::inspector::{BudgetInspector, StorageInspector};
-use crate::Result;
-use std::io::{self, Write};
+use crate::runtime::StorageKind;
+use crate::ui::formatter::Formatter;
+use crate::ui::repl::{self, ReplHelper};
+use crate::{DebuggerError, Result};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use std::path::{Path, PathBuf};
+
+/// Seconds added per ledger by `ledger advance` when no duration is given.
+const LEDGER_CLOSE_SECONDS: u64 = 5;
 
 /// Terminal user interface for interactive debugging.
 pub struct DebuggerUI {
     engine: DebuggerEngine,
     storage_inspector: StorageInspector,
+    evaluator: Evaluator,
+    contract_path: Option<PathBuf>,
+    watches: Vec<String>,
+    /// Commands entered in this session, oldest first
+    history: Vec<String>,
+    /// Restored history not yet handed to the line editor
+    pending_history: Vec<String>,
 }
 
 impl DebuggerUI {
@@ -14,28 +33,91 @@ impl DebuggerUI {
         Ok(Self {
             engine,
             storage_inspector: StorageInspector::new(),
+            evaluator: Evaluator::new(),
+            contract_path: None,
+            watches: Vec::new(),
+            history: Vec::new(),
+            pending_history: Vec::new(),
         })
     }
 
+    /// Record where the contract was loaded from, for saved sessions.
+    pub fn set_contract_path(&mut self, path: PathBuf) {
+        self.contract_path = Some(path);
+    }
+
+    /// Restore the REPL side of a saved session: watches, history and the
+    /// tracked storage view. The engine is restored by [`Session::restore`].
+    pub fn apply_session(&mut self, session: &Session) {
+        if let Some(path) = &session.contract.path {
+            self.contract_path = Some(path.clone());
+        }
+        self.watches = session.watches.clone();
+        self.history = session.history.clone();
+        self.pending_history = session.history.clone();
+        self.storage_inspector = StorageInspector::new();
+        for (key, value) in &session.storage.entries {
+            self.storage_inspector.set(key.clone(), value.clone());
+        }
+    }
+
+    /// Capture the engine and REPL state as a session.
+    pub fn capture_session(&mut self) -> Session {
+        let mut session = Session::capture(&mut self.engine, self.contract_path.as_deref());
+        session.storage = StorageState {
+            entries: self.storage_inspector.get_all().clone(),
+        };
+        session.watches = self.watches.clone();
+        session.history = self.history.clone();
+        session
+    }
+
     /// Run the interactive UI loop.
     pub fn run(&mut self) -> Result<()> {
         self.print_help();
 
+        let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new().map_err(|e| {
+            DebuggerError::FileError(format!("Failed to initialise line editor: {}", e))
+        })?;
+        editor.set_helper(Some(ReplHelper::new(self.engine.executor().wasm_bytes())));
+        let history_path = repl::history_path();
+        if let Some(path) = &history_path {
+            // A missing history file just means this is the first session
+            let _ = editor.load_history(path);
+        }
+
         loop {
-            print!("\n(debug) ");
-            io::stdout().flush().map_err(|e| {
-                crate::DebuggerError::FileError(format!("Failed to flush stdout: {}", e))
-            })?;
+            for entry in self.pending_history.drain(..) {
+                let _ = editor.add_history_entry(entry);
+            }
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).map_err(|e| {
-                crate::DebuggerError::FileError(format!("Failed to read line: {}", e))
-            })?;
+            let input = match editor.readline("\n(debug) ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => {
+                    return Err(
+                        DebuggerError::FileError(format!("Failed to read line: {}", e)).into(),
+                    )
+                }
+            };
 
             let command = input.trim();
             if command.is_empty() {
                 continue;
             }
+            let _ = editor.add_history_entry(command);
+            self.history.push(command.to_string());
+            if let Some(path) = &history_path {
+                save_history(&mut editor, path);
+            }
+
+            if command == "history" {
+                for (i, entry) in editor.history().iter().enumerate() {
+                    println!("{:>5}  {}", i + 1, entry);
+                }
+                continue;
+            }
 
             match self.handle_command(command) {
                 Ok(should_exit) => {
@@ -73,9 +155,67 @@ impl DebuggerUI {
             "i" | "inspect" => {
                 self.inspect();
             }
-            "storage" => {
+            "call" => {
+                if parts.len() < 2 {
+                    println!("Usage: call <function> [json-args]");
+                } else {
+                    let args = Some(skip_words(command, 2)).filter(|rest| !rest.is_empty());
+                    let result = self.engine.execute(parts[1], args)?;
+                    println!("Result: {}", result);
+                    self.show_watches();
+                }
+            }
+            "storage" if parts.len() == 1 => {
                 self.storage_inspector.display();
             }
+            "storage" => self.storage_command(&parts, command)?,
+            "ledger" => self.ledger_command(&parts)?,
+            "snapshot" => match parts.get(1..) {
+                Some(["save", path]) => {
+                    self.engine.executor().save_snapshot(Path::new(path))?;
+                    println!("Snapshot saved to {}", path);
+                }
+                Some(["load", path]) => {
+                    self.engine.executor_mut().load_snapshot(Path::new(path))?;
+                    println!("Snapshot loaded from {}", path);
+                }
+                _ => println!("Usage: snapshot save|load <file>"),
+            },
+            "session" => match parts.get(1..) {
+                Some(["save", path]) => {
+                    self.capture_session().save(Path::new(path))?;
+                    println!("Session saved to {}", path);
+                }
+                Some(["load", path]) => {
+                    let session = Session::load(Path::new(path))?;
+                    self.engine = session.restore()?;
+                    self.evaluator = Evaluator::new();
+                    self.apply_session(&session);
+                    println!(
+                        "Session loaded from {} ({} breakpoints, {} watches)",
+                        path,
+                        session.config.debug.breakpoints.len(),
+                        session.watches.len()
+                    );
+                }
+                _ => println!("Usage: session save|load <file>"),
+            },
+            "watch" => {
+                let expr = skip_words(command, 1);
+                if expr.is_empty() {
+                    self.show_watches();
+                } else {
+                    self.watches.push(expr.to_string());
+                    println!("Watch {}: {}", self.watches.len(), expr);
+                }
+            }
+            "unwatch" => match parts.get(1).and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) if n >= 1 && n <= self.watches.len() => {
+                    let expr = self.watches.remove(n - 1);
+                    println!("Removed watch {}: {}", n, expr);
+                }
+                _ => println!("Usage: unwatch <number>  (see: watch)"),
+            },
             "stack" => {
                 if let Ok(state) = self.engine.state().lock() {
                     state.call_stack().display();
@@ -84,6 +224,35 @@ impl DebuggerUI {
             "budget" => {
                 BudgetInspector::display(self.engine.executor().host());
             }
+            "locals" => match self.engine.inspect_locals() {
+                Ok(snapshot) => println!("{}", Formatter::format_locals(&snapshot)),
+                Err(e) => println!("Cannot inspect locals: {}", e),
+            },
+            "memory" | "mem" => {
+                let output = self.engine.memory_inspector()?.run_command(&parts[1..])?;
+                println!("{}", output);
+            }
+            "eval" => {
+                let expr = command["eval".len()..].trim();
+                if expr.is_empty() {
+                    println!("Usage: eval <expr>  (e.g. eval balance(alice) >= 100)");
+                } else {
+                    let result = self.evaluator.evaluate(self.engine.executor(), expr)?;
+                    let value = Formatter::format_sc_val(&result.value);
+                    match result.binding {
+                        Some(name) => println!("{} = {}", name, value),
+                        None => println!("{}", value),
+                    }
+                }
+            }
+            "vars" => {
+                if self.evaluator.variables().is_empty() {
+                    println!("No variables bound (use: eval let <name> = <expr>)");
+                }
+                for (name, value) in self.evaluator.variables() {
+                    println!("{} = {}", name, Formatter::format_sc_val(value));
+                }
+            }
             "break" => {
                 if parts.len() < 2 {
                     tracing::warn!("breakpoint set without function name");
@@ -122,6 +291,86 @@ impl DebuggerUI {
         Ok(false)
     }
 
+    fn storage_command(&mut self, parts: &[&str], command: &str) -> Result<()> {
+        const USAGE: &str =
+            "Usage: storage get|del <instance|persistent|temporary> <key>\n       storage set <instance|persistent|temporary> <key> <value>";
+
+        let Some(kind) = parts.get(2).and_then(|k| StorageKind::parse(k)) else {
+            println!("{}", USAGE);
+            return Ok(());
+        };
+        // Keys and values may be JSON containing spaces, so re-split the raw line
+        let args = repl::split_args(skip_words(command, 3))?;
+        let executor = self.engine.executor_mut();
+
+        match (parts[1], args.as_slice()) {
+            ("get", [key]) => match executor.storage_get(kind, key)? {
+                Some(value) => println!("{}", Formatter::format_sc_val(&value)),
+                None => println!("(not set)"),
+            },
+            ("set", [key, value]) => {
+                executor.storage_set(kind, key, value)?;
+                println!("Stored {} = {}", key, value);
+            }
+            ("del", [key]) => {
+                if executor.storage_remove(kind, key)? {
+                    println!("Removed {}", key);
+                } else {
+                    println!("(not set)");
+                }
+            }
+            _ => println!("{}", USAGE),
+        }
+        Ok(())
+    }
+
+    fn ledger_command(&mut self, parts: &[&str]) -> Result<()> {
+        let executor = self.engine.executor_mut();
+        let (sequence, timestamp) = match parts.get(1..) {
+            Some([]) | Some(["info"]) => executor.ledger_info(),
+            Some(["advance", rest @ ..]) if rest.len() <= 2 => {
+                let ledgers = match rest.first() {
+                    Some(n) => n.parse::<u32>().map_err(|_| {
+                        DebuggerError::InvalidArguments(format!("Invalid ledger count: {}", n))
+                    })?,
+                    None => 1,
+                };
+                let seconds = match rest.get(1) {
+                    Some(s) => s.parse::<u64>().map_err(|_| {
+                        DebuggerError::InvalidArguments(format!("Invalid seconds: {}", s))
+                    })?,
+                    None => u64::from(ledgers) * LEDGER_CLOSE_SECONDS,
+                };
+                executor.advance_ledger(ledgers, seconds)
+            }
+            _ => {
+                println!("Usage: ledger [info] | ledger advance [ledgers] [seconds]");
+                return Ok(());
+            }
+        };
+        println!("Ledger sequence: {}, timestamp: {}", sequence, timestamp);
+        Ok(())
+    }
+
+    /// Evaluate and print every watch expression.
+    fn show_watches(&mut self) {
+        if self.watches.is_empty() {
+            return;
+        }
+        println!("Watches:");
+        for (i, expr) in self.watches.iter().enumerate() {
+            match self.evaluator.evaluate(self.engine.executor(), expr) {
+                Ok(result) => println!(
+                    "  {}: {} = {}",
+                    i + 1,
+                    expr,
+                    Formatter::format_sc_val(&result.value)
+                ),
+                Err(e) => println!("  {}: {} = <error: {}>", i + 1, expr, e),
+            }
+        }
+    }
+
     fn inspect(&self) {
         println!("\n=== Current State ===");
         if let Ok(state) = self.engine.state().lock() {
@@ -144,13 +393,54 @@ impl DebuggerUI {
         println!("  step | s           Step execution");
         println!("  continue | c       Continue execution");
         println!("  inspect | i        Show current state");
+        println!("  call <f> [args]    Invoke a function (tab completes argument templates)");
         println!("  storage            Show tracked storage view");
+        println!("  storage get <kind> <key>");
+        println!("  storage set <kind> <key> <value>");
+        println!("  storage del <kind> <key>");
+        println!(
+            "                     Edit contract storage (kind: instance|persistent|temporary)"
+        );
+        println!("  ledger [info]      Show ledger sequence and timestamp");
+        println!("  ledger advance [n] [secs]");
+        println!("                     Advance the ledger by n ledgers (default 1)");
+        println!("  snapshot save <f>  Save environment state to a file");
+        println!("  snapshot load <f>  Restore environment state from a file");
         println!("  stack              Show call stack");
         println!("  budget             Show budget usage");
+        println!("  locals             Show WASM locals and value stack");
+        println!("  memory | mem       Inspect linear memory (mem help)");
+        println!("  eval <expr>        Evaluate against a fork of the current state");
+        println!("  eval let <n> = <e> Evaluate and bind the result to a variable");
+        println!("  vars               Show bound variables");
+        println!("  watch [expr]       Add a watch expression, or show all watches");
+        println!("  unwatch <n>        Remove a watch expression");
+        println!("  session save <f>   Save the whole session to a file");
+        println!("  session load <f>   Reopen a saved session");
         println!("  break <func>       Set breakpoint");
         println!("  list-breaks        List breakpoints");
         println!("  clear <func>       Clear breakpoint");
+        println!("  history            Show command history");
         println!("  help               Show this help");
         println!("  quit | q           Exit debugger");
     }
 }
+
+/// The remainder of `command` after its first `n` whitespace-separated words.
+fn skip_words(command: &str, n: usize) -> &str {
+    let mut rest = command.trim_start();
+    for _ in 0..n {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        rest = rest[end..].trim_start();
+    }
+    rest.trim_end()
+}
+
+fn save_history(editor: &mut Editor<ReplHelper, DefaultHistory>, path: &Path) {
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Err(e) = editor.save_history(path) {
+        tracing::debug!(error = %e, "Failed to save REPL history");
+    }
+}
@@ -21,6 +21,7 @@
 //! | `bool`   | `{"type": "bool", "value": true}`        | Boolean                        |
 //! | `symbol` | `{"type": "symbol", "value": "hello"}`   | Soroban Symbol (≤32 chars)     |
 //! | `string` | `{"type": "string", "value": "long..."}`  | Soroban String (any length)    |
+//! | `xdr`    | `{"type": "xdr", "value": "AAAAAwAAACo="}` | Any value, as base64 `ScVal` XDR |
 //!
 //! Bare values (without type annotation) still work:
 //! - Numbers → `i128`
@@ -42,7 +43,7 @@ pub enum ArgumentParseError {
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
 
-    #[error("Unsupported type: {0}. Supported types: u32, i32, u64, u128, i128, bool, string, symbol, address, option, tuple, vec, bytes, bytesn")]
+    #[error("Unsupported type: {0}. Supported types: u32, i32, u64, u128, i128, bool, string, symbol, address, option, tuple, vec, bytes, bytesn, xdr")]
     UnsupportedType(String),
 
     #[error("Failed to convert value: {0}")]
@@ -116,6 +117,11 @@ impl ArgumentParser {
         self.parse_value(&value)
     }
 
+    /// Parse a single JSON value, bare or type-annotated, into one Soroban value
+    pub fn parse_single_value(&self, value: &Value) -> Result<Val, ArgumentParseError> {
+        self.json_to_soroban_val(value)
+    }
+
     /// Parse a JSON value into a Vec of Soroban values
     ///
     /// If the JSON is an array, each element becomes a separate argument.
@@ -208,6 +214,7 @@ impl ArgumentParser {
             "vec" => self.convert_vec(val, obj),
             "bytes" => self.convert_bytes(val),
             "bytesn" => self.convert_bytesn(val, obj),
+            "xdr" => self.convert_xdr(val),
             other => Err(ArgumentParseError::UnsupportedType(other.to_string())),
         }
     }
@@ -518,6 +525,24 @@ impl ArgumentParser {
         })
     }
 
+    /// Convert base64 `ScVal` XDR, for values no other annotation can express
+    fn convert_xdr(&self, value: &Value) -> Result<Val, ArgumentParseError> {
+        use soroban_sdk::xdr::{Limits, ReadXdr, ScVal};
+
+        let s = value
+            .as_str()
+            .ok_or_else(|| ArgumentParseError::TypeMismatch {
+                expected: "base64 string for xdr".to_string(),
+                actual: format!("{}", value),
+            })?;
+        let sc_val = ScVal::from_xdr_base64(s, Limits::none()).map_err(|e| {
+            ArgumentParseError::InvalidArgument(format!("Invalid ScVal XDR: {}", e))
+        })?;
+        Val::try_from_val(&self.env, &sc_val).map_err(|e| {
+            ArgumentParseError::ConversionError(format!("Failed to convert XDR value: {:?}", e))
+        })
+    }
+
     fn convert_address(&self, value: &Value) -> Result<Val, ArgumentParseError> {
         let s = value
             .as_str()
@@ -778,6 +803,26 @@ mod tests {
         assert_eq!(vals.len(), 1);
     }
 
+    #[test]
+    fn test_parse_xdr_value() {
+        use soroban_sdk::xdr::{Limits, ScVal, WriteXdr};
+
+        let parser = create_parser();
+        let sc_val = ScVal::U128(soroban_sdk::xdr::UInt128Parts {
+            hi: 1,
+            lo: u64::MAX,
+        });
+        let value = serde_json::json!({
+            "type": "xdr",
+            "value": sc_val.to_xdr_base64(Limits::none()).unwrap(),
+        });
+        let val = parser.parse_single_value(&value).unwrap();
+        assert_eq!(ScVal::try_from_val(&parser.env, &val).unwrap(), sc_val);
+
+        let bad = serde_json::json!({"type": "xdr", "value": "not xdr"});
+        assert!(parser.parse_single_value(&bad).is_err());
+    }
+
     #[test]
     fn test_parse_null() {
         let parser = create_parser();
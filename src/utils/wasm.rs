@@ -16,6 +16,14 @@ pub fn compute_checksum(wasm_bytes: &[u8]) -> String {
 
 /// Parse exported functions from a WASM module.
 pub fn parse_functions(wasm_bytes: &[u8]) -> Result<Vec<String>> {
+    Ok(parse_function_exports(wasm_bytes)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Parse exported functions with their function indices.
+pub fn parse_function_exports(wasm_bytes: &[u8]) -> Result<Vec<(String, u32)>> {
     let mut functions = Vec::new();
     let parser = Parser::new(0);
 
@@ -28,7 +36,7 @@ pub fn parse_functions(wasm_bytes: &[u8]) -> Result<Vec<String>> {
                     DebuggerError::WasmLoadError(format!("Failed to read export: {}", e))
                 })?;
                 if matches!(export.kind, wasmparser::ExternalKind::Func) {
-                    functions.push(export.name.to_string());
+                    functions.push((export.name.to_string(), export.index));
                 }
             }
         }
@@ -37,6 +45,30 @@ pub fn parse_functions(wasm_bytes: &[u8]) -> Result<Vec<String>> {
     Ok(functions)
 }
 
+/// Function index of the imported host function `module.name`, if the
+/// contract imports it.
+pub fn find_function_import(wasm_bytes: &[u8], module: &str, name: &str) -> Result<Option<u32>> {
+    let mut index = 0u32;
+    for payload in Parser::new(0).parse_all(wasm_bytes) {
+        if let Payload::ImportSection(reader) = payload
+            .map_err(|e| DebuggerError::WasmLoadError(format!("Failed to parse WASM: {}", e)))?
+        {
+            for import in reader {
+                let import = import.map_err(|e| {
+                    DebuggerError::WasmLoadError(format!("Failed to read import: {}", e))
+                })?;
+                if let wasmparser::TypeRef::Func(_) = import.ty {
+                    if import.module == module && import.name == name {
+                        return Ok(Some(index));
+                    }
+                    index += 1;
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CrossContractCall {
     pub caller: String,
@@ -564,15 +596,13 @@ fn stringm_to_string(bytes: &[u8]) -> String {
         .to_string()
 }
 
-/// Parse full function signatures from the WASM `contractspecv0` custom section.
+/// Read every `ScSpecEntry` from the WASM `contractspecv0` custom section.
 ///
-/// Returns an empty `Vec` (not an error) when no spec section is present —
-/// this keeps callers simple and backward-compatible with contracts that
-/// pre-date the spec section.
-pub fn parse_function_signatures(wasm_bytes: &[u8]) -> Result<Vec<FunctionSignature>> {
+/// Returns an empty `Vec` when no spec section is present.
+fn read_spec_entries(wasm_bytes: &[u8]) -> Result<Vec<stellar_xdr::curr::ScSpecEntry>> {
     use stellar_xdr::curr::{Limited, Limits, ReadXdr, ScSpecEntry};
 
-    let mut signatures = Vec::new();
+    let mut entries = Vec::new();
     let parser = Parser::new(0);
 
     for payload in parser.parse_all(wasm_bytes) {
@@ -591,41 +621,79 @@ pub fn parse_function_signatures(wasm_bytes: &[u8]) -> Result<Vec<FunctionSignat
         let mut limited = Limited::new(cursor, Limits::none());
 
         // The section is a packed sequence of XDR-encoded ScSpecEntry values.
-        loop {
-            match ScSpecEntry::read_xdr(&mut limited) {
-                Ok(ScSpecEntry::FunctionV0(func)) => {
-                    let name = stringm_to_string(func.name.0.as_slice());
-
-                    let params = func
-                        .inputs
-                        .iter()
-                        .map(|input| FunctionParam {
-                            name: stringm_to_string(input.name.as_slice()),
-                            type_name: spec_type_to_string(&input.type_),
-                        })
-                        .collect();
-
-                    let return_type = func.outputs.first().map(spec_type_to_string);
-
-                    signatures.push(FunctionSignature {
-                        name,
-                        params,
-                        return_type,
-                    });
-                }
-                Ok(_) => {
-                    // UDT definitions, events, etc. — skip
-                }
-                Err(_) => break, // end of section or corrupt data
-            }
+        while let Ok(entry) = ScSpecEntry::read_xdr(&mut limited) {
+            entries.push(entry);
         }
 
         break; // only one contractspecv0 section exists per contract
     }
 
+    Ok(entries)
+}
+
+/// Parse full function signatures from the WASM `contractspecv0` custom section.
+///
+/// Returns an empty `Vec` (not an error) when no spec section is present —
+/// this keeps callers simple and backward-compatible with contracts that
+/// pre-date the spec section.
+pub fn parse_function_signatures(wasm_bytes: &[u8]) -> Result<Vec<FunctionSignature>> {
+    use stellar_xdr::curr::ScSpecEntry;
+
+    let signatures = read_spec_entries(wasm_bytes)?
+        .into_iter()
+        .filter_map(|entry| match entry {
+            ScSpecEntry::FunctionV0(func) => Some(FunctionSignature {
+                name: stringm_to_string(func.name.0.as_slice()),
+                params: func
+                    .inputs
+                    .iter()
+                    .map(|input| FunctionParam {
+                        name: stringm_to_string(input.name.as_slice()),
+                        type_name: spec_type_to_string(&input.type_),
+                    })
+                    .collect(),
+                return_type: func.outputs.first().map(spec_type_to_string),
+            }),
+            // UDT definitions, events, etc. — skip
+            _ => None,
+        })
+        .collect();
+
     Ok(signatures)
 }
 
+/// One variant of a `#[contracterror]` enum declared in the contract spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractErrorSpec {
+    /// Name of the enum, e.g. `TokenError`.
+    pub enum_name: String,
+    /// Name of the variant, e.g. `InsufficientBalance`.
+    pub name: String,
+    /// The code returned as `Error(Contract, #value)`.
+    pub value: u32,
+    /// The variant's doc comment, if any.
+    pub doc: String,
+}
+
+/// Parse the `#[contracterror]` enums from the WASM `contractspecv0` section.
+pub fn parse_contract_errors(wasm_bytes: &[u8]) -> Result<Vec<ContractErrorSpec>> {
+    use stellar_xdr::curr::ScSpecEntry;
+
+    let mut errors = Vec::new();
+    for entry in read_spec_entries(wasm_bytes)? {
+        if let ScSpecEntry::UdtErrorEnumV0(spec) = entry {
+            let enum_name = stringm_to_string(spec.name.as_slice());
+            errors.extend(spec.cases.iter().map(|case| ContractErrorSpec {
+                enum_name: enum_name.clone(),
+                name: stringm_to_string(case.name.as_slice()),
+                value: case.value,
+                doc: stringm_to_string(case.doc.as_slice()).trim().to_string(),
+            }));
+        }
+    }
+    Ok(errors)
+}
+
 // ─── tests ────────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
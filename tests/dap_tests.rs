@@ -0,0 +1,60 @@
+use assert_cmd::Command;
+use serde_json::{json, Value};
+
+fn frame(message: Value) -> String {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+fn parse_frames(mut output: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while let Some(start) = output.find("\r\n\r\n") {
+        let length: usize = output[..start]
+            .trim()
+            .strip_prefix("Content-Length: ")
+            .expect("Content-Length header")
+            .parse()
+            .unwrap();
+        let body = &output[start + 4..start + 4 + length];
+        messages.push(serde_json::from_str(body).unwrap());
+        output = &output[start + 4 + length..];
+    }
+    messages
+}
+
+/// `dap` speaks framed DAP on stdin/stdout and nothing else
+#[test]
+fn test_dap_over_stdio() {
+    let input = [
+        frame(json!({"seq": 1, "type": "request", "command": "initialize", "arguments": {"adapterID": "soroban"}})),
+        frame(json!({"seq": 2, "type": "request", "command": "threads"})),
+        frame(json!({"seq": 3, "type": "request", "command": "launch", "arguments": {}})),
+        frame(json!({"seq": 4, "type": "request", "command": "disconnect"})),
+    ]
+    .concat();
+
+    let output = Command::cargo_bin("soroban-debug")
+        .unwrap()
+        .arg("dap")
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let messages = parse_frames(&String::from_utf8(output.stdout).unwrap());
+    let commands: Vec<&str> = messages
+        .iter()
+        .map(|m| m["command"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        commands,
+        vec!["initialize", "threads", "launch", "disconnect"]
+    );
+    assert_eq!(messages[0]["body"]["supportsFunctionBreakpoints"], true);
+    assert_eq!(messages[1]["body"]["threads"][0]["id"], 1);
+    assert_eq!(messages[2]["success"], false);
+    assert!(messages[2]["message"]
+        .as_str()
+        .unwrap()
+        .contains("contractPath"));
+}
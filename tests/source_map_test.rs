@@ -1,4 +1,3 @@
-#![cfg(any())]
 use soroban_debugger::debugger::source_map::{SourceLocation, SourceMap};
 use std::path::PathBuf;
 
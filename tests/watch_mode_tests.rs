@@ -1,4 +1,3 @@
-#![cfg(any())]
 use assert_cmd::Command;
 use std::fs;
 use std::path::PathBuf;
@@ -32,8 +31,7 @@ fn test_watch_mode_invocation() {
             .timeout(Duration::from_secs(2));
 
         // This will timeout, which is expected for watch mode
-        let result = cmd.assert();
-        result
+        cmd.assert()
     });
 
     // Give it a moment to start
@@ -114,3 +112,27 @@ fn test_watch_mode_help_text() {
         .stdout(predicates::str::contains("--watch"))
         .stdout(predicates::str::contains("Watch the WASM file for changes"));
 }
+
+/// Test that a failing run is reported and watch mode keeps waiting
+#[test]
+fn test_watch_mode_survives_invalid_contract() {
+    let temp_dir = TempDir::new().unwrap();
+    let wasm_path = temp_dir.path().join("broken.wasm");
+    fs::write(&wasm_path, b"not wasm").unwrap();
+
+    let mut cmd = Command::cargo_bin("soroban-debug").unwrap();
+    cmd.env("NO_COLOR", "1")
+        .arg("run")
+        .arg("--contract")
+        .arg(&wasm_path)
+        .arg("--function")
+        .arg("test")
+        .arg("--watch")
+        .timeout(Duration::from_secs(2));
+
+    let output = cmd.output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--- Initial Run ---"));
+    assert!(stdout.contains("Error:"));
+    assert!(stdout.contains("Waiting for changes..."));
+}